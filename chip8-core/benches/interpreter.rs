@@ -0,0 +1,101 @@
+//! Benchmarks for the interpreter loop, run with
+//! `cargo bench -p chip8-core --features bench`.
+//!
+//! Each workload is a small program looping forever, run a frame at a time at
+//! a clock rate high enough that the loop, rather than the frame setup,
+//! dominates.
+
+use chip8_core::palette::Palette;
+use chip8_core::{Chip8, HEIGHT, WIDTH};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// 1000 instructions per 60Hz frame.
+const CYCLES_PER_SECOND: u32 = 60_000;
+
+/// Counts in V1 and adds, XORs and shifts it into V0, V2 and V3 forever,
+/// never drawing.
+const MATH_HEAVY: [u8; 12] = [
+    0x60, 0x01, // V0 = 1
+    0x71, 0x01, // V1 += 1
+    0x80, 0x14, // V0 += V1
+    0x82, 0x16, // V2 >>= 1
+    0x83, 0x13, // V3 ^= V1
+    0x12, 0x02, // jump to 0x202
+];
+
+/// Draws the font's 0 over and over, moving it across the screen.
+const DRAW_HEAVY: [u8; 10] = [
+    0xA0, 0x50, // I = the font's 0
+    0xD0, 0x15, // draw it at (V0, V1)
+    0x70, 0x08, // V0 += 8
+    0x71, 0x03, // V1 += 3
+    0x12, 0x02, // jump to 0x202
+];
+
+fn chip_8_running(program: &[u8]) -> Chip8 {
+    let mut chip_8 = Chip8::new();
+    chip_8.initialize().unwrap();
+    chip_8.set_cycles_per_second(Some(CYCLES_PER_SECOND));
+    chip_8.load_program(program.to_vec()).unwrap();
+    chip_8
+}
+
+fn run_frame(c: &mut Criterion) {
+    let mut group = c.benchmark_group("run_frame");
+
+    for (name, program) in [("math", &MATH_HEAVY[..]), ("draw", &DRAW_HEAVY[..])] {
+        group.bench_function(name, |b| {
+            let mut chip_8 = chip_8_running(program);
+            b.iter(|| chip_8.run_frame().unwrap());
+        });
+    }
+
+    group.finish();
+}
+
+/// Compares running instructions through the dispatch table, as
+/// [`Chip8::cycle`] does, with decoding each one as it runs, as
+/// [`Chip8::cycle_decoded`] does.
+fn dispatch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dispatch");
+
+    group.bench_function("table", |b| {
+        let mut chip_8 = chip_8_running(&MATH_HEAVY);
+
+        b.iter(|| {
+            for _ in 0..1000 {
+                chip_8.cycle().unwrap();
+            }
+        });
+    });
+
+    group.bench_function("decoded", |b| {
+        let mut chip_8 = chip_8_running(&MATH_HEAVY);
+
+        b.iter(|| {
+            for _ in 0..1000 {
+                black_box(chip_8.cycle_decoded().unwrap());
+            }
+        });
+    });
+
+    group.finish();
+}
+
+/// Converts a screen full of sprites from the packed rows into pixels, as the
+/// frontend does every frame something is drawn.
+fn write_rgba(c: &mut Criterion) {
+    let mut chip_8 = chip_8_running(&DRAW_HEAVY);
+    chip_8.run_frame().unwrap();
+
+    let frame = chip_8.take_frame().unwrap().0;
+    let mut buffer = vec![0; (WIDTH * HEIGHT) as usize];
+    let palette = Palette::default();
+
+    c.bench_function("write_rgba", |b| {
+        b.iter(|| frame.write_rgba(&mut buffer, 0..HEIGHT as usize, &palette));
+    });
+}
+
+criterion_group!(benches, run_frame, dispatch, write_rgba);
+criterion_main!(benches);
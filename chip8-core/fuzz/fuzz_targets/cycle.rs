@@ -0,0 +1,27 @@
+//! Runs random ROMs with random keys held, checking that the emulator never
+//! panics. Run with `cargo fuzz run cycle` from `chip8-core`.
+
+#![no_main]
+
+use chip8_core::{Chip8, Keys};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (u16, Vec<u8>)| {
+    let (keys, program) = input;
+
+    let mut chip_8 = Chip8::new();
+    chip_8.initialize().unwrap();
+
+    // ROMs too big to load are rejected with an error, which is fine too.
+    if chip_8.load_program(program).is_err() {
+        return;
+    }
+
+    chip_8.set_keys(Keys(keys));
+
+    for _ in 0..10_000 {
+        if chip_8.cycle_checked().is_err() {
+            break;
+        }
+    }
+});
@@ -0,0 +1,64 @@
+//! Console output for debugging programs, an extension of this emulator
+//! turned on with [`Quirks::console_output`](super::quirks::Quirks::console_output).
+//!
+//! While it is on, bytes written to [`CONSOLE_ADDRESS`] by `FX55` or `FX33`
+//! are collected as output for the host to print, instead of being stored.
+//! Printing a message is then a matter of writing it a character at a time:
+//!
+//! ```text
+//! i := 0
+//! v0 := 72 save v0   # H
+//! v0 := 105 save v0  # i
+//! v0 := 10 save v0   # a newline
+//! ```
+
+use super::Chip8;
+
+/// The address bytes are written to for the console.
+pub const CONSOLE_ADDRESS: u16 = 0x000;
+
+impl Chip8 {
+    /// Returns the bytes written to the console since the last call.
+    pub fn take_console_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.console_output)
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use crate::Chip8;
+
+    #[test]
+    fn bytes_written_to_the_console_are_collected() {
+        let program = vec![
+            0xA0, 0x00, // I = 0x000
+            0x60, 0x48, // V0 = 'H'
+            0xF0, 0x55, // save V0
+            0x60, 0x69, // V0 = 'i'
+            0xF0, 0x55, // save V0
+            0x12, 0x0A, // loop forever
+        ];
+
+        for console_output in [false, true] {
+            let mut chip_8 = Chip8::new();
+            chip_8.initialize().unwrap();
+            chip_8.load_program(program.clone()).unwrap();
+            chip_8.quirks.console_output = console_output;
+
+            for _ in 0..5 {
+                chip_8.cycle().unwrap();
+            }
+
+            match console_output {
+                true => {
+                    assert_eq!(chip_8.take_console_output(), b"Hi");
+                    assert_eq!(chip_8.memory()[0], 0);
+                }
+                false => {
+                    assert!(chip_8.take_console_output().is_empty());
+                    assert_eq!(chip_8.memory()[0], b'i');
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Which parts of memory a program ran, read and wrote.
+//!
+//! Bytes that are run are code, and bytes that are only read are data (like
+//! sprites), which is how a disassembler can tell them apart. Writes to bytes
+//! that are code are how self-modifying programs patch themselves.
+
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+
+use tracing::info;
+
+use super::memory::MEMORY_SIZE;
+use super::Chip8;
+
+/// What a program did with each byte of memory.
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    /// The first bytes of the instructions that ran.
+    instructions: Vec<bool>,
+    executed: Vec<bool>,
+    read: Vec<bool>,
+    written: Vec<bool>,
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self::new(MEMORY_SIZE)
+    }
+}
+
+impl Coverage {
+    /// Creates empty coverage for `size` bytes of memory.
+    fn new(size: usize) -> Self {
+        Self {
+            instructions: vec![false; size],
+            executed: vec![false; size],
+            read: vec![false; size],
+            written: vec![false; size],
+        }
+    }
+
+    /// Returns true if the byte at `address` was part of an instruction that ran.
+    pub fn executed(&self, address: u16) -> bool {
+        self.executed.get(address as usize) == Some(&true)
+    }
+
+    /// Returns true if an instruction starting at `address` ran.
+    pub fn instruction_ran(&self, address: u16) -> bool {
+        self.instructions.get(address as usize) == Some(&true)
+    }
+
+    /// Returns true if the byte at `address` was read by an instruction,
+    /// like a sprite drawn with `DXYN`.
+    pub fn read(&self, address: u16) -> bool {
+        self.read.get(address as usize) == Some(&true)
+    }
+
+    /// Returns true if the byte at `address` was written by an instruction.
+    pub fn written(&self, address: u16) -> bool {
+        self.written.get(address as usize) == Some(&true)
+    }
+
+    /// Writes the ranges of addresses that were executed, read and written,
+    /// one range per line like `executed 0x200-0x2A1`.
+    pub fn write_text(&self, mut writer: impl Write) -> io::Result<()> {
+        for (kind, covered) in self.kinds() {
+            for range in ranges(covered) {
+                writeln!(
+                    writer,
+                    "{kind} 0x{:03X}-0x{:03X}",
+                    range.start(),
+                    range.end()
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the same ranges as [`Self::write_text`] as a JSON object, with
+    /// each kind mapped to a list of inclusive `[start, end]` pairs.
+    pub fn write_json(&self, mut writer: impl Write) -> io::Result<()> {
+        let kinds: Vec<String> = self
+            .kinds()
+            .into_iter()
+            .map(|(kind, covered)| {
+                let ranges: Vec<String> = ranges(covered)
+                    .map(|range| format!("[{}, {}]", range.start(), range.end()))
+                    .collect();
+
+                format!("  \"{kind}\": [{}]", ranges.join(", "))
+            })
+            .collect();
+
+        writeln!(writer, "{{\n{}\n}}", kinds.join(",\n"))
+    }
+
+    fn kinds(&self) -> [(&'static str, &[bool]); 3] {
+        [
+            ("executed", &self.executed),
+            ("read", &self.read),
+            ("written", &self.written),
+        ]
+    }
+}
+
+/// Returns the runs of `true` in `covered` as ranges of addresses.
+fn ranges(covered: &[bool]) -> impl Iterator<Item = RangeInclusive<u16>> + '_ {
+    let mut address = 0;
+
+    std::iter::from_fn(move || {
+        let start = address + covered[address..].iter().position(|&byte| byte)?;
+        let end = covered[start..]
+            .iter()
+            .position(|&byte| !byte)
+            .map_or(covered.len(), |length| start + length);
+
+        address = end;
+        Some(start as u16..=(end - 1) as u16)
+    })
+}
+
+impl Chip8 {
+    /// Starts or stops tracking which addresses the program runs, reads and
+    /// writes. Stopping throws away what was tracked.
+    pub fn set_coverage_enabled(&mut self, enabled: bool) {
+        let size = self.memory.size().bytes();
+        self.coverage = enabled.then(|| Coverage::new(size));
+    }
+
+    /// Returns what was tracked since [`Self::set_coverage_enabled`] turned it on.
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    /// Records that the instruction at `address` ran.
+    pub(crate) fn record_execution(&mut self, address: u16) {
+        if let Some(coverage) = &mut self.coverage {
+            if let Some(instruction) = coverage.instructions.get_mut(address as usize) {
+                *instruction = true;
+            }
+
+            for byte in [address, address.wrapping_add(1)] {
+                if let Some(executed) = coverage.executed.get_mut(byte as usize) {
+                    *executed = true;
+                }
+            }
+        }
+    }
+
+    /// Records that an instruction read the byte at `address`.
+    pub(crate) fn record_read(&mut self, address: u16) {
+        if let Some(read) = self
+            .coverage
+            .as_mut()
+            .and_then(|coverage| coverage.read.get_mut(address as usize))
+        {
+            *read = true;
+        }
+    }
+
+    /// Records that an instruction wrote `value` to `address`, and runs
+    /// [`Hooks::on_memory_write`](super::hooks::Hooks::on_memory_write).
+    /// Writes to bytes that have already run as code are logged, and can stop
+    /// the emulator with [`Self::set_break_on_self_modifying_code`].
+    pub(crate) fn record_write(&mut self, address: u16, value: u8) {
+        self.run_hooks(|hooks, chip_8| hooks.on_memory_write(chip_8, address, value));
+        self.check_write_protection(address);
+
+        let Some(coverage) = &mut self.coverage else {
+            return;
+        };
+
+        if let Some(written) = coverage.written.get_mut(address as usize) {
+            *written = true;
+        }
+
+        if coverage.executed(address) {
+            info!(
+                target: "chip_8::trace",
+                "0x{:03X} wrote to 0x{address:03X}, which has already run as code",
+                self.program_counter.wrapping_sub(2)
+            );
+
+            self.debugger
+                .self_modifying_write(self.program_counter, address);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use crate::Chip8;
+
+    #[test]
+    fn code_and_data_are_told_apart() {
+        // Points I at the sprite at 0x206, draws it, then loops forever.
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8
+            .load_program(vec![0xA2, 0x06, 0xD0, 0x01, 0x12, 0x04, 0xFF, 0x00])
+            .unwrap();
+        chip_8.set_coverage_enabled(true);
+
+        for _ in 0..4 {
+            chip_8.cycle().unwrap();
+        }
+
+        let mut report = Vec::new();
+        chip_8.coverage().unwrap().write_text(&mut report).unwrap();
+
+        assert_eq!(
+            String::from_utf8(report).unwrap(),
+            "executed 0x200-0x205\nread 0x206-0x206\n"
+        );
+
+        let mut report = Vec::new();
+        chip_8.coverage().unwrap().write_json(&mut report).unwrap();
+
+        assert_eq!(
+            String::from_utf8(report).unwrap(),
+            "{\n  \"executed\": [[512, 517]],\n  \"read\": [[518, 518]],\n  \"written\": []\n}\n"
+        );
+    }
+}
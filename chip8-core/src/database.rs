@@ -0,0 +1,283 @@
+//! A database of known programs, used to pick the right settings for a game
+//! without having to pass them on the command line.
+//!
+//! Programs are identified by the SHA-1 hash in their [`LoadedRom`]. This is
+//! similar to the community CHIP-8 database, but much smaller and in a simpler
+//! format. The format is described at the top of `rom_database.txt`.
+
+use super::memory::MEMORY_SIZE;
+use super::{palette::Palette, quirks::Quirks, rom::LoadedRom, Chip8Error};
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+/// The database that is compiled into the emulator.
+const EMBEDDED_DATABASE: &str = include_str!("rom_database.txt");
+
+/// Everything we know about a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomInfo {
+    /// The name of the program.
+    pub title: String,
+    /// The machine the program was written for, like `chip-8` or `schip`.
+    pub platform: String,
+    /// The quirks the program expects.
+    pub quirks: Quirks,
+    /// How many cycles the program expects to run per 60Hz frame, if it
+    /// needs something other than the default.
+    pub tickrate: Option<u32>,
+    /// The colors the program was designed for, if any.
+    pub palette: Option<Palette>,
+    /// The parts of memory the program keeps its high scores in, which
+    /// frontends can save between runs.
+    pub high_scores: Vec<RangeInclusive<u16>>,
+}
+
+/// A set of [`RomInfo`]s, looked up by SHA-1 hash.
+#[derive(Debug, Default)]
+pub struct RomDatabase {
+    entries: HashMap<[u8; 20], RomInfo>,
+}
+
+impl RomDatabase {
+    /// Returns the database that is compiled into the emulator.
+    pub fn embedded() -> Self {
+        Self::parse(EMBEDDED_DATABASE).expect("the embedded ROM database should be valid")
+    }
+
+    /// Parses a database written in the same format as the embedded one.
+    pub fn parse(text: &str) -> Result<Self, Chip8Error> {
+        let mut database = Self::default();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (sha1, info) =
+                parse_entry(line).map_err(|reason| Chip8Error::InvalidRomDatabaseEntry {
+                    line: index + 1,
+                    reason,
+                })?;
+
+            database.entries.insert(sha1, info);
+        }
+
+        Ok(database)
+    }
+
+    /// Adds all the entries from `other`, replacing any with the same hash.
+    pub fn extend(&mut self, other: RomDatabase) {
+        self.entries.extend(other.entries);
+    }
+
+    /// Returns what we know about a loaded program, if anything.
+    pub fn lookup(&self, rom: &LoadedRom) -> Option<&RomInfo> {
+        self.entries.get(&rom.sha1)
+    }
+
+    /// Returns how many programs are in the database.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there are no programs in the database.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+fn parse_entry(line: &str) -> Result<([u8; 20], RomInfo), String> {
+    let mut fields: Vec<&str> = line.split('|').map(str::trim).collect();
+
+    // The high scores were added later, so older files leave them out.
+    let high_scores = match fields.len() {
+        7 => fields.pop().unwrap(),
+        _ => "-",
+    };
+
+    let [sha1, title, platform, quirks, tickrate, palette] = fields[..] else {
+        return Err(format!("expected 6 or 7 fields, found {}", fields.len()));
+    };
+
+    let info = RomInfo {
+        title: title.to_string(),
+        platform: match platform {
+            "chip-8" | "schip" | "xo-chip" => platform.to_string(),
+            _ => return Err(format!("unknown platform {platform:?}")),
+        },
+        quirks: parse_quirks(quirks)?,
+        tickrate: match tickrate {
+            "-" => None,
+            _ => Some(
+                tickrate
+                    .parse()
+                    .map_err(|_| format!("invalid tickrate {tickrate:?}"))?,
+            ),
+        },
+        palette: parse_palette(palette)?,
+        high_scores: parse_high_scores(high_scores)?,
+    };
+
+    Ok((parse_sha1(sha1)?, info))
+}
+
+fn parse_sha1(sha1: &str) -> Result<[u8; 20], String> {
+    let mut bytes = [0; 20];
+
+    if sha1.len() != 40 || !sha1.is_ascii() {
+        return Err(format!("invalid SHA-1 hash {sha1:?}"));
+    }
+
+    for (byte, digits) in bytes.iter_mut().zip(sha1.as_bytes().chunks(2)) {
+        // The length check above means these are always 2 ASCII characters.
+        let digits = std::str::from_utf8(digits).unwrap();
+        *byte =
+            u8::from_str_radix(digits, 16).map_err(|_| format!("invalid SHA-1 hash {sha1:?}"))?;
+    }
+
+    Ok(bytes)
+}
+
+fn parse_quirks(names: &str) -> Result<Quirks, String> {
+    let mut quirks = Quirks::default();
+
+    if names == "-" {
+        return Ok(quirks);
+    }
+
+    for name in names.split(',').map(str::trim) {
+        match name {
+            "display-wait" => quirks.display_wait = true,
+            "wrap-sprites" => quirks.wrap_sprites = true,
+            "shift-vy" => quirks.shift_vy = true,
+            "increment-index" => quirks.increment_index = true,
+            "index-overflow-flag" => quirks.index_overflow_flag = true,
+            "console-output" => quirks.console_output = true,
+            _ => return Err(format!("unknown quirk {name:?}")),
+        }
+    }
+
+    Ok(quirks)
+}
+
+fn parse_palette(palette: &str) -> Result<Option<Palette>, String> {
+    let palette = match palette {
+        "-" => return Ok(None),
+        "classic" => Palette::CLASSIC,
+        "green-phosphor" => Palette::GREEN_PHOSPHOR,
+        "amber" => Palette::AMBER,
+        _ => {
+            let colors = palette
+                .split_once('/')
+                .map(|(fg, bg)| (u32::from_str_radix(fg, 16), u32::from_str_radix(bg, 16)));
+
+            match colors {
+                Some((Ok(foreground), Ok(background)))
+                    if foreground <= 0xFFFFFF && background <= 0xFFFFFF =>
+                {
+                    Palette {
+                        foreground,
+                        background,
+                    }
+                }
+                _ => return Err(format!("invalid palette {palette:?}")),
+            }
+        }
+    };
+
+    Ok(Some(palette))
+}
+
+fn parse_high_scores(ranges: &str) -> Result<Vec<RangeInclusive<u16>>, String> {
+    if ranges == "-" {
+        return Ok(Vec::new());
+    }
+
+    ranges
+        .split(',')
+        .map(str::trim)
+        .map(|range| {
+            let invalid = || format!("invalid address range {range:?}");
+            let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+            let start = u16::from_str_radix(start, 16).map_err(|_| invalid())?;
+            let end = u16::from_str_radix(end, 16).map_err(|_| invalid())?;
+
+            match start <= end && (end as usize) < MEMORY_SIZE {
+                true => Ok(start..=end),
+                false => Err(invalid()),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::RomDatabase;
+    use crate::{palette::Palette, quirks::Quirks, rom::LoadedRom, Chip8, Chip8Error};
+
+    #[test]
+    fn embedded_database_parses() {
+        RomDatabase::embedded();
+    }
+
+    #[test]
+    fn embedded_database_knows_the_splash_screen() {
+        let rom = Chip8::new()
+            .load_program(include_bytes!("../../src/demo/splash.ch8").to_vec())
+            .unwrap();
+
+        let database = RomDatabase::embedded();
+        let info = database.lookup(&rom).unwrap();
+        assert_eq!(info.title, "CHIP-8 Splash");
+        assert_eq!(info.platform, "chip-8");
+        assert_eq!(info.quirks, Quirks::default());
+        assert_eq!(info.tickrate, None);
+    }
+
+    #[test]
+    fn entries_are_looked_up_by_hash() {
+        let database = RomDatabase::parse(
+            "# A comment\n\
+             a9993e364706816aba3e25717850c26c9cd0d89d | ABC | schip | display-wait, wrap-sprites | 30 | ffffff/000080 | 2F0-2F5, 300-300\n",
+        )
+        .unwrap();
+
+        let mut rom = LoadedRom {
+            size: 3,
+            sha1: [
+                0xa9, 0x99, 0x3e, 0x36, 0x47, 0x06, 0x81, 0x6a, 0xba, 0x3e, 0x25, 0x71, 0x78, 0x50,
+                0xc2, 0x6c, 0x9c, 0xd0, 0xd8, 0x9d,
+            ],
+            entry_point: 0x200,
+        };
+
+        let info = database.lookup(&rom).unwrap();
+        assert_eq!(info.title, "ABC");
+        assert!(info.quirks.display_wait && info.quirks.wrap_sprites);
+        assert_eq!(info.tickrate, Some(30));
+        assert_eq!(
+            info.palette,
+            Some(Palette {
+                foreground: 0xFFFFFF,
+                background: 0x000080
+            })
+        );
+
+        assert_eq!(info.high_scores, [0x2F0..=0x2F5, 0x300..=0x300]);
+
+        rom.sha1[0] = 0;
+        assert!(database.lookup(&rom).is_none());
+    }
+
+    #[test]
+    fn invalid_entries_report_their_line() {
+        let error = RomDatabase::parse("\n0000 | Short hash | chip-8 | - | - | -\n").unwrap_err();
+
+        assert!(matches!(
+            error,
+            Chip8Error::InvalidRomDatabaseEntry { line: 2, .. }
+        ));
+    }
+}
@@ -0,0 +1,434 @@
+//! Ways of stopping a running program so it can be inspected.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use super::console::CONSOLE_ADDRESS;
+use super::memory::Program;
+use super::{Chip8, Chip8Error, Instruction, PROGRAM_OFFSET};
+
+/// Where and why the emulator stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stop {
+    /// The address of the next instruction, which hasn't run yet.
+    pub address: u16,
+    /// Why the emulator stopped.
+    pub reason: StopReason,
+}
+
+/// Why the emulator stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The next instruction is at an address set with [`Chip8::set_breakpoints`].
+    Breakpoint,
+    /// One instruction ran after [`Chip8::step`].
+    Step,
+    /// [`Chip8::pause`] was called.
+    Pause,
+    /// The next instruction is one set with [`Chip8::break_on_instruction`],
+    /// given by the name of its [`Instruction`] variant.
+    Instruction(&'static str),
+    /// The last instruction wrote to `address`, which had already run as
+    /// code. See [`Chip8::set_break_on_self_modifying_code`].
+    SelfModifyingWrite {
+        #[allow(missing_docs)]
+        address: u16,
+    },
+    /// The last instruction wrote to `address`, which is protected. See
+    /// [`Chip8::set_write_protection`].
+    ProtectedWrite {
+        #[allow(missing_docs)]
+        address: u16,
+        #[allow(missing_docs)]
+        region: ProtectedRegion,
+    },
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Breakpoint => write!(f, "breakpoint"),
+            Self::Step => write!(f, "step"),
+            Self::Pause => write!(f, "pause"),
+            Self::Instruction(name) => write!(f, "{name}"),
+            Self::SelfModifyingWrite { address } => write!(f, "write to 0x{address:03X}"),
+            Self::ProtectedWrite { address, region } => {
+                write!(f, "write to 0x{address:03X} in {region}")
+            }
+        }
+    }
+}
+
+/// What to do when an instruction writes to a [`ProtectedRegion`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WriteProtection {
+    /// Let the write happen, like the original interpreters did.
+    #[default]
+    Off,
+    /// Return [`Chip8Error::ProtectedWrite`] once the instruction finishes.
+    Error,
+    /// Stop the emulator once the instruction finishes, with
+    /// [`StopReason::ProtectedWrite`].
+    Break,
+}
+
+/// Memory that programs rarely mean to write to, so writes there are usually
+/// stray stores through a bad I.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtectedRegion {
+    /// The interpreter's memory below 0x200, which holds the fonts and stack.
+    Interpreter,
+    /// The bytes of the program that was loaded.
+    Program,
+}
+
+impl fmt::Display for ProtectedRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Interpreter => write!(f, "the interpreter's memory"),
+            Self::Program => write!(f, "the program's code"),
+        }
+    }
+}
+
+/// The debugger's state, kept by the [`Chip8`].
+#[derive(Debug, Default)]
+pub(crate) struct Debugger {
+    /// The addresses to break on.
+    breakpoints: BTreeSet<u16>,
+    /// The names of the instructions to break on.
+    break_on: BTreeSet<&'static str>,
+    /// Whether to stop when code is overwritten.
+    break_on_self_modifying_code: bool,
+    /// What to do about writes to protected memory.
+    write_protection: WriteProtection,
+    /// Set by a protected write with [`WriteProtection::Error`], and returned
+    /// once the instruction finishes.
+    protected_write: Option<Chip8Error>,
+    /// Set while the emulator is stopped.
+    stop: Option<Stop>,
+    /// Set by [`Chip8::resume`] so the instruction we stopped on runs instead
+    /// of stopping again straight away.
+    resuming: bool,
+    /// Set by [`Chip8::step`] to stop again after one instruction.
+    stepping: bool,
+}
+
+impl Debugger {
+    /// Forgets that the emulator stopped, like when the program is restarted.
+    pub(crate) fn clear_stop(&mut self) {
+        self.stop = None;
+        self.protected_write = None;
+        self.resuming = false;
+        self.stepping = false;
+    }
+
+    /// Returns true if the instruction at `address` shouldn't run, either
+    /// because we are already stopped or because it is one we break on.
+    pub(crate) fn should_stop(&mut self, address: u16, instruction: &'static str) -> bool {
+        if self.stop.is_some() {
+            return true;
+        }
+
+        if std::mem::take(&mut self.resuming) {
+            return false;
+        }
+
+        let reason = if std::mem::take(&mut self.stepping) {
+            StopReason::Step
+        } else if self.breakpoints.contains(&address) {
+            StopReason::Breakpoint
+        } else if self.break_on.contains(instruction) {
+            StopReason::Instruction(instruction)
+        } else {
+            return false;
+        };
+
+        self.stop = Some(Stop { address, reason });
+        true
+    }
+
+    /// Stops the emulator once the current instruction finishes if
+    /// [`Chip8::set_break_on_self_modifying_code`] is on. `address` is the
+    /// address of the next instruction.
+    pub(crate) fn self_modifying_write(&mut self, address: u16, written: u16) {
+        if self.break_on_self_modifying_code {
+            self.stop = Some(Stop {
+                address,
+                reason: StopReason::SelfModifyingWrite { address: written },
+            });
+        }
+    }
+}
+
+impl Chip8 {
+    /// Checks a write to `address` by the instruction that just ran against
+    /// [`Self::set_write_protection`].
+    pub(crate) fn check_write_protection(&mut self, address: u16) {
+        // Writes to the console and peripherals don't touch memory.
+        if self.debugger.write_protection == WriteProtection::Off
+            || (self.quirks.console_output && address == CONSOLE_ADDRESS)
+            || self.peripherals.contains(address)
+        {
+            return;
+        }
+
+        let in_program = |program: &Program| program.contains(address as usize);
+
+        let region = if (address as usize) < PROGRAM_OFFSET {
+            ProtectedRegion::Interpreter
+        } else if self.program.as_ref().is_some_and(in_program) {
+            ProtectedRegion::Program
+        } else {
+            return;
+        };
+
+        let pc = self.program_counter.wrapping_sub(2);
+
+        match self.debugger.write_protection {
+            WriteProtection::Off => {}
+            WriteProtection::Error => {
+                self.debugger.protected_write = Some(Chip8Error::ProtectedWrite {
+                    pc,
+                    address,
+                    region,
+                });
+            }
+            WriteProtection::Break => {
+                self.debugger.stop = Some(Stop {
+                    address: self.program_counter,
+                    reason: StopReason::ProtectedWrite { address, region },
+                });
+            }
+        }
+    }
+
+    /// Returns the error left by a protected write with
+    /// [`WriteProtection::Error`], if there was one.
+    pub(crate) fn take_protected_write(&mut self) -> Result<(), Chip8Error> {
+        match self.debugger.protected_write.take() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Sets what happens when an instruction writes below 0x200 or over the
+    /// program it is part of, which is almost always a bug when developing a
+    /// program. Off by default.
+    pub fn set_write_protection(&mut self, protection: WriteProtection) {
+        self.debugger.write_protection = protection;
+    }
+
+    /// Stops the emulator before the next instruction of this kind runs, given
+    /// the name of its [`Instruction`] variant like `"Draw"` or `"Random"`.
+    /// Names are matched ignoring case.
+    pub fn break_on_instruction(&mut self, name: &str) -> Result<(), Chip8Error> {
+        let name = Instruction::NAMES
+            .iter()
+            .find(|known| known.eq_ignore_ascii_case(name))
+            .ok_or_else(|| Chip8Error::UnknownInstructionName {
+                name: name.to_string(),
+            })?;
+
+        self.debugger.break_on.insert(name);
+        Ok(())
+    }
+
+    /// Stops the emulator before the instruction at any of these addresses
+    /// runs, replacing the breakpoints set before.
+    pub fn set_breakpoints(&mut self, addresses: impl IntoIterator<Item = u16>) {
+        self.debugger.breakpoints = addresses.into_iter().collect();
+    }
+
+    /// Stops breaking on any kind of instruction.
+    pub fn clear_instruction_breaks(&mut self) {
+        self.debugger.break_on.clear();
+    }
+
+    /// Stops the emulator after an instruction writes to memory that has
+    /// already run as code, which is how self-modifying programs patch their
+    /// own instructions. Needs [`Self::set_coverage_enabled`] to be on, which
+    /// keeps track of what has run.
+    pub fn set_break_on_self_modifying_code(&mut self, enabled: bool) {
+        self.debugger.break_on_self_modifying_code = enabled;
+    }
+
+    /// Returns where the emulator stopped, or `None` if it is running. While
+    /// stopped, [`Self::cycle`] does nothing.
+    pub fn stopped(&self) -> Option<Stop> {
+        self.debugger.stop
+    }
+
+    /// Carries on from where the emulator stopped, running the instruction it
+    /// stopped on.
+    pub fn resume(&mut self) {
+        if self.debugger.stop.take().is_some() {
+            self.debugger.resuming = true;
+        }
+    }
+
+    /// Runs the instruction the emulator stopped on, then stops again.
+    pub fn step(&mut self) {
+        if self.debugger.stop.take().is_some() {
+            self.debugger.resuming = true;
+            self.debugger.stepping = true;
+        }
+    }
+
+    /// Stops the emulator before the next instruction runs.
+    pub fn pause(&mut self) {
+        if self.debugger.stop.is_none() {
+            self.debugger.stop = Some(Stop {
+                address: self.program_counter,
+                reason: StopReason::Pause,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{ProtectedRegion, Stop, StopReason, WriteProtection};
+    use crate::{Chip8, Chip8Error};
+
+    #[test]
+    fn breaks_before_the_instruction_runs() {
+        // Sets V0, draws, then sets V1.
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8
+            .load_program(vec![0x60, 0x01, 0xD0, 0x01, 0x61, 0x01])
+            .unwrap();
+        chip_8.break_on_instruction("draw").unwrap();
+
+        chip_8.cycle().unwrap();
+        chip_8.cycle().unwrap();
+        chip_8.cycle().unwrap();
+
+        assert_eq!(
+            chip_8.stopped(),
+            Some(Stop {
+                address: 0x202,
+                reason: StopReason::Instruction("Draw")
+            })
+        );
+        assert_eq!(chip_8.stats().total_cycles, 1);
+
+        chip_8.resume();
+        chip_8.cycle().unwrap();
+        chip_8.cycle().unwrap();
+
+        assert_eq!(chip_8.stopped(), None);
+        assert_eq!(chip_8.registers[1], 1);
+    }
+
+    #[test]
+    fn breakpoints_and_steps_stop_at_addresses() {
+        // Sets V0 to V3 in turn.
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8
+            .load_program(vec![0x60, 0x01, 0x61, 0x01, 0x62, 0x01, 0x63, 0x01])
+            .unwrap();
+        chip_8.set_breakpoints([0x202]);
+
+        for _ in 0..3 {
+            chip_8.cycle().unwrap();
+        }
+
+        assert_eq!(
+            chip_8.stopped(),
+            Some(Stop {
+                address: 0x202,
+                reason: StopReason::Breakpoint
+            })
+        );
+
+        chip_8.step();
+
+        for _ in 0..3 {
+            chip_8.cycle().unwrap();
+        }
+
+        assert_eq!(
+            chip_8.stopped(),
+            Some(Stop {
+                address: 0x204,
+                reason: StopReason::Step
+            })
+        );
+        assert_eq!(chip_8.registers[..3], [1, 1, 0]);
+    }
+
+    #[test]
+    fn breaks_after_code_is_overwritten() {
+        // Points I at the next instruction and overwrites it with V0.
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8
+            .load_program(vec![0xA2, 0x02, 0xF0, 0x55, 0x12, 0x04])
+            .unwrap();
+        chip_8.set_coverage_enabled(true);
+        chip_8.set_break_on_self_modifying_code(true);
+
+        chip_8.cycle().unwrap();
+        chip_8.cycle().unwrap();
+
+        assert_eq!(
+            chip_8.stopped(),
+            Some(Stop {
+                address: 0x204,
+                reason: StopReason::SelfModifyingWrite { address: 0x202 }
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_names_are_rejected() {
+        let mut chip_8 = Chip8::new();
+
+        assert!(chip_8.break_on_instruction("Teleport").is_err());
+    }
+
+    #[test]
+    fn writes_to_protected_memory_are_caught() {
+        // Stores V0 at 0x100, then at 0x300, then over its own first byte.
+        let program = vec![
+            0xA1, 0x00, 0xF0, 0x55, 0xA3, 0x00, 0xF0, 0x55, 0xA2, 0x00, 0xF0, 0x55,
+        ];
+
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8.load_program(program.clone()).unwrap();
+        chip_8.set_write_protection(WriteProtection::Error);
+
+        chip_8.cycle().unwrap();
+        assert!(matches!(
+            chip_8.cycle(),
+            Err(Chip8Error::ProtectedWrite {
+                pc: 0x202,
+                address: 0x100,
+                region: ProtectedRegion::Interpreter,
+            })
+        ));
+
+        chip_8.load_program(program).unwrap();
+        chip_8.set_write_protection(WriteProtection::Break);
+        chip_8.set_program_counter(0x204);
+
+        for _ in 0..5 {
+            chip_8.cycle().unwrap();
+        }
+
+        assert_eq!(
+            chip_8.stopped(),
+            Some(Stop {
+                address: 0x20C,
+                reason: StopReason::ProtectedWrite {
+                    address: 0x200,
+                    region: ProtectedRegion::Program,
+                },
+            })
+        );
+    }
+}
@@ -0,0 +1,284 @@
+//! A disassembler that turns programs back into [Octo](https://github.com/JohnEarnest/Octo)
+//! statements, like the ones [`octo::assemble`](super::octo::assemble) reads.
+//!
+//! Only bytes that can be reached by following the program from its entry
+//! point are disassembled as instructions. Jumps, calls and skips are
+//! followed, and everything else (usually sprites) is written out as bytes.
+//! `BNNN` jumps depend on V0, so they can't be followed, but a
+//! [`Coverage`] from running the program fills in the gaps.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use super::coverage::Coverage;
+use super::Instruction;
+
+/// How many data bytes are written per line.
+const BYTES_PER_LINE: usize = 8;
+
+/// Disassembles a program loaded at `offset`, which is also where it starts
+/// running. Addresses that `coverage` says were executed are treated as code
+/// as well.
+///
+/// Each line ends with a comment giving the address and the raw bytes.
+pub fn disassemble(program: &[u8], offset: u16, coverage: Option<&Coverage>) -> String {
+    let disassembly = Disassembly::new(program, offset, coverage);
+    let mut source = String::new();
+    let mut address = offset;
+    let end = offset + program.len() as u16;
+
+    while address < end {
+        if disassembly.labels.contains(&address) {
+            let _ = writeln!(source, ": {}", label(address));
+        }
+
+        match disassembly.instruction_at(address) {
+            Some(instruction) => {
+                let _ = writeln!(
+                    source,
+                    "\t{:<24}# 0x{address:03X}  {:04X}",
+                    disassembly.statement(instruction),
+                    disassembly.word(address)
+                );
+                address += 2;
+            }
+            None => {
+                // Data runs until the next instruction or label.
+                let start = address;
+                address += 1;
+
+                while address < end
+                    && (address - start) < BYTES_PER_LINE as u16
+                    && !disassembly.code.contains(&address)
+                    && !disassembly.labels.contains(&address)
+                {
+                    address += 1;
+                }
+
+                let bytes: Vec<String> = (start..address)
+                    .map(|address| format!("0x{:02X}", disassembly.byte(address)))
+                    .collect();
+                let _ = writeln!(source, "\t{:<24}# 0x{start:03X}", bytes.join(" "));
+            }
+        }
+    }
+
+    source
+}
+
+/// Returns the address, raw word and decoded instruction of every instruction
+/// that can be reached, in address order. See [`disassemble`] for the arguments.
+pub(crate) fn reachable_instructions(
+    program: &[u8],
+    offset: u16,
+    coverage: Option<&Coverage>,
+) -> Vec<(u16, u16, Instruction)> {
+    let disassembly = Disassembly::new(program, offset, coverage);
+
+    disassembly
+        .code
+        .iter()
+        .filter_map(|&address| {
+            let instruction = disassembly.instruction_at(address)?;
+            Some((address, disassembly.word(address), instruction))
+        })
+        .collect()
+}
+
+fn label(address: u16) -> String {
+    format!("label_{address:03X}")
+}
+
+/// The results of following a program from its entry point.
+struct Disassembly<'a> {
+    program: &'a [u8],
+    offset: u16,
+    /// The addresses of the instructions that can be reached.
+    code: BTreeSet<u16>,
+    /// The addresses that are jumped to, called or pointed at by I.
+    labels: BTreeSet<u16>,
+}
+
+impl<'a> Disassembly<'a> {
+    fn new(program: &'a [u8], offset: u16, coverage: Option<&Coverage>) -> Self {
+        let mut disassembly = Self {
+            program,
+            offset,
+            code: BTreeSet::new(),
+            labels: BTreeSet::new(),
+        };
+
+        let mut pending = vec![offset];
+
+        if let Some(coverage) = coverage {
+            let end = offset + program.len() as u16;
+
+            pending.extend((offset..end).filter(|&address| coverage.instruction_ran(address)));
+        }
+
+        while let Some(address) = pending.pop() {
+            if disassembly.code.contains(&address) {
+                continue;
+            }
+
+            let Some(instruction) = disassembly.decode(address) else {
+                continue;
+            };
+
+            disassembly.code.insert(address);
+
+            let next = address + 2;
+            let skip = address + 4;
+
+            match instruction {
+                Instruction::Jump { nnn } => {
+                    disassembly.labels.insert(nnn);
+                    pending.push(nnn);
+                }
+                Instruction::Call { nnn } => {
+                    disassembly.labels.insert(nnn);
+                    pending.extend([nnn, next]);
+                }
+                Instruction::JumpWithPcOffset { nnn } => {
+                    disassembly.labels.insert(nnn);
+                }
+                Instruction::Return => {}
+                Instruction::SkipIfRegisterEquals { .. }
+                | Instruction::SkipIfRegisterNotEquals { .. }
+                | Instruction::SkipIfRegisterVxEqualsVy { .. }
+                | Instruction::SkipIfRegisterVxNotEqualsVy { .. }
+                | Instruction::SkipIfKeyPressed { .. }
+                | Instruction::SkipIfKeyNotPressed { .. } => pending.extend([next, skip]),
+                Instruction::SetIndexRegister { nnn } => {
+                    disassembly.labels.insert(nnn);
+                    pending.push(next);
+                }
+                _ => pending.push(next),
+            }
+        }
+
+        // Labels outside of the program can't be written.
+        let program_range = offset..offset + program.len() as u16;
+        disassembly
+            .labels
+            .retain(|address| program_range.contains(address));
+        disassembly
+    }
+
+    fn contains(&self, address: u16) -> bool {
+        (self.offset..self.offset + self.program.len() as u16).contains(&address)
+    }
+
+    fn byte(&self, address: u16) -> u8 {
+        self.program[(address - self.offset) as usize]
+    }
+
+    fn word(&self, address: u16) -> u16 {
+        (self.byte(address) as u16) << 8 | self.byte(address + 1) as u16
+    }
+
+    /// Decodes the instruction at `address`, if there is a valid one that
+    /// fits in the program.
+    fn decode(&self, address: u16) -> Option<Instruction> {
+        if !self.contains(address) || !self.contains(address + 1) {
+            return None;
+        }
+
+        Instruction::new(self.word(address)).ok()
+    }
+
+    /// Returns the instruction at `address` if it's code.
+    fn instruction_at(&self, address: u16) -> Option<Instruction> {
+        match self.code.contains(&address) {
+            true => self.decode(address),
+            false => None,
+        }
+    }
+
+    /// Returns a label for `address` if it has one, or the number otherwise.
+    fn target(&self, address: u16) -> String {
+        match self.labels.contains(&address) {
+            true => label(address),
+            false => format!("0x{address:03X}"),
+        }
+    }
+
+    /// Writes an instruction as an Octo statement.
+    fn statement(&self, instruction: Instruction) -> String {
+        match instruction {
+            Instruction::CallMachineCodeRoutine | Instruction::Unknown => {
+                unreachable!("{} is never decoded", instruction.name())
+            }
+            Instruction::Clear => "clear".to_string(),
+            Instruction::Return => "return".to_string(),
+            Instruction::Jump { nnn } => format!("jump {}", self.target(nnn)),
+            Instruction::Call { nnn } => format!(":call {}", self.target(nnn)),
+            // Octo's conditions say when the next statement runs, which is
+            // when the skip doesn't happen.
+            Instruction::SkipIfRegisterEquals { vx, nn } => {
+                format!("if v{vx:X} != 0x{nn:02X} then")
+            }
+            Instruction::SkipIfRegisterNotEquals { vx, nn } => {
+                format!("if v{vx:X} == 0x{nn:02X} then")
+            }
+            Instruction::SkipIfRegisterVxEqualsVy { vx, vy } => {
+                format!("if v{vx:X} != v{vy:X} then")
+            }
+            Instruction::SkipIfRegisterVxNotEqualsVy { vx, vy } => {
+                format!("if v{vx:X} == v{vy:X} then")
+            }
+            Instruction::SkipIfKeyPressed { vx } => format!("if v{vx:X} -key then"),
+            Instruction::SkipIfKeyNotPressed { vx } => format!("if v{vx:X} key then"),
+            Instruction::SetImmediate { vx, nn } => format!("v{vx:X} := 0x{nn:02X}"),
+            Instruction::AddImmediate { vx, nn } => format!("v{vx:X} += 0x{nn:02X}"),
+            Instruction::Copy { vx, vy } => format!("v{vx:X} := v{vy:X}"),
+            Instruction::BitwiseOr { vx, vy } => format!("v{vx:X} |= v{vy:X}"),
+            Instruction::BitwiseAnd { vx, vy } => format!("v{vx:X} &= v{vy:X}"),
+            Instruction::BitwiseXor { vx, vy } => format!("v{vx:X} ^= v{vy:X}"),
+            Instruction::Add { vx, vy } => format!("v{vx:X} += v{vy:X}"),
+            Instruction::Subtract { vx, vy } => format!("v{vx:X} -= v{vy:X}"),
+            Instruction::SetVxToVyMinusVx { vx, vy } => format!("v{vx:X} =- v{vy:X}"),
+            Instruction::RightShift { vx, vy } => format!("v{vx:X} >>= v{vy:X}"),
+            Instruction::LeftShift { vx, vy } => format!("v{vx:X} <<= v{vy:X}"),
+            Instruction::SetIndexRegister { nnn } => format!("i := {}", self.target(nnn)),
+            Instruction::JumpWithPcOffset { nnn } => format!("jump0 {}", self.target(nnn)),
+            Instruction::Random { vx, nn } => format!("v{vx:X} := random 0x{nn:02X}"),
+            Instruction::Draw { vx, vy, n } => format!("sprite v{vx:X} v{vy:X} {n}"),
+            Instruction::SetVxToDelayTimer { vx } => format!("v{vx:X} := delay"),
+            Instruction::AwaitKeyInput { vx } => format!("v{vx:X} := key"),
+            Instruction::SetDelayTimer { vx } => format!("delay := v{vx:X}"),
+            Instruction::SetSoundTimer { vx } => format!("buzzer := v{vx:X}"),
+            Instruction::AddToIndex { vx } => format!("i += v{vx:X}"),
+            Instruction::SetIndexToFontCharacter { vx } => format!("i := hex v{vx:X}"),
+            Instruction::SetIndexToBigFontCharacter { vx } => format!("i := bighex v{vx:X}"),
+            Instruction::SetIndexToBinaryCodedVx { vx } => format!("bcd v{vx:X}"),
+            Instruction::DumpRegisters { vx } => format!("save v{vx:X}"),
+            Instruction::LoadRegisters { vx } => format!("load v{vx:X}"),
+            Instruction::SaveFlags { vx } => format!("saveflags v{vx:X}"),
+            Instruction::LoadFlags { vx } => format!("loadflags v{vx:X}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::disassemble;
+
+    #[test]
+    fn sprites_are_not_disassembled() {
+        // Jumps over a sprite, draws it, then loops forever.
+        let program = [0x12, 0x04, 0xFF, 0x81, 0xA2, 0x02, 0xD0, 0x12, 0x12, 0x08];
+
+        assert_eq!(
+            disassemble(&program, 0x200, None),
+            "\tjump label_204          # 0x200  1204\n\
+             : label_202\n\
+             \t0xFF 0x81               # 0x202\n\
+             : label_204\n\
+             \ti := label_202          # 0x204  A202\n\
+             \tsprite v0 v1 2          # 0x206  D012\n\
+             : label_208\n\
+             \tjump label_208          # 0x208  1208\n"
+        );
+    }
+}
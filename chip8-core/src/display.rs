@@ -0,0 +1,95 @@
+//! The displays a program can draw to. Plain CHIP-8 has a monochrome screen
+//! of [`WIDTH`](crate::WIDTH) by [`HEIGHT`](crate::HEIGHT) pixels, but
+//! variants have taller or wider ones (see [`Resolution`]), and extensions
+//! like MegaChip bring bigger ones with their own colors, so the emulator
+//! talks to whichever is in use through [`Display`].
+
+use std::fmt;
+
+use super::palette::Palette;
+use super::screen::Screen;
+use super::{HEIGHT, WIDTH};
+
+/// The size of a screen in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resolution {
+    /// The number of pixels in each row.
+    pub width: usize,
+    /// The number of rows.
+    pub height: usize,
+}
+
+impl Resolution {
+    /// The 64x32 screen of plain CHIP-8.
+    pub const LORES: Self = Self {
+        width: WIDTH as usize,
+        height: HEIGHT as usize,
+    };
+
+    /// The 64x64 screen of hi-res CHIP-8 programs, which used two pages of
+    /// the original interpreter's display memory. See
+    /// [`Chip8::is_hi_res`](crate::Chip8::is_hi_res).
+    pub const TWO_PAGE: Self = Self {
+        width: 64,
+        height: 64,
+    };
+
+    /// The 128x64 screen of SUPER-CHIP's high resolution mode.
+    pub const HIRES: Self = Self {
+        width: 128,
+        height: 64,
+    };
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+/// A screen that instructions draw to and frontends show.
+pub trait Display: std::fmt::Debug {
+    /// The width of the display in pixels.
+    fn width(&self) -> usize;
+
+    /// The height of the display in pixels.
+    fn height(&self) -> usize;
+
+    /// The number of bit planes, which is 1 for monochrome displays and
+    /// displays that store colors directly.
+    fn planes(&self) -> usize {
+        1
+    }
+
+    /// Turns every pixel off.
+    fn clear(&mut self);
+
+    /// Converts the whole display into `0RGB` pixels, writing them to
+    /// `buffer` (which is laid out as `location = width*y + x`).
+    ///
+    /// Monochrome displays use the palette's colors, while displays with
+    /// colors of their own ignore it.
+    fn write_rgba(&self, buffer: &mut [u32], palette: &Palette);
+}
+
+impl Display for Screen {
+    fn width(&self) -> usize {
+        Screen::width(self)
+    }
+
+    fn height(&self) -> usize {
+        Screen::height(self)
+    }
+
+    fn planes(&self) -> usize {
+        Screen::planes(self)
+    }
+
+    fn clear(&mut self) {
+        Screen::clear(self);
+    }
+
+    fn write_rgba(&self, buffer: &mut [u32], palette: &Palette) {
+        self.render_rgba(buffer, palette);
+    }
+}
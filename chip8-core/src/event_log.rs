@@ -0,0 +1,195 @@
+//! A log of what happened in each 60Hz frame: the input, sprites drawn, the
+//! screen being cleared, the buzzer starting and stopping and the random
+//! numbers picked by `CXNN`. With the random seed it starts with, the log
+//! has everything needed to run the program again to the same result, and
+//! makes it easy to see where two runs went their separate ways.
+//!
+//! Logs are written as JSON Lines by [`EventLog`]. The first line holds the
+//! ROM's SHA-1 hash and the random seed, and each line after it is a frame:
+//!
+//! ```text
+//! {"rom_sha1":"0a1b...","random_seed":7}
+//! {"frame":0,"keys":32,"restart":false,"events":[{"type":"random","pc":518,"value":201},{"type":"draw","pc":522,"x":0,"y":0,"rows":5,"collided":false}]}
+//! ```
+
+use std::io::{self, Write};
+
+use super::replay::FrameInput;
+use super::Chip8;
+
+/// Something that happened while a frame ran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// The screen was cleared by the instruction at `pc`.
+    Clear {
+        /// The address of the instruction.
+        pc: u16,
+    },
+    /// A sprite was drawn by the instruction at `pc`.
+    Draw {
+        /// The address of the instruction.
+        pc: u16,
+        /// The x coordinate it was drawn at, before wrapping.
+        x: u8,
+        /// The y coordinate it was drawn at, before wrapping.
+        y: u8,
+        /// The N of `DXYN`.
+        rows: u8,
+        /// Whether it turned a pixel off.
+        collided: bool,
+    },
+    /// The buzzer started or stopped sounding.
+    Buzzer {
+        /// True if it started.
+        active: bool,
+    },
+    /// `CXNN` at `pc` picked a random number.
+    Random {
+        /// The address of the instruction.
+        pc: u16,
+        /// The number it picked, before it was masked with NN.
+        value: u8,
+    },
+}
+
+impl Event {
+    /// Returns the event as a JSON object.
+    pub fn to_json(&self) -> String {
+        match self {
+            Event::Clear { pc } => format!(r#"{{"type":"clear","pc":{pc}}}"#),
+            Event::Draw {
+                pc,
+                x,
+                y,
+                rows,
+                collided,
+            } => format!(
+                r#"{{"type":"draw","pc":{pc},"x":{x},"y":{y},"rows":{rows},"collided":{collided}}}"#
+            ),
+            Event::Buzzer { active } => format!(r#"{{"type":"buzzer","active":{active}}}"#),
+            Event::Random { pc, value } => {
+                format!(r#"{{"type":"random","pc":{pc},"value":{value}}}"#)
+            }
+        }
+    }
+}
+
+/// Writes an event log, a line per frame. See the
+/// [module documentation](self).
+#[derive(Debug)]
+pub struct EventLog<W: Write> {
+    writer: W,
+    /// The number of the next frame.
+    frame: u64,
+}
+
+impl<W: Write> EventLog<W> {
+    /// Starts a log of a run of the ROM with the hash `rom_sha1`, with the
+    /// random numbers seeded with `random_seed`.
+    pub fn new(mut writer: W, rom_sha1: [u8; 20], random_seed: u64) -> io::Result<Self> {
+        let rom_sha1: String = rom_sha1.iter().map(|byte| format!("{byte:02x}")).collect();
+        writeln!(
+            writer,
+            r#"{{"rom_sha1":"{rom_sha1}","random_seed":{random_seed}}}"#
+        )?;
+
+        Ok(Self { writer, frame: 0 })
+    }
+
+    /// Writes the next frame, which ran with `input` and had `events`
+    /// happen, like the ones from [`Chip8::take_events`].
+    pub fn write_frame(&mut self, input: FrameInput, events: &[Event]) -> io::Result<()> {
+        let events: Vec<String> = events.iter().map(Event::to_json).collect();
+
+        writeln!(
+            self.writer,
+            r#"{{"frame":{},"keys":{},"restart":{},"events":[{}]}}"#,
+            self.frame,
+            input.keys.0,
+            input.restart,
+            events.join(",")
+        )?;
+
+        self.frame += 1;
+        Ok(())
+    }
+
+    /// Writes out anything that is buffered.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Chip8 {
+    /// Starts or stops keeping the [`Event`]s that happen. Stopping clears
+    /// them.
+    pub fn set_event_logging(&mut self, enabled: bool) {
+        self.events = enabled.then(Vec::new);
+    }
+
+    /// Returns the [`Event`]s since the last call, if logging was turned on
+    /// with [`Self::set_event_logging`].
+    pub fn take_events(&mut self) -> Vec<Event> {
+        self.events.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Keeps `event` if events are being logged.
+    pub(crate) fn log_event(&mut self, event: Event) {
+        if let Some(events) = &mut self.events {
+            events.push(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::EventLog;
+    use crate::replay::FrameInput;
+    use crate::{Chip8, Keys};
+
+    #[test]
+    fn frames_are_logged_with_their_events() {
+        let program = vec![
+            0x00, 0xE0, // clear the screen
+            0xC0, 0x0F, // V0 = random & 0x0F
+            0x61, 0x02, // V1 = 2
+            0xF1, 0x18, // sound timer = V1
+            0xD0, 0x05, // draw at (V0, V0)
+            0x12, 0x0A, // loop forever
+        ];
+
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        let loaded_rom = chip_8.load_program(program).unwrap();
+        chip_8.set_random_seed(3);
+        chip_8.set_event_logging(true);
+
+        let mut log = EventLog::new(Vec::new(), loaded_rom.sha1, 3).unwrap();
+
+        for frame in 0..3 {
+            let input = FrameInput {
+                keys: Keys(frame),
+                restart: false,
+            };
+            input.run(&mut chip_8).unwrap();
+            log.write_frame(input, &chip_8.take_events()).unwrap();
+        }
+
+        let text = String::from_utf8(log.writer).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].ends_with(r#""random_seed":3}"#));
+
+        assert!(chip_8.take_events().is_empty());
+
+        assert!(lines[1].starts_with(r#"{"frame":0,"keys":0,"restart":false,"events":[{"type":"clear","pc":512},{"type":"random","pc":514,"value":"#));
+        assert!(lines[1].contains(r#"{"type":"buzzer","active":true}"#));
+        assert!(lines[1].contains(r#""rows":5,"collided":false}]}"#));
+        assert!(lines[2].ends_with(r#""events":[{"type":"buzzer","active":false}]}"#));
+        assert_eq!(
+            lines[3],
+            r#"{"frame":2,"keys":2,"restart":false,"events":[]}"#
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! Running a program with no window, for checking ROMs from scripts.
+
+use std::fmt;
+
+use super::{Chip8, Chip8Error, Frame};
+
+/// How a program run with [`run`] ended up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadlessRun {
+    /// How many instructions ran.
+    pub cycles: u64,
+    /// The address of the next instruction.
+    pub program_counter: u16,
+    /// True if the run stopped because it reached the address it was told
+    /// to run until.
+    pub reached_pc: bool,
+    /// The SHA-1 hash of the screen. See [`Frame::sha1_hex`](super::Frame::sha1_hex).
+    pub screen_hash: String,
+    /// What was on the screen, which can be printed with
+    /// [`Frame::to_ascii`].
+    pub frame: Frame,
+}
+
+impl fmt::Display for HeadlessRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Cycles: {}", self.cycles)?;
+        writeln!(f, "PC: 0x{:03X}", self.program_counter)?;
+        write!(f, "Screen: {}", self.screen_hash)
+    }
+}
+
+/// Runs a program loaded at `offset` with no keys pressed for `cycles`
+/// instructions, or until the next instruction is at `until_pc`.
+pub fn run(
+    program: &[u8],
+    offset: u16,
+    cycles: u64,
+    until_pc: Option<u16>,
+) -> Result<HeadlessRun, Chip8Error> {
+    let mut chip_8 = Chip8::new();
+    chip_8.initialize()?;
+    chip_8.load_program_at(offset as usize, program.to_vec())?;
+
+    let mut reached_pc = false;
+
+    for _ in 0..cycles {
+        if until_pc == Some(chip_8.program_counter) {
+            reached_pc = true;
+            break;
+        }
+
+        chip_8.cycle()?;
+    }
+
+    Ok(HeadlessRun {
+        cycles: chip_8.stats.total_cycles,
+        program_counter: chip_8.program_counter,
+        reached_pc: reached_pc || until_pc == Some(chip_8.program_counter),
+        screen_hash: chip_8.screen.frame().sha1_hex(),
+        frame: chip_8.screen.frame(),
+    })
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::run;
+    use crate::Frame;
+
+    #[test]
+    fn runs_until_the_pc_is_reached() {
+        // Draws its own first bytes as a sprite, then loops forever.
+        let program = [0x00, 0xE0, 0xA2, 0x00, 0xD0, 0x05, 0x12, 0x06];
+
+        let finished = run(&program, 0x200, 100, Some(0x206)).unwrap();
+        assert_eq!(finished.cycles, 3);
+        assert!(finished.reached_pc);
+        assert_ne!(finished.screen_hash, Frame::default().sha1_hex());
+
+        let timed_out = run(&program, 0x200, 2, Some(0x208)).unwrap();
+        assert_eq!(timed_out.cycles, 2);
+        assert!(!timed_out.reached_pc);
+        assert_eq!(timed_out.screen_hash, Frame::default().sha1_hex());
+    }
+}
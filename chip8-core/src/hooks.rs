@@ -0,0 +1,115 @@
+//! Callbacks that let code outside the emulator watch and change a program as
+//! it runs, like the scripts run with the `script` feature.
+
+use std::fmt;
+
+use super::Chip8;
+
+/// Callbacks run by the emulator. Each one is given the emulator, so it can
+/// read and change the registers and memory with the methods below.
+///
+/// Every callback does nothing by default.
+pub trait Hooks: fmt::Debug + Send {
+    /// Called after the instruction at `address` runs. `word` is the
+    /// instruction as it was read from memory.
+    fn on_instruction(&mut self, _chip_8: &mut Chip8, _address: u16, _word: u16) {}
+
+    /// Called after an instruction writes `value` to `address`.
+    fn on_memory_write(&mut self, _chip_8: &mut Chip8, _address: u16, _value: u8) {}
+
+    /// Called at the end of every [`Chip8::run_frame`].
+    fn on_frame(&mut self, _chip_8: &mut Chip8) {}
+}
+
+impl Chip8 {
+    /// Sets the callbacks to run, replacing any set before. `None` removes them.
+    pub fn set_hooks(&mut self, hooks: Option<Box<dyn Hooks>>) {
+        self.hooks = hooks;
+    }
+
+    /// Runs `callback` with the hooks, if there are any. The hooks are taken
+    /// out while they run, so nothing they do runs them again.
+    pub(crate) fn run_hooks(&mut self, callback: impl FnOnce(&mut dyn Hooks, &mut Chip8)) {
+        if let Some(mut hooks) = self.hooks.take() {
+            callback(hooks.as_mut(), self);
+
+            // The hooks may have replaced themselves.
+            self.hooks.get_or_insert(hooks);
+        }
+    }
+
+    /// Returns the value of register VX.
+    pub fn register(&self, vx: u8) -> u8 {
+        self.registers[vx as usize & 0xF]
+    }
+
+    /// Sets register VX.
+    pub fn set_register(&mut self, vx: u8, value: u8) {
+        self.registers[vx as usize & 0xF] = value;
+    }
+
+    /// Returns the index register, I.
+    pub fn index_register(&self) -> u16 {
+        self.index_register
+    }
+
+    /// Sets the index register, I.
+    pub fn set_index_register(&mut self, value: u16) {
+        self.index_register = value;
+    }
+
+    /// Returns the address of the next instruction to run.
+    pub fn program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    /// Sets the address of the next instruction to run.
+    pub fn set_program_counter(&mut self, address: u16) {
+        self.program_counter = self.memory.wrap(address);
+    }
+
+    /// Returns all of memory.
+    pub fn memory(&self) -> &[u8] {
+        self.memory.bytes()
+    }
+
+    /// Sets the byte at `address`. Addresses wrap around at the end of memory.
+    pub fn set_memory_byte(&mut self, address: u16, value: u8) {
+        self.memory.set_byte(address as usize, value);
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::Hooks;
+    use crate::Chip8;
+
+    /// Counts down V0 every frame, and copies every write to the byte after.
+    #[derive(Debug)]
+    struct Trainer;
+
+    impl Hooks for Trainer {
+        fn on_memory_write(&mut self, chip_8: &mut Chip8, address: u16, value: u8) {
+            chip_8.set_memory_byte(address + 1, value);
+        }
+
+        fn on_frame(&mut self, chip_8: &mut Chip8) {
+            chip_8.set_register(0, chip_8.register(0) - 1);
+        }
+    }
+
+    #[test]
+    fn hooks_see_and_change_the_program() {
+        // Sets V0 to 5, points I at 0x300, saves V0 there, then loops forever.
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8
+            .load_program(vec![0x60, 0x05, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x06])
+            .unwrap();
+        chip_8.set_hooks(Some(Box::new(Trainer)));
+        chip_8.run_frame().unwrap();
+
+        assert_eq!(chip_8.memory()[0x300..0x302], [5, 5]);
+        assert_eq!(chip_8.register(0), 4);
+    }
+}
@@ -0,0 +1,458 @@
+//! A precomputed dispatch table that executes raw instruction words without
+//! building an [`Instruction`](super::Instruction) first.
+//!
+//! This is the fast path used by [`Chip8::cycle`]. Each entry is picked from
+//! the first nibble of the word, and for the families that share a first
+//! nibble (`0`, `8`, `E` and `F`) from the last nibble or byte as well.
+
+use super::execution::DrawState;
+use crate::memory::HI_RES_CLEAR;
+use crate::{Chip8, Chip8Error};
+
+/// Executes a raw instruction word.
+type Handler = fn(&mut Chip8, u16) -> Result<(), Chip8Error>;
+
+/// An entry in the dispatch table.
+#[derive(Clone, Copy)]
+pub(crate) struct Opcode {
+    /// The name of the matching [`Instruction`](super::Instruction) variant.
+    pub(crate) name: &'static str,
+    pub(crate) handler: Handler,
+}
+
+/// How the rest of the word is used to pick an [`Opcode`] once the first
+/// nibble is known.
+enum Dispatch {
+    Opcode(Opcode),
+    ByLastNibble(&'static [Opcode; 16]),
+    ByLastByte(&'static [Opcode; 256]),
+}
+
+fn vx(raw: u16) -> u8 {
+    ((raw & 0x0F00) >> 8) as u8
+}
+
+fn vy(raw: u16) -> u8 {
+    ((raw & 0x00F0) >> 4) as u8
+}
+
+fn nnn(raw: u16) -> u16 {
+    raw & 0x0FFF
+}
+
+fn nn(raw: u16) -> u8 {
+    (raw & 0x00FF) as u8
+}
+
+fn n(raw: u16) -> u8 {
+    (raw & 0x000F) as u8
+}
+
+const INVALID: Opcode = Opcode {
+    name: "Invalid",
+    handler: |_, raw| Err(Chip8Error::InvalidInstruction { instruction: raw }),
+};
+
+// 0NNN is technically an instruction, but we do not want to implement it
+// because it runs machine-specific instructions and is not compatible with
+// every CHIP-8 machine.
+const NOT_COMPATIBLE: Opcode = Opcode {
+    name: "CallMachineCodeRoutine",
+    handler: |_, _| Err(Chip8Error::ProgramNotCompatible),
+};
+
+/// Also used for `0230` in hi-res programs, which the table maps to
+/// [`NOT_COMPATIBLE`] like the rest of `0NNN`.
+pub(crate) const CLEAR: Opcode = Opcode {
+    name: "Clear",
+    handler: |chip_8, _| {
+        chip_8.instruction_clear();
+        Ok(())
+    },
+};
+
+/// Builds a table indexed by the last byte of the word, where every byte
+/// not listed in `entries` maps to `fallback`.
+const fn by_last_byte(fallback: Opcode, entries: &[(u8, Opcode)]) -> [Opcode; 256] {
+    let mut table = [fallback; 256];
+    let mut i = 0;
+
+    while i < entries.len() {
+        table[entries[i].0 as usize] = entries[i].1;
+        i += 1;
+    }
+
+    table
+}
+
+static SYSTEM: [Opcode; 256] = by_last_byte(
+    NOT_COMPATIBLE,
+    &[
+        (0xE0, CLEAR),
+        (
+            0xEE,
+            Opcode {
+                name: "Return",
+                handler: |chip_8, _| chip_8.instruction_return(),
+            },
+        ),
+    ],
+);
+
+static ARITHMETIC: [Opcode; 16] = [
+    Opcode {
+        name: "Copy",
+        handler: |chip_8, raw| {
+            chip_8.instruction_copy(vx(raw), vy(raw));
+            Ok(())
+        },
+    },
+    Opcode {
+        name: "BitwiseOr",
+        handler: |chip_8, raw| {
+            chip_8.instruction_bitwise_or(vx(raw), vy(raw));
+            Ok(())
+        },
+    },
+    Opcode {
+        name: "BitwiseAnd",
+        handler: |chip_8, raw| {
+            chip_8.instruction_bitwise_and(vx(raw), vy(raw));
+            Ok(())
+        },
+    },
+    Opcode {
+        name: "BitwiseXor",
+        handler: |chip_8, raw| {
+            chip_8.instruction_bitwise_xor(vx(raw), vy(raw));
+            Ok(())
+        },
+    },
+    Opcode {
+        name: "Add",
+        handler: |chip_8, raw| {
+            chip_8.instruction_add(vx(raw), vy(raw));
+            Ok(())
+        },
+    },
+    Opcode {
+        name: "Subtract",
+        handler: |chip_8, raw| {
+            chip_8.instruction_subtract(vx(raw), vy(raw));
+            Ok(())
+        },
+    },
+    Opcode {
+        name: "RightShift",
+        handler: |chip_8, raw| {
+            chip_8.instruction_right_shift(vx(raw), vy(raw));
+            Ok(())
+        },
+    },
+    Opcode {
+        name: "SetVxToVyMinusVx",
+        handler: |chip_8, raw| {
+            chip_8.instruction_set_vx_to_vy_minus_vx(vx(raw), vy(raw));
+            Ok(())
+        },
+    },
+    INVALID,
+    INVALID,
+    INVALID,
+    INVALID,
+    INVALID,
+    INVALID,
+    Opcode {
+        name: "LeftShift",
+        handler: |chip_8, raw| {
+            chip_8.instruction_left_shift(vx(raw), vy(raw));
+            Ok(())
+        },
+    },
+    INVALID,
+];
+
+static KEYS: [Opcode; 256] = by_last_byte(
+    INVALID,
+    &[
+        (
+            0x9E,
+            Opcode {
+                name: "SkipIfKeyPressed",
+                handler: |chip_8, raw| {
+                    chip_8.instruction_skip_if_key_pressed(vx(raw));
+                    Ok(())
+                },
+            },
+        ),
+        (
+            0xA1,
+            Opcode {
+                name: "SkipIfKeyNotPressed",
+                handler: |chip_8, raw| {
+                    chip_8.instruction_skip_if_key_not_pressed(vx(raw));
+                    Ok(())
+                },
+            },
+        ),
+    ],
+);
+
+static MISC: [Opcode; 256] = by_last_byte(
+    INVALID,
+    &[
+        (
+            0x07,
+            Opcode {
+                name: "SetVxToDelayTimer",
+                handler: |chip_8, raw| {
+                    chip_8.instruction_set_vx_to_delay_timer(vx(raw));
+                    Ok(())
+                },
+            },
+        ),
+        (
+            0x0A,
+            Opcode {
+                name: "AwaitKeyInput",
+                handler: |chip_8, raw| {
+                    chip_8.instruction_await_key_input(vx(raw));
+                    Ok(())
+                },
+            },
+        ),
+        (
+            0x15,
+            Opcode {
+                name: "SetDelayTimer",
+                handler: |chip_8, raw| {
+                    chip_8.instruction_set_delay_timer(vx(raw));
+                    Ok(())
+                },
+            },
+        ),
+        (
+            0x18,
+            Opcode {
+                name: "SetSoundTimer",
+                handler: |chip_8, raw| {
+                    chip_8.instruction_set_sound_timer(vx(raw));
+                    Ok(())
+                },
+            },
+        ),
+        (
+            0x1E,
+            Opcode {
+                name: "AddToIndex",
+                handler: |chip_8, raw| {
+                    chip_8.instruction_add_to_index(vx(raw));
+                    Ok(())
+                },
+            },
+        ),
+        (
+            0x29,
+            Opcode {
+                name: "SetIndexToFontCharacter",
+                handler: |chip_8, raw| {
+                    chip_8.instruction_set_index_to_font_character(vx(raw));
+                    Ok(())
+                },
+            },
+        ),
+        (
+            0x30,
+            Opcode {
+                name: "SetIndexToBigFontCharacter",
+                handler: |chip_8, raw| {
+                    chip_8.instruction_set_index_to_big_font_character(vx(raw));
+                    Ok(())
+                },
+            },
+        ),
+        (
+            0x33,
+            Opcode {
+                name: "SetIndexToBinaryCodedVx",
+                handler: |chip_8, raw| {
+                    chip_8.instruction_set_index_to_binary_coded_vx(vx(raw));
+                    Ok(())
+                },
+            },
+        ),
+        (
+            0x55,
+            Opcode {
+                name: "DumpRegisters",
+                handler: |chip_8, raw| {
+                    chip_8.instruction_dump_registers(vx(raw));
+                    Ok(())
+                },
+            },
+        ),
+        (
+            0x65,
+            Opcode {
+                name: "LoadRegisters",
+                handler: |chip_8, raw| {
+                    chip_8.instruction_load_registers(vx(raw));
+                    Ok(())
+                },
+            },
+        ),
+        (
+            0x75,
+            Opcode {
+                name: "SaveFlags",
+                handler: |chip_8, raw| {
+                    chip_8.instruction_save_flags(vx(raw));
+                    Ok(())
+                },
+            },
+        ),
+        (
+            0x85,
+            Opcode {
+                name: "LoadFlags",
+                handler: |chip_8, raw| {
+                    chip_8.instruction_load_flags(vx(raw));
+                    Ok(())
+                },
+            },
+        ),
+    ],
+);
+
+/// Indexed by the first nibble of the word.
+static PRIMARY: [Dispatch; 16] = [
+    Dispatch::ByLastByte(&SYSTEM),
+    Dispatch::Opcode(Opcode {
+        name: "Jump",
+        handler: |chip_8, raw| {
+            chip_8.instruction_jump(nnn(raw));
+            Ok(())
+        },
+    }),
+    Dispatch::Opcode(Opcode {
+        name: "Call",
+        handler: |chip_8, raw| chip_8.instruction_call(nnn(raw)),
+    }),
+    Dispatch::Opcode(Opcode {
+        name: "SkipIfRegisterEquals",
+        handler: |chip_8, raw| {
+            chip_8.instruction_skip_if_register_equals(vx(raw), nn(raw));
+            Ok(())
+        },
+    }),
+    Dispatch::Opcode(Opcode {
+        name: "SkipIfRegisterNotEquals",
+        handler: |chip_8, raw| {
+            chip_8.instruction_skip_if_register_not_equals(vx(raw), nn(raw));
+            Ok(())
+        },
+    }),
+    Dispatch::Opcode(Opcode {
+        name: "SkipIfRegisterVxEqualsVy",
+        handler: |chip_8, raw| {
+            chip_8.instruction_skip_if_register_vx_equals_vy(vx(raw), vy(raw));
+            Ok(())
+        },
+    }),
+    Dispatch::Opcode(Opcode {
+        name: "SetImmediate",
+        handler: |chip_8, raw| {
+            chip_8.instruction_set_immediate(vx(raw), nn(raw));
+            Ok(())
+        },
+    }),
+    Dispatch::Opcode(Opcode {
+        name: "AddImmediate",
+        handler: |chip_8, raw| {
+            chip_8.instruction_add_immediate(vx(raw), nn(raw));
+            Ok(())
+        },
+    }),
+    Dispatch::ByLastNibble(&ARITHMETIC),
+    Dispatch::Opcode(Opcode {
+        name: "SkipIfRegisterVxNotEqualsVy",
+        handler: |chip_8, raw| {
+            chip_8.instruction_skip_if_register_vx_not_equals_vy(vx(raw), vy(raw));
+            Ok(())
+        },
+    }),
+    Dispatch::Opcode(Opcode {
+        name: "SetIndexRegister",
+        handler: |chip_8, raw| {
+            chip_8.instruction_set_index_register(nnn(raw));
+            Ok(())
+        },
+    }),
+    Dispatch::Opcode(Opcode {
+        name: "JumpWithPcOffset",
+        handler: |chip_8, raw| {
+            chip_8.instruction_jump_with_pc_offset(nnn(raw));
+            Ok(())
+        },
+    }),
+    Dispatch::Opcode(Opcode {
+        name: "Random",
+        handler: |chip_8, raw| {
+            chip_8.instruction_random(vx(raw), nn(raw));
+            Ok(())
+        },
+    }),
+    Dispatch::Opcode(Opcode {
+        name: "Draw",
+        handler: |chip_8, raw| {
+            let draw_state = chip_8.instruction_draw(vx(raw), vy(raw), n(raw));
+            chip_8.waiting_for_vblank = draw_state == DrawState::WaitingForVblank;
+            Ok(())
+        },
+    }),
+    Dispatch::ByLastByte(&KEYS),
+    Dispatch::ByLastByte(&MISC),
+];
+
+/// Looks up the table entry for a raw instruction word.
+pub(crate) fn lookup(raw: u16) -> &'static Opcode {
+    match &PRIMARY[(raw >> 12) as usize] {
+        Dispatch::Opcode(opcode) => opcode,
+        Dispatch::ByLastNibble(table) => &table[(raw & 0x000F) as usize],
+        Dispatch::ByLastByte(table) => &table[(raw & 0x00FF) as usize],
+    }
+}
+
+/// Like [`lookup`], but also finds the words that only mean something in the
+/// mode `chip_8` is in, like `0230` in hi-res programs.
+pub(crate) fn lookup_in_mode(chip_8: &Chip8, raw: u16) -> &'static Opcode {
+    if raw == HI_RES_CLEAR && chip_8.is_hi_res() {
+        return &CLEAR;
+    }
+
+    #[cfg(feature = "megachip")]
+    if let Some(opcode) = crate::megachip::lookup(chip_8, raw) {
+        return opcode;
+    }
+
+    lookup(raw)
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::lookup;
+    use crate::{Chip8Error, Instruction};
+
+    #[test]
+    fn table_matches_decoder_for_every_word() {
+        for raw in 0..=u16::MAX {
+            let expected = match Instruction::new(raw) {
+                Ok(instruction) => instruction.name(),
+                Err(Chip8Error::ProgramNotCompatible) => "CallMachineCodeRoutine",
+                Err(_) => "Invalid",
+            };
+
+            assert_eq!(lookup(raw).name, expected, "mismatch for 0x{raw:04X}");
+        }
+    }
+}
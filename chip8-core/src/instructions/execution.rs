@@ -0,0 +1,641 @@
+//! A module set aside for containing all of the methods on [`Chip8`] that emulate
+//! the execution of each instruction.
+//!
+//! Each `instruction_*` method runs the [`Instruction`](super::Instruction)
+//! it is named after, which is where they are documented.
+
+// The instructions are documented on `Instruction`.
+#![allow(missing_docs)]
+
+use crate::display::Resolution;
+use crate::event_log::Event;
+use crate::{memory::Font, rpl::RPL_FLAG_COUNT, Chip8, Chip8Error};
+
+/// What happened when a `DXYN` instruction was executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawState {
+    /// The sprite was drawn.
+    Drawn(DrawResult),
+    /// The display wait quirk is on and no vertical blank has happened since
+    /// the last draw, so nothing was drawn. The program counter was moved back
+    /// so the instruction runs again in the next frame.
+    WaitingForVblank,
+}
+
+/// How a sprite drawing went, which decides what VF is set to.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DrawResult {
+    /// True if any pixel that was on was turned off.
+    pub collided: bool,
+    /// How many rows of the sprite turned a pixel off.
+    pub collided_rows: u8,
+    /// How many rows of the sprite were left out because they were past the
+    /// bottom of the screen.
+    pub clipped_rows: u8,
+}
+
+impl Chip8 {
+    pub fn instruction_clear(&mut self) {
+        self.display_mut().clear();
+        self.log_event(Event::Clear {
+            pc: self.program_counter.wrapping_sub(2),
+        });
+    }
+
+    pub fn instruction_return(&mut self) -> Result<(), Chip8Error> {
+        self.program_counter = self.pop_call()?;
+        Ok(())
+    }
+
+    pub fn instruction_jump(&mut self, nnn: u16) {
+        self.program_counter = nnn;
+    }
+
+    pub fn instruction_call(&mut self, nnn: u16) -> Result<(), Chip8Error> {
+        self.push_call(nnn)?;
+        self.program_counter = nnn;
+        Ok(())
+    }
+
+    pub fn instruction_skip_if_register_equals(&mut self, vx: u8, nn: u8) {
+        if self.registers[vx as usize] == nn {
+            self.program_counter = self.program_counter.wrapping_add(2);
+        }
+    }
+
+    pub fn instruction_skip_if_register_not_equals(&mut self, vx: u8, nn: u8) {
+        if self.registers[vx as usize] != nn {
+            self.program_counter = self.program_counter.wrapping_add(2);
+        }
+    }
+
+    pub fn instruction_skip_if_register_vx_equals_vy(&mut self, vx: u8, vy: u8) {
+        if self.registers[vx as usize] == self.registers[vy as usize] {
+            self.program_counter = self.program_counter.wrapping_add(2);
+        }
+    }
+
+    pub fn instruction_set_immediate(&mut self, vx: u8, nn: u8) {
+        self.registers[vx as usize] = nn;
+    }
+
+    pub fn instruction_add_immediate(&mut self, vx: u8, nn: u8) {
+        // Unlike 8XY4, this never touches the carry flag.
+        self.registers[vx as usize] = self.registers[vx as usize].wrapping_add(nn);
+    }
+
+    pub fn instruction_copy(&mut self, vx: u8, vy: u8) {
+        self.registers[vx as usize] = self.registers[vy as usize]
+    }
+
+    pub fn instruction_bitwise_or(&mut self, vx: u8, vy: u8) {
+        self.registers[vx as usize] |= self.registers[vy as usize]
+    }
+
+    pub fn instruction_bitwise_and(&mut self, vx: u8, vy: u8) {
+        self.registers[vx as usize] &= self.registers[vy as usize]
+    }
+
+    pub fn instruction_bitwise_xor(&mut self, vx: u8, vy: u8) {
+        self.registers[vx as usize] ^= self.registers[vy as usize]
+    }
+
+    // The arithmetic instructions write VF after the result, so when VX is
+    // VF the flag is what's left in it.
+    pub fn instruction_add(&mut self, vx: u8, vy: u8) {
+        let (sum, overflow_occurred) =
+            self.registers[vx as usize].overflowing_add(self.registers[vy as usize]);
+
+        self.registers[vx as usize] = sum;
+        self.registers[0xF] = overflow_occurred as u8;
+    }
+
+    pub fn instruction_subtract(&mut self, vx: u8, vy: u8) {
+        let (difference, underflow_occurred) =
+            self.registers[vx as usize].overflowing_sub(self.registers[vy as usize]);
+
+        self.registers[vx as usize] = difference;
+        self.registers[0xF] = !underflow_occurred as u8;
+    }
+
+    pub fn instruction_right_shift(&mut self, vx: u8, vy: u8) {
+        let source = self.registers[self.shift_source(vx, vy) as usize];
+
+        self.registers[vx as usize] = source >> 1;
+        self.registers[0xF] = source & 0b0000_0001;
+    }
+
+    pub fn instruction_set_vx_to_vy_minus_vx(&mut self, vx: u8, vy: u8) {
+        let (difference, underflow_occurred) =
+            self.registers[vy as usize].overflowing_sub(self.registers[vx as usize]);
+
+        self.registers[vx as usize] = difference;
+        self.registers[0xF] = !underflow_occurred as u8;
+    }
+
+    pub fn instruction_left_shift(&mut self, vx: u8, vy: u8) {
+        let source = self.registers[self.shift_source(vx, vy) as usize];
+
+        self.registers[vx as usize] = source << 1;
+        self.registers[0xF] = source >> 7;
+    }
+
+    /// The register the shifts read from, which depends on
+    /// [`Quirks::shift_vy`](crate::quirks::Quirks::shift_vy).
+    fn shift_source(&self, vx: u8, vy: u8) -> u8 {
+        if self.quirks.shift_vy {
+            vy
+        } else {
+            vx
+        }
+    }
+
+    pub fn instruction_skip_if_register_vx_not_equals_vy(&mut self, vx: u8, vy: u8) {
+        if self.registers[vx as usize] != self.registers[vy as usize] {
+            self.program_counter = self.program_counter.wrapping_add(2);
+        }
+    }
+
+    pub fn instruction_set_index_register(&mut self, nnn: u16) {
+        self.index_register = nnn;
+    }
+    pub fn instruction_jump_with_pc_offset(&mut self, nnn: u16) {
+        self.program_counter = self.registers[0x0] as u16 + nnn;
+    }
+    pub fn instruction_random(&mut self, vx: u8, nn: u8) {
+        let value = rand::Rng::gen_range(&mut self.random.0, 0..=255);
+        self.registers[vx as usize] = value & nn;
+
+        self.log_event(Event::Random {
+            pc: self.program_counter.wrapping_sub(2),
+            value,
+        });
+    }
+
+    pub fn instruction_draw(&mut self, vx: u8, vy: u8, n: u8) -> DrawState {
+        // Wait for the start of the next frame by running this instruction again.
+        if self.quirks.display_wait && self.in_frame && !self.vblank {
+            self.program_counter = self.program_counter.wrapping_sub(2);
+            return DrawState::WaitingForVblank;
+        }
+
+        self.vblank = false;
+
+        let (x, y) = (self.registers[vx as usize], self.registers[vy as usize]);
+
+        #[cfg(feature = "megachip")]
+        if let Some(screen) = &mut self.megachip {
+            let result = screen.draw_sprite(&self.memory, self.index_register, x, y);
+            self.registers[0xF] = result.collided as u8;
+            self.log_draw(x, y, n, result);
+            return DrawState::Drawn(result);
+        }
+
+        // SUPER-CHIP's hires mode draws 16x16 sprites for DXY0, with two
+        // bytes per row.
+        let hires = self.screen.resolution() == Resolution::HIRES;
+        let (rows, bytes_per_row) = match (n, hires) {
+            (0, true) => (16, 2),
+            _ => (n as usize, 1),
+        };
+
+        let wrap = self.quirks.wrap_sprites;
+
+        // Only the rows that end up on the screen are read from memory.
+        let height = self.screen.height();
+        let top = y as usize % height;
+        let fitting = match wrap {
+            true => rows,
+            false => rows.min(height - top),
+        };
+
+        let mut sprite = [0; 16];
+
+        for (row, sprite_row) in sprite[..fitting].iter_mut().enumerate() {
+            let row_address = self.index_register.wrapping_add(row as u16 * bytes_per_row);
+
+            for byte in 0..bytes_per_row {
+                let sprite_address = row_address.wrapping_add(byte);
+                *sprite_row = *sprite_row << 8 | self.read_data(sprite_address) as u16;
+            }
+
+            // Narrow sprites go in the upper half of the row.
+            *sprite_row <<= 16 - 8 * bytes_per_row;
+        }
+
+        let result = self.screen.draw_sprite(x, y, &sprite[..rows], wrap);
+
+        // In hires mode VF is the number of rows that collided, counting the
+        // rows left off the bottom like SUPER-CHIP did, and everywhere else
+        // it is 1 if any did.
+        self.registers[0xF] = match hires {
+            true => result.collided_rows + result.clipped_rows,
+            false => result.collided as u8,
+        };
+
+        self.log_draw(x, y, n, result);
+        DrawState::Drawn(result)
+    }
+
+    fn log_draw(&mut self, x: u8, y: u8, rows: u8, result: DrawResult) {
+        self.log_event(Event::Draw {
+            pc: self.program_counter.wrapping_sub(2),
+            x,
+            y,
+            rows,
+            collided: result.collided,
+        });
+    }
+
+    pub fn instruction_skip_if_key_pressed(&mut self, vx: u8) {
+        if self.keypad.held.is_down(self.registers[vx as usize]) {
+            self.program_counter = self.program_counter.wrapping_add(2);
+        }
+    }
+
+    pub fn instruction_skip_if_key_not_pressed(&mut self, vx: u8) {
+        if !self.keypad.held.is_down(self.registers[vx as usize]) {
+            self.program_counter = self.program_counter.wrapping_add(2);
+        }
+    }
+
+    pub fn instruction_set_vx_to_delay_timer(&mut self, vx: u8) {
+        self.registers[vx as usize] = self.timers.delay()
+    }
+
+    pub fn instruction_await_key_input(&mut self, vx: u8) {
+        match self.keypad.take_key_release() {
+            Some(key) => self.registers[vx as usize] = key,
+            None => self.program_counter = self.program_counter.wrapping_sub(2),
+        }
+    }
+
+    pub fn instruction_set_delay_timer(&mut self, vx: u8) {
+        self.timers.set_delay(self.registers[vx as usize])
+    }
+
+    pub fn instruction_set_sound_timer(&mut self, vx: u8) {
+        let buzzer_was_active = self.is_buzzer_active();
+
+        self.timers.set_sound(self.registers[vx as usize]);
+
+        if self.timers.sound() > 0 {
+            self.stats.sound_activations += 1;
+        }
+
+        self.record_buzzer_change(buzzer_was_active);
+    }
+
+    pub fn instruction_add_to_index(&mut self, vx: u8) {
+        let sum = self.index_register as u32 + self.registers[vx as usize] as u32;
+        self.index_register = self.memory.wrap(sum as u16);
+
+        // Only the Amiga interpreter sets VF here.
+        if self.quirks.index_overflow_flag {
+            self.registers[0xF] = (sum > self.memory.size().last_address() as u32) as u8;
+        }
+    }
+
+    pub fn instruction_set_index_to_font_character(&mut self, vx: u8) {
+        self.index_register = self.memory.font_address(self.registers[vx as usize])
+    }
+
+    pub fn instruction_set_index_to_big_font_character(&mut self, vx: u8) {
+        // There are only 10 big digits, so the others wrap around.
+        self.index_register = self
+            .memory
+            .character_address(Font::Big, self.registers[vx as usize])
+    }
+
+    pub fn instruction_set_index_to_binary_coded_vx(&mut self, vx: u8) {
+        let value = self.registers[vx as usize];
+        let digits = [value / 100, value / 10 % 10, value % 10];
+
+        for (offset, digit) in (0..).zip(digits) {
+            self.write_data(self.index_register.wrapping_add(offset), digit);
+        }
+    }
+
+    pub fn instruction_dump_registers(&mut self, vx: u8) {
+        for i in 0x0..=vx {
+            self.write_data(
+                self.index_register.wrapping_add(i as u16),
+                self.registers[i as usize],
+            );
+        }
+
+        if self.quirks.increment_index {
+            self.index_register = self.index_register.wrapping_add(vx as u16 + 1);
+        }
+    }
+
+    pub fn instruction_load_registers(&mut self, vx: u8) {
+        for i in 0x0..=vx {
+            self.registers[i as usize] = self.read_data(self.index_register.wrapping_add(i as u16));
+        }
+
+        if self.quirks.increment_index {
+            self.index_register = self.index_register.wrapping_add(vx as u16 + 1);
+        }
+    }
+
+    pub fn instruction_save_flags(&mut self, vx: u8) {
+        let count = (vx as usize + 1).min(RPL_FLAG_COUNT);
+        self.rpl_flags[..count].copy_from_slice(&self.registers[..count]);
+        self.rpl_flags_changed = true;
+    }
+
+    pub fn instruction_load_flags(&mut self, vx: u8) {
+        let count = (vx as usize + 1).min(RPL_FLAG_COUNT);
+        self.registers[..count].copy_from_slice(&self.rpl_flags[..count]);
+    }
+
+    pub fn instruction_unknown(&mut self) -> Result<(), Chip8Error> {
+        Err(Chip8Error::InvalidInstruction {
+            instruction: self
+                .memory
+                .word(self.program_counter.wrapping_sub(2) as usize),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{DrawResult, DrawState};
+    use crate::display::Resolution;
+    use crate::screen::Screen;
+    use crate::{Chip8, Instruction};
+
+    /// Points I at the font's 0, draws it twice at (0, 0), then loops forever.
+    const DRAW_TWICE: [u8; 8] = [0xA0, 0x50, 0xD0, 0x05, 0xD0, 0x05, 0x12, 0x06];
+
+    fn chip_8_with_program(program: &[u8]) -> Chip8 {
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8.load_program(program.to_vec()).unwrap();
+        chip_8
+    }
+
+    /// Draws the font's 8 (a 4x5 box with a bar through the middle) at
+    /// (V0, V1) = (62, 30), then loops forever.
+    const DRAW_IN_CORNER: [u8; 12] = [
+        0x60, 0x3E, 0x61, 0x1E, 0xA0, 0x78, 0xD0, 0x15, 0x12, 0x08, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn sprites_are_clipped_at_the_edges_by_default() {
+        let mut chip_8 = chip_8_with_program(&DRAW_IN_CORNER);
+        chip_8.run_frame().unwrap();
+
+        let lit: Vec<(usize, usize)> = chip_8
+            .pixel_rows()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.enumerate()
+                    .filter(|(_, lit)| *lit)
+                    .map(move |(x, _)| (x, y))
+            })
+            .collect();
+
+        // Only the top 2 rows of the left 2 columns fit on the screen, and the
+        // second of those rows only has its leftmost pixel on.
+        assert_eq!(lit, [(62, 30), (63, 30), (62, 31)]);
+    }
+
+    #[test]
+    fn sprites_wrap_around_the_edges_with_the_quirk() {
+        let mut chip_8 = chip_8_with_program(&DRAW_IN_CORNER);
+        chip_8.quirks.wrap_sprites = true;
+        chip_8.run_frame().unwrap();
+
+        let pixel = |x, y| chip_8.pixel(x, y);
+
+        // The 8's top bar covers x = 62, 63, 0, 1 on row 30.
+        assert!(pixel(62, 30) && pixel(63, 30) && pixel(0, 30) && pixel(1, 30));
+        assert!(!pixel(2, 30));
+        // Its middle bar wraps around to the top of the screen, at row 0.
+        assert!(pixel(62, 0) && pixel(63, 0) && pixel(0, 0) && pixel(1, 0));
+        // And its bottom bar ends up at row 2.
+        assert!(pixel(62, 2) && pixel(63, 2) && pixel(0, 2) && pixel(1, 2));
+        assert_eq!(chip_8.pixel_rows().flatten().filter(|&lit| lit).count(), 16);
+    }
+
+    #[test]
+    fn big_font_digits_are_drawn_8_pixels_wide() {
+        // Sets V0 to 8, points I at its big digit and draws it at (0, 0).
+        let mut chip_8 = chip_8_with_program(&[0x60, 0x08, 0xF0, 0x30, 0xD1, 0x1A, 0x12, 0x06]);
+        chip_8.run_frame().unwrap();
+
+        assert_eq!(chip_8.index_register, 0x0A0 + 8 * 10);
+
+        let row = |y| (0..8).map(|x| chip_8.pixel(x, y)).collect::<Vec<_>>();
+
+        // The top of the 8 is 0x3C, and its two loops meet at rows 4 and 5 (0x7E).
+        assert_eq!(row(0), [false, false, true, true, true, true, false, false]);
+        assert_eq!(row(4), [false, true, true, true, true, true, true, false]);
+        assert_eq!(row(9), row(0));
+        assert!(!row(10).contains(&true));
+    }
+
+    #[test]
+    fn display_wait_draws_once_per_frame() {
+        let mut chip_8 = chip_8_with_program(&DRAW_TWICE);
+        chip_8.quirks.display_wait = true;
+
+        chip_8.run_frame().unwrap();
+        assert!(chip_8.pixel(0, 0));
+        assert_eq!(chip_8.program_counter, 0x204);
+
+        chip_8.run_frame().unwrap();
+        assert!(!chip_8.pixel(0, 0));
+        assert_eq!(chip_8.registers[0xF], 1);
+    }
+
+    #[test]
+    fn display_wait_doesnt_hold_up_cycles_run_on_their_own() {
+        let mut chip_8 = chip_8_with_program(&DRAW_TWICE);
+        chip_8.quirks.display_wait = true;
+
+        for _ in 0..3 {
+            chip_8.cycle().unwrap();
+        }
+
+        assert!(!chip_8.pixel(0, 0));
+        assert_eq!(chip_8.registers[0xF], 1);
+        assert_eq!(chip_8.program_counter, 0x206);
+    }
+
+    #[test]
+    fn without_display_wait_both_draws_happen_in_one_frame() {
+        let mut chip_8 = chip_8_with_program(&DRAW_TWICE);
+
+        chip_8.run_frame().unwrap();
+        assert!(!chip_8.pixel(0, 0));
+        assert_eq!(chip_8.program_counter, 0x206);
+    }
+
+    #[test]
+    fn draws_report_collisions_and_clipped_rows() {
+        let mut chip_8 = chip_8_with_program(&DRAW_TWICE);
+        chip_8.cycle().unwrap();
+
+        let first = chip_8.instruction_draw(0, 0, 5);
+        let second = chip_8.instruction_draw(0, 0, 5);
+        assert_eq!(
+            first,
+            DrawState::Drawn(DrawResult {
+                collided: false,
+                collided_rows: 0,
+                clipped_rows: 0,
+            })
+        );
+        assert_eq!(
+            second,
+            DrawState::Drawn(DrawResult {
+                collided: true,
+                collided_rows: 5,
+                clipped_rows: 0,
+            })
+        );
+
+        // Only 2 of the 5 rows fit at y = 30.
+        chip_8.registers[1] = 30;
+        let DrawState::Drawn(clipped) = chip_8.instruction_draw(0, 1, 5) else {
+            panic!("the display wait quirk is off");
+        };
+        assert_eq!(clipped.clipped_rows, 3);
+        assert!(!clipped.collided);
+    }
+
+    #[test]
+    fn hires_draws_16x16_sprites_and_counts_collided_rows() {
+        // Draws a solid 16x16 sprite at (0, 56) twice, then loops forever.
+        let mut chip_8 =
+            chip_8_with_program(&[0xA3, 0x00, 0x61, 0x38, 0xD0, 0x10, 0xD0, 0x10, 0x12, 0x08]);
+        chip_8.screen = Screen::new(Resolution::HIRES, 1);
+
+        for address in 0x300..0x320 {
+            chip_8.set_memory_byte(address, 0xFF);
+        }
+
+        for _ in 0..3 {
+            chip_8.cycle().unwrap();
+        }
+
+        let frame = chip_8.screen.frame();
+        assert!(frame.pixel(15, 56) && frame.pixel(15, 63));
+        assert!(!frame.pixel(16, 56) && !frame.pixel(0, 0));
+        // The bottom 8 rows didn't fit on the screen.
+        assert_eq!(chip_8.registers[0xF], 8);
+
+        chip_8.cycle().unwrap();
+        assert_eq!(chip_8.registers[0xF], 16);
+    }
+
+    /// Runs each of `instructions` on its own from `registers`, returning VF
+    /// afterwards.
+    fn flags_after(chip_8: &mut Chip8, registers: &[(u8, u8)], instructions: &[u16]) -> Vec<u8> {
+        instructions
+            .iter()
+            .map(|&raw| {
+                chip_8.registers = [0; 16];
+
+                for &(register, value) in registers {
+                    chip_8.registers[register as usize] = value;
+                }
+
+                chip_8.execute(Instruction::new(raw).unwrap()).unwrap();
+                chip_8.registers[0xF]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn vf_is_written_after_the_result() {
+        // Like the flags test ROM, uses VF as X so that the result is thrown
+        // away and only the flag is left.
+        let mut chip_8 = chip_8_with_program(&[]);
+        let flags = flags_after(
+            &mut chip_8,
+            &[(0x1, 0x10), (0xF, 0xF8)],
+            &[0x8F14, 0x8F15, 0x8F17, 0x8FF6, 0x8FFE],
+        );
+        assert_eq!(flags, [1, 1, 0, 0, 1]);
+
+        // And VF as Y, where the result goes to VX and the flag is still set.
+        let flags = flags_after(
+            &mut chip_8,
+            &[(0x1, 0x10), (0xF, 0xF8)],
+            &[0x81F4, 0x81F5, 0x81F7],
+        );
+        assert_eq!(flags, [1, 0, 1]);
+    }
+
+    #[test]
+    fn loads_and_stores_move_i_with_the_quirk() {
+        // Stores V0-V2 at 0x300, then loads V0-V2 from I again.
+        let program = [0xA3, 0x00, 0xF2, 0x55, 0xF2, 0x65];
+
+        let mut chip_8 = chip_8_with_program(&program);
+        chip_8.registers[..3].copy_from_slice(&[1, 2, 3]);
+
+        for _ in 0..3 {
+            chip_8.cycle().unwrap();
+        }
+
+        assert_eq!(chip_8.index_register, 0x300);
+        assert_eq!(chip_8.registers[..3], [1, 2, 3]);
+
+        let mut chip_8 = chip_8_with_program(&program);
+        chip_8.quirks.increment_index = true;
+        chip_8.registers[..3].copy_from_slice(&[1, 2, 3]);
+
+        for _ in 0..3 {
+            chip_8.cycle().unwrap();
+        }
+
+        // The load started past the store, where memory is still empty.
+        assert_eq!(chip_8.index_register, 0x306);
+        assert_eq!(chip_8.registers[..3], [0, 0, 0]);
+    }
+
+    #[test]
+    fn adding_to_i_sets_vf_on_overflow_with_the_quirk() {
+        let mut chip_8 = chip_8_with_program(&[]);
+        chip_8.quirks.index_overflow_flag = true;
+        chip_8.index_register = 0xFF8;
+        chip_8.registers[0x1] = 0x08;
+
+        let add = Instruction::new(0xF11E).unwrap();
+        chip_8.execute(add).unwrap();
+        assert_eq!((chip_8.index_register, chip_8.registers[0xF]), (0x000, 1));
+
+        chip_8.index_register = 0xFF0;
+        chip_8.execute(add).unwrap();
+        assert_eq!((chip_8.index_register, chip_8.registers[0xF]), (0xFF8, 0));
+
+        // Without it, VF is left alone, and I still wraps around.
+        chip_8.quirks.index_overflow_flag = false;
+        chip_8.registers[0xF] = 0x2A;
+        chip_8.index_register = 0xFFFF;
+        chip_8.execute(add).unwrap();
+        assert_eq!(
+            (chip_8.index_register, chip_8.registers[0xF]),
+            (0x0007, 0x2A)
+        );
+    }
+
+    #[test]
+    fn shifts_read_vy_with_the_quirk() {
+        let mut chip_8 = chip_8_with_program(&[]);
+        chip_8.registers[0x1] = 0x81;
+        chip_8.registers[0x2] = 0x02;
+
+        chip_8.execute(Instruction::new(0x8126).unwrap()).unwrap();
+        assert_eq!((chip_8.registers[0x1], chip_8.registers[0xF]), (0x40, 1));
+
+        chip_8.quirks.shift_vy = true;
+        chip_8.execute(Instruction::new(0x812E).unwrap()).unwrap();
+        assert_eq!((chip_8.registers[0x1], chip_8.registers[0xF]), (0x04, 0));
+    }
+}
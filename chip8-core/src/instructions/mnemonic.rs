@@ -0,0 +1,254 @@
+//! The textual form of instructions, using the mnemonics from
+//! [Cowgod's technical reference](http://devernay.free.fr/hacks/chip8/C8TECH10.HTM),
+//! like `LD V3, 0x2F` and `DRW V1, V2, 5`.
+//!
+//! Addresses and bytes are written in hex and sprite heights in decimal.
+//! When parsing, mnemonics and registers can be in any case, and numbers can
+//! be hex with a `0x` prefix or decimal.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::Instruction;
+use crate::Chip8Error;
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::CallMachineCodeRoutine => write!(f, "SYS"),
+            Self::Clear => write!(f, "CLS"),
+            Self::Return => write!(f, "RET"),
+            Self::Jump { nnn } => write!(f, "JP 0x{nnn:03X}"),
+            Self::Call { nnn } => write!(f, "CALL 0x{nnn:03X}"),
+            Self::SkipIfRegisterEquals { vx, nn } => write!(f, "SE V{vx:X}, 0x{nn:02X}"),
+            Self::SkipIfRegisterNotEquals { vx, nn } => write!(f, "SNE V{vx:X}, 0x{nn:02X}"),
+            Self::SkipIfRegisterVxEqualsVy { vx, vy } => write!(f, "SE V{vx:X}, V{vy:X}"),
+            Self::SetImmediate { vx, nn } => write!(f, "LD V{vx:X}, 0x{nn:02X}"),
+            Self::AddImmediate { vx, nn } => write!(f, "ADD V{vx:X}, 0x{nn:02X}"),
+            Self::Copy { vx, vy } => write!(f, "LD V{vx:X}, V{vy:X}"),
+            Self::BitwiseOr { vx, vy } => write!(f, "OR V{vx:X}, V{vy:X}"),
+            Self::BitwiseAnd { vx, vy } => write!(f, "AND V{vx:X}, V{vy:X}"),
+            Self::BitwiseXor { vx, vy } => write!(f, "XOR V{vx:X}, V{vy:X}"),
+            Self::Add { vx, vy } => write!(f, "ADD V{vx:X}, V{vy:X}"),
+            Self::Subtract { vx, vy } => write!(f, "SUB V{vx:X}, V{vy:X}"),
+            Self::RightShift { vx, vy } if vx == vy => write!(f, "SHR V{vx:X}"),
+            Self::RightShift { vx, vy } => write!(f, "SHR V{vx:X}, V{vy:X}"),
+            Self::SetVxToVyMinusVx { vx, vy } => write!(f, "SUBN V{vx:X}, V{vy:X}"),
+            Self::LeftShift { vx, vy } if vx == vy => write!(f, "SHL V{vx:X}"),
+            Self::LeftShift { vx, vy } => write!(f, "SHL V{vx:X}, V{vy:X}"),
+            Self::SkipIfRegisterVxNotEqualsVy { vx, vy } => write!(f, "SNE V{vx:X}, V{vy:X}"),
+            Self::SetIndexRegister { nnn } => write!(f, "LD I, 0x{nnn:03X}"),
+            Self::JumpWithPcOffset { nnn } => write!(f, "JP V0, 0x{nnn:03X}"),
+            Self::Random { vx, nn } => write!(f, "RND V{vx:X}, 0x{nn:02X}"),
+            Self::Draw { vx, vy, n } => write!(f, "DRW V{vx:X}, V{vy:X}, {n}"),
+            Self::SkipIfKeyPressed { vx } => write!(f, "SKP V{vx:X}"),
+            Self::SkipIfKeyNotPressed { vx } => write!(f, "SKNP V{vx:X}"),
+            Self::SetVxToDelayTimer { vx } => write!(f, "LD V{vx:X}, DT"),
+            Self::AwaitKeyInput { vx } => write!(f, "LD V{vx:X}, K"),
+            Self::SetDelayTimer { vx } => write!(f, "LD DT, V{vx:X}"),
+            Self::SetSoundTimer { vx } => write!(f, "LD ST, V{vx:X}"),
+            Self::AddToIndex { vx } => write!(f, "ADD I, V{vx:X}"),
+            Self::SetIndexToFontCharacter { vx } => write!(f, "LD F, V{vx:X}"),
+            Self::SetIndexToBigFontCharacter { vx } => write!(f, "LD HF, V{vx:X}"),
+            Self::SetIndexToBinaryCodedVx { vx } => write!(f, "LD B, V{vx:X}"),
+            Self::DumpRegisters { vx } => write!(f, "LD [I], V{vx:X}"),
+            Self::LoadRegisters { vx } => write!(f, "LD V{vx:X}, [I]"),
+            Self::SaveFlags { vx } => write!(f, "LD R, V{vx:X}"),
+            Self::LoadFlags { vx } => write!(f, "LD V{vx:X}, R"),
+            Self::Unknown => write!(f, "UNKNOWN"),
+        }
+    }
+}
+
+/// One of the comma separated things after a mnemonic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    Register(u8),
+    Number(u16),
+    /// `I`
+    Index,
+    /// `[I]`
+    IndexMemory,
+    /// `DT`
+    DelayTimer,
+    /// `ST`
+    SoundTimer,
+    /// `K`
+    Key,
+    /// `F`
+    Font,
+    /// `HF`
+    BigFont,
+    /// `B`
+    Bcd,
+    /// `R`
+    Flags,
+}
+
+impl FromStr for Operand {
+    type Err = String;
+
+    fn from_str(operand: &str) -> Result<Self, Self::Err> {
+        let operand = operand.to_ascii_uppercase();
+
+        let parsed = match operand.as_str() {
+            "I" => Self::Index,
+            "[I]" => Self::IndexMemory,
+            "DT" => Self::DelayTimer,
+            "ST" => Self::SoundTimer,
+            "K" => Self::Key,
+            "F" => Self::Font,
+            "HF" => Self::BigFont,
+            "B" => Self::Bcd,
+            "R" => Self::Flags,
+            _ => {
+                if let Some(register) = operand
+                    .strip_prefix('V')
+                    .filter(|digit| digit.len() == 1)
+                    .and_then(|digit| u8::from_str_radix(digit, 16).ok())
+                {
+                    Self::Register(register)
+                } else if let Some(hex) = operand.strip_prefix("0X") {
+                    u16::from_str_radix(hex, 16)
+                        .map(Self::Number)
+                        .map_err(|_| format!("invalid number {operand}"))?
+                } else {
+                    operand
+                        .parse()
+                        .map(Self::Number)
+                        .map_err(|_| format!("unknown operand {operand}"))?
+                }
+            }
+        };
+
+        Ok(parsed)
+    }
+}
+
+/// Checks that a number fits in `max`, like `0xFFF` for an address.
+fn fits(number: u16, max: u16) -> Result<u16, String> {
+    match number <= max {
+        true => Ok(number),
+        false => Err(format!("0x{number:X} is larger than 0x{max:X}")),
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = Chip8Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: String| Chip8Error::InvalidMnemonic {
+            text: text.to_string(),
+            reason,
+        };
+
+        let text = text.trim();
+        let (mnemonic, operands) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let mnemonic = mnemonic.to_ascii_uppercase();
+
+        let operands = match operands.trim() {
+            "" => Vec::new(),
+            operands => operands
+                .split(',')
+                .map(|operand| operand.trim().parse())
+                .collect::<Result<Vec<Operand>, _>>()
+                .map_err(invalid)?,
+        };
+
+        let address = |nnn: u16| fits(nnn, 0xFFF).map_err(invalid);
+        let byte = |nn: u16| fits(nn, 0xFF).map(|nn| nn as u8).map_err(invalid);
+
+        use Operand::*;
+
+        let instruction = match (mnemonic.as_str(), operands.as_slice()) {
+            ("SYS", []) => Self::CallMachineCodeRoutine,
+            ("CLS", []) => Self::Clear,
+            ("RET", []) => Self::Return,
+            ("JP", &[Number(nnn)]) => Self::Jump { nnn: address(nnn)? },
+            ("JP", &[Register(0), Number(nnn)]) => Self::JumpWithPcOffset { nnn: address(nnn)? },
+            ("CALL", &[Number(nnn)]) => Self::Call { nnn: address(nnn)? },
+            ("SE", &[Register(vx), Number(nn)]) => Self::SkipIfRegisterEquals { vx, nn: byte(nn)? },
+            ("SE", &[Register(vx), Register(vy)]) => Self::SkipIfRegisterVxEqualsVy { vx, vy },
+            ("SNE", &[Register(vx), Number(nn)]) => {
+                Self::SkipIfRegisterNotEquals { vx, nn: byte(nn)? }
+            }
+            ("SNE", &[Register(vx), Register(vy)]) => Self::SkipIfRegisterVxNotEqualsVy { vx, vy },
+            ("LD", &[Register(vx), Number(nn)]) => Self::SetImmediate { vx, nn: byte(nn)? },
+            ("LD", &[Register(vx), Register(vy)]) => Self::Copy { vx, vy },
+            ("LD", &[Index, Number(nnn)]) => Self::SetIndexRegister { nnn: address(nnn)? },
+            ("LD", &[Register(vx), DelayTimer]) => Self::SetVxToDelayTimer { vx },
+            ("LD", &[Register(vx), Key]) => Self::AwaitKeyInput { vx },
+            ("LD", &[DelayTimer, Register(vx)]) => Self::SetDelayTimer { vx },
+            ("LD", &[SoundTimer, Register(vx)]) => Self::SetSoundTimer { vx },
+            ("LD", &[Font, Register(vx)]) => Self::SetIndexToFontCharacter { vx },
+            ("LD", &[BigFont, Register(vx)]) => Self::SetIndexToBigFontCharacter { vx },
+            ("LD", &[Bcd, Register(vx)]) => Self::SetIndexToBinaryCodedVx { vx },
+            ("LD", &[IndexMemory, Register(vx)]) => Self::DumpRegisters { vx },
+            ("LD", &[Register(vx), IndexMemory]) => Self::LoadRegisters { vx },
+            ("LD", &[Flags, Register(vx)]) => Self::SaveFlags { vx },
+            ("LD", &[Register(vx), Flags]) => Self::LoadFlags { vx },
+            ("ADD", &[Register(vx), Number(nn)]) => Self::AddImmediate { vx, nn: byte(nn)? },
+            ("ADD", &[Register(vx), Register(vy)]) => Self::Add { vx, vy },
+            ("ADD", &[Index, Register(vx)]) => Self::AddToIndex { vx },
+            ("OR", &[Register(vx), Register(vy)]) => Self::BitwiseOr { vx, vy },
+            ("AND", &[Register(vx), Register(vy)]) => Self::BitwiseAnd { vx, vy },
+            ("XOR", &[Register(vx), Register(vy)]) => Self::BitwiseXor { vx, vy },
+            ("SUB", &[Register(vx), Register(vy)]) => Self::Subtract { vx, vy },
+            ("SUBN", &[Register(vx), Register(vy)]) => Self::SetVxToVyMinusVx { vx, vy },
+            // Without VY, the shifts use VX for both.
+            ("SHR", &[Register(vx)]) => Self::RightShift { vx, vy: vx },
+            ("SHR", &[Register(vx), Register(vy)]) => Self::RightShift { vx, vy },
+            ("SHL", &[Register(vx)]) => Self::LeftShift { vx, vy: vx },
+            ("SHL", &[Register(vx), Register(vy)]) => Self::LeftShift { vx, vy },
+            ("RND", &[Register(vx), Number(nn)]) => Self::Random { vx, nn: byte(nn)? },
+            ("DRW", &[Register(vx), Register(vy), Number(n)]) => Self::Draw {
+                vx,
+                vy,
+                n: fits(n, 0xF).map_err(invalid)? as u8,
+            },
+            ("SKP", &[Register(vx)]) => Self::SkipIfKeyPressed { vx },
+            ("SKNP", &[Register(vx)]) => Self::SkipIfKeyNotPressed { vx },
+            ("UNKNOWN", []) => Self::Unknown,
+            _ => return Err(invalid("unknown instruction".to_string())),
+        };
+
+        Ok(instruction)
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use proptest::prelude::*;
+
+    use crate::instructions::Instruction;
+
+    #[test]
+    fn mnemonics_are_parsed() {
+        assert_eq!(
+            "ld v3, 0x2F".parse::<Instruction>().unwrap(),
+            Instruction::SetImmediate { vx: 3, nn: 0x2F }
+        );
+        assert_eq!(
+            " DRW V1,V2 , 5".parse::<Instruction>().unwrap(),
+            Instruction::Draw { vx: 1, vy: 2, n: 5 }
+        );
+        assert_eq!(
+            "JP V0, 512".parse::<Instruction>().unwrap(),
+            Instruction::JumpWithPcOffset { nnn: 0x200 }
+        );
+
+        assert!("LD V3, 0x100".parse::<Instruction>().is_err());
+        assert!("DRW V1, V2, 16".parse::<Instruction>().is_err());
+        assert!("LD VG, 1".parse::<Instruction>().is_err());
+        assert!("JMP 0x200".parse::<Instruction>().is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn mnemonics_round_trip(raw: u16) {
+            if let Ok(instruction) = Instruction::new(raw) {
+                prop_assert_eq!(instruction.to_string().parse::<Instruction>().unwrap(), instruction);
+            }
+        }
+    }
+}
@@ -0,0 +1,484 @@
+//! Decoding, encoding and formatting of CHIP-8 instructions, and running them.
+//!
+//! [`Instruction`] is the one decoder shared by the emulator, the
+//! disassembler, the linter and the debugging tools. Words are decoded with
+//! [`Instruction::new`], encoded back with [`Instruction::encode`], and
+//! written and parsed as mnemonics like `LD V3, 0x2F` with its `Display` and
+//! `FromStr` implementations.
+use super::Chip8Error;
+
+pub(crate) mod dispatch;
+pub mod execution;
+mod mnemonic;
+
+/// A representation of all the CHIP-8 opcodes.
+///
+/// The names of the opcodes are unofficial and made by me. This means
+/// that they could be named inaccurately or be able to find resources
+/// on. Because of this, the hexadecimal representation is stated
+/// in the docs above each variant, using the following
+/// placeholder symbols to represent different meanings.  
+///
+/// The following information was taken from the
+/// [wikipedia page](https://en.wikipedia.org/wiki/CHIP-8#Opcode_table).
+///
+/// - NNN: address
+/// - NN: 8-bit constant
+/// - N: 4-bit constant
+/// - X and Y: 4-bit register identifier
+/// - PC : Program Counter
+/// - I : 16bit register (For memory address) (Similar to void pointer);
+/// - VN: One of the 16 available variables. N may be 0 to F (hexadecimal);
+// The fields are named after the placeholders above.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// Represented by 0NNN.
+    ///
+    /// This will remain unimplemented as it was used to pause
+    /// the chip-8 interpreter and run hardware specific code,
+    /// which was not used for most games.
+    #[allow(dead_code)]
+    CallMachineCodeRoutine,
+    /// Represented by `00E0`.
+    ///
+    /// Clears the screen.
+    Clear,
+    /// Represented by `00EE`.
+    ///
+    /// Returns from subroutine by popping the new program
+    /// counter from the stack.
+    Return,
+    /// Represented by `1NNN`.
+    ///
+    /// Sets program counter to NNN.
+    #[allow(missing_docs)]
+    Jump { nnn: u16 },
+    /// Represented by `2NNN`.
+    ///
+    /// Calls a subroutine by setting the program counter
+    /// to NNN and pushing the previous program counter to
+    /// the stack.
+    Call { nnn: u16 },
+    /// Represented by 3XNN.
+    /// Skips over the instruction if register VX == NN.
+    SkipIfRegisterEquals { vx: u8, nn: u8 },
+    /// Represented by 4XNN.
+    ///
+    /// Skips over the instruction if register VX != NN.
+    SkipIfRegisterNotEquals { vx: u8, nn: u8 },
+    /// Represented by 5XY0.
+    ///
+    /// Skips over the instruction if register VX == VY.
+    SkipIfRegisterVxEqualsVy { vx: u8, vy: u8 },
+    /// Represented by `6XNN`.
+    /// Sets register VX to NN.
+    #[allow(missing_docs)]
+    SetImmediate { vx: u8, nn: u8 },
+    /// Represented by `7XNN`.
+    ///
+    /// Adds the value NN to register VX, leaving VF alone on an overflow.
+    AddImmediate { vx: u8, nn: u8 },
+    /// Represented by `8XY0`
+    ///
+    /// Copies register VY to VX.
+    Copy { vx: u8, vy: u8 },
+    /// Represented by `8XY1`
+    ///
+    /// Sets VX = VX | VY
+    BitwiseOr { vx: u8, vy: u8 },
+    /// Represented by `8XY2`
+    ///
+    /// Sets VX = VX & VY
+    BitwiseAnd { vx: u8, vy: u8 },
+    /// Represented by `8XY3`
+    ///
+    /// Sets VX = VX ^ VY
+    BitwiseXor { vx: u8, vy: u8 },
+    /// Represented by `8XY4`
+    ///
+    /// Sets VX = VX + VY. Sets VF to 1 if there is an overflow (and
+    /// 0 if there is not).
+    Add { vx: u8, vy: u8 },
+    /// Represented by `8XY5`
+    ///
+    /// Sets VX = VX - VY. Sets VF to 0 if there is an underflow (and 1
+    /// if there is not.)
+    Subtract { vx: u8, vy: u8 },
+    /// Represented by `8XY6`
+    ///
+    /// Shifts VX right by 1, then stores the bit that was shifted out in VF.
+    /// With [`Quirks::shift_vy`](crate::quirks::Quirks::shift_vy) on, VY is
+    /// shifted into VX instead.
+    RightShift { vx: u8, vy: u8 },
+    /// Represented by `8XY7`
+    ///
+    /// Sets VX = VY - VX. VF is set to 0 if there is an underflow, and
+    /// is set to 1 if there is not.
+    SetVxToVyMinusVx { vx: u8, vy: u8 },
+    /// Represented by `8XYE`
+    ///
+    /// Shifts VX left by 1, then stores the bit that was shifted out in VF.
+    /// With [`Quirks::shift_vy`](crate::quirks::Quirks::shift_vy) on, VY is
+    /// shifted into VX instead.
+    LeftShift { vx: u8, vy: u8 },
+    /// Represented by 9XY0.
+    ///
+    /// Skips over the instruction if register VX != VY.
+    SkipIfRegisterVxNotEqualsVy { vx: u8, vy: u8 },
+    /// Represented by `ANNN`.
+    ///
+    /// Sets the index register to NNN.
+    SetIndexRegister { nnn: u16 },
+    /// Represented by `BNNN`.
+    ///
+    /// Sets the program counter to V0 + NNN
+    JumpWithPcOffset { nnn: u16 },
+    /// Represented by `CXNN`.
+    ///
+    /// Sets VX to the result of bitwise AND operation between a random number (who's
+    /// values are within 0..=255) and NN.
+    Random { vx: u8, nn: u8 },
+    /// Represented by `DXYN`.
+    ///
+    /// Draws a sprite at coordinates (VX, VY) that has width of 8 pixels and a
+    /// height of N pixels. Each row of 8 pixels is read as bit coded (so 1 byte per row),
+    /// starting from the memory location in the index register. VF is set to 1 if any
+    /// screen pixels are flipped from set to unset when the sprite is drawn, and 0 otherwise.
+    ///
+    /// In SUPER-CHIP's hires mode, `DXY0` draws a 16x16 sprite with 2 bytes per row
+    /// instead, and VF is set to the number of rows that flipped a pixel from set to
+    /// unset or were cut off by the bottom of the screen.
+    Draw { vx: u8, vy: u8, n: u8 },
+    /// Represented by `EX9E`.
+    ///
+    /// Skip next instruction if the key stored in VX is pressed.
+    SkipIfKeyPressed { vx: u8 },
+    /// Represented by `EXA1`.
+    ///
+    /// Skip next instruction if the key stored in VX is not pressed.
+    SkipIfKeyNotPressed { vx: u8 },
+    /// Represented by `FX07`.
+    ///
+    /// Sets VX to the value of the delay timer.
+    SetVxToDelayTimer { vx: u8 },
+    /// Represented by `FX0A`.
+    ///
+    /// A key press is awaited, and then stored in VX.
+    AwaitKeyInput { vx: u8 },
+    /// Represented by `FX15`.
+    ///
+    /// Sets the delay timer to VX.
+    SetDelayTimer { vx: u8 },
+    /// Represented by `FX18`.
+    ///
+    /// Sets the sound timer to VX.
+    SetSoundTimer { vx: u8 },
+    /// Represented by `FX1E`.
+    ///
+    /// Adds VX to the index register. See
+    /// [`Quirks::index_overflow_flag`](crate::quirks::Quirks::index_overflow_flag)
+    /// for when it sets VF.
+    AddToIndex { vx: u8 },
+    /// Represented by `FX29`.
+    ///
+    /// Sets the index register to the memory location of the small font's
+    /// character for the low nibble of VX.
+    SetIndexToFontCharacter { vx: u8 },
+    /// Represented by `FX30`.
+    ///
+    /// Sets the index register to the memory location for the 8x10 high
+    /// resolution digit stored in VX. This is a SUPER-CHIP instruction.
+    SetIndexToBigFontCharacter { vx: u8 },
+    /// Represented by `FX33`.
+    ///
+    /// Stores the binary-coded decimal representation of VX, with the
+    /// hundreds digit in memory at location in I, the tens digit at
+    /// location I+1, and the ones digit at location I+2
+    SetIndexToBinaryCodedVx { vx: u8 },
+    /// Represented by `FX55`.
+    ///
+    /// Stores the registers from V0 to VX (including VX) in memory, starting at
+    /// the address stored in the index register. (mem[I] = V0, mem[I+1] = V1, ...)
+    /// See [`Quirks::increment_index`](crate::quirks::Quirks::increment_index)
+    /// for what happens to I afterwards.
+    DumpRegisters { vx: u8 },
+    /// Represented by `FX65`.
+    ///
+    /// Loads the values V0 to VX (including VX) from memory. starting at
+    /// the address stored in the index register. (V0 = mem[I], V1 = mem[I+1], ...)
+    /// See [`Quirks::increment_index`](crate::quirks::Quirks::increment_index)
+    /// for what happens to I afterwards.
+    LoadRegisters { vx: u8 },
+    /// Represented by `FX75`.
+    ///
+    /// Stores V0 to VX (including VX) in the RPL user flags, which are kept
+    /// between runs. Only 8 flags exist, so registers past V7 are left out.
+    /// This is a SUPER-CHIP instruction.
+    SaveFlags { vx: u8 },
+    /// Represented by `FX85`.
+    ///
+    /// Loads V0 to VX (including VX) from the RPL user flags. Only 8 flags
+    /// exist, so registers past V7 are left alone. This is a SUPER-CHIP
+    /// instruction.
+    LoadFlags { vx: u8 },
+    /// A value that does not represent any instruction.
+    ///
+    /// If a raw instruction parses into this, it is
+    /// erroneous.
+    Unknown,
+}
+
+impl Instruction {
+    /// The names of every variant, as returned by [`Self::name`].
+    pub const NAMES: [&'static str; 39] = [
+        "CallMachineCodeRoutine",
+        "Clear",
+        "Return",
+        "Jump",
+        "Call",
+        "SkipIfRegisterEquals",
+        "SkipIfRegisterNotEquals",
+        "SkipIfRegisterVxEqualsVy",
+        "SetImmediate",
+        "AddImmediate",
+        "Copy",
+        "BitwiseOr",
+        "BitwiseAnd",
+        "BitwiseXor",
+        "Add",
+        "Subtract",
+        "RightShift",
+        "SetVxToVyMinusVx",
+        "LeftShift",
+        "SkipIfRegisterVxNotEqualsVy",
+        "SetIndexRegister",
+        "JumpWithPcOffset",
+        "Random",
+        "Draw",
+        "SkipIfKeyPressed",
+        "SkipIfKeyNotPressed",
+        "SetVxToDelayTimer",
+        "AwaitKeyInput",
+        "SetDelayTimer",
+        "SetSoundTimer",
+        "AddToIndex",
+        "SetIndexToFontCharacter",
+        "SetIndexToBigFontCharacter",
+        "SetIndexToBinaryCodedVx",
+        "DumpRegisters",
+        "LoadRegisters",
+        "SaveFlags",
+        "LoadFlags",
+        "Unknown",
+    ];
+
+    /// Returns the name of the instruction's variant, like `"Draw"`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::CallMachineCodeRoutine => "CallMachineCodeRoutine",
+            Self::Clear => "Clear",
+            Self::Return => "Return",
+            Self::Jump { .. } => "Jump",
+            Self::Call { .. } => "Call",
+            Self::SkipIfRegisterEquals { .. } => "SkipIfRegisterEquals",
+            Self::SkipIfRegisterNotEquals { .. } => "SkipIfRegisterNotEquals",
+            Self::SkipIfRegisterVxEqualsVy { .. } => "SkipIfRegisterVxEqualsVy",
+            Self::SetImmediate { .. } => "SetImmediate",
+            Self::AddImmediate { .. } => "AddImmediate",
+            Self::Copy { .. } => "Copy",
+            Self::BitwiseOr { .. } => "BitwiseOr",
+            Self::BitwiseAnd { .. } => "BitwiseAnd",
+            Self::BitwiseXor { .. } => "BitwiseXor",
+            Self::Add { .. } => "Add",
+            Self::Subtract { .. } => "Subtract",
+            Self::RightShift { .. } => "RightShift",
+            Self::SetVxToVyMinusVx { .. } => "SetVxToVyMinusVx",
+            Self::LeftShift { .. } => "LeftShift",
+            Self::SkipIfRegisterVxNotEqualsVy { .. } => "SkipIfRegisterVxNotEqualsVy",
+            Self::SetIndexRegister { .. } => "SetIndexRegister",
+            Self::JumpWithPcOffset { .. } => "JumpWithPcOffset",
+            Self::Random { .. } => "Random",
+            Self::Draw { .. } => "Draw",
+            Self::SkipIfKeyPressed { .. } => "SkipIfKeyPressed",
+            Self::SkipIfKeyNotPressed { .. } => "SkipIfKeyNotPressed",
+            Self::SetVxToDelayTimer { .. } => "SetVxToDelayTimer",
+            Self::AwaitKeyInput { .. } => "AwaitKeyInput",
+            Self::SetDelayTimer { .. } => "SetDelayTimer",
+            Self::SetSoundTimer { .. } => "SetSoundTimer",
+            Self::AddToIndex { .. } => "AddToIndex",
+            Self::SetIndexToFontCharacter { .. } => "SetIndexToFontCharacter",
+            Self::SetIndexToBigFontCharacter { .. } => "SetIndexToBigFontCharacter",
+            Self::SetIndexToBinaryCodedVx { .. } => "SetIndexToBinaryCodedVx",
+            Self::DumpRegisters { .. } => "DumpRegisters",
+            Self::LoadRegisters { .. } => "LoadRegisters",
+            Self::SaveFlags { .. } => "SaveFlags",
+            Self::LoadFlags { .. } => "LoadFlags",
+            Self::Unknown => "Unknown",
+        }
+    }
+
+    /// Returns the raw form of the instruction, the inverse of [`Self::new`].
+    ///
+    /// Bits that are ignored when decoding, like the last nibble of `5XY0`,
+    /// are encoded as 0. [`Self::CallMachineCodeRoutine`] is encoded as `0000` and
+    /// [`Self::Unknown`] as `FFFF`, neither of which [`Self::new`] accepts.
+    pub fn encode(&self) -> u16 {
+        let x = |vx: u8| (vx as u16 & 0xF) << 8;
+        let xy = |vx: u8, vy: u8| x(vx) | (vy as u16 & 0xF) << 4;
+        let xnn = |vx: u8, nn: u8| x(vx) | nn as u16;
+
+        match *self {
+            Self::CallMachineCodeRoutine => 0x0000,
+            Self::Clear => 0x00E0,
+            Self::Return => 0x00EE,
+            Self::Jump { nnn } => 0x1000 | nnn & 0x0FFF,
+            Self::Call { nnn } => 0x2000 | nnn & 0x0FFF,
+            Self::SkipIfRegisterEquals { vx, nn } => 0x3000 | xnn(vx, nn),
+            Self::SkipIfRegisterNotEquals { vx, nn } => 0x4000 | xnn(vx, nn),
+            Self::SkipIfRegisterVxEqualsVy { vx, vy } => 0x5000 | xy(vx, vy),
+            Self::SetImmediate { vx, nn } => 0x6000 | xnn(vx, nn),
+            Self::AddImmediate { vx, nn } => 0x7000 | xnn(vx, nn),
+            Self::Copy { vx, vy } => 0x8000 | xy(vx, vy),
+            Self::BitwiseOr { vx, vy } => 0x8001 | xy(vx, vy),
+            Self::BitwiseAnd { vx, vy } => 0x8002 | xy(vx, vy),
+            Self::BitwiseXor { vx, vy } => 0x8003 | xy(vx, vy),
+            Self::Add { vx, vy } => 0x8004 | xy(vx, vy),
+            Self::Subtract { vx, vy } => 0x8005 | xy(vx, vy),
+            Self::RightShift { vx, vy } => 0x8006 | xy(vx, vy),
+            Self::SetVxToVyMinusVx { vx, vy } => 0x8007 | xy(vx, vy),
+            Self::LeftShift { vx, vy } => 0x800E | xy(vx, vy),
+            Self::SkipIfRegisterVxNotEqualsVy { vx, vy } => 0x9000 | xy(vx, vy),
+            Self::SetIndexRegister { nnn } => 0xA000 | nnn & 0x0FFF,
+            Self::JumpWithPcOffset { nnn } => 0xB000 | nnn & 0x0FFF,
+            Self::Random { vx, nn } => 0xC000 | xnn(vx, nn),
+            Self::Draw { vx, vy, n } => 0xD000 | xy(vx, vy) | n as u16 & 0xF,
+            Self::SkipIfKeyPressed { vx } => 0xE09E | x(vx),
+            Self::SkipIfKeyNotPressed { vx } => 0xE0A1 | x(vx),
+            Self::SetVxToDelayTimer { vx } => 0xF007 | x(vx),
+            Self::AwaitKeyInput { vx } => 0xF00A | x(vx),
+            Self::SetDelayTimer { vx } => 0xF015 | x(vx),
+            Self::SetSoundTimer { vx } => 0xF018 | x(vx),
+            Self::AddToIndex { vx } => 0xF01E | x(vx),
+            Self::SetIndexToFontCharacter { vx } => 0xF029 | x(vx),
+            Self::SetIndexToBigFontCharacter { vx } => 0xF030 | x(vx),
+            Self::SetIndexToBinaryCodedVx { vx } => 0xF033 | x(vx),
+            Self::DumpRegisters { vx } => 0xF055 | x(vx),
+            Self::LoadRegisters { vx } => 0xF065 | x(vx),
+            Self::SaveFlags { vx } => 0xF075 | x(vx),
+            Self::LoadFlags { vx } => 0xF085 | x(vx),
+            Self::Unknown => 0xFFFF,
+        }
+    }
+
+    /// Decodes a raw instruction, returning an error for words that aren't
+    /// instructions.
+    pub fn new(raw: u16) -> Result<Instruction, Chip8Error> {
+        // We extract the first nibble of the raw u16,
+        // which helps us create a match tree to figure out
+        // which opcode a u16 is.
+        let first_nibble = raw >> 12;
+
+        //println!("{:04X}", first_nibble);
+        //println!("0x{:04X}", raw);
+
+        // These arguments are in the same location in memory each time,
+        // so it's just cleaner to write them all up here.
+        let vx = ((raw & 0x0F00) >> 8) as u8;
+        let vy = ((raw & 0x00F0) >> 4) as u8;
+        let nnn = raw & 0x0FFF;
+        let nn = (raw & 0x00FF) as u8;
+        let n = (raw & 0x000F) as u8;
+
+        let instruction = match first_nibble {
+            0x0 => {
+                let last_byte = raw & 0x00FF;
+
+                match last_byte {
+                    0xE0 => Self::Clear,
+                    0xEE => Self::Return,
+                    // 0NNN is technically an instruction, but we do not
+                    // want to implement it because it runs machine-specific
+                    // instructions and is not compatible with every
+                    // CHIP-8 machine.
+                    _ => return Err(Chip8Error::ProgramNotCompatible),
+                }
+            }
+            0x1 => Self::Jump { nnn },
+            0x2 => Self::Call { nnn },
+            0x3 => Self::SkipIfRegisterEquals { vx, nn },
+            0x4 => Self::SkipIfRegisterNotEquals { vx, nn },
+            0x5 => Self::SkipIfRegisterVxEqualsVy { vx, vy },
+            0x6 => Self::SetImmediate { vx, nn },
+            0x7 => Self::AddImmediate { vx, nn },
+            0x8 => {
+                let last_nibble = (raw & 0x000F) as u8;
+
+                match last_nibble {
+                    0x0 => Self::Copy { vx, vy },
+                    0x1 => Self::BitwiseOr { vx, vy },
+                    0x2 => Self::BitwiseAnd { vx, vy },
+                    0x3 => Self::BitwiseXor { vx, vy },
+                    0x4 => Self::Add { vx, vy },
+                    0x5 => Self::Subtract { vx, vy },
+                    0x6 => Self::RightShift { vx, vy },
+                    0x7 => Self::SetVxToVyMinusVx { vx, vy },
+                    0xE => Self::LeftShift { vx, vy },
+                    _ => return Err(Chip8Error::InvalidInstruction { instruction: raw }),
+                }
+            }
+            0x9 => Self::SkipIfRegisterVxNotEqualsVy { vx, vy },
+            0xA => Self::SetIndexRegister { nnn },
+            0xB => Self::JumpWithPcOffset { nnn },
+            0xC => Self::Random { vx, nn },
+            0xD => Self::Draw { vx, vy, n },
+            0xE => {
+                let last_byte = (raw & 0x00FF) as u8;
+
+                match last_byte {
+                    0x9E => Self::SkipIfKeyPressed { vx },
+                    0xA1 => Self::SkipIfKeyNotPressed { vx },
+                    _ => return Err(Chip8Error::InvalidInstruction { instruction: raw }),
+                }
+            }
+            0xF => {
+                let last_byte = (raw & 0x00FF) as u8;
+
+                match last_byte {
+                    0x07 => Self::SetVxToDelayTimer { vx },
+                    0x0A => Self::AwaitKeyInput { vx },
+                    0x15 => Self::SetDelayTimer { vx },
+                    0x18 => Self::SetSoundTimer { vx },
+                    0x1E => Self::AddToIndex { vx },
+                    0x29 => Self::SetIndexToFontCharacter { vx },
+                    0x30 => Self::SetIndexToBigFontCharacter { vx },
+                    0x33 => Self::SetIndexToBinaryCodedVx { vx },
+                    0x55 => Self::DumpRegisters { vx },
+                    0x65 => Self::LoadRegisters { vx },
+                    0x75 => Self::SaveFlags { vx },
+                    0x85 => Self::LoadFlags { vx },
+                    _ => return Err(Chip8Error::InvalidInstruction { instruction: raw }),
+                }
+            }
+            _ => return Err(Chip8Error::InvalidInstruction { instruction: raw }),
+        };
+
+        Ok(instruction)
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use proptest::prelude::*;
+
+    use super::Instruction;
+
+    proptest! {
+        #[test]
+        fn encoding_is_the_inverse_of_decoding(raw: u16) {
+            if let Ok(instruction) = Instruction::new(raw) {
+                prop_assert_eq!(Instruction::new(instruction.encode()).unwrap(), instruction);
+            }
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! The hex keypad, fed with key presses and releases as they happen.
+//!
+//! `EX9E` and `EXA1` look at which keys are held down when they run, while
+//! `FX0A` waits for a key to be pressed and let go, going through the presses
+//! and releases that happened since it last ran. Events that no `FX0A` is
+//! waiting for are dropped after the next instruction, so it never sees a key
+//! press from long before it started waiting.
+
+use std::collections::VecDeque;
+use std::ops::BitOr;
+
+use super::Chip8;
+
+/// A set of keys, like the ones held down, with one bit per key and key 0 in
+/// the lowest bit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Keys(pub u16);
+
+impl Keys {
+    /// Returns true if `key` is in the set. Keys above `0xF` never are.
+    pub fn is_down(self, key: u8) -> bool {
+        key < 16 && self.0 >> key & 1 == 1
+    }
+
+    /// Adds `key` to the set, unless it is above `0xF`.
+    pub fn press(&mut self, key: u8) {
+        if key < 16 {
+            self.0 |= 1 << key;
+        }
+    }
+
+    /// Removes `key` from the set.
+    pub fn release(&mut self, key: u8) {
+        if key < 16 {
+            self.0 &= !(1 << key);
+        }
+    }
+}
+
+impl BitOr for Keys {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// A key being pressed or let go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// The key, from `0x0` to `0xF`.
+    pub key: u8,
+    /// True if the key was pressed, and false if it was let go.
+    pub pressed: bool,
+}
+
+/// The keys held down and the events `FX0A` hasn't seen yet.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Keypad {
+    pub(crate) held: Keys,
+    events: VecDeque<KeyEvent>,
+    /// The key `FX0A` saw pressed, which it waits to be let go.
+    pressed_while_waiting: Option<u8>,
+}
+
+impl Keypad {
+    /// Forgets the events, after an instruction other than `FX0A` ran.
+    pub(crate) fn clear_events(&mut self) {
+        self.events.clear();
+        self.pressed_while_waiting = None;
+    }
+
+    /// Goes through the events for `FX0A`, returning the last key pressed once
+    /// it has been let go.
+    pub(crate) fn take_key_release(&mut self) -> Option<u8> {
+        while let Some(event) = self.events.pop_front() {
+            match (event.pressed, self.pressed_while_waiting) {
+                (true, _) => self.pressed_while_waiting = Some(event.key),
+                (false, Some(key)) if key == event.key => {
+                    self.pressed_while_waiting = None;
+                    return Some(key);
+                }
+                (false, _) => {}
+            }
+        }
+
+        None
+    }
+}
+
+impl Chip8 {
+    /// Presses or lets go of a key on the keypad. Pressing a key that is
+    /// already held down, or letting go of one that isn't, does nothing, and
+    /// keys above `0xF` are ignored.
+    pub fn key_event(&mut self, key: u8, pressed: bool) {
+        if key > 0xF || self.keypad.held.is_down(key) == pressed {
+            return;
+        }
+
+        match pressed {
+            true => self.keypad.held.press(key),
+            false => self.keypad.held.release(key),
+        }
+
+        self.keypad.events.push_back(KeyEvent { key, pressed });
+    }
+
+    /// Presses the keys in `keys` that aren't held down and lets go of the
+    /// ones that are but aren't in `keys`, for frontends that check which keys
+    /// are down every frame rather than getting events.
+    pub fn set_keys(&mut self, keys: Keys) {
+        for key in 0..16 {
+            self.key_event(key, keys.is_down(key));
+        }
+    }
+
+    /// Returns the keys held down.
+    pub fn keys(&self) -> Keys {
+        self.keypad.held
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use crate::Chip8;
+
+    #[test]
+    fn await_key_waits_for_a_release() {
+        // Waits for a key in V0, then loops forever.
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8.load_program(vec![0xF0, 0x0A, 0x12, 0x02]).unwrap();
+
+        chip_8.key_event(0x5, true);
+        chip_8.cycle().unwrap();
+        assert_eq!(chip_8.program_counter, 0x200);
+
+        // Only a release of the last key pressed counts.
+        chip_8.key_event(0x7, false);
+        chip_8.key_event(0x9, true);
+        chip_8.key_event(0x5, false);
+        chip_8.cycle().unwrap();
+        assert_eq!(chip_8.program_counter, 0x200);
+
+        chip_8.key_event(0x9, false);
+        chip_8.cycle().unwrap();
+        assert_eq!(chip_8.program_counter, 0x202);
+        assert_eq!(chip_8.registers[0], 0x9);
+        assert_eq!(chip_8.stats().key_reads, 3);
+    }
+}
@@ -0,0 +1,937 @@
+//! An implementation of an emulator for the CHIP-8 interpreter.
+//!
+//! This crate is the emulator on its own, with the tools built on it like the
+//! assembler, disassembler and debugger, but no window, audio or command line.
+//! The `chip_8_emulator` binary is the frontend built on top of it.
+
+#![warn(missing_docs, missing_debug_implementations)]
+// Not every part of the emulator API is used by the frontend.
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use tracing::{debug_span, trace, trace_span};
+
+use self::{
+    coverage::Coverage,
+    debugger::Debugger,
+    display::{Display, Resolution},
+    event_log::Event,
+    hooks::Hooks,
+    instructions::{
+        dispatch,
+        execution::{DrawResult, DrawState},
+    },
+    keypad::Keypad,
+    peripheral::Peripherals,
+    quirks::Quirks,
+    rpl::RPL_FLAG_COUNT,
+    save_state::History,
+    screen::Screen,
+    sound::BuzzerEvent,
+    stack::Stack,
+    stats::Stats,
+    timing::TimingModel,
+    tracepoint::Tracepoint,
+    watchdog::Throttled,
+};
+use memory::{Memory, Program, HI_RES_CLEAR};
+
+pub mod console;
+pub mod coverage;
+pub mod database;
+pub mod debugger;
+pub mod disassembler;
+pub mod display;
+pub mod event_log;
+pub mod headless;
+pub mod hooks;
+pub mod instructions;
+pub mod keypad;
+pub mod lint;
+pub mod lockstep;
+#[cfg(feature = "megachip")]
+pub mod megachip;
+mod memory;
+pub mod octo;
+pub mod palette;
+pub mod patch;
+pub mod peripheral;
+pub mod quirks;
+pub mod replay;
+pub mod rom;
+pub mod rpl;
+pub mod save_state;
+pub mod scheduler;
+mod screen;
+pub mod sound;
+pub mod sprite;
+mod stack;
+pub mod stats;
+pub mod test_vectors;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod timers;
+pub mod timing;
+pub mod trace_log;
+pub mod tracepoint;
+pub mod watchdog;
+
+pub use self::instructions::Instruction;
+pub use self::keypad::Keys;
+pub use self::memory::{Font, MemorySize, Preserve, FONT_SET, PROGRAM_OFFSET};
+pub use self::screen::Frame;
+pub use self::stack::{CallFrame, StackStorage, DEFAULT_STACK_DEPTH, MAX_STACK_DEPTH};
+pub use self::timers::Timers;
+
+/// The width of the plain CHIP-8 screen in pixels. Other screens can be
+/// wider, see [`Chip8::resolution`].
+pub const WIDTH: u32 = 64;
+/// The height of the plain CHIP-8 screen in pixels. Other screens can be
+/// taller, see [`Chip8::resolution`].
+pub const HEIGHT: u32 = 32;
+
+/// An error used for errors related to the operation of the CHIP-8 emulator.
+#[allow(missing_docs)]
+#[derive(Debug, thiserror::Error)]
+pub enum Chip8Error {
+    #[error("Interpreter memory is uninitialized")]
+    InterpreterMemoryIsUninitialized,
+    #[error("Interpreter memory already initialized")]
+    InterpreterMemoryAlreadyInitialized,
+    #[error("Program not loaded")]
+    ProgramNotLoaded,
+    /// Used when a program is loaded at an offset outside of the program
+    /// area of memory (0x200 to the end of memory).
+    #[error("Invalid load offset 0x{offset:03X}")]
+    InvalidLoadOffset { offset: usize },
+    /// Used when a program does not fit in memory after the offset it is
+    /// loaded at.
+    #[error("Program is {size} bytes, but only {capacity} bytes are available")]
+    ProgramTooLarge { size: usize, capacity: usize },
+    /// Used when a font given to [`Chip8::set_font`] is the wrong size or
+    /// doesn't fit in memory.
+    #[error("Invalid font: {reason}")]
+    InvalidFont { reason: String },
+    /// Used when a line of a ROM database can't be parsed.
+    #[error("Invalid ROM database entry on line {line}: {reason}")]
+    InvalidRomDatabaseEntry { line: usize, reason: String },
+    /// Used by [`Chip8::step_back`] when there are no earlier states left.
+    #[error("No earlier states in the history")]
+    HistoryEmpty,
+    /// Used when a [`SaveState`](save_state::SaveState) doesn't match this emulator.
+    #[error("Invalid save state")]
+    InvalidSaveState,
+    /// Used when a save state given as JSON to
+    /// [`SaveState::from_json`](save_state::SaveState::from_json) isn't one.
+    #[cfg(feature = "json")]
+    #[error("Invalid save state JSON: {reason}")]
+    InvalidStateJson { reason: String },
+    /// Used when a [`Replay`](replay::Replay) can't be read or played back.
+    #[error("Invalid replay: {reason}")]
+    InvalidReplay { reason: String },
+    /// Used when a [`Peripheral`](peripheral::Peripheral) is mapped outside
+    /// of memory or over another one.
+    #[error("Invalid peripheral mapping: {reason}")]
+    InvalidPeripheralMapping { reason: String },
+    /// Used when an instruction is named that isn't an
+    /// [`Instruction`] variant.
+    #[error("Unknown instruction {name}")]
+    UnknownInstructionName { name: String },
+    /// Used when a [`Tracepoint`]'s message can't be parsed.
+    #[error("Invalid tracepoint: {reason}")]
+    InvalidTracepoint { reason: String },
+    /// Used when a line of a test vector file can't be parsed.
+    #[error("Invalid test vector on line {line}: {reason}")]
+    InvalidTestVector { line: usize, reason: String },
+    /// Used when an IPS or BPS patch can't be applied.
+    #[error("Invalid patch: {reason}")]
+    InvalidPatch { reason: String },
+    /// Used when a line of an execution log can't be parsed.
+    #[error("Invalid trace log on line {line}: {reason}")]
+    InvalidTraceLog { line: usize, reason: String },
+    /// Used when an instruction's mnemonic can't be parsed.
+    #[error("Invalid instruction {text:?}: {reason}")]
+    InvalidMnemonic { text: String, reason: String },
+    /// Used when flags saved by Octo can't be read by
+    /// [`rpl::flags_from_octo_json`].
+    #[cfg(feature = "json")]
+    #[error("Invalid Octo flags: {reason}")]
+    InvalidOctoFlags { reason: String },
+    /// Used when Octo source code can't be assembled.
+    #[error("Octo assembly error on line {line}: {message}")]
+    Assembly { line: usize, message: String },
+    /// Used when a call is made with the stack already full. `pc` is the
+    /// address of the call, and `depth` is how many calls were on the stack.
+    #[error("Stack overflow at 0x{pc:03X} with {depth} calls on the stack")]
+    StackOverflow { pc: u16, depth: usize },
+    /// Used when a return is made with the stack empty. `pc` is the address
+    /// of the return.
+    #[error("Stack underflow at 0x{pc:03X}")]
+    StackUnderflow { pc: u16 },
+    /// Triggered when the emulator encounters instruction 0NNN.
+    /// This would normally pause the chip-8 interpreter and run
+    /// hardware-dependant code, and is not used for the majority of roms.
+    #[error("Program not compatible")]
+    ProgramNotCompatible,
+    /// Used by [`Chip8::cycle_checked`] when the program counter has run past
+    /// the end of memory.
+    #[error("Program counter 0x{pc:04X} is past the end of memory")]
+    ProgramCounterOutOfBounds { pc: u16 },
+    /// Used when write protection is set to
+    /// [`WriteProtection::Error`](debugger::WriteProtection::Error) and an
+    /// instruction writes to protected memory. `pc` is the address of the
+    /// instruction.
+    #[error("0x{pc:03X} wrote to 0x{address:03X}, which is in {region}")]
+    ProtectedWrite {
+        pc: u16,
+        address: u16,
+        region: debugger::ProtectedRegion,
+    },
+    /// Used when the raw word does not translate to an instruction,
+    /// like 0xFFFF.
+    #[error("Invalid Instruction 0x{instruction:04X}")]
+    InvalidInstruction { instruction: u16 },
+    /// Used when the execution code for an instruction is unimplemented.
+    #[error("Unimplemented instruction {instruction:#?}")]
+    UnimplementedInstruction { instruction: Instruction },
+}
+
+/// The clock rate [`Chip8`] assumes when deciding how often to tick the timers,
+/// until told otherwise with [`Chip8::set_cycles_per_second`].
+pub const DEFAULT_CYCLES_PER_SECOND: u32 = 720;
+
+/// Decides which cycles the 60Hz timers tick on, based on how many units of
+/// time (see [`TimingModel::units_per_second`]) pass per second.
+#[derive(Debug, Copy, Clone)]
+struct TimerClock {
+    /// `None` if the timers are only ticked manually with [`Chip8::tick_timers`].
+    units_per_second: Option<u32>,
+    /// Goes up by 60 for every unit of time, and the timers tick every time it
+    /// passes `units_per_second`. This keeps the timers at exactly 60Hz even
+    /// when the clock rate isn't a multiple of 60.
+    accumulator: u32,
+}
+
+impl Default for TimerClock {
+    fn default() -> Self {
+        Self {
+            units_per_second: Some(DEFAULT_CYCLES_PER_SECOND),
+            accumulator: 0,
+        }
+    }
+}
+
+impl TimerClock {
+    /// Advances the clock by an instruction that took `cost` units of time,
+    /// returning how many times the timers should tick.
+    fn advance(&mut self, cost: u32) -> u32 {
+        let Some(units_per_second) = self.units_per_second else {
+            return 0;
+        };
+
+        self.accumulator += 60 * cost;
+
+        let ticks = self.accumulator / units_per_second;
+        self.accumulator %= units_per_second;
+        ticks
+    }
+}
+
+/// The span an instruction runs in, with its address and opcode as fields so
+/// that logs can be filtered by them.
+fn instruction_span(address: u16, word: u16, name: &'static str) -> tracing::Span {
+    trace_span!(
+        "instruction",
+        pc = format_args!("0x{address:03X}"),
+        opcode = format_args!("0x{word:04X}"),
+        instruction = name
+    )
+}
+
+/// The random number generator behind `CXNN`. It is seeded by the operating
+/// system unless [`Chip8::set_random_seed`] is used.
+#[derive(Debug, Clone)]
+struct Random(StdRng);
+
+impl Default for Random {
+    fn default() -> Self {
+        Self(StdRng::from_entropy())
+    }
+}
+
+/// A frame being run a cycle at a time. See [`Chip8::begin_frame`].
+#[derive(Debug)]
+pub(crate) struct FrameRun {
+    /// The clock to put back once the frame is over.
+    timer_clock: TimerClock,
+    /// The units of time (see [`TimingModel::units_per_second`]) left to
+    /// spend on instructions this frame.
+    time_left: u32,
+    /// See [`Chip8::set_watchdog`] for more information.
+    instructions_since_draw: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum EmulatorState {
+    #[default]
+    InterpreterMemoryUninitialized,
+    InterpreterMemoryInitialized,
+    ProgramLoaded,
+}
+
+impl EmulatorState {
+    fn change_states(&mut self, new_state: EmulatorState) -> Result<(), Chip8Error> {
+        if new_state == Self::InterpreterMemoryUninitialized {
+            unreachable!("Cannot uninitialize uninitialized memory.")
+        }
+
+        // If we don't meet any invalid states, move to the next state.
+        *self = new_state;
+        Ok(())
+    }
+}
+
+/// A struct used to emulate a CHIP-8 interpreter.
+#[allow(dead_code)]
+#[derive(Debug, Default)]
+pub struct Chip8 {
+    /// See [`Memory`] for more information.
+    memory: Memory,
+    /// See [`Screen`] for more information.
+    screen: Screen,
+    /// The registers used for emulating general purpose registers V0-VE.
+    ///
+    /// Starts with general purpose registers V0-VE. Fhe last register, VF
+    // is used for the "carry" flag during addition, "no borrow" flag during
+    /// subtraction, and is set upon pixel collision.
+    registers: [u8; 16],
+    /// Used for pointing to memory locations.
+    index_register: u16,
+    /// Points to the next instruction.
+    program_counter: u16,
+    /// The return addresses of the calls that haven't returned yet.
+    stack: Stack,
+    /// See [`Timers`] for more information.
+    pub timers: Timers,
+    emulator_state: EmulatorState,
+    /// See [`Self::key_event`] for more information.
+    keypad: Keypad,
+    /// If this is true, then we need to redraw the frame.
+    pub needs_redraw: bool,
+    /// If this is true, the program is restarted with [`Self::reset`] at the
+    /// start of the next cycle.
+    pub needs_program_restart: bool,
+    /// See [`Program`] for more information.
+    program: Option<Program>,
+    /// See [`Self::set_buzzer_recording`] for more information.
+    buzzer_events: Option<Vec<BuzzerEvent>>,
+    /// See [`Self::set_event_logging`] for more information.
+    events: Option<Vec<Event>>,
+    /// See [`Self::set_history_capacity`] for more information.
+    history: History,
+    /// See [`Self::call_stack`] for more information.
+    call_stack: Vec<CallFrame>,
+    /// See [`Debugger`] for more information.
+    debugger: Debugger,
+    /// See [`Self::add_tracepoint`] for more information.
+    tracepoints: BTreeMap<u16, Vec<Tracepoint>>,
+    /// See [`Self::set_coverage_enabled`] for more information.
+    coverage: Option<Coverage>,
+    /// See [`Self::set_hooks`] for more information.
+    hooks: Option<Box<dyn Hooks>>,
+    /// See [`Self::map_peripheral`] for more information.
+    peripherals: Peripherals,
+    /// See [`Self::take_console_output`] for more information.
+    console_output: Vec<u8>,
+    /// See [`Stats`] for more information.
+    stats: Stats,
+    /// See [`TimerClock`] for more information.
+    timer_clock: TimerClock,
+    /// See [`Self::set_timing_model`] for more information.
+    timing: TimingModel,
+    /// The time the last frame's final instruction ran over by, which is
+    /// taken from the next frame.
+    timing_overrun: u32,
+    /// See [`Self::set_watchdog`] for more information.
+    watchdog: Option<u32>,
+    /// See [`Self::take_throttled`] for more information.
+    throttled: Option<Throttled>,
+    /// See [`Self::set_random_seed`] for more information.
+    random: Random,
+    /// See [`Self::rpl_flags`] for more information.
+    rpl_flags: [u8; RPL_FLAG_COUNT],
+    /// See [`Self::take_rpl_flags_changed`] for more information.
+    rpl_flags_changed: bool,
+    /// See [`Quirks`] for more information.
+    pub quirks: Quirks,
+    /// The MegaChip screen, which replaces [`Self::screen`] while MegaChip
+    /// is on.
+    #[cfg(feature = "megachip")]
+    megachip: Option<megachip::MegaChipScreen>,
+    /// True if a vertical blank has happened since the last sprite was drawn.
+    vblank: bool,
+    /// Set when a `DXYN` instruction is waiting for the next vertical blank
+    /// because of [`Quirks::display_wait`].
+    waiting_for_vblank: bool,
+    /// True between [`Self::begin_frame`] and [`Self::end_frame`]. Cycles run
+    /// on their own have no frames to wait for, so they never wait for a
+    /// vertical blank.
+    in_frame: bool,
+}
+
+impl Chip8 {
+    /// Creates a new emulator with empty memory. You still have to initialize
+    /// to with [`Self::initialize`] to load programs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an emulator with `size` memory, like XO-CHIP's 64K, instead
+    /// of the standard 4K.
+    pub fn with_memory_size(size: MemorySize) -> Self {
+        Self {
+            memory: Memory::new(size),
+            ..Self::default()
+        }
+    }
+
+    /// Returns how much memory the emulator has.
+    pub fn memory_size(&self) -> MemorySize {
+        self.memory.size()
+    }
+
+    /// Prints V0 to VF to stdout.
+    pub fn print_all_registers(&self) {
+        for i in 0x0..=0xF {
+            println!("Register {i} is {}", self.registers[i as usize]);
+        }
+    }
+
+    /// Prints the word the index register points at to stdout.
+    pub fn print_current_op(&self) {
+        println!("{}", self.memory.word(self.index_register as usize));
+    }
+
+    /// Sets how many cycles are run per second, so that [`Self::cycle`] can tick
+    /// the delay and sound timers at 60Hz. Passing `None` stops the timers from
+    /// ticking automatically, leaving it to the caller to use [`Self::tick_timers`].
+    ///
+    /// Defaults to [`DEFAULT_CYCLES_PER_SECOND`]. This is the same as
+    /// [`Self::set_timing_model`] with [`TimingModel::Fixed`].
+    pub fn set_cycles_per_second(&mut self, cycles_per_second: Option<u32>) {
+        let cycles_per_second = cycles_per_second.filter(|&cycles| cycles > 0);
+
+        if let Some(cycles_per_second) = cycles_per_second {
+            self.timing = TimingModel::Fixed(cycles_per_second);
+        }
+
+        self.timer_clock = TimerClock {
+            units_per_second: cycles_per_second,
+            accumulator: 0,
+        };
+    }
+
+    /// Sets how long each instruction takes to run, which decides how many run
+    /// in a frame with [`Self::run_frame`] and when [`Self::cycle`] ticks the
+    /// timers.
+    ///
+    /// Defaults to [`TimingModel::Fixed`] at [`DEFAULT_CYCLES_PER_SECOND`].
+    pub fn set_timing_model(&mut self, timing: TimingModel) {
+        self.timing = timing;
+        self.timing_overrun = 0;
+        self.timer_clock = TimerClock {
+            units_per_second: Some(timing.units_per_second()),
+            accumulator: 0,
+        };
+    }
+
+    /// Returns how long each instruction takes to run.
+    pub fn timing_model(&self) -> TimingModel {
+        self.timing
+    }
+
+    /// Seeds the random numbers used by `CXNN`, so that two runs with the same
+    /// seed and the same key presses do exactly the same thing.
+    pub fn set_random_seed(&mut self, seed: u64) {
+        self.random = Random(StdRng::seed_from_u64(seed));
+    }
+
+    /// Decrements the delay and sound timers. This happens automatically at
+    /// 60Hz unless disabled with [`Self::set_cycles_per_second`].
+    pub fn tick_timers(&mut self) {
+        let buzzer_was_active = self.is_buzzer_active();
+
+        self.timers.tick();
+
+        self.record_buzzer_change(buzzer_was_active);
+    }
+
+    /// Returns true while the sound timer is above 0, which is when the
+    /// buzzer should be sounding.
+    pub fn is_buzzer_active(&self) -> bool {
+        self.timers.sound() > 0
+    }
+
+    /// Returns the counters describing what the emulator has done since it
+    /// was initialized.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Returns true if the loaded program is for the hi-res variant of CHIP-8,
+    /// which has a 64x64 screen. This is detected by [`Self::load_program`].
+    pub fn is_hi_res(&self) -> bool {
+        self.screen.resolution() == Resolution::TWO_PAGE
+    }
+
+    /// Returns the size of the CHIP-8 screen, which frontends should size
+    /// themselves from rather than assuming [`WIDTH`] and [`HEIGHT`].
+    pub fn resolution(&self) -> Resolution {
+        self.screen.resolution()
+    }
+
+    /// Returns the display instructions are drawing to: the CHIP-8 screen,
+    /// or the MegaChip one while MegaChip is on.
+    pub fn display(&self) -> &dyn Display {
+        #[cfg(feature = "megachip")]
+        if let Some(screen) = &self.megachip {
+            return screen;
+        }
+
+        &self.screen
+    }
+
+    /// Like [`Self::display`], but for drawing to.
+    fn display_mut(&mut self) -> &mut dyn Display {
+        #[cfg(feature = "megachip")]
+        if let Some(screen) = &mut self.megachip {
+            return screen;
+        }
+
+        &mut self.screen
+    }
+
+    /// Returns true if the pixel of the CHIP-8 screen at the given x and y
+    /// is white.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pixel is off the screen.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.screen.pixel(x, y)
+    }
+
+    /// Returns each row of the CHIP-8 screen from the top down, as whether
+    /// each pixel is white from left to right.
+    pub fn pixel_rows(&self) -> impl Iterator<Item = impl Iterator<Item = bool> + '_> + '_ {
+        self.screen.rows()
+    }
+
+    /// Turns a pixel of the CHIP-8 screen white or black, as if it was drawn
+    /// there, so the frontend gets it with the next frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pixel is off the screen.
+    pub fn set_pixel(&mut self, x: usize, y: usize, white: bool) {
+        self.screen.set_pixel(x, y, white);
+    }
+
+    /// XORs an 8 pixel wide sprite onto the CHIP-8 screen with its top left
+    /// corner at x and y, one byte per row, exactly like `DXYN` would with the
+    /// current resolution and quirks. This is for hosts that want to draw
+    /// sprites themselves, like a preview in a sprite editor, so it leaves
+    /// the registers, memory and display wait alone.
+    pub fn draw_sprite(&mut self, x: u8, y: u8, data: &[u8]) -> DrawResult {
+        let rows: Vec<u16> = data.iter().map(|&byte| (byte as u16) << 8).collect();
+        self.screen
+            .draw_sprite(x, y, &rows, self.quirks.wrap_sprites)
+    }
+
+    /// Turns every pixel of the CHIP-8 screen black, like `00E0`.
+    pub fn clear_screen(&mut self) {
+        self.screen.clear();
+    }
+
+    /// Returns the frame and the range of rows that changed since the last call,
+    /// or `None` if nothing was drawn. See [`Screen::take_frame`].
+    pub fn take_frame(&mut self) -> Option<(Frame, Range<usize>)> {
+        self.screen.take_frame()
+    }
+
+    /// Runs a moves the emulator state by one cycle. Requires both the interpreter memory
+    /// to be initialized via [`Self::initialize`] and a program to be loaded in with
+    /// [`Self::load_program`].
+    ///
+    /// The delay and sound timers are ticked at 60Hz based on the rate set with
+    /// [`Self::set_cycles_per_second`].
+    pub fn cycle(&mut self) -> Result<(), Chip8Error> {
+        let _cycle = trace_span!("cycle", cycle = self.stats.total_cycles).entered();
+
+        if self.needs_program_restart {
+            self.reset()?;
+        }
+
+        if self.emulator_state != EmulatorState::ProgramLoaded {
+            return Err(Chip8Error::ProgramNotLoaded);
+        }
+
+        let opcode =
+            dispatch::lookup_in_mode(self, self.memory.word(self.program_counter as usize));
+
+        if self.debugger.should_stop(self.program_counter, opcode.name) {
+            return Ok(());
+        }
+
+        self.run_tracepoints();
+        self.record_execution(self.program_counter);
+
+        self.record_history();
+
+        let address = self.program_counter;
+        let raw = self.fetch();
+        let cost = self.timing.cost(raw);
+
+        {
+            let _instruction = instruction_span(address, raw, opcode.name).entered();
+            (opcode.handler)(self, raw)?;
+            trace!("executed");
+        }
+
+        self.wrap_addresses();
+        self.take_protected_write()?;
+
+        // Only FX0A looks at the key events.
+        if raw & 0xF0FF != 0xF00A {
+            self.keypad.clear_events();
+        }
+
+        self.stats.record_instruction(opcode.name);
+        self.run_hooks(|hooks, chip_8| hooks.on_instruction(chip_8, address, raw));
+
+        for _ in 0..self.timer_clock.advance(cost) {
+            self.tick_timers();
+        }
+
+        Ok(())
+    }
+
+    /// Runs one cycle like [`Self::cycle`], but stops with an error if the
+    /// instruction at the program counter runs off the end of memory instead
+    /// of wrapping around to the start.
+    ///
+    /// Neither this nor [`Self::cycle`] panics, whatever is in memory, so this
+    /// is what the fuzz target in `chip8-core/fuzz` runs.
+    pub fn cycle_checked(&mut self) -> Result<(), Chip8Error> {
+        if !self.needs_program_restart
+            && self.program_counter as usize + 1 >= self.memory.size().bytes()
+        {
+            return Err(Chip8Error::ProgramCounterOutOfBounds {
+                pc: self.program_counter,
+            });
+        }
+
+        self.cycle()
+    }
+
+    /// Runs one cycle like [`Self::cycle`], but by decoding the word into an
+    /// [`Instruction`] and executing that, which is returned afterwards.
+    ///
+    /// This is slower than the dispatch table used by [`Self::cycle`], but lets
+    /// debugging tools see exactly which instruction ran. Returns `None` if the
+    /// emulator is [stopped](Self::stopped) and nothing ran.
+    pub fn cycle_decoded(&mut self) -> Result<Option<Instruction>, Chip8Error> {
+        let _cycle = trace_span!("cycle", cycle = self.stats.total_cycles).entered();
+
+        if self.needs_program_restart {
+            self.reset()?;
+        }
+
+        if self.emulator_state != EmulatorState::ProgramLoaded {
+            return Err(Chip8Error::ProgramNotLoaded);
+        }
+
+        let address = self.program_counter as usize;
+        let instruction = self.decode(self.memory.word(address))?;
+
+        if self
+            .debugger
+            .should_stop(self.program_counter, instruction.name())
+        {
+            return Ok(None);
+        }
+
+        self.run_tracepoints();
+        self.record_execution(self.program_counter);
+
+        self.record_history();
+        self.program_counter = self.program_counter.wrapping_add(2);
+
+        // Read before running, in case the instruction overwrites itself.
+        let word = self.memory.word(address);
+
+        {
+            let _instruction = instruction_span(address as u16, word, instruction.name()).entered();
+            self.execute(instruction)?;
+            trace!("executed");
+        }
+
+        self.wrap_addresses();
+        self.take_protected_write()?;
+
+        if !matches!(instruction, Instruction::AwaitKeyInput { .. }) {
+            self.keypad.clear_events();
+        }
+
+        self.stats.record_instruction(instruction.name());
+        self.run_hooks(|hooks, chip_8| hooks.on_instruction(chip_8, address as u16, word));
+
+        for _ in 0..self.timer_clock.advance(self.timing.cost(word)) {
+            self.tick_timers();
+        }
+
+        Ok(Some(instruction))
+    }
+
+    /// Runs one 60Hz frame's worth of cycles (based on the rate set with
+    /// [`Self::set_cycles_per_second`] or [`Self::set_timing_model`]) and then
+    /// ticks the timers once.
+    ///
+    /// Each frame starts with a vertical blank. With [`Quirks::display_wait`] on,
+    /// a `DXYN` that has to wait for the next vertical blank ends the frame early.
+    pub fn run_frame(&mut self) -> Result<(), Chip8Error> {
+        // Time stands still while the emulator is stopped.
+        if self.stopped().is_some() {
+            return Ok(());
+        }
+
+        let _frame = debug_span!("frame", start_cycle = self.stats.total_cycles).entered();
+        let mut frame = self.begin_frame();
+
+        let result = loop {
+            match self.step_frame(&mut frame) {
+                Ok(true) => {}
+                Ok(false) => break Ok(()),
+                Err(err) => break Err(err),
+            }
+        };
+
+        self.end_frame(frame);
+        result
+    }
+
+    /// Starts a frame that is run a cycle at a time with [`Self::step_frame`],
+    /// for running emulators side by side. [`Self::run_frame`] does all three
+    /// steps at once.
+    pub(crate) fn begin_frame(&mut self) -> FrameRun {
+        let frame_time = self.timing.units_per_second().div_ceil(60);
+        let time_left = frame_time.saturating_sub(self.timing_overrun);
+        self.timing_overrun = self.timing_overrun.saturating_sub(frame_time);
+
+        // The timers are ticked once at the end of the frame instead.
+        let timer_clock = self.timer_clock;
+        self.timer_clock.units_per_second = None;
+
+        self.vblank = true;
+        self.in_frame = true;
+
+        FrameRun {
+            timer_clock,
+            time_left,
+            instructions_since_draw: 0,
+        }
+    }
+
+    /// Runs the next cycle of a frame, returning false without running
+    /// anything once the frame is over.
+    pub(crate) fn step_frame(&mut self, frame: &mut FrameRun) -> Result<bool, Chip8Error> {
+        if frame.time_left == 0 || self.waiting_for_vblank || self.stopped().is_some() {
+            return Ok(false);
+        }
+
+        let word = self.memory.word(self.program_counter as usize);
+
+        if !self.watch(frame, word) {
+            return Ok(false);
+        }
+
+        // An instruction that doesn't fit in what is left of the frame still
+        // runs, and the time it runs over by is taken from the next frame.
+        let cost = self.timing.cost(word);
+        self.timing_overrun += cost.saturating_sub(frame.time_left);
+        frame.time_left = frame.time_left.saturating_sub(cost);
+
+        self.cycle()?;
+        Ok(true)
+    }
+
+    /// Finishes a frame started with [`Self::begin_frame`], ticking the timers.
+    pub(crate) fn end_frame(&mut self, frame: FrameRun) {
+        self.timer_clock = frame.timer_clock;
+        self.waiting_for_vblank = false;
+        self.in_frame = false;
+        self.tick_timers();
+        self.run_hooks(|hooks, chip_8| hooks.on_frame(chip_8));
+    }
+
+    /// Wraps I and the program counter around to the start of memory if an
+    /// instruction moved them past the end, so that they never point outside
+    /// of it.
+    fn wrap_addresses(&mut self) {
+        self.program_counter = self.memory.wrap(self.program_counter);
+        self.index_register = self.memory.wrap(self.index_register);
+    }
+
+    /// Fetches the current instruction word and increments the PC by 2.
+    fn fetch(&mut self) -> u16 {
+        let word = self.memory.word(self.program_counter as usize);
+
+        // If we increment the PC before we pull an instruction from it,
+        // we're gonna have problems.
+        self.program_counter = self.program_counter.wrapping_add(2);
+
+        word
+    }
+
+    /// Decodes the instruction word into an [`Instruction`]
+    fn decode(&self, raw: u16) -> Result<Instruction, Chip8Error> {
+        match raw {
+            HI_RES_CLEAR if self.is_hi_res() => Ok(Instruction::Clear),
+            _ => Instruction::new(raw),
+        }
+    }
+
+    /// Executes the provided instruction.
+    fn execute(&mut self, instruction: Instruction) -> Result<(), Chip8Error> {
+        match instruction {
+            Instruction::CallMachineCodeRoutine => {
+                return Err(Chip8Error::UnimplementedInstruction { instruction })
+            }
+            Instruction::Clear => self.instruction_clear(),
+            Instruction::Return => self.instruction_return()?,
+            Instruction::Jump { nnn } => self.instruction_jump(nnn),
+            Instruction::Call { nnn } => self.instruction_call(nnn)?,
+            Instruction::SkipIfRegisterEquals { vx, nn } => {
+                self.instruction_skip_if_register_equals(vx, nn)
+            }
+            Instruction::SkipIfRegisterNotEquals { vx, nn } => {
+                self.instruction_skip_if_register_not_equals(vx, nn)
+            }
+            Instruction::SkipIfRegisterVxEqualsVy { vx, vy } => {
+                self.instruction_skip_if_register_vx_equals_vy(vx, vy)
+            }
+            Instruction::SetImmediate { vx, nn } => self.instruction_set_immediate(vx, nn),
+            Instruction::AddImmediate { vx, nn } => self.instruction_add_immediate(vx, nn),
+            Instruction::Copy { vx, vy } => self.instruction_copy(vx, vy),
+            Instruction::BitwiseOr { vx, vy } => self.instruction_bitwise_or(vx, vy),
+            Instruction::BitwiseAnd { vx, vy } => self.instruction_bitwise_and(vx, vy),
+            Instruction::BitwiseXor { vx, vy } => self.instruction_bitwise_xor(vx, vy),
+            Instruction::Add { vx, vy } => self.instruction_add(vx, vy),
+            Instruction::Subtract { vx, vy } => self.instruction_subtract(vx, vy),
+            Instruction::RightShift { vx, vy } => self.instruction_right_shift(vx, vy),
+            Instruction::SetVxToVyMinusVx { vx, vy } => {
+                self.instruction_set_vx_to_vy_minus_vx(vx, vy)
+            }
+            Instruction::LeftShift { vx, vy } => self.instruction_left_shift(vx, vy),
+            Instruction::SkipIfRegisterVxNotEqualsVy { vx, vy } => {
+                self.instruction_skip_if_register_vx_not_equals_vy(vx, vy)
+            }
+            Instruction::SetIndexRegister { nnn } => self.instruction_set_index_register(nnn),
+            Instruction::JumpWithPcOffset { nnn } => self.instruction_jump_with_pc_offset(nnn),
+            Instruction::Random { vx, nn } => self.instruction_random(vx, nn),
+            Instruction::Draw { vx, vy, n } => {
+                let draw_state = self.instruction_draw(vx, vy, n);
+                self.waiting_for_vblank = draw_state == DrawState::WaitingForVblank;
+            }
+            Instruction::SkipIfKeyPressed { vx } => self.instruction_skip_if_key_pressed(vx),
+            Instruction::SkipIfKeyNotPressed { vx } => self.instruction_skip_if_key_not_pressed(vx),
+            Instruction::SetVxToDelayTimer { vx } => self.instruction_set_vx_to_delay_timer(vx),
+            Instruction::AwaitKeyInput { vx } => self.instruction_await_key_input(vx),
+            Instruction::SetDelayTimer { vx } => self.instruction_set_delay_timer(vx),
+            Instruction::SetSoundTimer { vx } => self.instruction_set_sound_timer(vx),
+            Instruction::AddToIndex { vx } => self.instruction_add_to_index(vx),
+            Instruction::SetIndexToFontCharacter { vx } => {
+                self.instruction_set_index_to_font_character(vx)
+            }
+            Instruction::SetIndexToBigFontCharacter { vx } => {
+                self.instruction_set_index_to_big_font_character(vx)
+            }
+            Instruction::SetIndexToBinaryCodedVx { vx } => {
+                self.instruction_set_index_to_binary_coded_vx(vx)
+            }
+            Instruction::DumpRegisters { vx } => self.instruction_dump_registers(vx),
+            Instruction::LoadRegisters { vx } => self.instruction_load_registers(vx),
+            Instruction::SaveFlags { vx } => self.instruction_save_flags(vx),
+            Instruction::LoadFlags { vx } => self.instruction_load_flags(vx),
+            Instruction::Unknown => self.instruction_unknown()?,
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use proptest::prelude::*;
+
+    use super::Chip8;
+
+    #[test]
+    fn sprites_drawn_by_the_host_match_dxyn() {
+        // Draws the font's 0 at (60, 30), then loops forever.
+        let program = [0x60, 0x3C, 0x61, 0x1E, 0xA0, 0x50, 0xD0, 0x15, 0x12, 0x08];
+
+        let mut emulated = Chip8::new();
+        emulated.initialize().unwrap();
+        emulated.load_program(program.to_vec()).unwrap();
+
+        for _ in 0..4 {
+            emulated.cycle().unwrap();
+        }
+
+        let mut drawn = Chip8::new();
+        drawn.initialize().unwrap();
+        drawn.load_program(program.to_vec()).unwrap();
+
+        let result = drawn.draw_sprite(60, 30, &super::FONT_SET[..5]);
+        assert_eq!(
+            drawn.take_frame().unwrap().0,
+            emulated.take_frame().unwrap().0
+        );
+        assert_eq!((result.collided, result.clipped_rows), (false, 3));
+
+        assert!(drawn.draw_sprite(60, 30, &super::FONT_SET[..5]).collided);
+        drawn.clear_screen();
+        assert!(!drawn.pixel_rows().flatten().any(|pixel| pixel));
+    }
+
+    proptest! {
+        #[test]
+        fn random_programs_never_panic(
+            program in proptest::collection::vec(any::<u8>(), 0..=0xE00),
+            keys: u16,
+        ) {
+            for checked in [true, false] {
+                let mut chip_8 = Chip8::new();
+                chip_8.initialize().unwrap();
+                chip_8.load_program(program.clone()).unwrap();
+                chip_8.set_keys(super::Keys(keys));
+
+                for _ in 0..1000 {
+                    let result = match checked {
+                        true => chip_8.cycle_checked(),
+                        false => chip_8.cycle_decoded().map(|_| ()),
+                    };
+
+                    if result.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
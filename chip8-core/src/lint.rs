@@ -0,0 +1,182 @@
+//! Finds the instructions in a program that behave differently depending on
+//! which CHIP-8 interpreter runs it.
+//!
+//! Programs are checked statically by following them like the
+//! [disassembler](super::disassembler) does, and then run for a while to see
+//! which of the instructions found actually run.
+
+use std::fmt;
+
+use super::disassembler::reachable_instructions;
+use super::{Chip8, Chip8Error, Instruction};
+
+/// Behavior that differs between interpreters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QuirkDependence {
+    /// An `8XY6` or `8XYE` with X and Y differing. The COSMAC VIP shifts VY
+    /// into VX, while SUPER-CHIP shifts VX in place.
+    ShiftSource,
+    /// An `FX55` or `FX65` followed by an instruction that uses I. The COSMAC
+    /// VIP leaves I past the last register, while SUPER-CHIP leaves it alone.
+    LoadStoreIndex,
+    /// A `BNNN` with the top nibble of NNN set. The COSMAC VIP adds V0, while
+    /// SUPER-CHIP reads it as `BXNN` and adds VX.
+    JumpWithOffset,
+}
+
+impl QuirkDependence {
+    /// A short name for the quirk, like `shift-source`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ShiftSource => "shift-source",
+            Self::LoadStoreIndex => "load-store-index",
+            Self::JumpWithOffset => "jump-with-offset",
+        }
+    }
+}
+
+/// An instruction that depends on a quirk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Finding {
+    /// The address of the instruction.
+    pub address: u16,
+    /// The raw instruction word.
+    pub word: u16,
+    /// What it depends on.
+    pub quirk: QuirkDependence,
+    /// Whether the instruction ran while the program was being run.
+    pub executed: bool,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "0x{:03X}  {:04X}  {}",
+            self.address,
+            self.word,
+            self.quirk.name()
+        )?;
+
+        if self.executed {
+            write!(f, " (ran)")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks a program loaded at `offset`, running it for `frames` frames with
+/// no keys pressed to see which instructions run.
+///
+/// If the program stops with an error while running, the findings from
+/// before then are still returned.
+pub fn lint(program: &[u8], offset: u16, frames: u32) -> Result<Vec<Finding>, Chip8Error> {
+    let mut chip_8 = Chip8::new();
+    chip_8.initialize()?;
+    chip_8.load_program_at(offset as usize, program.to_vec())?;
+    chip_8.set_coverage_enabled(true);
+
+    for _ in 0..frames {
+        if chip_8.run_frame().is_err() {
+            break;
+        }
+    }
+
+    // Running the program finds code that the static pass can't, like the
+    // targets of BNNN jumps.
+    let instructions = reachable_instructions(program, offset, chip_8.coverage());
+    let mut findings = Vec::new();
+
+    for (index, &(address, word, instruction)) in instructions.iter().enumerate() {
+        let vx = (word >> 8) & 0xF;
+        let vy = (word >> 4) & 0xF;
+
+        let quirk = match instruction {
+            Instruction::RightShift { .. } | Instruction::LeftShift { .. } if vx != vy => {
+                QuirkDependence::ShiftSource
+            }
+            Instruction::DumpRegisters { .. } | Instruction::LoadRegisters { .. }
+                if uses_index_next(&instructions[index + 1..], address) =>
+            {
+                QuirkDependence::LoadStoreIndex
+            }
+            Instruction::JumpWithPcOffset { .. } if vx != 0 => QuirkDependence::JumpWithOffset,
+            _ => continue,
+        };
+
+        findings.push(Finding {
+            address,
+            word,
+            quirk,
+            executed: chip_8
+                .coverage()
+                .is_some_and(|coverage| coverage.instruction_ran(address)),
+        });
+    }
+
+    Ok(findings)
+}
+
+/// Returns true if the straight line of code after the instruction at
+/// `address` uses I before setting it.
+fn uses_index_next(following: &[(u16, u16, Instruction)], address: u16) -> bool {
+    let mut expected_address = address + 2;
+
+    for &(address, _, instruction) in following {
+        // Anything that isn't straight after is reached some other way.
+        if address != expected_address {
+            return false;
+        }
+
+        match instruction {
+            Instruction::Draw { .. }
+            | Instruction::AddToIndex { .. }
+            | Instruction::SetIndexToBinaryCodedVx { .. }
+            | Instruction::DumpRegisters { .. }
+            | Instruction::LoadRegisters { .. } => return true,
+            Instruction::SetIndexRegister { .. }
+            | Instruction::SetIndexToFontCharacter { .. }
+            | Instruction::SetIndexToBigFontCharacter { .. }
+            | Instruction::Jump { .. }
+            | Instruction::JumpWithPcOffset { .. }
+            | Instruction::Call { .. }
+            | Instruction::Return => return false,
+            _ => {}
+        }
+
+        expected_address += 2;
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{lint, Finding, QuirkDependence};
+
+    #[test]
+    fn quirky_instructions_are_found() {
+        // Shifts V1 into V0, loads V0-V1 twice in a row, then loops forever.
+        // The shift after the loop can't be reached.
+        let program = [0x80, 0x16, 0xF1, 0x65, 0xF1, 0x65, 0x12, 0x06, 0x80, 0x1E];
+
+        assert_eq!(
+            lint(&program, 0x200, 1).unwrap(),
+            [
+                Finding {
+                    address: 0x200,
+                    word: 0x8016,
+                    quirk: QuirkDependence::ShiftSource,
+                    executed: true
+                },
+                Finding {
+                    address: 0x202,
+                    word: 0xF165,
+                    quirk: QuirkDependence::LoadStoreIndex,
+                    executed: true
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,169 @@
+//! Runs a program on two emulators with different [`Quirks`] side by side, to
+//! find where it starts depending on one of them.
+//!
+//! Both emulators run a cycle at a time within each frame, so a quirk that
+//! changes timing (like [`Quirks::display_wait`]) shows up on the cycle where
+//! one emulator waits and the other doesn't.
+
+use std::fmt;
+
+use super::quirks::Quirks;
+use super::save_state::SaveState;
+use super::trace_log::TraceEntry;
+use super::{Chip8, Chip8Error};
+
+/// Where the two emulators first differed.
+#[derive(Debug)]
+pub struct Divergence {
+    /// The frame it happened in, counting from 0.
+    pub frame: u32,
+    /// How many cycles both emulators had run when it happened.
+    pub cycle: u64,
+    /// The names of what differs, like `pc`, `v3` or `screen`.
+    pub differences: Vec<String>,
+    /// The registers of each emulator.
+    pub states: [TraceEntry; 2],
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Diverged in frame {}, after {} cycles",
+            self.frame, self.cycle
+        )?;
+        writeln!(f, "  a:        {}", self.states[0])?;
+        writeln!(f, "  b:        {}", self.states[1])?;
+        write!(f, "  differs:  {}", self.differences.join(", "))
+    }
+}
+
+/// Runs a program loaded at `offset` for `frames` frames with no keys pressed,
+/// once with quirks `a` and once with quirks `b`, and returns the first cycle
+/// where the emulators differ. Returns `None` if they never did.
+///
+/// If either emulator stops with an error, that's where they differ. If both
+/// fail on the same cycle, the error is returned.
+pub fn lockstep(
+    program: &[u8],
+    offset: u16,
+    a: Quirks,
+    b: Quirks,
+    frames: u32,
+) -> Result<Option<Divergence>, Chip8Error> {
+    let mut emulators = [a, b].map(|quirks| {
+        let mut chip_8 = Chip8::new();
+        chip_8.quirks = quirks;
+        chip_8
+    });
+
+    for chip_8 in &mut emulators {
+        chip_8.initialize()?;
+        chip_8.load_program_at(offset as usize, program.to_vec())?;
+    }
+
+    let mut cycle = 0;
+
+    for frame in 0..frames {
+        let mut runs = emulators.each_mut().map(|chip_8| chip_8.begin_frame());
+
+        loop {
+            let mut ran = [false; 2];
+            let mut errors = [None, None];
+
+            for (index, chip_8) in emulators.iter_mut().enumerate() {
+                match chip_8.step_frame(&mut runs[index]) {
+                    Ok(step) => ran[index] = step,
+                    Err(err) => errors[index] = Some(err),
+                }
+            }
+
+            if ran == [false; 2] && errors.iter().all(Option::is_none) {
+                break;
+            }
+
+            cycle += 1;
+
+            let mut differences = match errors {
+                [Some(err), Some(_)] => return Err(err),
+                [Some(err), None] => vec![format!("a failed: {err}")],
+                [None, Some(err)] => vec![format!("b failed: {err}")],
+                [None, None] => Vec::new(),
+            };
+
+            differences.extend(compare(
+                &emulators[0].save_state(),
+                &emulators[1].save_state(),
+            ));
+
+            if !differences.is_empty() {
+                return Ok(Some(Divergence {
+                    frame,
+                    cycle,
+                    differences,
+                    states: emulators.each_ref().map(TraceEntry::capture),
+                }));
+            }
+        }
+
+        for (chip_8, run) in emulators.iter_mut().zip(runs) {
+            chip_8.end_frame(run);
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns the names of what differs between two states.
+fn compare(a: &SaveState, b: &SaveState) -> Vec<String> {
+    let diff = a.diff(b);
+    let mut differences: Vec<String> = diff
+        .registers
+        .into_iter()
+        .map(|change| change.name)
+        .collect();
+
+    if !diff.screen.is_empty() {
+        differences.push("screen".to_string());
+    }
+
+    differences.extend(
+        diff.memory
+            .first()
+            .map(|range| format!("memory at 0x{:03X}", range.start)),
+    );
+
+    differences
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::lockstep;
+    use crate::quirks::Quirks;
+
+    #[test]
+    fn display_wait_is_found() {
+        // Points I at the font, draws the top of a 0 twice, then loops forever.
+        let program = [0xA0, 0x50, 0xD0, 0x01, 0xD0, 0x01, 0x12, 0x06];
+        let display_wait = Quirks {
+            display_wait: true,
+            ..Quirks::default()
+        };
+
+        let divergence = lockstep(&program, 0x200, Quirks::default(), display_wait, 2)
+            .unwrap()
+            .unwrap();
+
+        // The first draw is straight after the vertical blank, but the second
+        // has to wait for the next one. Drawing it again without waiting erases
+        // it and sets VF.
+        assert_eq!((divergence.frame, divergence.cycle), (0, 3));
+        assert_eq!(divergence.differences, ["pc", "vf", "screen"]);
+
+        assert!(
+            lockstep(&program, 0x200, Quirks::default(), Quirks::default(), 2)
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,264 @@
+//! Partial support for MegaChip8, an extension with a 256x192 screen and
+//! sprites with up to 255 colors. Only built with the `megachip` feature.
+//!
+//! Programs switch to the MegaChip screen with `0011` and back with `0010`.
+//! While it is on:
+//!
+//! - `02NN` loads NN colors from I into the palette, as 4 bytes each in ARGB
+//!   order, starting at color 1. Color 0 is transparent.
+//! - `03NN` and `04NN` set the width and height of sprites, where 0 is 256.
+//! - `09NN` sets the color that counts as a collision when drawn over.
+//! - `DXYN` draws a sprite of one palette index per byte from I, ignoring N.
+//! - `00E0` clears the MegaChip screen.
+//!
+//! The 24-bit `01NN NNNN`, sound, blend modes and scrolling aren't supported,
+//! and neither is the MegaChip screen in save states. The extension's opcodes
+//! only run through [`Chip8::cycle`], as they aren't [`Instruction`]s.
+//!
+//! [`Instruction`]: crate::Instruction
+
+use super::display::Display;
+use super::instructions::dispatch::Opcode;
+use super::instructions::execution::DrawResult;
+use super::memory::Memory;
+use super::palette::Palette;
+use super::Chip8;
+
+/// The width of the MegaChip screen in pixels.
+pub const WIDTH: usize = 256;
+/// The height of the MegaChip screen in pixels.
+pub const HEIGHT: usize = 192;
+
+/// The 256x192 screen used while MegaChip is on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MegaChipScreen {
+    /// The `0RGB` color of each pixel, laid out as `location = WIDTH*y + x`.
+    pixels: Vec<u32>,
+    /// The palette index each pixel was drawn with, for collisions.
+    indices: Vec<u8>,
+    /// Colors loaded with `02NN`, in `ARGB` order.
+    palette: [u32; 256],
+    sprite_width: usize,
+    sprite_height: usize,
+    collision_index: u8,
+}
+
+impl Default for MegaChipScreen {
+    /// Initializes the screen to black, with no colors loaded.
+    fn default() -> Self {
+        Self {
+            pixels: vec![0; WIDTH * HEIGHT],
+            indices: vec![0; WIDTH * HEIGHT],
+            palette: [0; 256],
+            sprite_width: 0,
+            sprite_height: 0,
+            collision_index: 0,
+        }
+    }
+}
+
+impl MegaChipScreen {
+    /// Loads `count` colors from memory starting at `address` into the
+    /// palette, starting at color 1.
+    pub(crate) fn load_palette(&mut self, memory: &Memory, address: u16, count: u8) {
+        for (index, color) in self.palette[1..=count as usize].iter_mut().enumerate() {
+            let color_address = address as usize + index * 4;
+            let bytes = [0, 1, 2, 3].map(|offset| memory.byte(color_address + offset));
+
+            *color = u32::from_be_bytes(bytes);
+        }
+    }
+
+    /// Draws the current sprite from memory at `address` with its top left
+    /// corner at x and y. Pixels past the edges are clipped, and color 0 is
+    /// left undrawn. A row collides if a pixel of the collision color was
+    /// drawn over in it.
+    pub(crate) fn draw_sprite(
+        &mut self,
+        memory: &Memory,
+        address: u16,
+        x: u8,
+        y: u8,
+    ) -> DrawResult {
+        let (width, height) = (self.sprite_width.max(1), self.sprite_height.max(1));
+        let mut result = DrawResult::default();
+
+        for row in 0..height {
+            let mut row_collided = false;
+
+            for column in 0..width {
+                let index = memory.byte(address as usize + row * width + column);
+                let (pixel_x, pixel_y) = (x as usize + column, y as usize + row);
+
+                if index == 0 || pixel_x >= WIDTH || pixel_y >= HEIGHT {
+                    continue;
+                }
+
+                let location = pixel_y * WIDTH + pixel_x;
+
+                if self.indices[location] == self.collision_index {
+                    row_collided = true;
+                }
+
+                self.indices[location] = index;
+                self.pixels[location] = self.palette[index as usize] & 0x00FFFFFF;
+            }
+
+            if row_collided {
+                result.collided_rows = result.collided_rows.saturating_add(1);
+            }
+        }
+
+        result.collided = result.collided_rows > 0;
+        result.clipped_rows = (y as usize + height)
+            .saturating_sub(HEIGHT)
+            .min(u8::MAX as usize) as u8;
+        result
+    }
+}
+
+impl Display for MegaChipScreen {
+    fn width(&self) -> usize {
+        WIDTH
+    }
+
+    fn height(&self) -> usize {
+        HEIGHT
+    }
+
+    fn clear(&mut self) {
+        self.pixels.fill(0);
+        self.indices.fill(0);
+    }
+
+    fn write_rgba(&self, buffer: &mut [u32], _palette: &Palette) {
+        buffer[..WIDTH * HEIGHT].copy_from_slice(&self.pixels);
+    }
+}
+
+const ON: Opcode = Opcode {
+    name: "MegaChipOn",
+    handler: |chip_8, _| {
+        chip_8.megachip = Some(MegaChipScreen::default());
+        Ok(())
+    },
+};
+
+const OFF: Opcode = Opcode {
+    name: "MegaChipOff",
+    handler: |chip_8, _| {
+        chip_8.megachip = None;
+        chip_8.screen.clear();
+        Ok(())
+    },
+};
+
+const LOAD_PALETTE: Opcode = Opcode {
+    name: "LoadPalette",
+    handler: |chip_8, raw| {
+        if let Some(screen) = &mut chip_8.megachip {
+            screen.load_palette(&chip_8.memory, chip_8.index_register, raw as u8);
+        }
+        Ok(())
+    },
+};
+
+const SET_SPRITE_WIDTH: Opcode = Opcode {
+    name: "SetSpriteWidth",
+    handler: |chip_8, raw| {
+        if let Some(screen) = &mut chip_8.megachip {
+            screen.sprite_width = match raw as u8 {
+                0 => 256,
+                width => width as usize,
+            };
+        }
+        Ok(())
+    },
+};
+
+const SET_SPRITE_HEIGHT: Opcode = Opcode {
+    name: "SetSpriteHeight",
+    handler: |chip_8, raw| {
+        if let Some(screen) = &mut chip_8.megachip {
+            screen.sprite_height = match raw as u8 {
+                0 => 256,
+                height => height as usize,
+            };
+        }
+        Ok(())
+    },
+};
+
+const SET_COLLISION_COLOR: Opcode = Opcode {
+    name: "SetCollisionColor",
+    handler: |chip_8, raw| {
+        if let Some(screen) = &mut chip_8.megachip {
+            screen.collision_index = raw as u8;
+        }
+        Ok(())
+    },
+};
+
+/// Returns the entry for a MegaChip opcode, or `None` if `raw` isn't one or
+/// MegaChip is off.
+pub(crate) fn lookup(chip_8: &Chip8, raw: u16) -> Option<&'static Opcode> {
+    let opcode = match raw {
+        0x0011 => &ON,
+        _ if chip_8.megachip.is_none() => return None,
+        0x0010 => &OFF,
+        0x0200..=0x02FF => &LOAD_PALETTE,
+        0x0300..=0x03FF => &SET_SPRITE_WIDTH,
+        0x0400..=0x04FF => &SET_SPRITE_HEIGHT,
+        0x0900..=0x09FF => &SET_COLLISION_COLOR,
+        _ => return None,
+    };
+
+    Some(opcode)
+}
+
+#[cfg(test)]
+mod test_super {
+    use crate::palette::Palette;
+    use crate::Chip8;
+
+    #[test]
+    fn color_sprites_are_drawn_from_the_palette() {
+        let program = vec![
+            0x00, 0x11, // MegaChip on
+            0xA3, 0x00, // I = 0x300
+            0x02, 0x02, // load 2 colors
+            0x03, 0x02, // sprites are 2 pixels wide
+            0x04, 0x01, // and 1 pixel tall
+            0x09, 0x02, // color 2 collides
+            0xA3, 0x08, // I = 0x308
+            0x60, 0xFF, // V0 = 255
+            0xD0, 0x10, // draw it at (V0, V1)
+            0xD0, 0x10, // and again
+        ];
+
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8.quirks.display_wait = false;
+        chip_8.load_program(program).unwrap();
+
+        for (address, byte) in (0x300..).zip([0xFF, 0xFF, 0, 0, 0xFF, 0, 0xFF, 0, 2, 1]) {
+            chip_8.set_memory_byte(address, byte);
+        }
+
+        for _ in 0..9 {
+            chip_8.cycle().unwrap();
+        }
+
+        let display = chip_8.display();
+        let mut buffer = vec![0; display.width() * display.height()];
+        display.write_rgba(&mut buffer, &Palette::default());
+
+        // The second pixel was clipped at the right edge.
+        assert_eq!(buffer[255], 0x0000FF00);
+        assert_eq!(buffer[0], 0);
+        assert_eq!(chip_8.registers[0xF], 0);
+
+        chip_8.cycle().unwrap();
+        assert_eq!(chip_8.registers[0xF], 1);
+    }
+}
@@ -0,0 +1,715 @@
+use std::ops::RangeInclusive;
+
+use crate::display::Resolution;
+use crate::{keypad::Keypad, rom::LoadedRom, Chip8, Chip8Error, EmulatorState};
+use sha1::{Digest, Sha1};
+use tracing::{info, warn};
+
+use super::{screen::Screen, stats::Stats, Timers};
+
+/// The address where programs start in memory, unless they are loaded with
+/// [`Chip8::load_program_at`].
+pub const PROGRAM_OFFSET: usize = 0x200;
+/// Hi-res programs start with this jump to 0x260, where they set up the
+/// original interpreter for the 64x64 screen. See [`Chip8::is_hi_res`].
+pub(crate) const HI_RES_HEADER: u16 = 0x1260;
+/// Where hi-res programs really start. The set up before it is skipped,
+/// since the emulator switches to the 64x64 screen itself.
+pub(crate) const HI_RES_ENTRY_POINT: usize = 0x2C0;
+/// Clears the 64x64 screen in hi-res programs. Everywhere else it would call
+/// a machine code routine, which isn't supported.
+pub(crate) const HI_RES_CLEAR: u16 = 0x0230;
+pub(crate) const FONT_SET_OFFSET: usize = 0x050;
+pub(crate) const BIG_FONT_SET_OFFSET: usize = 0x0A0;
+/// The size of plain CHIP-8's memory. See [`MemorySize`] for the others.
+pub(crate) const MEMORY_SIZE: usize = 0x1000;
+
+/// The default font set used in the CHIP-8 interpreter.
+/// It works by treating the first 4 bits of each byte as pixels,
+/// which means each subsequent byte translates to a row of pixels below
+/// the current row.
+///
+/// This [website](https://multigesture.net/articles/how-to-write-an-emulator-chip-8-interpreter/)
+/// was used for the table, as well as a demonstration of how
+/// this works.
+pub const FONT_SET: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+/// The high resolution font set from SUPER-CHIP, used by `FX30`.
+/// Unlike [`FONT_SET`], every bit of each byte is a pixel, so each
+/// digit is 8x10 pixels. It only has the decimal digits.
+pub(crate) const BIG_FONT_SET: [u8; 100] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+];
+
+/// The fonts that `FX29` and `FX30` point I into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    /// The 4x5 hexadecimal digits used by `FX29`, 5 bytes each. Starts out
+    /// as [`FONT_SET`].
+    Small,
+    /// SUPER-CHIP's 8x10 decimal digits used by `FX30`, 10 bytes each.
+    Big,
+}
+
+impl Font {
+    /// The number of bytes in each character.
+    pub fn character_size(self) -> usize {
+        match self {
+            Self::Small => 5,
+            Self::Big => 10,
+        }
+    }
+
+    /// The number of characters in the font.
+    pub fn characters(self) -> usize {
+        match self {
+            Self::Small => 16,
+            Self::Big => 10,
+        }
+    }
+}
+
+/// How much memory the emulator has. Addresses wrap around at the end of it,
+/// so I and the program counter never point outside of it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MemorySize {
+    /// The 4K of the original interpreters, with 12-bit addresses.
+    #[default]
+    Standard,
+    /// XO-CHIP's 64K, with 16-bit addresses.
+    Extended,
+}
+
+impl MemorySize {
+    /// The number of bytes of memory.
+    pub fn bytes(self) -> usize {
+        match self {
+            Self::Standard => MEMORY_SIZE,
+            Self::Extended => 0x10000,
+        }
+    }
+
+    /// The highest address, which is also the mask addresses wrap with.
+    pub fn last_address(self) -> u16 {
+        (self.bytes() - 1) as u16
+    }
+}
+
+/// Where a font is loaded in memory, and its bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FontRegion {
+    address: usize,
+    bytes: Vec<u8>,
+}
+
+/// Regions:
+/// - 0x000-0x1FF is used for the CHIP-8 interpreter (used for the stack
+///   with [`StackStorage::Memory`](crate::StackStorage::Memory)).
+/// - 0x050-0x09F is used for the built-in pixel font set.
+/// - 0x0A0-0x103 is used for the built-in high resolution font set.
+/// - 0x200 to the end is used for the program ROM and scratch RAM.
+///
+/// The fonts can be moved with [`Chip8::set_font`].
+///
+/// Has a capacity of [`MEMORY_SIZE`] bytes unless made with [`Self::new`].
+#[derive(Debug)]
+pub(crate) struct Memory {
+    bytes: Vec<u8>,
+    size: MemorySize,
+    /// The small and big fonts, in the order of [`Font`].
+    fonts: [FontRegion; 2],
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new(MemorySize::default())
+    }
+}
+
+impl Memory {
+    /// Creates zeroed memory of the given size, with the default fonts.
+    pub(crate) fn new(size: MemorySize) -> Self {
+        Self {
+            bytes: vec![0; size.bytes()],
+            size,
+            fonts: [
+                FontRegion {
+                    address: FONT_SET_OFFSET,
+                    bytes: FONT_SET.to_vec(),
+                },
+                FontRegion {
+                    address: BIG_FONT_SET_OFFSET,
+                    bytes: BIG_FONT_SET.to_vec(),
+                },
+            ],
+        }
+    }
+
+    /// Returns how much memory there is.
+    pub(crate) fn size(&self) -> MemorySize {
+        self.size
+    }
+
+    /// Wraps `address` around to the start of memory if it's past the end.
+    pub(crate) fn wrap(&self, address: u16) -> u16 {
+        address & self.size.last_address()
+    }
+
+    /// Retrieves a byte from memory address. Addresses past the end of
+    /// memory wrap around to the start, like the interpreter's 12-bit
+    /// addresses do.
+    pub(crate) fn byte(&self, address: usize) -> u8 {
+        self.bytes[address % self.bytes.len()]
+    }
+
+    /// Sets a byte at memory address, wrapping around like [`Self::byte`].
+    pub(crate) fn set_byte(&mut self, address: usize, byte: u8) {
+        let address = address % self.bytes.len();
+        self.bytes[address] = byte;
+    }
+
+    /// Retrieves a word from memory address. This combines
+    /// `memory[address]` and `memory[address+1]` into a u16, wrapping around
+    /// like [`Self::byte`].
+    pub(crate) fn word(&self, address: usize) -> u16 {
+        ((self.byte(address) as u16) << 8) | self.byte(address.wrapping_add(1)) as u16
+    }
+
+    /// Returns all of memory.
+    pub(crate) fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Replaces all of memory, like when loading a
+    /// [`SaveState`](super::save_state::SaveState).
+    pub(crate) fn restore(&mut self, bytes: &[u8]) -> Result<(), Chip8Error> {
+        if bytes.len() != self.bytes.len() {
+            return Err(Chip8Error::InvalidSaveState);
+        }
+
+        self.bytes.copy_from_slice(bytes);
+
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    /// Sets a word at memory address. This writes to the
+    /// bytes at `memory[address]` and `memory[address+1]`.
+    pub(crate) fn set_word(&mut self, address: usize, word: u16) {
+        self.set_byte(address, (word >> 8) as u8);
+        self.set_byte(address.wrapping_add(1), (word & 0xFF) as u8);
+    }
+
+    /// Loads both font sets into memory at their addresses.
+    pub(crate) fn load_font_set(&mut self) -> Result<(), Chip8Error> {
+        for font in [Font::Small, Font::Big] {
+            self.load_font(font);
+        }
+
+        Ok(())
+    }
+
+    fn load_font(&mut self, font: Font) {
+        let region = &self.fonts[font as usize];
+
+        for (address, byte) in (region.address..).zip(region.bytes.clone()) {
+            self.set_byte(address, byte);
+        }
+    }
+
+    /// Replaces `font` with `bytes` at `address` and loads it.
+    pub(crate) fn set_font(
+        &mut self,
+        font: Font,
+        address: usize,
+        bytes: Vec<u8>,
+    ) -> Result<(), Chip8Error> {
+        let expected = font.characters() * font.character_size();
+
+        if bytes.len() != expected {
+            return Err(Chip8Error::InvalidFont {
+                reason: format!("expected {expected} bytes, found {}", bytes.len()),
+            });
+        }
+
+        if address + bytes.len() > self.bytes.len() {
+            return Err(Chip8Error::InvalidFont {
+                reason: format!("0x{address:03X} doesn't leave room for {expected} bytes"),
+            });
+        }
+
+        self.fonts[font as usize] = FontRegion { address, bytes };
+        self.load_font(font);
+
+        Ok(())
+    }
+
+    /// Returns the address of `character` in `font`. Characters past the end
+    /// of the font wrap around, so the small font only looks at the low
+    /// nibble like the original interpreter did.
+    pub(crate) fn character_address(&self, font: Font, character: u8) -> u16 {
+        let offset = character as usize % font.characters() * font.character_size();
+        ((self.fonts[font as usize].address + offset) % self.bytes.len()) as u16
+    }
+
+    /// Returns the address of `digit` in the small font, which is where
+    /// `FX29` points I.
+    pub(crate) fn font_address(&self, digit: u8) -> u16 {
+        self.character_address(Font::Small, digit)
+    }
+}
+
+/// The last program loaded, kept so it can be reloaded by [`Chip8::reset`].
+#[derive(Debug)]
+pub(crate) struct Program {
+    offset: usize,
+    bytes: Vec<u8>,
+}
+
+/// Part of the machine that [`Chip8::reload_program`] carries over to the
+/// new program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Preserve {
+    /// One of V0 to VF, from `0x0` to `0xF`.
+    Register(u8),
+    /// I.
+    IndexRegister,
+    /// The bytes at these addresses.
+    Memory(RangeInclusive<u16>),
+}
+
+impl Program {
+    /// Returns true if the byte at `address` was loaded from the program.
+    pub(crate) fn contains(&self, address: usize) -> bool {
+        (self.offset..self.offset + self.bytes.len()).contains(&address)
+    }
+}
+
+impl Chip8 {
+    /// Initializes the emulator's system memory and loads fonts into memory.
+    /// You can now load a program with [`Self::load_program`].
+    pub fn initialize(&mut self) -> Result<(), Chip8Error> {
+        // Clear memory, keeping its size and fonts.
+        let fonts = self.memory.fonts.clone();
+        self.memory = Memory::new(self.memory.size());
+        self.memory.fonts = fonts;
+
+        // Clear screen
+        self.screen = Screen::default();
+        #[cfg(feature = "megachip")]
+        {
+            self.megachip = None;
+        }
+
+        self.registers = [0; 16];
+        self.index_register = 0;
+        self.program_counter = PROGRAM_OFFSET as u16;
+
+        self.stack.clear();
+
+        self.timers = Timers::default();
+        self.timer_clock.accumulator = 0;
+        self.timing_overrun = 0;
+        self.throttled = None;
+        self.keypad = Keypad::default();
+
+        self.needs_program_restart = false;
+        self.stats = Stats::default();
+        self.history.clear();
+        self.call_stack.clear();
+        self.debugger.clear_stop();
+
+        self.memory.load_font_set()?;
+
+        self.emulator_state
+            .change_states(EmulatorState::InterpreterMemoryInitialized)?;
+
+        // Screen memory is already initialized.
+        // The actual screen window is initialized in the main function
+
+        Ok(())
+    }
+
+    /// Loads a program into memory from raw bytes. Requires that [`Self::initialize`]
+    /// has been called. You can now start emulation cycles with [`Self::cycle`].
+    ///
+    /// To load a new program, simply call [`Self::load_program`] again. Calling
+    /// [`Self::initialize`] first also clears the registers and screen left
+    /// behind by the old program.
+    ///
+    /// The program is kept so that it can be restarted with [`Self::reset`].
+    ///
+    /// Returns an error if the program does not fit in memory.
+    pub fn load_program(&mut self, program_bytes: Vec<u8>) -> Result<LoadedRom, Chip8Error> {
+        self.load_program_at(PROGRAM_OFFSET, program_bytes)
+    }
+
+    /// Like [`Self::load_program`], but loads the program (and starts running it)
+    /// at `offset` instead of [`PROGRAM_OFFSET`]. This is needed for variants
+    /// like the ETI-660, which loads programs at 0x600.
+    ///
+    /// The offset can't be inside the interpreter's area of memory.
+    pub fn load_program_at(
+        &mut self,
+        offset: usize,
+        program_bytes: Vec<u8>,
+    ) -> Result<LoadedRom, Chip8Error> {
+        let size = self.memory.size().bytes();
+
+        if !(PROGRAM_OFFSET..size).contains(&offset) {
+            return Err(Chip8Error::InvalidLoadOffset { offset });
+        }
+
+        let capacity = size - offset;
+
+        if program_bytes.len() > capacity {
+            return Err(Chip8Error::ProgramTooLarge {
+                size: program_bytes.len(),
+                capacity,
+            });
+        }
+
+        // Instructions are 2 bytes each, so an odd length usually means the
+        // file is truncated or isn't a CHIP-8 program at all.
+        if !program_bytes.len().is_multiple_of(2) {
+            warn!(
+                "Program is an odd number of bytes ({}), it may be corrupted",
+                program_bytes.len()
+            );
+        }
+
+        let hi_res =
+            offset == PROGRAM_OFFSET && program_bytes.starts_with(&HI_RES_HEADER.to_be_bytes());
+        let entry_point = match hi_res {
+            true => HI_RES_ENTRY_POINT,
+            false => offset,
+        };
+
+        let loaded_rom = LoadedRom {
+            size: program_bytes.len(),
+            sha1: Sha1::digest(&program_bytes).into(),
+            entry_point: entry_point as u16,
+        };
+
+        self.emulator_state
+            .change_states(EmulatorState::ProgramLoaded)?;
+
+        // We clear out the bytes before the program, in case a previous
+        // program was loaded at a lower offset.
+        for address in PROGRAM_OFFSET..offset {
+            self.memory.set_byte(address, 0);
+        }
+
+        // We load it in starting at the offset.
+        let mut current_memory_address = offset;
+
+        for &byte in &program_bytes {
+            self.memory.set_byte(current_memory_address, byte);
+
+            current_memory_address += 1;
+        }
+
+        // We clear out the rest of the bytes and variables as well so that
+        // nothing interferes with this program (under the assumption that this
+        // can be called multiple times to switch programs).
+        for address in current_memory_address..size {
+            self.memory.set_byte(address, 0);
+        }
+
+        if hi_res {
+            info!("Found the hi-res header, switching to the 64x64 screen");
+        }
+
+        let resolution = match hi_res {
+            true => Resolution::TWO_PAGE,
+            false => Resolution::LORES,
+        };
+        self.screen = Screen::new(resolution, 1);
+        self.program_counter = entry_point as u16;
+        self.program = Some(Program {
+            offset,
+            bytes: program_bytes,
+        });
+
+        Ok(loaded_rom)
+    }
+
+    /// Restarts the loaded program from the beginning, as if the emulator was
+    /// initialized and the program was loaded again. Quirks and the clock rate
+    /// are kept.
+    pub fn reset(&mut self) -> Result<LoadedRom, Chip8Error> {
+        let program = self.program.take().ok_or(Chip8Error::ProgramNotLoaded)?;
+
+        self.initialize()?;
+        self.load_program_at(program.offset, program.bytes)
+    }
+
+    /// Replaces the program with a new build of it, starting it over like
+    /// [`Self::reset`] but carrying over everything in `preserve`, so that
+    /// a ROM being worked on can pick up close to where it left off. The new
+    /// program is loaded at the same address as the old one.
+    ///
+    /// If the new program can't be loaded, the old one carries on where it
+    /// was and the error is returned.
+    pub fn reload_program(
+        &mut self,
+        program_bytes: Vec<u8>,
+        preserve: &[Preserve],
+    ) -> Result<LoadedRom, Chip8Error> {
+        let state = self.save_state();
+        let old_program = self.program.take().ok_or(Chip8Error::ProgramNotLoaded)?;
+
+        self.initialize()?;
+
+        let loaded_rom = match self.load_program_at(old_program.offset, program_bytes) {
+            Ok(loaded_rom) => loaded_rom,
+            Err(err) => {
+                self.program = Some(old_program);
+                self.load_state(&state)?;
+                return Err(err);
+            }
+        };
+
+        for preserved in preserve {
+            match preserved {
+                Preserve::Register(register) => {
+                    let register = *register as usize & 0xF;
+                    self.registers[register] = state.registers[register];
+                }
+                Preserve::IndexRegister => self.index_register = state.index_register,
+                Preserve::Memory(range) => {
+                    for address in range.clone().map(usize::from) {
+                        if let Some(&byte) = state.memory.get(address) {
+                            self.memory.set_byte(address, byte);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(loaded_rom)
+    }
+
+    /// Replaces one of the fonts with `bytes`, loaded at `address`. `FX29`
+    /// and `FX30` point I into it from then on, and it is loaded again
+    /// whenever the emulator is initialized.
+    ///
+    /// The font has to have [`Font::characters`] characters of
+    /// [`Font::character_size`] bytes each, and fit in memory.
+    pub fn set_font(&mut self, font: Font, address: u16, bytes: Vec<u8>) -> Result<(), Chip8Error> {
+        self.memory.set_font(font, address as usize, bytes)
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{Font, MemorySize, Preserve};
+    use crate::display::Resolution;
+    use crate::{Chip8, Chip8Error};
+
+    #[test]
+    fn programs_can_be_loaded_at_an_offset() {
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        let loaded_rom = chip_8.load_program_at(0x600, vec![0x16, 0x00]).unwrap();
+
+        assert_eq!(loaded_rom.entry_point, 0x600);
+        assert_eq!(chip_8.program_counter, 0x600);
+        assert_eq!(chip_8.memory.word(0x600), 0x1600);
+        assert_eq!(chip_8.memory.word(0x200), 0x0000);
+    }
+
+    #[test]
+    fn reset_restarts_the_program() {
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8
+            .load_program_at(0x300, vec![0x60, 0x2A, 0xA3, 0x00])
+            .unwrap();
+
+        for _ in 0..2 {
+            chip_8.cycle().unwrap();
+        }
+
+        chip_8.memory.set_byte(0x301, 0xFF);
+        chip_8.needs_program_restart = true;
+        chip_8.cycle().unwrap();
+
+        // The first instruction ran again from the original program bytes.
+        assert_eq!(chip_8.program_counter, 0x302);
+        assert_eq!(chip_8.registers[0], 0x2A);
+        assert_eq!(chip_8.index_register, 0);
+        assert!(!chip_8.needs_program_restart);
+    }
+
+    #[test]
+    fn reloading_keeps_what_is_preserved() {
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8
+            .load_program(vec![0x60, 0x01, 0x61, 0x02, 0xA3, 0x00, 0xF1, 0x55])
+            .unwrap();
+
+        for _ in 0..4 {
+            chip_8.cycle().unwrap();
+        }
+
+        let preserve = [Preserve::Register(1), Preserve::Memory(0x301..=0x301)];
+        chip_8.reload_program(vec![0x12, 0x00], &preserve).unwrap();
+
+        assert_eq!(chip_8.program_counter, 0x200);
+        assert_eq!(chip_8.memory.word(0x200), 0x1200);
+        assert_eq!(chip_8.registers[..2], [0, 2]);
+        assert_eq!(chip_8.index_register, 0);
+        assert_eq!(chip_8.memory.byte(0x300), 0);
+        assert_eq!(chip_8.memory.byte(0x301), 2);
+
+        // A build that doesn't fit leaves the old one running.
+        chip_8.cycle().unwrap();
+        let too_large = vec![0; 0x1000];
+        assert!(chip_8.reload_program(too_large, &preserve).is_err());
+        assert_eq!(chip_8.memory.word(0x200), 0x1200);
+        assert_eq!(chip_8.registers[1], 2);
+        chip_8.cycle().unwrap();
+    }
+
+    #[test]
+    fn programs_cannot_be_loaded_over_the_interpreter() {
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+
+        assert!(matches!(
+            chip_8.load_program_at(0x100, vec![0x00]),
+            Err(Chip8Error::InvalidLoadOffset { offset: 0x100 })
+        ));
+    }
+
+    #[test]
+    fn programs_that_do_not_fit_are_rejected() {
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+
+        assert!(matches!(
+            chip_8.load_program(vec![0; 0xE01]),
+            Err(Chip8Error::ProgramTooLarge {
+                size: 0xE01,
+                capacity: 0xE00
+            })
+        ));
+        assert!(chip_8.load_program(vec![0; 0xE00]).is_ok());
+    }
+
+    #[test]
+    fn loaded_roms_are_hashed() {
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        let loaded_rom = chip_8.load_program(b"abc".to_vec()).unwrap();
+
+        assert_eq!(loaded_rom.size, 3);
+        assert_eq!(
+            loaded_rom.sha1_hex(),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+    }
+
+    #[test]
+    fn addresses_wrap_at_the_end_of_memory() {
+        // V0 = 2, I = 0xFFF, I += V0
+        let program = vec![0x60, 0x02, 0xAF, 0xFF, 0xF0, 0x1E];
+
+        for (size, index) in [
+            (MemorySize::Standard, 0x001),
+            (MemorySize::Extended, 0x1001),
+        ] {
+            let mut chip_8 = Chip8::with_memory_size(size);
+            chip_8.initialize().unwrap();
+            chip_8.load_program(program.clone()).unwrap();
+
+            for _ in 0..3 {
+                chip_8.cycle().unwrap();
+            }
+
+            assert_eq!(chip_8.memory().len(), size.bytes());
+            assert_eq!(chip_8.index_register, index);
+        }
+
+        // Only 64K fits a program bigger than 4K.
+        let mut chip_8 = Chip8::with_memory_size(MemorySize::Extended);
+        chip_8.initialize().unwrap();
+        assert!(chip_8.load_program(vec![0; 0x1000]).is_ok());
+    }
+
+    #[test]
+    fn custom_fonts_are_kept_when_initializing() {
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+
+        let font: Vec<u8> = (0..80).collect();
+        chip_8.set_font(Font::Small, 0x100, font).unwrap();
+        chip_8.initialize().unwrap();
+
+        assert_eq!(chip_8.memory.font_address(0x13), 0x10F);
+        assert_eq!(chip_8.memory.byte(0x10F), 15);
+        assert!(matches!(
+            chip_8.set_font(Font::Big, 0x100, vec![0; 80]),
+            Err(Chip8Error::InvalidFont { .. })
+        ));
+    }
+
+    #[test]
+    fn hi_res_programs_get_a_taller_screen() {
+        let mut program = vec![0; 0xC0];
+        program[..2].copy_from_slice(&[0x12, 0x60]);
+        program.extend([
+            0x61, 0x3C, // V1 = 60
+            0xA0, 0x50, // I = the font's 0
+            0xD0, 0x15, // draw it at (V0, V1)
+            0x02, 0x30, // clear the screen
+        ]);
+
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        let loaded_rom = chip_8.load_program(program).unwrap();
+
+        assert_eq!(loaded_rom.entry_point, 0x2C0);
+        assert!(chip_8.is_hi_res());
+
+        for _ in 0..3 {
+            chip_8.cycle().unwrap();
+        }
+
+        let frame = chip_8.screen.frame();
+        assert_eq!(frame.resolution(), Resolution::TWO_PAGE);
+        assert!(frame.pixel(0, 60) && frame.pixel(0, 63));
+
+        chip_8.cycle().unwrap();
+        assert!(!chip_8.pixel_rows().flatten().any(|pixel| pixel));
+        assert_eq!(chip_8.resolution().height, 64);
+    }
+}
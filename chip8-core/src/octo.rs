@@ -0,0 +1,726 @@
+//! An assembler for programs written in [Octo](https://github.com/JohnEarnest/Octo)'s
+//! assembly language, so `.8o` files can be run without assembling them first.
+//!
+//! This supports the core of the language: labels, `:const`, `:alias`, `:org`,
+//! `:call`, raw bytes, every statement that maps to a CHIP-8 or SUPER-CHIP
+//! instruction, `if ... then`, `if ... begin ... else ... end` and
+//! `loop ... while ... again`. Macros, `:calc` and the comparison operators
+//! that expand into several instructions (`<`, `>`, `<=` and `>=`) are not
+//! supported yet.
+//!
+//! Like Octo, programs start running at the `main` label. A `jump main` is
+//! placed at the start of the program to get there.
+
+use super::{memory::MEMORY_SIZE, memory::PROGRAM_OFFSET, Chip8Error};
+use std::collections::{BTreeMap, HashMap};
+
+/// Assembles Octo source code into a program that can be passed to
+/// [`Chip8::load_program`](super::Chip8::load_program).
+pub fn assemble(source: &str) -> Result<Vec<u8>, Chip8Error> {
+    assemble_with_source_map(source).map(|(program, _)| program)
+}
+
+/// Assembles Octo source code like [`assemble`], also returning which lines
+/// the bytes of the program came from.
+pub fn assemble_with_source_map(source: &str) -> Result<(Vec<u8>, SourceMap), Chip8Error> {
+    let mut assembler = Assembler::new(source);
+    assembler.run()?;
+    assembler.finish()
+}
+
+/// Maps between the lines of an Octo program and the addresses they were
+/// assembled to.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    /// The address of the first byte each line assembled to, keyed by line
+    /// number (starting at 1).
+    lines: BTreeMap<usize, u16>,
+    /// The names of the labels, keyed by their address.
+    labels: BTreeMap<u16, String>,
+}
+
+impl SourceMap {
+    /// Returns the first line at or after `line` that assembled to anything,
+    /// along with its address. Lines like comments don't have an address of
+    /// their own, so this is where a breakpoint set on them ends up.
+    pub fn address_of_line(&self, line: usize) -> Option<(usize, u16)> {
+        self.lines
+            .range(line..)
+            .next()
+            .map(|(&line, &address)| (line, address))
+    }
+
+    /// Returns the line that the byte at `address` was assembled from.
+    pub fn line_of_address(&self, address: u16) -> Option<usize> {
+        self.lines
+            .iter()
+            .filter(|(_, &start)| start <= address)
+            .max_by_key(|(_, &start)| start)
+            .map(|(&line, _)| line)
+    }
+
+    /// The names of the labels keyed by their address, which can be used as
+    /// the symbols for [`Chip8::backtrace`](super::Chip8::backtrace).
+    pub fn labels(&self) -> &BTreeMap<u16, String> {
+        &self.labels
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+}
+
+/// A branch that is waiting for the end of the block it jumps over.
+#[derive(Debug)]
+enum Block {
+    /// Started by `loop`, at this address.
+    Loop { start: u16, breaks: Vec<u16> },
+    /// Started by `if ... begin`. The jump at this address goes to the `else`
+    /// (or `end` if there isn't one).
+    If { jump: u16 },
+    /// After an `else`. The jump at this address goes to the `end`.
+    Else { jump: u16 },
+}
+
+/// What a condition in `if` and `while` compares.
+#[derive(Debug, Clone, Copy)]
+enum Condition {
+    EqualsImmediate(u8, u8),
+    NotEqualsImmediate(u8, u8),
+    EqualsRegister(u8, u8),
+    NotEqualsRegister(u8, u8),
+    KeyPressed(u8),
+    KeyNotPressed(u8),
+}
+
+impl Condition {
+    /// The instruction that skips the next instruction when the condition
+    /// is true.
+    fn skip_if_true(self) -> u16 {
+        match self {
+            Self::EqualsImmediate(x, nn) => 0x3000 | (x as u16) << 8 | nn as u16,
+            Self::NotEqualsImmediate(x, nn) => 0x4000 | (x as u16) << 8 | nn as u16,
+            Self::EqualsRegister(x, y) => 0x5000 | (x as u16) << 8 | (y as u16) << 4,
+            Self::NotEqualsRegister(x, y) => 0x9000 | (x as u16) << 8 | (y as u16) << 4,
+            Self::KeyPressed(x) => 0xE09E | (x as u16) << 8,
+            Self::KeyNotPressed(x) => 0xE0A1 | (x as u16) << 8,
+        }
+    }
+
+    fn negate(self) -> Self {
+        match self {
+            Self::EqualsImmediate(x, nn) => Self::NotEqualsImmediate(x, nn),
+            Self::NotEqualsImmediate(x, nn) => Self::EqualsImmediate(x, nn),
+            Self::EqualsRegister(x, y) => Self::NotEqualsRegister(x, y),
+            Self::NotEqualsRegister(x, y) => Self::EqualsRegister(x, y),
+            Self::KeyPressed(x) => Self::KeyNotPressed(x),
+            Self::KeyNotPressed(x) => Self::KeyPressed(x),
+        }
+    }
+}
+
+struct Assembler<'a> {
+    tokens: Vec<Token<'a>>,
+    position: usize,
+    /// The assembled program, starting at [`PROGRAM_OFFSET`].
+    bytes: Vec<u8>,
+    /// Where the next byte is written.
+    address: u16,
+    labels: HashMap<&'a str, u16>,
+    constants: HashMap<&'a str, u16>,
+    aliases: HashMap<&'a str, u8>,
+    /// Instructions that end with the address of a label, which are filled
+    /// in at the end.
+    fixups: Vec<(u16, Token<'a>)>,
+    blocks: Vec<(Block, Token<'a>)>,
+    /// See [`SourceMap::lines`].
+    lines: BTreeMap<usize, u16>,
+}
+
+impl<'a> Assembler<'a> {
+    fn new(source: &'a str) -> Self {
+        let tokens = source
+            .lines()
+            .enumerate()
+            .flat_map(|(index, line)| {
+                // Everything after a # is a comment.
+                let code = line.split('#').next().unwrap_or_default();
+
+                code.split_whitespace().map(move |text| Token {
+                    text,
+                    line: index + 1,
+                })
+            })
+            .collect();
+
+        Self {
+            tokens,
+            position: 0,
+            bytes: Vec::new(),
+            address: PROGRAM_OFFSET as u16,
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            fixups: Vec::new(),
+            blocks: Vec::new(),
+            lines: BTreeMap::new(),
+        }
+    }
+
+    fn run(&mut self) -> Result<(), Chip8Error> {
+        let start = Token {
+            text: "main",
+            line: 1,
+        };
+        self.emit_with_label(0x1000, start)?;
+
+        while let Some(token) = self.next_token() {
+            self.statement(token)?;
+        }
+
+        match self.blocks.pop() {
+            Some((_, token)) => Err(error(token, "block is never closed")),
+            None => Ok(()),
+        }
+    }
+
+    fn finish(mut self) -> Result<(Vec<u8>, SourceMap), Chip8Error> {
+        for (address, token) in std::mem::take(&mut self.fixups) {
+            let target = *self
+                .labels
+                .get(token.text)
+                .ok_or_else(|| error(token, format!("undefined label {:?}", token.text)))?;
+
+            let index = address as usize - PROGRAM_OFFSET;
+            self.bytes[index] |= (target >> 8) as u8 & 0x0F;
+            self.bytes[index + 1] = target as u8;
+        }
+
+        let source_map = SourceMap {
+            lines: self.lines,
+            labels: self
+                .labels
+                .iter()
+                .map(|(&name, &address)| (address, name.to_string()))
+                .collect(),
+        };
+
+        Ok((self.bytes, source_map))
+    }
+
+    fn next_token(&mut self) -> Option<Token<'a>> {
+        let token = self.tokens.get(self.position).copied();
+        self.position += 1;
+        token
+    }
+
+    /// Returns the next token, or an error pointing at `after` if the
+    /// source ends early.
+    fn expect_token(&mut self, after: Token<'a>) -> Result<Token<'a>, Chip8Error> {
+        self.next_token()
+            .ok_or_else(|| error(after, format!("expected something after {:?}", after.text)))
+    }
+
+    fn statement(&mut self, token: Token<'a>) -> Result<(), Chip8Error> {
+        match token.text {
+            ":" => {
+                let name = self.expect_token(token)?;
+                self.define(name, self.address, true)?;
+            }
+            ":const" => {
+                let name = self.expect_token(token)?;
+                let value = self.expect_token(name)?;
+                let value = self.number(value, 0xFFFF)?;
+                self.define(name, value, false)?;
+            }
+            ":alias" => {
+                let name = self.expect_token(token)?;
+                let register = self.expect_token(name)?;
+                let register = self.register(register)?;
+                self.aliases.insert(name.text, register);
+            }
+            ":org" => {
+                let address = self.expect_token(token)?;
+                let address = self.number(address, 0xFFF)?;
+
+                if (address as usize) < PROGRAM_OFFSET + self.bytes.len() {
+                    return Err(error(token, "can't move the address backwards"));
+                }
+
+                self.address = address;
+            }
+            ":call" => {
+                let target = self.expect_token(token)?;
+                self.emit_with_label(0x2000, target)?;
+            }
+            ":byte" => {
+                let value = self.expect_token(token)?;
+                let value = self.number(value, 0xFF)?;
+                self.emit_byte(value as u8, token)?;
+            }
+            "clear" => self.emit(0x00E0, token)?,
+            "return" | ";" => self.emit(0x00EE, token)?,
+            "jump" => {
+                let target = self.expect_token(token)?;
+                self.emit_with_label(0x1000, target)?;
+            }
+            "jump0" => {
+                let target = self.expect_token(token)?;
+                self.emit_with_label(0xB000, target)?;
+            }
+            "native" => {
+                let target = self.expect_token(token)?;
+                self.emit_with_label(0x0000, target)?;
+            }
+            "bcd" => self.register_statement(0xF033, token)?,
+            "save" => self.register_statement(0xF055, token)?,
+            "load" => self.register_statement(0xF065, token)?,
+            "saveflags" => self.register_statement(0xF075, token)?,
+            "loadflags" => self.register_statement(0xF085, token)?,
+            "sprite" => {
+                let x = self.expect_token(token)?;
+                let y = self.expect_token(x)?;
+                let n = self.expect_token(y)?;
+                let word = 0xD000
+                    | (self.register(x)? as u16) << 8
+                    | (self.register(y)? as u16) << 4
+                    | self.number(n, 0xF)?;
+                self.emit(word, token)?;
+            }
+            "delay" | "buzzer" => {
+                self.expect_text(token, ":=")?;
+                let vx = self.expect_token(token)?;
+                let base = match token.text {
+                    "delay" => 0xF015,
+                    _ => 0xF018,
+                };
+                self.emit(base | (self.register(vx)? as u16) << 8, token)?;
+            }
+            "i" => self.index_statement(token)?,
+            "if" => self.if_statement(token)?,
+            "loop" => self.blocks.push((
+                Block::Loop {
+                    start: self.address,
+                    breaks: Vec::new(),
+                },
+                token,
+            )),
+            "while" => {
+                let condition = self.condition(token)?;
+                self.emit(condition.skip_if_true(), token)?;
+
+                let jump = self.address;
+                self.emit(0x1000, token)?;
+
+                let loop_block = self
+                    .blocks
+                    .iter_mut()
+                    .rev()
+                    .find_map(|(block, _)| match block {
+                        Block::Loop { breaks, .. } => Some(breaks),
+                        _ => None,
+                    })
+                    .ok_or_else(|| error(token, "while outside of a loop"))?;
+                loop_block.push(jump);
+            }
+            "again" => match self.blocks.pop() {
+                Some((Block::Loop { start, breaks }, _)) => {
+                    self.emit(0x1000 | start, token)?;
+
+                    for jump in breaks {
+                        self.patch_jump(jump, self.address);
+                    }
+                }
+                _ => return Err(error(token, "again without a loop")),
+            },
+            "else" => match self.blocks.pop() {
+                Some((Block::If { jump }, begin)) => {
+                    let else_jump = self.address;
+                    self.emit(0x1000, token)?;
+                    self.patch_jump(jump, self.address);
+                    self.blocks.push((Block::Else { jump: else_jump }, begin));
+                }
+                _ => return Err(error(token, "else without an if ... begin")),
+            },
+            "end" => match self.blocks.pop() {
+                Some((Block::If { jump } | Block::Else { jump }, _)) => {
+                    self.patch_jump(jump, self.address);
+                }
+                _ => return Err(error(token, "end without an if ... begin")),
+            },
+            _ if self.is_register(token.text) => self.register_assignment(token)?,
+            _ if token
+                .text
+                .starts_with(|c: char| c.is_ascii_digit() || c == '-') =>
+            {
+                let value = self.number(token, 0xFF)?;
+                self.emit_byte(value as u8, token)?;
+            }
+            // Anything else is the name of a subroutine to call.
+            _ => self.emit_with_label(0x2000, token)?,
+        }
+
+        Ok(())
+    }
+
+    fn define(&mut self, name: Token<'a>, value: u16, is_label: bool) -> Result<(), Chip8Error> {
+        if self.labels.contains_key(name.text) || self.constants.contains_key(name.text) {
+            return Err(error(name, format!("{:?} is already defined", name.text)));
+        }
+
+        match is_label {
+            true => self.labels.insert(name.text, value),
+            false => self.constants.insert(name.text, value),
+        };
+
+        Ok(())
+    }
+
+    fn expect_text(&mut self, after: Token<'a>, text: &str) -> Result<(), Chip8Error> {
+        let token = self.expect_token(after)?;
+
+        match token.text == text {
+            true => Ok(()),
+            false => Err(error(
+                token,
+                format!("expected {text:?}, found {:?}", token.text),
+            )),
+        }
+    }
+
+    /// Statements like `bcd vX` that take a single register.
+    fn register_statement(&mut self, base: u16, token: Token<'a>) -> Result<(), Chip8Error> {
+        let vx = self.expect_token(token)?;
+        self.emit(base | (self.register(vx)? as u16) << 8, token)
+    }
+
+    fn index_statement(&mut self, token: Token<'a>) -> Result<(), Chip8Error> {
+        let operator = self.expect_token(token)?;
+
+        match operator.text {
+            ":=" => {
+                let value = self.expect_token(operator)?;
+
+                match value.text {
+                    "hex" => self.register_statement(0xF029, value),
+                    "bighex" => self.register_statement(0xF030, value),
+                    // Anything that isn't a number is a label.
+                    _ => match self.number(value, 0xFFF) {
+                        Ok(nnn) => self.emit(0xA000 | nnn, token),
+                        Err(_) => self.emit_with_label(0xA000, value),
+                    },
+                }
+            }
+            "+=" => self.register_statement(0xF01E, operator),
+            _ => Err(error(
+                operator,
+                format!("unknown operator {:?} for i", operator.text),
+            )),
+        }
+    }
+
+    fn if_statement(&mut self, token: Token<'a>) -> Result<(), Chip8Error> {
+        let condition = self.condition(token)?;
+        let keyword = self.expect_token(token)?;
+
+        match keyword.text {
+            // The next statement is skipped when the condition is false.
+            "then" => self.emit(condition.negate().skip_if_true(), token),
+            // The jump over the block is skipped when the condition is true.
+            "begin" => {
+                self.emit(condition.skip_if_true(), token)?;
+                let jump = self.address;
+                self.emit(0x1000, token)?;
+                self.blocks.push((Block::If { jump }, token));
+                Ok(())
+            }
+            _ => Err(error(
+                keyword,
+                format!("expected then or begin, found {:?}", keyword.text),
+            )),
+        }
+    }
+
+    fn condition(&mut self, after: Token<'a>) -> Result<Condition, Chip8Error> {
+        let vx = self.expect_token(after)?;
+        let x = self.register(vx)?;
+        let operator = self.expect_token(vx)?;
+
+        match operator.text {
+            "key" => return Ok(Condition::KeyPressed(x)),
+            "-key" => return Ok(Condition::KeyNotPressed(x)),
+            _ => {}
+        }
+
+        let operand = self.expect_token(operator)?;
+        let is_register = self.is_register(operand.text);
+
+        let condition = match (operator.text, is_register) {
+            ("==", false) => Condition::EqualsImmediate(x, self.number(operand, 0xFF)? as u8),
+            ("!=", false) => Condition::NotEqualsImmediate(x, self.number(operand, 0xFF)? as u8),
+            ("==", true) => Condition::EqualsRegister(x, self.register(operand)?),
+            ("!=", true) => Condition::NotEqualsRegister(x, self.register(operand)?),
+            _ => {
+                return Err(error(
+                    operator,
+                    format!("unsupported comparison {:?}", operator.text),
+                ))
+            }
+        };
+
+        Ok(condition)
+    }
+
+    /// Statements that start with a register, like `v0 += 1`.
+    fn register_assignment(&mut self, vx: Token<'a>) -> Result<(), Chip8Error> {
+        let x = (self.register(vx)? as u16) << 8;
+        let operator = self.expect_token(vx)?;
+        let operand = self.expect_token(operator)?;
+
+        let word = match (operator.text, operand.text) {
+            (":=", "random") => {
+                let mask = self.expect_token(operand)?;
+                0xC000 | x | self.number(mask, 0xFF)?
+            }
+            (":=", "delay") => 0xF007 | x,
+            (":=", "key") => 0xF00A | x,
+            (operator_text, _) if self.is_register(operand.text) => {
+                let y = (self.register(operand)? as u16) << 4;
+                let last_nibble = match operator_text {
+                    ":=" => 0x0,
+                    "|=" => 0x1,
+                    "&=" => 0x2,
+                    "^=" => 0x3,
+                    "+=" => 0x4,
+                    "-=" => 0x5,
+                    ">>=" => 0x6,
+                    "=-" => 0x7,
+                    "<<=" => 0xE,
+                    _ => {
+                        return Err(error(
+                            operator,
+                            format!("unknown operator {operator_text:?}"),
+                        ))
+                    }
+                };
+
+                0x8000 | x | y | last_nibble
+            }
+            (":=", _) => 0x6000 | x | self.number(operand, 0xFF)?,
+            ("+=", _) => 0x7000 | x | self.number(operand, 0xFF)?,
+            ("-=", _) => 0x7000 | x | (self.number(operand, 0xFF)? as u8).wrapping_neg() as u16,
+            _ => {
+                return Err(error(
+                    operator,
+                    format!("unknown operator {:?}", operator.text),
+                ))
+            }
+        };
+
+        self.emit(word, vx)
+    }
+
+    fn is_register(&self, text: &str) -> bool {
+        self.aliases.contains_key(text) || parse_register(text).is_some()
+    }
+
+    fn register(&self, token: Token<'a>) -> Result<u8, Chip8Error> {
+        self.aliases
+            .get(token.text)
+            .copied()
+            .or_else(|| parse_register(token.text))
+            .ok_or_else(|| {
+                error(
+                    token,
+                    format!("expected a register, found {:?}", token.text),
+                )
+            })
+    }
+
+    /// Parses a number or constant, which must be no more than `max`.
+    /// Negative numbers are stored as two's complement bytes, so they are only
+    /// allowed where a byte is expected.
+    fn number(&self, token: Token<'a>, max: u16) -> Result<u16, Chip8Error> {
+        if let Some(&value) = self.constants.get(token.text) {
+            return match value <= max {
+                true => Ok(value),
+                false => Err(error(token, format!("{:?} is too large here", token.text))),
+            };
+        }
+
+        let (negative, digits) = match token.text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, token.text),
+        };
+
+        let parsed = if let Some(hex) = digits.strip_prefix("0x") {
+            i32::from_str_radix(hex, 16)
+        } else if let Some(binary) = digits.strip_prefix("0b") {
+            i32::from_str_radix(binary, 2)
+        } else {
+            digits.parse()
+        };
+
+        let value = match parsed {
+            Ok(value) if negative && max == 0xFF && value <= 128 => (256 - value) as u16 & 0xFF,
+            Ok(value) if !negative && value <= max as i32 => value as u16,
+            Ok(_) => return Err(error(token, format!("{:?} is out of range", token.text))),
+            Err(_) => {
+                return Err(error(
+                    token,
+                    format!("expected a number, found {:?}", token.text),
+                ))
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn emit_byte(&mut self, byte: u8, token: Token<'a>) -> Result<(), Chip8Error> {
+        let index = self.address as usize - PROGRAM_OFFSET;
+
+        if self.address as usize >= MEMORY_SIZE {
+            return Err(error(token, "program does not fit in memory"));
+        }
+
+        if self.bytes.len() <= index {
+            self.bytes.resize(index + 1, 0);
+        }
+
+        self.bytes[index] = byte;
+        self.lines.entry(token.line).or_insert(self.address);
+        self.address += 1;
+
+        Ok(())
+    }
+
+    fn emit(&mut self, word: u16, token: Token<'a>) -> Result<(), Chip8Error> {
+        self.emit_byte((word >> 8) as u8, token)?;
+        self.emit_byte(word as u8, token)
+    }
+
+    /// Emits an instruction whose low 12 bits are the address of a label.
+    fn emit_with_label(&mut self, word: u16, label: Token<'a>) -> Result<(), Chip8Error> {
+        self.fixups.push((self.address, label));
+        self.emit(word, label)
+    }
+
+    /// Points the jump at `jump` to `target`.
+    fn patch_jump(&mut self, jump: u16, target: u16) {
+        let index = jump as usize - PROGRAM_OFFSET;
+        self.bytes[index] = 0x10 | (target >> 8) as u8 & 0x0F;
+        self.bytes[index + 1] = target as u8;
+    }
+}
+
+/// Parses `v0` to `vF` (in either case).
+fn parse_register(text: &str) -> Option<u8> {
+    let digit = text.strip_prefix(['v', 'V'])?;
+
+    match digit.len() {
+        1 => u8::from_str_radix(digit, 16).ok(),
+        _ => None,
+    }
+}
+
+fn error(token: Token<'_>, message: impl Into<String>) -> Chip8Error {
+    Chip8Error::Assembly {
+        line: token.line,
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{assemble, assemble_with_source_map};
+    use crate::Chip8Error;
+
+    #[test]
+    fn statements_assemble_to_instructions() {
+        let program = assemble(
+            ": main
+                clear
+                v0 := 5 # Comments are ignored
+                v1 += v0
+                i := sprite
+                sprite v0 v1 3
+                if v0 != 5 then v2 := key
+                jump main
+             : sprite 0xF0 0b10010000 -16",
+        )
+        .unwrap();
+
+        assert_eq!(
+            program,
+            [
+                0x12, 0x02, 0x00, 0xE0, 0x60, 0x05, 0x81, 0x04, 0xA2, 0x12, 0xD0, 0x13, 0x30, 0x05,
+                0xF2, 0x0A, 0x12, 0x02, 0xF0, 0x90, 0xF0,
+            ]
+        );
+    }
+
+    #[test]
+    fn blocks_assemble_to_jumps() {
+        let program = assemble(
+            ": main
+                loop
+                    while v0 != 3
+                    if v0 key begin v1 := 1 else v1 := 2 end
+                    v0 += 1
+                again",
+        )
+        .unwrap();
+
+        assert_eq!(
+            program,
+            [
+                0x12, 0x02, // jump main
+                0x40, 0x03, 0x12, 0x14, // while v0 != 3
+                0xE0, 0x9E, 0x12, 0x0E, // if v0 key begin
+                0x61, 0x01, 0x12, 0x10, // v1 := 1 else
+                0x61, 0x02, // v1 := 2 end
+                0x70, 0x01, // v0 += 1
+                0x12, 0x02, // again
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_map_to_addresses() {
+        let (_, source_map) = assemble_with_source_map(
+            ": main
+                # Waits for a key.
+                v0 := key
+                jump main",
+        )
+        .unwrap();
+
+        assert_eq!(source_map.address_of_line(2), Some((3, 0x202)));
+        assert_eq!(source_map.line_of_address(0x205), Some(4));
+        assert_eq!(source_map.labels()[&0x202], "main");
+    }
+
+    #[test]
+    fn negative_numbers_only_fit_in_bytes() {
+        assert_eq!(
+            assemble(": main\n  v0 := -1\n").unwrap(),
+            [0x12, 0x02, 0x60, 0xFF]
+        );
+
+        for statement in ["sprite v0 v1 -1", "i := -1", ":org -1", "jump -2"] {
+            assert!(
+                assemble(&format!(": main\n  {statement}\n")).is_err(),
+                "{statement} assembled"
+            );
+        }
+    }
+
+    #[test]
+    fn errors_point_at_their_line() {
+        let error = assemble(": main\n  v0 := 5\n  jump nowhere\n").unwrap_err();
+
+        assert!(matches!(error, Chip8Error::Assembly { line: 3, .. }));
+    }
+}
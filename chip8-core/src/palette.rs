@@ -0,0 +1,47 @@
+//! Colors used when converting the screen into pixels for the frontend.
+
+/// The colors that white and black CHIP-8 pixels are rendered as.
+///
+/// Colors are encoded as `0RGB` (so `0x00FF0000` is red), which is the
+/// format expected by the frontend's frame buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    /// The color used for pixels that are on.
+    pub foreground: u32,
+    /// The color used for pixels that are off.
+    pub background: u32,
+}
+
+impl Palette {
+    /// White pixels on a black background.
+    pub const CLASSIC: Self = Self {
+        foreground: 0x00FFFFFF,
+        background: 0x00000000,
+    };
+
+    /// Green pixels on a dark green background, like an old P1 phosphor monitor.
+    pub const GREEN_PHOSPHOR: Self = Self {
+        foreground: 0x0033FF66,
+        background: 0x00051A0A,
+    };
+
+    /// Amber pixels on a dark brown background, like an old P3 phosphor monitor.
+    pub const AMBER: Self = Self {
+        foreground: 0x00FFB000,
+        background: 0x001A1000,
+    };
+
+    /// Returns the palette with the foreground and background swapped.
+    pub fn inverted(self) -> Self {
+        Self {
+            foreground: self.background,
+            background: self.foreground,
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::CLASSIC
+    }
+}
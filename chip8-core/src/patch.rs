@@ -0,0 +1,283 @@
+//! Applying [IPS](https://zerosoft.zophar.net/ips.php) and
+//! [BPS](https://github.com/blakesmith/rombp/blob/master/docs/bps_spec.md)
+//! patches to ROMs, like the bugfix patches shared for some games.
+//!
+//! Patches are checked against the memory the ROM is loaded into, so a patch
+//! can't write past the end of memory.
+
+use super::memory::MEMORY_SIZE;
+use super::Chip8Error;
+
+/// Applies `patch` to `rom`, which will be loaded at `load_offset`. The kind
+/// of patch is worked out from its header.
+pub fn apply(rom: &[u8], patch: &[u8], load_offset: usize) -> Result<Vec<u8>, Chip8Error> {
+    let capacity = MEMORY_SIZE.saturating_sub(load_offset);
+
+    if let Some(records) = patch.strip_prefix(b"PATCH") {
+        apply_ips(rom, records, capacity)
+    } else if patch.starts_with(b"BPS1") {
+        apply_bps(rom, patch, capacity)
+    } else {
+        Err(invalid("not an IPS or BPS patch"))
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> Chip8Error {
+    Chip8Error::InvalidPatch {
+        reason: reason.into(),
+    }
+}
+
+/// Reads bytes from a patch, failing if it ends early.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], Chip8Error> {
+        if count > self.bytes.len() {
+            return Err(invalid("the patch ends early"));
+        }
+
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    /// Reads a big-endian number `count` bytes long.
+    fn number(&mut self, count: usize) -> Result<usize, Chip8Error> {
+        Ok(self
+            .take(count)?
+            .iter()
+            .fold(0, |number, &byte| number << 8 | byte as usize))
+    }
+
+    /// Reads one of BPS's variable-length numbers.
+    fn varint(&mut self) -> Result<usize, Chip8Error> {
+        let mut number = 0usize;
+        let mut shift = 1usize;
+
+        loop {
+            let byte = self.take(1)?[0];
+            number = (byte as usize & 0x7F)
+                .checked_mul(shift)
+                .and_then(|value| number.checked_add(value))
+                .ok_or_else(|| invalid("number too large"))?;
+
+            if byte & 0x80 != 0 {
+                return Ok(number);
+            }
+
+            shift = shift
+                .checked_mul(0x80)
+                .ok_or_else(|| invalid("number too large"))?;
+            number = number
+                .checked_add(shift)
+                .ok_or_else(|| invalid("number too large"))?;
+        }
+    }
+}
+
+fn apply_ips(rom: &[u8], records: &[u8], capacity: usize) -> Result<Vec<u8>, Chip8Error> {
+    let mut patched = rom.to_vec();
+    let mut reader = Reader { bytes: records };
+
+    loop {
+        let header = reader.take(3)?;
+
+        if header == b"EOF" {
+            break;
+        }
+
+        let offset = header
+            .iter()
+            .fold(0, |number, &byte| number << 8 | byte as usize);
+        let size = reader.number(2)?;
+
+        // A size of 0 means the record is one byte repeated.
+        let data = match size {
+            0 => {
+                let count = reader.number(2)?;
+                vec![reader.take(1)?[0]; count]
+            }
+            size => reader.take(size)?.to_vec(),
+        };
+
+        let end = offset + data.len();
+
+        if end > capacity {
+            return Err(invalid(format!(
+                "a record writes up to 0x{end:X}, but only 0x{capacity:X} bytes fit in memory"
+            )));
+        }
+
+        if end > patched.len() {
+            patched.resize(end, 0);
+        }
+
+        patched[offset..end].copy_from_slice(&data);
+    }
+
+    // Some patches truncate the ROM after the end marker.
+    if let Ok(size) = reader.number(3) {
+        patched.truncate(size);
+    }
+
+    Ok(patched)
+}
+
+fn apply_bps(rom: &[u8], patch: &[u8], capacity: usize) -> Result<Vec<u8>, Chip8Error> {
+    // The patch ends with the checksums of the source, the target and
+    // everything in the patch before its own checksum.
+    if patch.len() < 4 + 12 {
+        return Err(invalid("the patch ends early"));
+    }
+
+    let (body, checksums) = patch.split_at(patch.len() - 12);
+    let checksum =
+        |index: usize| u32::from_le_bytes(checksums[index * 4..index * 4 + 4].try_into().unwrap());
+
+    if crc32(&patch[..patch.len() - 4]) != checksum(2) {
+        return Err(invalid("the patch is corrupt"));
+    }
+
+    if crc32(rom) != checksum(0) {
+        return Err(invalid("the patch is for a different ROM"));
+    }
+
+    let mut reader = Reader { bytes: &body[4..] };
+
+    let source_size = reader.varint()?;
+    let target_size = reader.varint()?;
+    let metadata_size = reader.varint()?;
+    reader.take(metadata_size)?;
+
+    if source_size != rom.len() {
+        return Err(invalid("the patch is for a different ROM"));
+    }
+
+    if target_size > capacity {
+        return Err(invalid(format!(
+            "the patched ROM is 0x{target_size:X} bytes, but only 0x{capacity:X} bytes fit in memory"
+        )));
+    }
+
+    let mut target = Vec::with_capacity(target_size);
+    let mut source_offset = 0usize;
+    let mut target_offset = 0usize;
+
+    while !reader.bytes.is_empty() {
+        let action = reader.varint()?;
+        let length = (action >> 2) + 1;
+
+        if target.len() + length > target_size {
+            return Err(invalid("an action writes past the end of the patched ROM"));
+        }
+
+        match action & 3 {
+            // Copies from the same place in the source.
+            0 => {
+                let bytes = rom
+                    .get(target.len()..target.len() + length)
+                    .ok_or_else(|| invalid("an action reads past the end of the ROM"))?;
+                target.extend_from_slice(bytes);
+            }
+            // Copies bytes from the patch.
+            1 => target.extend_from_slice(reader.take(length)?),
+            // Copies from elsewhere in the source.
+            2 => {
+                source_offset = relative(source_offset, reader.varint()?)?;
+                let bytes = rom
+                    .get(source_offset..source_offset + length)
+                    .ok_or_else(|| invalid("an action reads past the end of the ROM"))?;
+                target.extend_from_slice(bytes);
+                source_offset += length;
+            }
+            // Copies from earlier in the target, which can overlap what is
+            // being written, so it goes a byte at a time.
+            _ => {
+                target_offset = relative(target_offset, reader.varint()?)?;
+
+                for _ in 0..length {
+                    let byte = *target.get(target_offset).ok_or_else(|| {
+                        invalid("an action reads past the end of the patched ROM")
+                    })?;
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    if target.len() != target_size || crc32(&target) != checksum(1) {
+        return Err(invalid(
+            "the patched ROM doesn't match the patch's checksum",
+        ));
+    }
+
+    Ok(target)
+}
+
+/// Moves `offset` by one of BPS's signed relative offsets.
+fn relative(offset: usize, encoded: usize) -> Result<usize, Chip8Error> {
+    let distance = encoded >> 1;
+
+    match encoded & 1 {
+        0 => offset.checked_add(distance),
+        _ => offset.checked_sub(distance),
+    }
+    .ok_or_else(|| invalid("an action reads before the start of the ROM"))
+}
+
+/// The CRC-32 checksum used by BPS patches.
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| match crc & 1 {
+            0 => crc >> 1,
+            _ => crc >> 1 ^ 0xEDB8_8320,
+        })
+    })
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{apply, crc32};
+
+    #[test]
+    fn ips_patches_are_applied() {
+        let rom = [0x00, 0xE0, 0x12, 0x00];
+
+        // Replaces the jump with a call, and adds three 0xFF bytes past the end.
+        let patch = b"PATCH\x00\x00\x02\x00\x02\x22\x02\x00\x00\x04\x00\x00\x00\x03\xFFEOF";
+
+        assert_eq!(
+            apply(&rom, patch, 0x200).unwrap(),
+            [0x00, 0xE0, 0x22, 0x02, 0xFF, 0xFF, 0xFF]
+        );
+
+        // 0xE00 bytes fit after 0x200, so a record can't end past that.
+        let patch = b"PATCH\x00\x0D\xFF\x00\x02\x00\x00EOF";
+        assert!(apply(&rom, patch, 0x200).is_err());
+    }
+
+    #[test]
+    fn bps_patches_are_applied() {
+        let rom = [0x00, 0xE0, 0x12, 0x00];
+        let target = [0x00, 0xE0, 0x22, 0x02, 0x00, 0xE0];
+
+        let mut patch = b"BPS1".to_vec();
+        // The sizes of the source, target and metadata, as varints.
+        patch.extend([0x84, 0x86, 0x80]);
+        // Reads 2 bytes from the source, writes 2 bytes from the patch, then
+        // copies the first 2 bytes of the target.
+        patch.extend([0x84, 0x85, 0x22, 0x02, 0x87, 0x80]);
+        patch.extend(crc32(&rom).to_le_bytes());
+        patch.extend(crc32(&target).to_le_bytes());
+        patch.extend(crc32(&patch).to_le_bytes());
+
+        assert_eq!(apply(&rom, &patch, 0x200).unwrap(), target);
+
+        // Patches are made for one ROM.
+        assert!(apply(&[0x00, 0xE0, 0x12, 0x02], &patch, 0x200).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! Memory-mapped peripherals: host code that stands in for a range of
+//! memory, so programs can talk to it with the usual memory instructions.
+//! This makes room for experiments like a real-time clock a program reads
+//! the time from, or a serial port it prints through.
+//!
+//! Only the instructions that read and write data go through peripherals:
+//! `FX33` and `FX55` write to them, and `FX65` and the sprite rows of `DXYN`
+//! read from them. Instructions are always fetched from memory, and the
+//! memory under a peripheral is left as it is. Nothing written to a
+//! peripheral is undone by [`Chip8::step_back`] or kept in save states.
+//!
+//! 0x000 to 0x04F is free for peripherals on every interpreter, as the fonts
+//! start after it.
+
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use super::console::CONSOLE_ADDRESS;
+use super::{Chip8, Chip8Error};
+
+/// A device mapped into memory with [`Chip8::map_peripheral`].
+pub trait Peripheral: fmt::Debug + Send {
+    /// Returns the byte a program reads at `offset`, counted from the first
+    /// address the peripheral is mapped at.
+    fn read(&mut self, offset: u16) -> u8;
+
+    /// Called when a program writes `value` at `offset`.
+    fn write(&mut self, offset: u16, value: u8);
+}
+
+/// The peripherals mapped into memory.
+#[derive(Debug, Default)]
+pub(crate) struct Peripherals {
+    mapped: Vec<(RangeInclusive<u16>, Box<dyn Peripheral>)>,
+}
+
+impl Peripherals {
+    /// Returns true if a peripheral is mapped at `address`.
+    pub(crate) fn contains(&self, address: u16) -> bool {
+        self.mapped
+            .iter()
+            .any(|(addresses, _)| addresses.contains(&address))
+    }
+
+    /// Returns the peripheral mapped at `address`, and the offset of the
+    /// address into it.
+    fn at(&mut self, address: u16) -> Option<(&mut Box<dyn Peripheral>, u16)> {
+        self.mapped
+            .iter_mut()
+            .find(|(addresses, _)| addresses.contains(&address))
+            .map(|(addresses, peripheral)| (peripheral, address - addresses.start()))
+    }
+}
+
+impl Chip8 {
+    /// Maps `peripheral` into memory at `addresses`, so the data
+    /// instructions read and write it instead of memory there.
+    ///
+    /// Returns [`Chip8Error::InvalidPeripheralMapping`] if the addresses are
+    /// past the end of memory or another peripheral is already mapped at any
+    /// of them.
+    pub fn map_peripheral(
+        &mut self,
+        addresses: RangeInclusive<u16>,
+        peripheral: Box<dyn Peripheral>,
+    ) -> Result<(), Chip8Error> {
+        let invalid = |reason: String| Chip8Error::InvalidPeripheralMapping { reason };
+
+        if addresses.is_empty() || *addresses.end() > self.memory.size().last_address() {
+            return Err(invalid(format!(
+                "0x{:03X}-0x{:03X} isn't in memory",
+                addresses.start(),
+                addresses.end()
+            )));
+        }
+
+        if let Some((mapped, _)) = self.peripherals.mapped.iter().find(|(mapped, _)| {
+            mapped.start() <= addresses.end() && addresses.start() <= mapped.end()
+        }) {
+            return Err(invalid(format!(
+                "0x{:03X}-0x{:03X} is already mapped",
+                mapped.start(),
+                mapped.end()
+            )));
+        }
+
+        self.peripherals.mapped.push((addresses, peripheral));
+        Ok(())
+    }
+
+    /// Removes every peripheral, leaving memory where they were mapped.
+    pub fn unmap_peripherals(&mut self) {
+        self.peripherals.mapped.clear();
+    }
+
+    /// Reads the byte at `address` for an instruction, from a peripheral if
+    /// one is mapped there.
+    pub(crate) fn read_data(&mut self, address: u16) -> u8 {
+        let address = self.memory.wrap(address);
+        self.record_read(address);
+
+        match self.peripherals.at(address) {
+            Some((peripheral, offset)) => peripheral.read(offset),
+            None => self.memory.byte(address as usize),
+        }
+    }
+
+    /// Writes the byte at `address` for an instruction, to the console or a
+    /// peripheral if either is there.
+    pub(crate) fn write_data(&mut self, address: u16, value: u8) {
+        let address = self.memory.wrap(address);
+
+        if self.quirks.console_output && address == CONSOLE_ADDRESS {
+            self.console_output.push(value);
+        } else if let Some((peripheral, offset)) = self.peripherals.at(address) {
+            peripheral.write(offset, value);
+        } else {
+            self.memory.set_byte(address as usize, value);
+        }
+
+        self.record_write(address, value);
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::Peripheral;
+    use crate::Chip8;
+    use std::sync::{Arc, Mutex};
+
+    /// Counts up every time it is read, and keeps what is written to it.
+    #[derive(Debug, Default)]
+    struct Counter {
+        count: u8,
+        written: Arc<Mutex<Vec<(u16, u8)>>>,
+    }
+
+    impl Peripheral for Counter {
+        fn read(&mut self, _offset: u16) -> u8 {
+            self.count += 1;
+            self.count
+        }
+
+        fn write(&mut self, offset: u16, value: u8) {
+            self.written.lock().unwrap().push((offset, value));
+        }
+    }
+
+    #[test]
+    fn data_instructions_go_through_peripherals() {
+        let program = vec![
+            0x60, 0x2A, // V0 = 42
+            0x61, 0x07, // V1 = 7
+            0xA0, 0x10, // I = 0x010
+            0xF1, 0x55, // save V0 and V1 at I
+            0xF1, 0x65, // load V0 and V1 from I
+            0x12, 0x0A, // loop forever
+        ];
+
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8.load_program(program).unwrap();
+
+        let counter = Counter::default();
+        let written = Arc::clone(&counter.written);
+        chip_8
+            .map_peripheral(0x010..=0x01F, Box::new(counter))
+            .unwrap();
+        assert!(chip_8
+            .map_peripheral(0x01F..=0x020, Box::<Counter>::default())
+            .is_err());
+
+        for _ in 0..5 {
+            chip_8.cycle().unwrap();
+        }
+
+        assert_eq!(*written.lock().unwrap(), [(0, 42), (1, 7)]);
+        assert_eq!(chip_8.registers[..2], [1, 2]);
+        // The memory under the peripheral wasn't touched.
+        assert_eq!(chip_8.memory()[0x010..0x012], [0, 0]);
+    }
+}
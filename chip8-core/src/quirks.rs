@@ -0,0 +1,45 @@
+//! Behaviours that differ between CHIP-8 interpreters.
+//!
+//! Programs written for one interpreter sometimes rely on its particular
+//! behaviour, so the ones we know about can be switched on and off here.
+//! The defaults match what most modern programs expect.
+
+/// The set of quirks the emulator should follow.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// On the original COSMAC VIP, `DXYN` waits for the next vertical blank
+    /// (60 times a second) before drawing. This limits programs to one sprite
+    /// per frame, and some games rely on it to run at the right speed.
+    ///
+    /// Only has an effect when cycles are run with
+    /// [`Chip8::run_frame`](super::Chip8::run_frame). Cycles run one at a time
+    /// with [`Chip8::cycle`](super::Chip8::cycle) draw straight away.
+    pub display_wait: bool,
+
+    /// Whether sprites drawn past the right or bottom edge of the screen wrap
+    /// around to the opposite edge instead of being clipped. Clipping is what
+    /// most interpreters do, but some programs (like some versions of BLITZ)
+    /// expect wrapping.
+    pub wrap_sprites: bool,
+
+    /// On the original COSMAC VIP, `8XY6` and `8XYE` shift VY and store the
+    /// result in VX. CHIP-48 and SUPER-CHIP shift VX in place and ignore VY,
+    /// which is what most programs since then expect.
+    pub shift_vy: bool,
+
+    /// On the original COSMAC VIP, `FX55` and `FX65` leave I just past the
+    /// last register they stored or loaded, by adding X + 1 to it. Later
+    /// interpreters leave I where it was, but some older games count on
+    /// stepping through memory this way.
+    pub increment_index: bool,
+
+    /// The CHIP-8 interpreter for the Amiga sets VF to 1 when `FX1E` takes I
+    /// past the end of memory (0xFFF), and to 0 otherwise. Spacefight 2091! relies on it, but
+    /// other interpreters leave VF alone.
+    pub index_overflow_flag: bool,
+
+    /// Not a quirk of any real interpreter, but an extension for debugging:
+    /// bytes written to 0x000 are printed to the host's console instead of
+    /// being stored. See [`console`](super::console).
+    pub console_output: bool,
+}
@@ -0,0 +1,298 @@
+//! Recordings of the keys held during each 60Hz frame of a run, which play
+//! back to exactly the same run as long as the emulator starts out the same
+//! way. A [`Replay`] keeps the settings that change how a program runs along
+//! with the input, so it can set up the emulator itself with
+//! [`Replay::start`].
+//!
+//! Replays are saved as `.c8rec` files with [`Replay::to_bytes`]. The format
+//! starts with `C8RC` and a version byte, followed by the ROM's SHA-1 hash,
+//! the load address, the random seed, the quirks, the timing model, the
+//! frames the program was restarted on and then the keys of every frame,
+//! with numbers in big endian.
+
+use super::keypad::Keys;
+use super::quirks::Quirks;
+use super::timing::TimingModel;
+use super::{Chip8, Chip8Error};
+
+/// The first bytes of a replay file.
+const MAGIC: &[u8; 4] = b"C8RC";
+/// Changed whenever the layout of replay files does.
+const FORMAT_VERSION: u8 = 1;
+
+/// The input for one 60Hz frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FrameInput {
+    /// The keys held down during the frame.
+    pub keys: Keys,
+    /// Whether the program was restarted at the start of the frame.
+    pub restart: bool,
+}
+
+impl FrameInput {
+    /// Runs a frame of `chip_8` with this input.
+    pub fn run(self, chip_8: &mut Chip8) -> Result<(), Chip8Error> {
+        chip_8.needs_program_restart |= self.restart;
+        chip_8.set_keys(self.keys);
+        chip_8.run_frame()
+    }
+}
+
+/// A recording of a run. See the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+    /// The SHA-1 hash of the ROM it was recorded with.
+    pub rom_sha1: [u8; 20],
+    /// The address the ROM was loaded at.
+    pub load_offset: u16,
+    /// The seed given to [`Chip8::set_random_seed`], so `CXNN` picks the
+    /// same numbers.
+    pub random_seed: u64,
+    /// The quirks that were on.
+    pub quirks: Quirks,
+    /// How long instructions took.
+    pub timing: TimingModel,
+    /// The input of each frame, in order.
+    pub frames: Vec<FrameInput>,
+}
+
+impl Replay {
+    /// Starts an empty recording of `chip_8`, which should have just had the
+    /// ROM with the hash `rom_sha1` loaded at `load_offset`, and the random
+    /// seed set to `random_seed`.
+    pub fn new(chip_8: &Chip8, rom_sha1: [u8; 20], load_offset: u16, random_seed: u64) -> Self {
+        Self {
+            rom_sha1,
+            load_offset,
+            random_seed,
+            quirks: chip_8.quirks,
+            timing: chip_8.timing_model(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Sets up an emulator to play the replay back from the start, which is
+    /// done by running each of [`Self::frames`] in turn.
+    ///
+    /// Returns [`Chip8Error::InvalidReplay`] if `program` isn't the ROM the
+    /// replay was recorded with.
+    pub fn start(&self, program: Vec<u8>) -> Result<Chip8, Chip8Error> {
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize()?;
+
+        let loaded_rom = chip_8.load_program_at(self.load_offset as usize, program)?;
+
+        if loaded_rom.sha1 != self.rom_sha1 {
+            return Err(invalid("it was recorded with a different ROM"));
+        }
+
+        chip_8.quirks = self.quirks;
+        chip_8.set_timing_model(self.timing);
+        chip_8.set_random_seed(self.random_seed);
+
+        Ok(chip_8)
+    }
+
+    /// Encodes the replay for saving to a `.c8rec` file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(64 + self.frames.len() * 2);
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&self.rom_sha1);
+        bytes.extend_from_slice(&self.load_offset.to_be_bytes());
+        bytes.extend_from_slice(&self.random_seed.to_be_bytes());
+
+        let mut quirks = self.quirks;
+        let quirk_bits = quirk_flags(&mut quirks)
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits, (bit, &&mut on)| bits | (u32::from(on) << bit));
+        bytes.extend_from_slice(&quirk_bits.to_be_bytes());
+
+        match self.timing {
+            TimingModel::Fixed(cycles_per_second) => {
+                bytes.push(0);
+                bytes.extend_from_slice(&cycles_per_second.to_be_bytes());
+            }
+            TimingModel::Vip => bytes.push(1),
+        }
+
+        // Restarts are rare, so they are kept apart from the keys as the
+        // indices of the frames they happened on.
+        let restarts: Vec<u32> = (0..)
+            .zip(&self.frames)
+            .filter(|(_, frame)| frame.restart)
+            .map(|(index, _)| index)
+            .collect();
+
+        bytes.extend_from_slice(&(restarts.len() as u32).to_be_bytes());
+        for index in restarts {
+            bytes.extend_from_slice(&index.to_be_bytes());
+        }
+
+        bytes.extend_from_slice(&(self.frames.len() as u32).to_be_bytes());
+        for frame in &self.frames {
+            bytes.extend_from_slice(&frame.keys.0.to_be_bytes());
+        }
+
+        bytes
+    }
+
+    /// Decodes a replay encoded with [`Self::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Chip8Error> {
+        let mut reader = Reader { bytes };
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("it isn't a replay"));
+        }
+
+        if reader.array::<1>()?[0] != FORMAT_VERSION {
+            return Err(invalid("it was written by a different version"));
+        }
+
+        let rom_sha1 = reader.array()?;
+        let load_offset = u16::from_be_bytes(reader.array()?);
+        let random_seed = u64::from_be_bytes(reader.array()?);
+
+        let quirk_bits = u32::from_be_bytes(reader.array()?);
+        let mut quirks = Quirks::default();
+        for (bit, flag) in quirk_flags(&mut quirks).into_iter().enumerate() {
+            *flag = quirk_bits & (1 << bit) != 0;
+        }
+
+        let timing = match reader.array::<1>()?[0] {
+            0 => TimingModel::Fixed(u32::from_be_bytes(reader.array()?)),
+            1 => TimingModel::Vip,
+            model => return Err(invalid(&format!("unknown timing model {model}"))),
+        };
+
+        let restarts: Vec<u32> = (0..reader.u32()?)
+            .map(|_| reader.u32())
+            .collect::<Result<_, _>>()?;
+
+        let mut frames: Vec<FrameInput> = (0..reader.u32()?)
+            .map(|_| {
+                Ok(FrameInput {
+                    keys: Keys(u16::from_be_bytes(reader.array()?)),
+                    restart: false,
+                })
+            })
+            .collect::<Result<_, Chip8Error>>()?;
+
+        for index in restarts {
+            frames
+                .get_mut(index as usize)
+                .ok_or_else(|| invalid("a restart is past the last frame"))?
+                .restart = true;
+        }
+
+        if !reader.bytes.is_empty() {
+            return Err(invalid("there are bytes after the last frame"));
+        }
+
+        Ok(Self {
+            rom_sha1,
+            load_offset,
+            random_seed,
+            quirks,
+            timing,
+            frames,
+        })
+    }
+}
+
+/// The quirks in the order of their bits in a replay file. New quirks go on
+/// the end, so older replays are still read the same.
+fn quirk_flags(quirks: &mut Quirks) -> [&mut bool; 6] {
+    [
+        &mut quirks.display_wait,
+        &mut quirks.wrap_sprites,
+        &mut quirks.shift_vy,
+        &mut quirks.increment_index,
+        &mut quirks.index_overflow_flag,
+        &mut quirks.console_output,
+    ]
+}
+
+fn invalid(reason: &str) -> Chip8Error {
+    Chip8Error::InvalidReplay {
+        reason: reason.to_string(),
+    }
+}
+
+/// Reads the fields of a replay file from the front of its bytes.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Chip8Error> {
+        if len > self.bytes.len() {
+            return Err(invalid("it ends early"));
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Chip8Error> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u32(&mut self) -> Result<u32, Chip8Error> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{FrameInput, Replay};
+    use crate::{Chip8, Keys};
+
+    #[test]
+    fn replays_play_back_the_same_run() {
+        // Adds a random number to V1 for every frame key 5 is held, and
+        // draws V1's digit each time.
+        let program = vec![
+            0x65, 0x05, // V5 = 5
+            0xE5, 0xA1, // skip unless key 5 is held
+            0x12, 0x0C, // jump to the draw
+            0xC0, 0x0F, // V0 = random & 0x0F
+            0x81, 0x04, // V1 += V0
+            0x00, 0xE0, // clear the screen
+            0xF1, 0x29, // I = the digit in V1
+            0xD2, 0x25, // draw it
+            0x12, 0x02, // and start over
+        ];
+
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        let loaded_rom = chip_8.load_program(program.clone()).unwrap();
+        chip_8.set_random_seed(7);
+
+        let mut replay = Replay::new(&chip_8, loaded_rom.sha1, 0x200, 7);
+
+        for frame in 0..30 {
+            let input = FrameInput {
+                keys: Keys(u16::from(frame % 3 == 0) << 5),
+                restart: frame == 20,
+            };
+            replay.frames.push(input);
+            input.run(&mut chip_8).unwrap();
+        }
+
+        let replay = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        assert!(replay.frames[20].restart);
+
+        let mut played = replay.start(program).unwrap();
+
+        for input in &replay.frames {
+            input.run(&mut played).unwrap();
+        }
+
+        assert_eq!(played.save_state(), chip_8.save_state());
+        assert!(replay.start(vec![0x12, 0x00]).is_err());
+    }
+}
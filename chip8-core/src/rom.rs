@@ -0,0 +1,33 @@
+//! Information about programs after they have been loaded into memory.
+
+use std::fmt;
+
+/// A summary of a program loaded with [`Chip8::load_program`](super::Chip8::load_program).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadedRom {
+    /// The size of the program in bytes.
+    pub size: usize,
+    /// The SHA-1 hash of the program, which can be used to identify it.
+    pub sha1: [u8; 20],
+    /// The address the program starts running from.
+    pub entry_point: u16,
+}
+
+impl LoadedRom {
+    /// Returns the SHA-1 hash as a lowercase hexadecimal string.
+    pub fn sha1_hex(&self) -> String {
+        self.sha1.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+}
+
+impl fmt::Display for LoadedRom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes at 0x{:03X} (SHA-1 {})",
+            self.size,
+            self.entry_point,
+            self.sha1_hex()
+        )
+    }
+}
@@ -0,0 +1,132 @@
+//! The RPL user flags that SUPER-CHIP programs save to with `FX75` and load
+//! from with `FX85`.
+//!
+//! SUPER-CHIP ran on the HP-48 calculator, where these were 8 bytes of the
+//! calculator's own memory that stayed put after the program exited. Games
+//! use them to keep things like high scores, so they aren't cleared by
+//! [`Chip8::initialize`] or [`Chip8::reset`], and frontends can save them
+//! between runs with [`Chip8::rpl_flags`] and [`Chip8::set_rpl_flags`].
+//!
+//! With the `json` feature, flags saved by
+//! [Octo](https://github.com/JohnEarnest/Octo) as a JSON list of numbers can
+//! be read and written with [`flags_from_octo_json`] and
+//! [`flags_to_octo_json`] to carry them between the two.
+
+use super::Chip8;
+#[cfg(feature = "json")]
+use super::Chip8Error;
+
+/// How many RPL user flags there are.
+pub const RPL_FLAG_COUNT: usize = 8;
+/// How many flags Octo keeps. XO-CHIP lets `FX75` save all 16 registers.
+pub const OCTO_FLAG_COUNT: usize = 16;
+
+/// Writes flags the way Octo saves them, as a JSON list of
+/// [`OCTO_FLAG_COUNT`] numbers, with `null` for the ones never saved to.
+#[cfg(feature = "json")]
+pub fn flags_to_octo_json(flags: &[Option<u8>; OCTO_FLAG_COUNT]) -> String {
+    serde_json::to_string(flags).expect("a list of numbers can always be written")
+}
+
+/// Reads flags saved by Octo as a JSON list of numbers. Flags Octo never
+/// saved to are `null`, as are any missing from the end.
+///
+/// All of them are kept, not just the first [`RPL_FLAG_COUNT`], so they can
+/// be written back unchanged.
+///
+/// Returns [`Chip8Error::InvalidOctoFlags`] if it isn't a list of at most
+/// [`OCTO_FLAG_COUNT`] bytes.
+#[cfg(feature = "json")]
+pub fn flags_from_octo_json(json: &str) -> Result<[Option<u8>; OCTO_FLAG_COUNT], Chip8Error> {
+    let invalid = |reason: String| Chip8Error::InvalidOctoFlags { reason };
+
+    let list: Vec<Option<u8>> = serde_json::from_str(json)
+        .map_err(|err| invalid(format!("it isn't a list of bytes: {err}")))?;
+
+    if list.len() > OCTO_FLAG_COUNT {
+        return Err(invalid(format!(
+            "it has {} flags, but there are only {OCTO_FLAG_COUNT}",
+            list.len()
+        )));
+    }
+
+    let mut flags = [None; OCTO_FLAG_COUNT];
+    flags[..list.len()].copy_from_slice(&list);
+
+    Ok(flags)
+}
+
+impl Chip8 {
+    /// Returns the RPL user flags.
+    pub fn rpl_flags(&self) -> [u8; RPL_FLAG_COUNT] {
+        self.rpl_flags
+    }
+
+    /// Replaces the RPL user flags, like with ones saved by an earlier run.
+    pub fn set_rpl_flags(&mut self, flags: [u8; RPL_FLAG_COUNT]) {
+        self.rpl_flags = flags;
+    }
+
+    /// Returns true if the program has saved to the RPL user flags since the
+    /// last call, so frontends only write them out when they change.
+    pub fn take_rpl_flags_changed(&mut self) -> bool {
+        std::mem::take(&mut self.rpl_flags_changed)
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    #[cfg(feature = "json")]
+    use super::{flags_from_octo_json, flags_to_octo_json, OCTO_FLAG_COUNT};
+    use crate::Chip8;
+
+    #[test]
+    fn flags_survive_a_reset() {
+        // Sets V0 to V2, saves V0 to V9 to the flags, clears V0 to V2 and
+        // loads them back, then loops forever.
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8
+            .load_program(vec![
+                0x60, 0x0A, 0x61, 0x0B, 0x62, 0x0C, 0xF9, 0x75, 0x60, 0x00, 0x61, 0x00, 0x62, 0x00,
+                0xF2, 0x85, 0x12, 0x10,
+            ])
+            .unwrap();
+
+        for _ in 0..8 {
+            chip_8.cycle().unwrap();
+        }
+
+        assert_eq!(chip_8.registers[..3], [0x0A, 0x0B, 0x0C]);
+        assert_eq!(chip_8.rpl_flags(), [0x0A, 0x0B, 0x0C, 0, 0, 0, 0, 0]);
+        assert!(chip_8.take_rpl_flags_changed());
+        assert!(!chip_8.take_rpl_flags_changed());
+
+        chip_8.reset().unwrap();
+        assert_eq!(chip_8.rpl_flags(), [0x0A, 0x0B, 0x0C, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn flags_are_carried_to_and_from_octo() {
+        let mut flags = [None; OCTO_FLAG_COUNT];
+        flags[..3].copy_from_slice(&[Some(9), None, Some(12)]);
+        flags[15] = Some(255);
+
+        let json = flags_to_octo_json(&flags);
+
+        assert_eq!(
+            json,
+            "[9,null,12,null,null,null,null,null,null,null,null,null,null,null,null,255]"
+        );
+        assert_eq!(flags_from_octo_json(&json).unwrap(), flags);
+
+        let mut short = [None; OCTO_FLAG_COUNT];
+        short[..3].copy_from_slice(&[Some(9), None, Some(12)]);
+        assert_eq!(flags_from_octo_json("[ 9, null, 12 ]\n").unwrap(), short);
+
+        assert!(flags_from_octo_json("[256]").is_err());
+        assert!(flags_from_octo_json("{}").is_err());
+        assert!(flags_from_octo_json(&format!("[{}]", ["0"; 17].join(","))).is_err());
+    }
+}
@@ -0,0 +1,806 @@
+//! Snapshots of the emulator's state, and a history of them that lets the
+//! emulator step backwards.
+//!
+//! States can be written to files with [`SaveState::to_bytes`]. The format
+//! starts with `C8ST` and a version byte, followed by each field in order,
+//! with numbers in big endian and lists prefixed by their length. With the
+//! `lz4` feature, [`SaveState::to_compressed_bytes`] writes `C8SZ` followed
+//! by the same bytes compressed with LZ4. With the `json` feature,
+//! [`SaveState::to_json`] writes them as JSON for editing by hand.
+//!
+//! The history splits memory into pages, and a page that is the same as in
+//! the state before it is shared with that state instead of being copied, so
+//! most states only hold a page or two of memory instead of all of it.
+
+use super::display::Resolution;
+use super::{CallFrame, Chip8, Chip8Error, EmulatorState, Frame, Keys};
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+#[cfg(feature = "json")]
+mod json;
+
+/// Everything needed to put a [`Chip8`] back exactly how it was, apart from
+/// settings like the quirks and clock rate, and the [`Stats`](super::stats::Stats).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaveState {
+    /// All of memory, including the fonts.
+    pub memory: Vec<u8>,
+    /// What is on the screen.
+    pub frame: Frame,
+    /// V0 to VF.
+    pub registers: [u8; 16],
+    /// I.
+    pub index_register: u16,
+    /// The address of the next instruction.
+    pub program_counter: u16,
+    /// The return addresses on the stack, from the bottom up.
+    pub stack: Vec<u16>,
+    /// The value of the delay timer.
+    pub delay_timer: u8,
+    /// The value of the sound timer.
+    pub sound_timer: u8,
+    /// The keys held down.
+    pub keys: Keys,
+    /// See [`Chip8::call_stack`].
+    pub call_stack: Vec<CallFrame>,
+    /// How far the timers are towards their next tick.
+    pub(crate) timer_accumulator: u32,
+    pub(crate) vblank: bool,
+}
+
+/// The first bytes of a save state file.
+const MAGIC: &[u8; 4] = b"C8ST";
+/// The first bytes of a compressed save state file.
+const COMPRESSED_MAGIC: &[u8; 4] = b"C8SZ";
+/// Changed whenever the layout of save state files does.
+const FORMAT_VERSION: u8 = 1;
+
+impl SaveState {
+    /// Encodes the state for saving to a file. See the [module
+    /// documentation](self) for the format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.memory.len() + 2048);
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&(self.memory.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&self.memory);
+        bytes.extend_from_slice(&self.registers);
+        bytes.extend_from_slice(&self.index_register.to_be_bytes());
+        bytes.extend_from_slice(&self.program_counter.to_be_bytes());
+
+        bytes.extend_from_slice(&(self.stack.len() as u16).to_be_bytes());
+        for address in &self.stack {
+            bytes.extend_from_slice(&address.to_be_bytes());
+        }
+
+        bytes.push(self.delay_timer);
+        bytes.push(self.sound_timer);
+        bytes.extend_from_slice(&self.keys.0.to_be_bytes());
+
+        bytes.extend_from_slice(&(self.call_stack.len() as u16).to_be_bytes());
+        for frame in &self.call_stack {
+            bytes.extend_from_slice(&frame.call_site.to_be_bytes());
+            bytes.extend_from_slice(&frame.target.to_be_bytes());
+            bytes.extend_from_slice(&frame.cycle.to_be_bytes());
+        }
+
+        bytes.extend_from_slice(&self.timer_accumulator.to_be_bytes());
+        bytes.push(self.vblank.into());
+
+        // A frame is at most 128x64, so each of these fits in a byte.
+        bytes.push(self.frame.width() as u8);
+        bytes.push(self.frame.height() as u8);
+        bytes.push(self.frame.planes() as u8);
+        for plane in 0..self.frame.planes() {
+            for row in self.frame.plane_rows(plane) {
+                bytes.extend_from_slice(&row.to_be_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    /// Encodes the state like [`Self::to_bytes`], compressed with LZ4.
+    #[cfg(feature = "lz4")]
+    pub fn to_compressed_bytes(&self) -> Vec<u8> {
+        let mut bytes = COMPRESSED_MAGIC.to_vec();
+        bytes.extend(lz4_flex::compress_prepend_size(&self.to_bytes()));
+        bytes
+    }
+
+    /// Decodes a state encoded with [`Self::to_bytes`], or with
+    /// [`Self::to_compressed_bytes`] if the `lz4` feature is on.
+    ///
+    /// Returns [`Chip8Error::InvalidSaveState`] if the bytes aren't a save
+    /// state, or were written by a different version of the format.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Chip8Error> {
+        if let Some(compressed) = bytes.strip_prefix(COMPRESSED_MAGIC) {
+            return Self::from_compressed_bytes(compressed);
+        }
+
+        let mut reader = Reader { bytes };
+
+        if reader.array()? != *MAGIC || reader.u8()? != FORMAT_VERSION {
+            return Err(Chip8Error::InvalidSaveState);
+        }
+
+        let memory_len = u32::from_be_bytes(reader.array()?) as usize;
+        let memory = reader.take(memory_len)?.to_vec();
+        let registers = reader.array()?;
+        let index_register = reader.u16()?;
+        let program_counter = reader.u16()?;
+
+        let stack = (0..reader.u16()?)
+            .map(|_| reader.u16())
+            .collect::<Result<_, _>>()?;
+
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+        let keys = Keys(reader.u16()?);
+
+        let call_stack = (0..reader.u16()?)
+            .map(|_| {
+                Ok(CallFrame {
+                    call_site: reader.u16()?,
+                    target: reader.u16()?,
+                    cycle: u64::from_be_bytes(reader.array()?),
+                })
+            })
+            .collect::<Result<_, Chip8Error>>()?;
+
+        let timer_accumulator = u32::from_be_bytes(reader.array()?);
+        let vblank = reader.u8()? != 0;
+
+        let resolution = Resolution {
+            width: reader.u8()?.into(),
+            height: reader.u8()?.into(),
+        };
+        let plane_count = reader.u8()?.into();
+        let rows: Vec<u128> = (0..plane_count * resolution.height)
+            .map(|_| Ok(u128::from_be_bytes(reader.array()?)))
+            .collect::<Result<_, Chip8Error>>()?;
+        let frame =
+            Frame::from_rows(resolution, plane_count, &rows).ok_or(Chip8Error::InvalidSaveState)?;
+
+        if !reader.bytes.is_empty() {
+            return Err(Chip8Error::InvalidSaveState);
+        }
+
+        Ok(Self {
+            memory,
+            frame,
+            registers,
+            index_register,
+            program_counter,
+            stack,
+            delay_timer,
+            sound_timer,
+            keys,
+            call_stack,
+            timer_accumulator,
+            vblank,
+        })
+    }
+
+    #[cfg(feature = "lz4")]
+    fn from_compressed_bytes(compressed: &[u8]) -> Result<Self, Chip8Error> {
+        let bytes = lz4_flex::decompress_size_prepended(compressed)
+            .map_err(|_| Chip8Error::InvalidSaveState)?;
+
+        // A compressed state can't hold another one.
+        match bytes.starts_with(COMPRESSED_MAGIC) {
+            true => Err(Chip8Error::InvalidSaveState),
+            false => Self::from_bytes(&bytes),
+        }
+    }
+
+    #[cfg(not(feature = "lz4"))]
+    fn from_compressed_bytes(_compressed: &[u8]) -> Result<Self, Chip8Error> {
+        Err(Chip8Error::InvalidSaveState)
+    }
+
+    /// Returns what differs between this state and `other`, with this state
+    /// as the "before" side.
+    pub fn diff(&self, other: &Self) -> StateDiff {
+        let mut registers = Vec::new();
+        let mut compare = |name: String, before: u16, after: u16| {
+            if before != after {
+                registers.push(RegisterChange {
+                    name,
+                    before,
+                    after,
+                });
+            }
+        };
+
+        compare(
+            "pc".to_string(),
+            self.program_counter,
+            other.program_counter,
+        );
+        compare("i".to_string(), self.index_register, other.index_register);
+        compare(
+            "sp".to_string(),
+            self.stack.len() as u16,
+            other.stack.len() as u16,
+        );
+
+        for (index, (&before, &after)) in self.stack.iter().zip(&other.stack).enumerate() {
+            compare(format!("s{index}"), before, after);
+        }
+
+        for (register, (&before, &after)) in self.registers.iter().zip(&other.registers).enumerate()
+        {
+            compare(format!("v{register:x}"), before.into(), after.into());
+        }
+
+        compare(
+            "dt".to_string(),
+            self.delay_timer.into(),
+            other.delay_timer.into(),
+        );
+        compare(
+            "st".to_string(),
+            self.sound_timer.into(),
+            other.sound_timer.into(),
+        );
+
+        // Neighbouring addresses that changed are merged into one range.
+        let mut memory: Vec<Range<usize>> = Vec::new();
+
+        for (address, (before, after)) in self.memory.iter().zip(&other.memory).enumerate() {
+            if before == after {
+                continue;
+            }
+
+            match memory.last_mut() {
+                Some(range) if range.end == address => range.end += 1,
+                _ => memory.push(address..address + 1),
+            }
+        }
+
+        // Neighbouring rows that changed are merged into one region, covering
+        // the changed pixels of all of them.
+        let mut screen: Vec<ScreenRegion> = Vec::new();
+
+        let width = self.frame.width();
+        let unused_bits = u128::BITS as usize - width;
+        let planes = self.frame.planes().min(other.frame.planes());
+
+        for y in 0..self.frame.height().min(other.frame.height()) {
+            let changed = (0..planes).fold(0, |changed, plane| {
+                changed | (self.frame.plane_rows(plane)[y] ^ other.frame.plane_rows(plane)[y])
+            });
+
+            if changed == 0 {
+                continue;
+            }
+
+            let x = changed.leading_zeros() as usize - unused_bits
+                ..width - changed.trailing_zeros() as usize;
+
+            match screen.last_mut() {
+                Some(region) if region.y.end == y => {
+                    region.x = region.x.start.min(x.start)..region.x.end.max(x.end);
+                    region.y.end += 1;
+                }
+                _ => screen.push(ScreenRegion { x, y: y..y + 1 }),
+            }
+        }
+
+        StateDiff {
+            registers,
+            memory,
+            screen,
+        }
+    }
+}
+
+/// What differs between two [`SaveState`]s. See [`SaveState::diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    /// The registers and timers that differ, in the order `pc`, `i`, `sp`,
+    /// `v0` to `vf`, `dt` and `st`.
+    pub registers: Vec<RegisterChange>,
+    /// The ranges of addresses whose bytes differ.
+    pub memory: Vec<Range<usize>>,
+    /// The parts of the screen whose pixels differ, from top to bottom.
+    pub screen: Vec<ScreenRegion>,
+}
+
+impl StateDiff {
+    /// Returns true if the states were the same.
+    pub fn is_empty(&self) -> bool {
+        self.registers.is_empty() && self.memory.is_empty() && self.screen.is_empty()
+    }
+}
+
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no differences");
+        }
+
+        let mut lines = Vec::new();
+
+        for change in &self.registers {
+            lines.push(format!(
+                "{}: 0x{:02X} -> 0x{:02X}",
+                change.name, change.before, change.after
+            ));
+        }
+
+        for range in &self.memory {
+            lines.push(match range.len() {
+                1 => format!("memory: 0x{:03X}", range.start),
+                _ => format!("memory: 0x{:03X}-0x{:03X}", range.start, range.end - 1),
+            });
+        }
+
+        for region in &self.screen {
+            lines.push(format!(
+                "screen: x {}-{}, y {}-{}",
+                region.x.start,
+                region.x.end - 1,
+                region.y.start,
+                region.y.end - 1
+            ));
+        }
+
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// A register or timer with different values in two states.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterChange {
+    /// Like `pc`, `v3` or `dt`.
+    pub name: String,
+    /// The value in the first state.
+    pub before: u16,
+    /// The value in the second state.
+    pub after: u16,
+}
+
+/// A rectangle of the screen with pixels that differ in two states.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenRegion {
+    /// The columns, counting from the left.
+    pub x: Range<usize>,
+    /// The rows, counting from the top.
+    pub y: Range<usize>,
+}
+
+/// Reads the fields of a save state file from the front of its bytes.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Chip8Error> {
+        if len > self.bytes.len() {
+            return Err(Chip8Error::InvalidSaveState);
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Chip8Error> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, Chip8Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Chip8Error> {
+        Ok(u16::from_be_bytes(self.array()?))
+    }
+}
+
+/// The history keeps memory in pages of this many bytes.
+const PAGE_SIZE: usize = 256;
+
+/// A page of memory in the history, compressed with LZ4 if the `lz4` feature
+/// is on.
+#[derive(Debug)]
+struct Page(Box<[u8]>);
+
+impl Page {
+    fn new(bytes: &[u8]) -> Self {
+        #[cfg(feature = "lz4")]
+        let bytes: &[u8] = &lz4_flex::compress_prepend_size(bytes);
+
+        Self(bytes.into())
+    }
+
+    /// Adds the page's bytes to the end of `memory`.
+    fn extend(&self, memory: &mut Vec<u8>) {
+        #[cfg(feature = "lz4")]
+        let bytes = &lz4_flex::decompress_size_prepended(&self.0)
+            .expect("pages are compressed by Page::new");
+        #[cfg(not(feature = "lz4"))]
+        let bytes = &self.0;
+
+        memory.extend_from_slice(bytes);
+    }
+
+    /// How many bytes the page takes up.
+    fn size(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// A state in the history, with its memory kept in [`Page`]s instead of in
+/// [`SaveState::memory`].
+#[derive(Debug)]
+struct Entry {
+    state: SaveState,
+    pages: Vec<Arc<Page>>,
+    /// The bytes taken up by this entry, counting the pages it shares with
+    /// newer entries but not those it shares with older ones.
+    size: usize,
+}
+
+/// The states before each of the most recent cycles, oldest first.
+#[derive(Debug, Default)]
+pub(crate) struct History {
+    entries: VecDeque<Entry>,
+    /// The most states kept. 0 turns the history off.
+    capacity: usize,
+    /// The most bytes the states can take up, if there is a limit.
+    memory_limit: Option<usize>,
+    /// The bytes taken up by all of the states.
+    size: usize,
+    /// The memory of the newest state, which new states are compared with to
+    /// find the pages that can be shared.
+    newest_memory: Vec<u8>,
+}
+
+impl History {
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.size = 0;
+        self.newest_memory.clear();
+    }
+
+    fn push(&mut self, mut state: SaveState) {
+        let memory = std::mem::take(&mut state.memory);
+        let mut size = std::mem::size_of::<Entry>()
+            + std::mem::size_of_val(state.stack.as_slice())
+            + std::mem::size_of_val(state.call_stack.as_slice());
+
+        // Memory can change size when the program is reloaded into a
+        // different emulator, and then nothing is shared.
+        let newest = self
+            .entries
+            .back()
+            .filter(|_| self.newest_memory.len() == memory.len());
+
+        let pages = memory
+            .chunks(PAGE_SIZE)
+            .zip(
+                self.newest_memory
+                    .chunks(PAGE_SIZE)
+                    .map(Some)
+                    .chain(std::iter::repeat(None)),
+            )
+            .enumerate()
+            .map(|(index, (bytes, newest_bytes))| match newest {
+                Some(newest) if newest_bytes == Some(bytes) => Arc::clone(&newest.pages[index]),
+                _ => {
+                    let page = Page::new(bytes);
+                    size += page.size();
+                    Arc::new(page)
+                }
+            })
+            .collect();
+
+        self.newest_memory = memory;
+        self.size += size;
+        self.entries.push_back(Entry { state, pages, size });
+        self.shrink();
+    }
+
+    /// Takes the newest state off the history.
+    fn pop(&mut self) -> Option<SaveState> {
+        let mut entry = self.entries.pop_back()?;
+        self.size -= entry.size;
+
+        entry.state.memory = std::mem::take(&mut self.newest_memory);
+
+        if let Some(newest) = self.entries.back() {
+            for page in &newest.pages {
+                page.extend(&mut self.newest_memory);
+            }
+        }
+
+        Some(entry.state)
+    }
+
+    /// Drops the oldest states until the history fits in its capacity and
+    /// memory limit.
+    fn shrink(&mut self) {
+        while self.entries.len() > self.capacity
+            || self.memory_limit.is_some_and(|limit| self.usage() > limit)
+        {
+            let Some(oldest) = self.entries.pop_front() else {
+                break;
+            };
+
+            self.size -= oldest.size;
+
+            // The pages it shared with the next state are still around, so
+            // they count towards that state now.
+            if let Some(next) = self.entries.front_mut() {
+                for (page, next_page) in oldest.pages.iter().zip(&next.pages) {
+                    if Arc::ptr_eq(page, next_page) {
+                        next.size += page.size();
+                        self.size += page.size();
+                    }
+                }
+            }
+        }
+
+        if self.entries.is_empty() {
+            self.newest_memory.clear();
+        }
+    }
+
+    /// The bytes taken up by the history.
+    fn usage(&self) -> usize {
+        self.size + self.newest_memory.len()
+    }
+}
+
+impl Chip8 {
+    /// Takes a snapshot of the emulator's state.
+    pub fn save_state(&self) -> SaveState {
+        SaveState {
+            memory: self.memory.bytes().to_vec(),
+            frame: self.screen.frame(),
+            registers: self.registers,
+            index_register: self.index_register,
+            program_counter: self.program_counter,
+            stack: self.stack.entries().to_vec(),
+            delay_timer: self.timers.delay(),
+            sound_timer: self.timers.sound(),
+            keys: self.keypad.held,
+            call_stack: self.call_stack.clone(),
+            timer_accumulator: self.timer_clock.accumulator,
+            vblank: self.vblank,
+        }
+    }
+
+    /// Puts the emulator back into the state from [`Self::save_state`]. The
+    /// whole screen is marked as changed.
+    ///
+    /// Returns an error if the state's memory is the wrong size.
+    pub fn load_state(&mut self, state: &SaveState) -> Result<(), Chip8Error> {
+        self.memory.restore(&state.memory)?;
+        self.screen.restore(state.frame);
+        self.registers = state.registers;
+        self.index_register = state.index_register;
+        self.program_counter = state.program_counter;
+        self.stack.restore(&state.stack)?;
+        self.timers.set_delay(state.delay_timer);
+        self.timers.set_sound(state.sound_timer);
+        self.keypad.held = state.keys;
+        self.keypad.clear_events();
+        self.call_stack.clone_from(&state.call_stack);
+        self.timer_clock.accumulator = state.timer_accumulator;
+        self.vblank = state.vblank;
+        self.waiting_for_vblank = false;
+
+        self.emulator_state
+            .change_states(EmulatorState::ProgramLoaded)
+    }
+
+    /// Keeps the state from before each of the last `capacity` cycles, so they
+    /// can be undone with [`Self::step_back`]. A capacity of 0 (the default)
+    /// turns this off.
+    ///
+    /// Memory that didn't change is shared between states, so most states
+    /// take up a little over 2KB, most of it the screen. See also
+    /// [`Self::set_history_memory_limit`].
+    pub fn set_history_capacity(&mut self, capacity: usize) {
+        self.history.capacity = capacity;
+        self.history.shrink();
+    }
+
+    /// Limits the history to taking up about `limit` bytes, dropping the
+    /// oldest states to stay under it. `None` (the default) only limits it by
+    /// the number of states given to [`Self::set_history_capacity`].
+    pub fn set_history_memory_limit(&mut self, limit: Option<usize>) {
+        self.history.memory_limit = limit;
+        self.history.shrink();
+    }
+
+    /// Returns about how many bytes the history takes up.
+    pub fn history_memory_usage(&self) -> usize {
+        self.history.usage()
+    }
+
+    /// The number of cycles that can be undone with [`Self::step_back`].
+    pub fn history_len(&self) -> usize {
+        self.history.entries.len()
+    }
+
+    /// Undoes the last cycle, using the history turned on with
+    /// [`Self::set_history_capacity`].
+    ///
+    /// Returns [`Chip8Error::HistoryEmpty`] if there is nothing to undo.
+    pub fn step_back(&mut self) -> Result<(), Chip8Error> {
+        let state = self.history.pop().ok_or(Chip8Error::HistoryEmpty)?;
+
+        self.load_state(&state)
+    }
+
+    /// Saves the current state to the history, if it is turned on.
+    pub(crate) fn record_history(&mut self) {
+        if self.history.is_enabled() {
+            let state = self.save_state();
+            self.history.push(state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::SaveState;
+    use crate::Chip8;
+    use crate::Chip8Error;
+
+    /// Counts up in V0 and stores each value at 0x300, drawing a digit each time.
+    const COUNTER: [u8; 12] = [
+        0x70, 0x01, 0xA3, 0x00, 0xF0, 0x55, 0xF0, 0x29, 0xD1, 0x15, 0x12, 0x00,
+    ];
+
+    fn chip_8_with_counter() -> Chip8 {
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8.load_program(COUNTER.to_vec()).unwrap();
+        chip_8
+    }
+
+    #[test]
+    fn step_back_undoes_cycles() {
+        let mut chip_8 = chip_8_with_counter();
+        chip_8.set_history_capacity(100);
+
+        for _ in 0..6 {
+            chip_8.cycle().unwrap();
+        }
+
+        let state = chip_8.save_state();
+
+        for _ in 0..12 {
+            chip_8.cycle().unwrap();
+        }
+
+        assert_ne!(chip_8.save_state(), state);
+
+        for _ in 0..12 {
+            chip_8.step_back().unwrap();
+        }
+
+        assert_eq!(chip_8.save_state(), state);
+        assert_eq!(chip_8.history_len(), 6);
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut chip_8 = chip_8_with_counter();
+        chip_8.set_history_capacity(4);
+
+        for _ in 0..10 {
+            chip_8.cycle().unwrap();
+        }
+
+        for _ in 0..4 {
+            chip_8.step_back().unwrap();
+        }
+
+        assert!(matches!(chip_8.step_back(), Err(Chip8Error::HistoryEmpty)));
+        // We are back to after the 6th cycle, which jumped back to the start.
+        assert_eq!(chip_8.program_counter, 0x200);
+    }
+
+    #[test]
+    fn states_are_diffed() {
+        // Stores 0x2A at 0x300, then draws a 0 from the font.
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8
+            .load_program(vec![
+                0x60, 0x2A, 0xA3, 0x00, 0xF0, 0x55, 0xA0, 0x50, 0xD1, 0x15,
+            ])
+            .unwrap();
+
+        let before = chip_8.save_state();
+        assert!(before.diff(&before).is_empty());
+
+        for _ in 0..5 {
+            chip_8.cycle().unwrap();
+        }
+
+        let diff = before.diff(&chip_8.save_state());
+
+        assert_eq!(
+            diff.to_string(),
+            "pc: 0x200 -> 0x20A\n\
+             i: 0x00 -> 0x50\n\
+             v0: 0x00 -> 0x2A\n\
+             memory: 0x300\n\
+             screen: x 0-3, y 0-4"
+        );
+    }
+
+    #[test]
+    fn states_are_read_back_from_bytes() {
+        let mut chip_8 = chip_8_with_counter();
+
+        for _ in 0..20 {
+            chip_8.cycle().unwrap();
+        }
+
+        let state = chip_8.save_state();
+        let bytes = state.to_bytes();
+
+        assert_eq!(SaveState::from_bytes(&bytes).unwrap(), state);
+        assert!(matches!(
+            SaveState::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(Chip8Error::InvalidSaveState)
+        ));
+
+        #[cfg(feature = "lz4")]
+        {
+            let compressed = state.to_compressed_bytes();
+
+            assert!(compressed.len() < bytes.len());
+            assert_eq!(SaveState::from_bytes(&compressed).unwrap(), state);
+        }
+    }
+
+    #[test]
+    fn history_shares_memory_and_keeps_to_its_limit() {
+        let mut chip_8 = chip_8_with_counter();
+        chip_8.set_history_capacity(1000);
+
+        let mut states = Vec::new();
+
+        for _ in 0..100 {
+            states.push(chip_8.save_state());
+            chip_8.cycle().unwrap();
+        }
+
+        // Only the page the counter is stored in changes, so the history is
+        // smaller than 100 copies of memory, let alone of the whole state.
+        let usage = chip_8.history_memory_usage();
+        assert!(usage < 100 * chip_8.memory_size().bytes(), "{usage}");
+
+        chip_8.set_history_memory_limit(Some(usage / 2));
+        assert!(chip_8.history_memory_usage() <= usage / 2);
+
+        let kept = chip_8.history_len();
+        assert!(kept > 0 && kept < 100);
+
+        for state in states.iter().rev().take(kept) {
+            chip_8.step_back().unwrap();
+            assert_eq!(&chip_8.save_state(), state);
+        }
+
+        assert!(matches!(chip_8.step_back(), Err(Chip8Error::HistoryEmpty)));
+        assert_eq!(chip_8.history_memory_usage(), 0);
+    }
+}
@@ -0,0 +1,362 @@
+//! Save states as JSON, for reading and editing by hand. See
+//! [`SaveState::to_json`].
+
+use serde_json::{json, Map, Value};
+
+use super::SaveState;
+use crate::display::Resolution;
+use crate::{CallFrame, Chip8Error, Frame, Keys, MemorySize};
+
+/// How many bytes of memory go on each line.
+const MEMORY_CHUNK: usize = 64;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+impl SaveState {
+    /// Encodes the state as JSON that is meant to be edited by hand, like to
+    /// set a register or patch a byte before loading it with
+    /// [`Self::from_json`].
+    ///
+    /// Registers and addresses are hex strings like `"0x2A"`, memory is
+    /// base64 in chunks of 64 bytes keyed by their address, and each row of
+    /// the screen is a string with `#` for pixels that are on and `.` for
+    /// those that are off.
+    pub fn to_json(&self) -> String {
+        let registers: Map<String, Value> = self
+            .registers
+            .iter()
+            .enumerate()
+            .map(|(register, value)| (format!("v{register:x}"), json!(format!("0x{value:02X}"))))
+            .collect();
+
+        // The addresses are padded to the same width, so that they are
+        // sorted in order.
+        let digits = format!("{:X}", self.memory.len().saturating_sub(1)).len();
+        let memory: Map<String, Value> = self
+            .memory
+            .chunks(MEMORY_CHUNK)
+            .enumerate()
+            .map(|(index, chunk)| {
+                let address = index * MEMORY_CHUNK;
+                (format!("0x{address:0digits$X}"), json!(base64(chunk)))
+            })
+            .collect();
+
+        let planes: Vec<Vec<String>> = (0..self.frame.planes())
+            .map(|plane| {
+                self.frame
+                    .plane_rows(plane)
+                    .iter()
+                    .map(|&row| {
+                        (0..self.frame.width())
+                            .rev()
+                            .map(|bit| match row >> bit & 1 {
+                                1 => '#',
+                                _ => '.',
+                            })
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let call_stack: Vec<Value> = self
+            .call_stack
+            .iter()
+            .map(|frame| {
+                json!({
+                    "call_site": format!("0x{:03X}", frame.call_site),
+                    "target": format!("0x{:03X}", frame.target),
+                    "cycle": frame.cycle,
+                })
+            })
+            .collect();
+
+        let state = json!({
+            "registers": registers,
+            "i": format!("0x{:03X}", self.index_register),
+            "pc": format!("0x{:03X}", self.program_counter),
+            "stack": self.stack.iter().map(|address| format!("0x{address:03X}")).collect::<Vec<_>>(),
+            "delay_timer": self.delay_timer,
+            "sound_timer": self.sound_timer,
+            "keys": format!("0x{:04X}", self.keys.0),
+            "call_stack": call_stack,
+            "timer_accumulator": self.timer_accumulator,
+            "vblank": self.vblank,
+            "screen": {
+                "width": self.frame.width(),
+                "height": self.frame.height(),
+                "planes": planes,
+            },
+            "memory_size": self.memory.len(),
+            "memory": memory,
+        });
+
+        serde_json::to_string_pretty(&state).expect("a JSON value can always be written")
+    }
+
+    /// Decodes a state encoded with [`Self::to_json`]. Numbers can be given
+    /// as hex strings or as plain numbers.
+    ///
+    /// Returns [`Chip8Error::InvalidStateJson`] saying what was wrong if the
+    /// JSON isn't a save state.
+    pub fn from_json(text: &str) -> Result<Self, Chip8Error> {
+        let state: Value = serde_json::from_str(text).map_err(|err| invalid(err.to_string()))?;
+
+        let mut registers = [0; 16];
+        let register_values = field(&state, "registers")?;
+
+        for (register, value) in registers.iter_mut().enumerate() {
+            *value = number(register_values, &format!("v{register:x}"))?;
+        }
+
+        let memory_size: usize = number(&state, "memory_size")?;
+
+        // Checked before allocating, so a mistyped size can't ask for more
+        // memory than there is.
+        if ![MemorySize::Standard, MemorySize::Extended]
+            .iter()
+            .any(|size| size.bytes() == memory_size)
+        {
+            return Err(invalid(format!("{memory_size} isn't a memory size")));
+        }
+
+        let mut memory = vec![0; memory_size];
+
+        let chunks = field(&state, "memory")?
+            .as_object()
+            .ok_or_else(|| invalid("memory isn't an object"))?;
+
+        for (address, chunk) in chunks {
+            let start: usize = parse_number(&json!(address))
+                .ok_or_else(|| invalid(format!("{address} isn't an address")))?;
+            let bytes = chunk
+                .as_str()
+                .and_then(unbase64)
+                .ok_or_else(|| invalid(format!("memory at {address} isn't base64")))?;
+
+            let past_the_end = || invalid(format!("memory at {address} is past the end"));
+            let end = start.checked_add(bytes.len()).ok_or_else(past_the_end)?;
+
+            memory
+                .get_mut(start..end)
+                .ok_or_else(past_the_end)?
+                .copy_from_slice(&bytes);
+        }
+
+        let stack = list(&state, "stack")?
+            .iter()
+            .map(|address| parse_number(address).ok_or_else(|| invalid("stack has a bad address")))
+            .collect::<Result<_, _>>()?;
+
+        let call_stack = list(&state, "call_stack")?
+            .iter()
+            .map(|frame| {
+                Ok(CallFrame {
+                    call_site: number(frame, "call_site")?,
+                    target: number(frame, "target")?,
+                    cycle: number(frame, "cycle")?,
+                })
+            })
+            .collect::<Result<_, Chip8Error>>()?;
+
+        Ok(Self {
+            memory,
+            frame: screen(field(&state, "screen")?)?,
+            registers,
+            index_register: number(&state, "i")?,
+            program_counter: number(&state, "pc")?,
+            stack,
+            delay_timer: number(&state, "delay_timer")?,
+            sound_timer: number(&state, "sound_timer")?,
+            keys: Keys(number(&state, "keys")?),
+            call_stack,
+            timer_accumulator: number(&state, "timer_accumulator")?,
+            vblank: field(&state, "vblank")?
+                .as_bool()
+                .ok_or_else(|| invalid("vblank isn't true or false"))?,
+        })
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> Chip8Error {
+    Chip8Error::InvalidStateJson {
+        reason: reason.into(),
+    }
+}
+
+fn field<'a>(object: &'a Value, name: &str) -> Result<&'a Value, Chip8Error> {
+    object
+        .get(name)
+        .ok_or_else(|| invalid(format!("{name} is missing")))
+}
+
+fn list<'a>(object: &'a Value, name: &str) -> Result<&'a Vec<Value>, Chip8Error> {
+    field(object, name)?
+        .as_array()
+        .ok_or_else(|| invalid(format!("{name} isn't a list")))
+}
+
+/// Reads a number that fits in `T` from a field.
+fn number<T: TryFrom<u64>>(object: &Value, name: &str) -> Result<T, Chip8Error> {
+    parse_number(field(object, name)?)
+        .and_then(|number| T::try_from(number).ok())
+        .ok_or_else(|| invalid(format!("{name} isn't a number that fits")))
+}
+
+/// Reads a plain number or a hex string like `"0x2A"`.
+fn parse_number<T: TryFrom<u64>>(value: &Value) -> Option<T> {
+    let number = match value {
+        Value::Number(number) => number.as_u64()?,
+        Value::String(text) => {
+            let hex = text
+                .strip_prefix("0x")
+                .or_else(|| text.strip_prefix("0X"))?;
+            u64::from_str_radix(hex, 16).ok()?
+        }
+        _ => return None,
+    };
+
+    T::try_from(number).ok()
+}
+
+fn screen(screen: &Value) -> Result<Frame, Chip8Error> {
+    let resolution = Resolution {
+        width: number(screen, "width")?,
+        height: number(screen, "height")?,
+    };
+    let planes = list(screen, "planes")?;
+
+    let mut rows = Vec::new();
+
+    for plane in planes {
+        let plane = plane
+            .as_array()
+            .ok_or_else(|| invalid("a plane of the screen isn't a list"))?;
+
+        for row in plane {
+            let row = row
+                .as_str()
+                .filter(|row| row.len() == resolution.width)
+                .ok_or_else(|| invalid("a row of the screen isn't as wide as the screen"))?;
+
+            rows.push(
+                row.chars()
+                    .fold(0, |bits, pixel| bits << 1 | (pixel == '#') as u128),
+            );
+        }
+    }
+
+    Frame::from_rows(resolution, planes.len(), &rows)
+        .ok_or_else(|| invalid("the screen is the wrong size"))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (index, &byte)| {
+                group | (byte as u32) << (16 - index * 8)
+            });
+
+        for index in 0..4 {
+            match index <= chunk.len() {
+                true => encoded
+                    .push(BASE64_ALPHABET[(group >> (18 - index * 6)) as usize & 0x3F] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Decodes base64, returning `None` if it isn't valid.
+fn unbase64(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.trim_end_matches('=');
+    let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+
+    let sextets = encoded
+        .bytes()
+        .map(|character| BASE64_ALPHABET.iter().position(|&c| c == character))
+        .collect::<Option<Vec<_>>>()?;
+
+    for chunk in sextets.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (index, &sextet)| {
+                group | (sextet as u32) << (18 - index * 6)
+            });
+
+        for index in 0..chunk.len() - 1 {
+            bytes.push((group >> (16 - index * 8)) as u8);
+        }
+    }
+
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{base64, SaveState};
+    use crate::{Chip8, Chip8Error};
+
+    #[test]
+    fn states_survive_being_edited_as_json() {
+        // Stores 0x2A at 0x300, then draws a 0 from the font.
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8
+            .load_program(vec![
+                0x60, 0x2A, 0xA3, 0x00, 0xF0, 0x55, 0xA0, 0x50, 0xD1, 0x15,
+            ])
+            .unwrap();
+
+        for _ in 0..5 {
+            chip_8.cycle().unwrap();
+        }
+
+        let state = chip_8.save_state();
+        let json = state.to_json();
+
+        assert!(json.contains(r#""v0": "0x2A""#));
+        assert!(json.contains("\"####....."));
+        assert_eq!(SaveState::from_json(&json).unwrap(), state);
+
+        let edited = json.replace(r#""v3": "0x00""#, r#""v3": 7"#).replace(
+            &format!(r#""0x300": "{}"#, base64(&[0x2A, 0x00, 0x00])),
+            &format!(r#""0x300": "{}"#, base64(&[0x2A, 0xB2, 0xC0])),
+        );
+        let edited = SaveState::from_json(&edited).unwrap();
+
+        assert_eq!(edited.registers[3], 7);
+        assert_eq!(edited.memory[0x300..0x303], [0x2A, 0xB2, 0xC0]);
+        assert!(SaveState::from_json(&json.replace("\"pc\"", "\"pc2\"")).is_err());
+
+        let huge_memory = json.replace(
+            r#""memory_size": 4096"#,
+            r#""memory_size": 18446744073709551615"#,
+        );
+        assert!(matches!(
+            SaveState::from_json(&huge_memory),
+            Err(Chip8Error::InvalidStateJson { reason })
+                if reason == "18446744073709551615 isn't a memory size"
+        ));
+
+        let huge_address = json.replace(r#""0x300":"#, r#""0xFFFFFFFFFFFFFFFF":"#);
+        assert!(matches!(
+            SaveState::from_json(&huge_address),
+            Err(Chip8Error::InvalidStateJson { reason })
+                if reason == "memory at 0xFFFFFFFFFFFFFFFF is past the end"
+        ));
+    }
+}
@@ -0,0 +1,255 @@
+//! Running the emulator in real time. [`Chip8::run_frame`] runs a 60Hz
+//! frame's worth of cycles and ticks the timers, and a [`Scheduler`] decides
+//! when each frame should run so that they keep up with the clock. The
+//! frontend's emulator thread and its debug adapter sessions both run on one.
+//!
+//! The time comes from a [`Clock`], which is the system's unless the
+//! scheduler is made with [`Scheduler::with_clock`]. A [`MockClock`] only
+//! moves when it is slept on, which lets tests run many seconds of frames
+//! straight away and always get the same result.
+
+use std::cell::Cell;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use super::{Chip8, Chip8Error};
+
+/// The most frames a [`Scheduler`] runs at once to catch up, until told
+/// otherwise with [`Scheduler::set_max_catch_up`].
+pub const DEFAULT_MAX_CATCH_UP: u32 = 4;
+
+/// Where a [`Scheduler`] gets the time from.
+pub trait Clock: fmt::Debug {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+
+    /// Waits until `deadline`, returning straight away if it has passed.
+    fn sleep_until(&self, deadline: Instant);
+}
+
+/// The system's clock, which really sleeps.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep_until(&self, deadline: Instant) {
+        std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+    }
+}
+
+/// A clock that stands still until it is slept on or moved on with
+/// [`Self::advance`], for tests.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Cell<Instant>,
+}
+
+impl Default for MockClock {
+    /// Starts the clock at the current time.
+    fn default() -> Self {
+        Self {
+            now: Cell::new(Instant::now()),
+        }
+    }
+}
+
+impl MockClock {
+    /// Moves the clock on, like a frame taking `duration` to run.
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+
+    fn sleep_until(&self, deadline: Instant) {
+        self.now.set(self.now.get().max(deadline));
+    }
+}
+
+/// Decides how many frames are due at any moment, for running frames at a
+/// fixed rate however long each one takes.
+///
+/// Frames are timed from when the scheduler started rather than from each
+/// other, so waking up late or rounding the frame time doesn't add up into
+/// drift. If it falls too far behind, like after the computer was asleep, the
+/// frames it missed are dropped instead of all being run at once.
+#[derive(Debug, Clone)]
+pub struct Scheduler<C: Clock = SystemClock> {
+    clock: C,
+    frames_per_second: u32,
+    /// When the first frame was due.
+    start: Instant,
+    /// How many frames have been due since `start`.
+    frames: u64,
+    max_catch_up: u32,
+}
+
+impl Scheduler {
+    /// Creates a scheduler for `frames_per_second` frames a second on the
+    /// system's clock, with the first frame due now.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frames_per_second` is 0.
+    pub fn new(frames_per_second: u32) -> Self {
+        Self::with_clock(frames_per_second, SystemClock)
+    }
+}
+
+impl<C: Clock> Scheduler<C> {
+    /// Creates a scheduler like [`Scheduler::new`], but on another clock.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frames_per_second` is 0.
+    pub fn with_clock(frames_per_second: u32, clock: C) -> Self {
+        assert!(
+            frames_per_second > 0,
+            "there must be at least 1 frame a second"
+        );
+
+        Self {
+            start: clock.now(),
+            clock,
+            frames_per_second,
+            frames: 0,
+            max_catch_up: DEFAULT_MAX_CATCH_UP,
+        }
+    }
+
+    /// Returns the clock the scheduler runs on.
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Sets the most frames [`Self::frames_due`] returns at once. Anything
+    /// more is dropped.
+    pub fn set_max_catch_up(&mut self, frames: u32) {
+        self.max_catch_up = frames.max(1);
+    }
+
+    /// Starts the schedule over with the next frame due at `now`, like after
+    /// the emulator was paused.
+    pub fn reset(&mut self, now: Instant) {
+        self.start = now;
+        self.frames = 0;
+    }
+
+    /// Returns when the next frame is due.
+    pub fn next_frame(&self) -> Instant {
+        self.start
+            + Duration::from_nanos(self.frames * 1_000_000_000 / u64::from(self.frames_per_second))
+    }
+
+    /// Returns how many frames should be run at `now`, and counts them as
+    /// run.
+    pub fn frames_due(&mut self, now: Instant) -> u32 {
+        let mut due = 0;
+
+        while self.next_frame() <= now {
+            if due == self.max_catch_up {
+                // The frames just counted were the last ones run, so the
+                // next is a whole frame from now.
+                self.reset(now);
+                self.frames = 1;
+                break;
+            }
+
+            self.frames += 1;
+            due += 1;
+        }
+
+        due
+    }
+
+    /// Sleeps until the next frame is due.
+    pub fn wait(&self) {
+        self.clock.sleep_until(self.next_frame());
+    }
+
+    /// Runs the frames of `chip_8` that are due by the clock, then sleeps
+    /// until the next one is. Returns how many frames ran.
+    pub fn run(&mut self, chip_8: &mut Chip8) -> Result<u32, Chip8Error> {
+        let due = self.frames_due(self.clock.now());
+
+        for _ in 0..due {
+            chip_8.run_frame()?;
+        }
+
+        self.wait();
+        Ok(due)
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{Clock, MockClock, Scheduler};
+    use crate::Chip8;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn frames_keep_time_without_drifting() {
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        let mut scheduler = Scheduler::new(60);
+        scheduler.reset(start);
+
+        assert_eq!(scheduler.frames_due(start), 1);
+        assert_eq!(scheduler.frames_due(at(10)), 0);
+        assert_eq!(scheduler.frames_due(at(40)), 2);
+
+        // Waking up a little late every time doesn't push the schedule back.
+        let total: u32 = (1..=200)
+            .map(|step| scheduler.frames_due(at(step * 50 + 5)))
+            .sum();
+        assert_eq!(total, 600 - 3 + 1);
+        assert_eq!(
+            scheduler.next_frame(),
+            at(10_000) + Duration::from_nanos(16_666_666)
+        );
+
+        // Falling far behind drops the frames that were missed.
+        assert_eq!(scheduler.frames_due(at(60_000)), 4);
+        assert_eq!(scheduler.frames_due(at(60_010)), 0);
+        assert_eq!(
+            scheduler.next_frame(),
+            at(60_000) + Duration::from_nanos(16_666_666)
+        );
+    }
+
+    #[test]
+    fn a_mock_clock_runs_frames_without_sleeping() {
+        let program = vec![
+            0x60, 0xFF, // V0 = 255
+            0xF0, 0x15, // delay timer = V0
+            0x12, 0x04, // loop forever
+        ];
+
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8.load_program(program).unwrap();
+
+        let mut scheduler = Scheduler::with_clock(60, MockClock::default());
+        let start = scheduler.clock().now();
+        let real_start = Instant::now();
+
+        let mut frames = 0;
+        while scheduler.clock().now() < start + Duration::from_secs(2) {
+            frames += scheduler.run(&mut chip_8).unwrap();
+        }
+
+        assert_eq!(frames, 120);
+        // The timer was set in the first frame, and ticked at the end of it.
+        assert_eq!(chip_8.timers.delay(), 255 - 120);
+        assert!(real_start.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,605 @@
+use std::ops::Range;
+
+use sha1::{Digest, Sha1};
+
+use super::display::Resolution;
+use super::instructions::execution::DrawResult;
+use super::palette::Palette;
+
+/// The widest screen a [`Frame`] can hold, which is SUPER-CHIP's.
+const MAX_WIDTH: usize = 128;
+/// The tallest screen a [`Frame`] can hold.
+const MAX_HEIGHT: usize = 64;
+/// The most bit planes a [`Frame`] can hold, which is XO-CHIP's.
+const MAX_PLANES: usize = 2;
+
+/// A snapshot of the screen's pixels, packed one bit per pixel in each of
+/// its planes.
+///
+/// Each row is stored as a `u128`, where bit `width - 1` is the leftmost
+/// pixel (x = 0) and the least significant bit is the rightmost pixel. A set
+/// bit is white and an unset bit is black. A pixel is white if it is set in
+/// any plane.
+///
+/// The size is decided at runtime, from 64x32 up to 128x64. See [`Resolution`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    planes: [[u128; MAX_HEIGHT]; MAX_PLANES],
+    resolution: Resolution,
+    plane_count: usize,
+}
+
+impl Default for Frame {
+    /// Initializes the frame to a black 64x32 screen with one plane.
+    fn default() -> Self {
+        Self::blank(Resolution::LORES, 1)
+    }
+}
+
+impl Frame {
+    /// Creates a black frame.
+    fn blank(resolution: Resolution, plane_count: usize) -> Self {
+        assert!(
+            resolution.width <= MAX_WIDTH
+                && resolution.height <= MAX_HEIGHT
+                && (1..=MAX_PLANES).contains(&plane_count),
+            "unsupported screen of {resolution} with {plane_count} planes"
+        );
+
+        Self {
+            planes: [[0; MAX_HEIGHT]; MAX_PLANES],
+            resolution,
+            plane_count,
+        }
+    }
+
+    /// Creates a frame from the packed rows of each plane, one plane after
+    /// the other. Returns `None` if the frame couldn't hold them, or there
+    /// aren't `height` rows for each plane.
+    pub(crate) fn from_rows(
+        resolution: Resolution,
+        plane_count: usize,
+        rows: &[u128],
+    ) -> Option<Self> {
+        if !(1..=MAX_WIDTH).contains(&resolution.width)
+            || !(1..=MAX_HEIGHT).contains(&resolution.height)
+            || !(1..=MAX_PLANES).contains(&plane_count)
+            || rows.len() != plane_count * resolution.height
+        {
+            return None;
+        }
+
+        let mut frame = Self::blank(resolution, plane_count);
+        let mask = u128::MAX >> (MAX_WIDTH - resolution.width);
+
+        for (plane, plane_rows) in rows.chunks_exact(resolution.height).enumerate() {
+            for (y, &row) in plane_rows.iter().enumerate() {
+                frame.planes[plane][y] = row & mask;
+            }
+        }
+
+        Some(frame)
+    }
+
+    /// Returns the size of the frame.
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// Returns the number of pixels in each row.
+    pub fn width(&self) -> usize {
+        self.resolution.width
+    }
+
+    /// Returns the number of rows in the frame.
+    pub fn height(&self) -> usize {
+        self.resolution.height
+    }
+
+    /// Returns the number of bit planes in the frame.
+    pub fn planes(&self) -> usize {
+        self.plane_count
+    }
+
+    /// Returns true if the pixel at the given x and y is white.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        let bit = self.width() - 1 - x;
+
+        self.planes[..self.plane_count]
+            .iter()
+            .any(|rows| (rows[y] >> bit) & 1 == 1)
+    }
+
+    /// Returns each row of pixels from the top down, as whether each pixel
+    /// is white from left to right. Nothing is copied, so this is cheaper
+    /// than [`Self::unpack`] for looking through the frame once.
+    pub fn pixel_rows(&self) -> impl Iterator<Item = impl Iterator<Item = bool> + '_> + '_ {
+        (0..self.height()).map(move |y| (0..self.width()).map(move |x| self.pixel(x, y)))
+    }
+
+    /// Returns the packed rows of the first plane, from the top down.
+    pub fn rows(&self) -> &[u128] {
+        self.plane_rows(0)
+    }
+
+    /// Returns the packed rows of a plane, from the top down.
+    pub fn plane_rows(&self, plane: usize) -> &[u128] {
+        &self.planes[plane][..self.height()]
+    }
+
+    /// Returns the SHA-1 hash of the packed rows as a lowercase hexadecimal
+    /// string, for checking what is on the screen without storing all of it.
+    pub fn sha1_hex(&self) -> String {
+        let mut hasher = Sha1::new();
+        // Only the bytes that hold pixels are hashed, so the hash doesn't
+        // depend on how wide the rows are stored.
+        let unused_bytes = (MAX_WIDTH - self.width()) / 8;
+
+        for plane in 0..self.plane_count {
+            for row in self.plane_rows(plane) {
+                hasher.update(&row.to_be_bytes()[unused_bytes..]);
+            }
+        }
+
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// Unpacks the frame into one boolean per pixel, laid out as
+    /// `location = width*y + x`.
+    pub fn unpack(&self) -> Vec<bool> {
+        let mut pixels = vec![false; self.width() * self.height()];
+
+        for (y, row) in pixels.chunks_exact_mut(self.width()).enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = self.pixel(x, y);
+            }
+        }
+
+        pixels
+    }
+
+    /// Draws the frame as text with block characters, for printing to a
+    /// terminal. Each character is two pixels, one above the other, so the
+    /// pixels come out roughly square. Every line ends with a newline.
+    pub fn to_ascii(&self) -> String {
+        let mut text = String::with_capacity((self.width() + 1) * self.height().div_ceil(2) * 3);
+
+        for y in (0..self.height()).step_by(2) {
+            for x in 0..self.width() {
+                let bottom = y + 1 < self.height() && self.pixel(x, y + 1);
+
+                text.push(match (self.pixel(x, y), bottom) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
+            }
+
+            text.push('\n');
+        }
+
+        text
+    }
+
+    /// Converts the given rows of the frame into `0RGB` pixels using the
+    /// palette's colors, writing them to the same rows of `buffer` (which is
+    /// laid out as `location = width*y + x`).
+    pub fn write_rgba(&self, buffer: &mut [u32], rows: Range<usize>, palette: &Palette) {
+        let width = self.width();
+        let buffer_rows = buffer[rows.start * width..rows.end * width].chunks_exact_mut(width);
+
+        for (y, buffer_row) in rows.zip(buffer_rows) {
+            for (x, real_pixel) in buffer_row.iter_mut().enumerate() {
+                *real_pixel = match self.pixel(x, y) {
+                    true => palette.foreground,
+                    false => palette.background,
+                };
+            }
+        }
+    }
+}
+
+/// The memory used for the screen, stored as a packed [`Frame`].
+///
+/// The top left corner of the screen is (0, 0).
+#[derive(Debug)]
+pub struct Screen {
+    frame: Frame,
+    /// The rows that have changed since the last [`Self::take_frame`],
+    /// or `None` if nothing has been drawn since then.
+    dirty_rows: Option<Range<usize>>,
+}
+
+impl Default for Screen {
+    /// Initializes screen to black.
+    ///
+    /// The whole screen starts out dirty so that the first frame
+    /// always gets drawn.
+    fn default() -> Self {
+        Self::new(Resolution::LORES, 1)
+    }
+}
+
+impl Screen {
+    /// Creates a black screen of the given size and number of planes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the screen is bigger than 128x64 or has more than 2 planes.
+    pub fn new(resolution: Resolution, planes: usize) -> Self {
+        Self {
+            frame: Frame::blank(resolution, planes),
+            dirty_rows: Some(0..resolution.height),
+        }
+    }
+
+    /// Returns a copy of what is on the screen.
+    pub fn frame(&self) -> Frame {
+        self.frame
+    }
+
+    /// Returns the size of the screen.
+    pub fn resolution(&self) -> Resolution {
+        self.frame.resolution
+    }
+
+    /// Returns the number of pixels in each row.
+    pub fn width(&self) -> usize {
+        self.frame.width()
+    }
+
+    /// Returns the number of rows on the screen.
+    pub fn height(&self) -> usize {
+        self.frame.height()
+    }
+
+    /// Returns the number of bit planes on the screen.
+    pub fn planes(&self) -> usize {
+        self.frame.plane_count
+    }
+
+    /// Replaces what is on the screen, marking every row as changed. The
+    /// screen takes on the frame's height.
+    pub fn restore(&mut self, frame: Frame) {
+        self.frame = frame;
+        self.dirty_rows = Some(0..frame.height());
+    }
+
+    /// Clears the screen.
+    pub fn clear(&mut self) {
+        self.frame = Frame::blank(self.frame.resolution, self.frame.plane_count);
+        self.dirty_rows = Some(0..self.frame.height());
+    }
+
+    /// XORs a sprite row of up to 16 pixels onto the first plane of the
+    /// screen, with its leftmost pixel at the given x and y. The leftmost
+    /// pixel is the most significant bit, so an 8 pixel wide sprite's byte
+    /// goes in the upper half. Pixels past the right edge are wrapped around
+    /// to the left edge if `wrap` is true, and clipped otherwise.
+    ///
+    /// Returns true if any pixel was turned off that used to be on. This is
+    /// important as we change the value of VF to 1 when that happens.
+    pub fn draw_row(&mut self, x: u8, y: u8, sprite_row: u16, wrap: bool) -> bool {
+        // Line the sprite up with the leftmost pixel and then move it over.
+        // Shifting drops any bits past the right edge, and when wrapping
+        // those bits are brought back in on the left.
+        let width = self.width();
+        let aligned = (sprite_row as u128) << (width - 16);
+        let mut mask = aligned >> x;
+
+        if wrap && x > 0 {
+            mask |= (aligned << (width - x as usize)) & self.all_pixels();
+        }
+
+        if mask == 0 {
+            return false;
+        }
+
+        let row = &mut self.frame.planes[0][y as usize];
+        let collided = *row & mask != 0;
+
+        *row ^= mask;
+        self.mark_row_dirty(y as usize);
+
+        collided
+    }
+
+    /// Draws a sprite of rows like [`Self::draw_row`]'s, with its top left
+    /// corner at the given x and y. Coordinates past the edges wrap around
+    /// first, like `DXYN` does. Rows past the bottom edge are wrapped around
+    /// to the top if `wrap` is true, and clipped otherwise.
+    pub fn draw_sprite(&mut self, x: u8, y: u8, rows: &[u16], wrap: bool) -> DrawResult {
+        let (width, height) = (self.width(), self.height());
+        let x = (x as usize % width) as u8;
+        let y = y as usize % height;
+
+        let mut result = DrawResult::default();
+
+        for (row, &sprite_row) in rows.iter().enumerate() {
+            let row_y = y + row;
+
+            if row_y >= height && !wrap {
+                result.clipped_rows = (rows.len() - row).min(u8::MAX as usize) as u8;
+                break;
+            }
+
+            if self.draw_row(x, (row_y % height) as u8, sprite_row, wrap) {
+                result.collided_rows = result.collided_rows.saturating_add(1);
+            }
+        }
+
+        result.collided = result.collided_rows > 0;
+        result
+    }
+
+    /// Moves every plane down by `rows`, dropping the rows that go past the
+    /// bottom edge and leaving black ones at the top.
+    ///
+    /// Like the other scrolls, this is in the screen's own pixels. Scrolling
+    /// opcodes are given in 128x64 pixels, so in lores mode the caller decides
+    /// whether to halve the distance like SUPER-CHIP 1.1 did.
+    pub fn scroll_down(&mut self, rows: usize) {
+        let height = self.height();
+        let rows = rows.min(height);
+
+        for plane in self.planes_mut() {
+            plane.copy_within(0..height - rows, rows);
+            plane[..rows].fill(0);
+        }
+
+        self.mark_all_rows_dirty();
+    }
+
+    /// Moves every plane up by `rows`, dropping the rows that go past the
+    /// top edge and leaving black ones at the bottom.
+    pub fn scroll_up(&mut self, rows: usize) {
+        let height = self.height();
+        let rows = rows.min(height);
+
+        for plane in self.planes_mut() {
+            plane.copy_within(rows..height, 0);
+            plane[height - rows..height].fill(0);
+        }
+
+        self.mark_all_rows_dirty();
+    }
+
+    /// Moves every plane left by `columns`, dropping the pixels that go past
+    /// the left edge and leaving black ones on the right.
+    pub fn scroll_left(&mut self, columns: usize) {
+        let all_pixels = self.all_pixels();
+        let height = self.height();
+
+        for plane in self.planes_mut() {
+            for row in &mut plane[..height] {
+                *row = row.checked_shl(columns as u32).unwrap_or(0) & all_pixels;
+            }
+        }
+
+        self.mark_all_rows_dirty();
+    }
+
+    /// Moves every plane right by `columns`, dropping the pixels that go
+    /// past the right edge and leaving black ones on the left.
+    pub fn scroll_right(&mut self, columns: usize) {
+        let height = self.height();
+
+        for plane in self.planes_mut() {
+            for row in &mut plane[..height] {
+                *row = row.checked_shr(columns as u32).unwrap_or(0);
+            }
+        }
+
+        self.mark_all_rows_dirty();
+    }
+
+    /// Converts the whole screen into `0RGB` pixels using the palette's colors.
+    /// See [`Frame::write_rgba`].
+    pub fn render_rgba(&self, buffer: &mut [u32], palette: &Palette) {
+        self.frame.write_rgba(buffer, 0..self.height(), palette);
+    }
+
+    /// Draws the screen as text. See [`Frame::to_ascii`].
+    pub fn to_ascii(&self) -> String {
+        self.frame.to_ascii()
+    }
+
+    /// Returns true if the pixel at the given x and y is white.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pixel is off the screen.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        assert!(
+            x < self.width() && y < self.height(),
+            "({x}, {y}) is off the screen"
+        );
+        self.frame.pixel(x, y)
+    }
+
+    /// Returns each row of pixels from the top down. See
+    /// [`Frame::pixel_rows`].
+    pub fn rows(&self) -> impl Iterator<Item = impl Iterator<Item = bool> + '_> + '_ {
+        self.frame.pixel_rows()
+    }
+
+    /// Turns the pixel at the given x and y white or black, marking its row
+    /// as changed. White pixels are set in the first plane, and black ones
+    /// are cleared in every plane.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pixel is off the screen.
+    pub fn set_pixel(&mut self, x: usize, y: usize, white: bool) {
+        assert!(
+            x < self.width() && y < self.height(),
+            "({x}, {y}) is off the screen"
+        );
+        let bit = 1 << (self.width() - 1 - x);
+
+        match white {
+            true => self.frame.planes[0][y] |= bit,
+            false => {
+                for plane in self.planes_mut() {
+                    plane[y] &= !bit;
+                }
+            }
+        }
+
+        self.mark_row_dirty(y);
+    }
+
+    /// Returns true if any pixel has changed since the last call
+    /// to [`Self::take_frame`].
+    pub fn is_dirty(&self) -> bool {
+        self.dirty_rows.is_some()
+    }
+
+    /// Returns a copy of the frame along with the range of rows that changed
+    /// since the last call, and marks the screen as clean.
+    ///
+    /// Returns `None` if nothing was drawn in the meantime, so the frontend
+    /// can skip converting and presenting the frame.
+    pub fn take_frame(&mut self) -> Option<(Frame, Range<usize>)> {
+        let dirty_rows = self.dirty_rows.take()?;
+
+        Some((self.frame, dirty_rows))
+    }
+
+    /// Returns a row with every pixel that fits on the screen set.
+    fn all_pixels(&self) -> u128 {
+        u128::MAX >> (MAX_WIDTH - self.width())
+    }
+
+    /// Returns the rows of the planes in use.
+    fn planes_mut(&mut self) -> &mut [[u128; MAX_HEIGHT]] {
+        &mut self.frame.planes[..self.frame.plane_count]
+    }
+
+    fn mark_all_rows_dirty(&mut self) {
+        self.dirty_rows = Some(0..self.height());
+    }
+
+    /// Grows the dirty row range so that it includes row `y`.
+    fn mark_row_dirty(&mut self, y: usize) {
+        self.dirty_rows = match self.dirty_rows.take() {
+            Some(rows) => Some(rows.start.min(y)..rows.end.max(y + 1)),
+            None => Some(y..y + 1),
+        };
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::Screen;
+    use crate::display::Resolution;
+
+    #[test]
+    fn sprites_wrap_at_the_right_edge_of_every_resolution() {
+        for resolution in [Resolution::LORES, Resolution::TWO_PAGE, Resolution::HIRES] {
+            let mut screen = Screen::new(resolution, 1);
+            let x = resolution.width as u8 - 4;
+
+            screen.draw_row(x, 0, 0xFF00, true);
+            screen.draw_row(x, 1, 0xFF00, false);
+
+            let frame = screen.frame();
+            let lit = |y| (0..resolution.width).filter(|&x| frame.pixel(x, y)).count();
+
+            assert!(frame.pixel(0, 0) && frame.pixel(3, 0) && !frame.pixel(4, 0));
+            assert_eq!((lit(0), lit(1)), (8, 4), "{resolution}");
+        }
+    }
+
+    #[test]
+    fn screens_are_drawn_two_rows_to_a_character() {
+        let mut screen = Screen::new(Resolution::LORES, 1);
+        screen.draw_row(0, 0, 0xC000, false);
+        screen.draw_row(1, 1, 0x8000, false);
+        screen.draw_row(0, 3, 0x8000, false);
+
+        let text = screen.to_ascii();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines.len(), 16);
+        assert!(lines.iter().all(|line| line.chars().count() == 64));
+        assert!(lines[0].starts_with("▀█ "));
+        assert!(lines[1].starts_with("▄  "));
+        assert!(lines[2..].iter().all(|line| line.trim().is_empty()));
+    }
+
+    #[test]
+    fn pixels_can_be_read_and_set_one_at_a_time() {
+        let mut screen = Screen::new(Resolution::HIRES, 2);
+        screen.take_frame();
+
+        screen.set_pixel(127, 10, true);
+        screen.set_pixel(3, 12, true);
+        assert!(screen.pixel(127, 10) && screen.pixel(3, 12));
+        assert_eq!(screen.take_frame().unwrap().1, 10..13);
+
+        screen.set_pixel(3, 12, false);
+        assert!(!screen.pixel(3, 12));
+
+        let lit: Vec<(usize, usize)> = screen
+            .rows()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.enumerate()
+                    .filter(|(_, lit)| *lit)
+                    .map(move |(x, _)| (x, y))
+            })
+            .collect();
+        assert_eq!(lit, [(127, 10)]);
+    }
+
+    #[test]
+    fn scrolling_drops_the_pixels_at_the_edges() {
+        for resolution in [Resolution::LORES, Resolution::HIRES] {
+            let (right, bottom) = (resolution.width - 1, resolution.height - 1);
+            let mut screen = Screen::new(resolution, 2);
+
+            // One pixel in each corner.
+            screen.draw_row(0, 0, 0x8000, false);
+            screen.draw_row(right as u8, 0, 0x8000, false);
+            screen.draw_row(0, bottom as u8, 0x8000, false);
+            screen.draw_row(right as u8, bottom as u8, 0x8000, false);
+            screen.take_frame();
+
+            let lit = |screen: &Screen| {
+                let frame = screen.frame();
+                (0..resolution.height)
+                    .flat_map(|y| (0..resolution.width).map(move |x| (x, y)))
+                    .filter(|&(x, y)| frame.pixel(x, y))
+                    .collect::<Vec<_>>()
+            };
+
+            screen.scroll_right(4);
+            assert_eq!(lit(&screen), [(4, 0), (4, bottom)], "{resolution}");
+            assert_eq!(screen.take_frame().unwrap().1, 0..resolution.height);
+
+            screen.scroll_left(8);
+            assert!(lit(&screen).is_empty(), "{resolution}");
+
+            screen.draw_row(right as u8, 0, 0x8000, false);
+            screen.draw_row(right as u8, bottom as u8, 0x8000, false);
+            screen.scroll_down(1);
+            assert_eq!(lit(&screen), [(right, 1)], "{resolution}");
+
+            screen.scroll_up(2);
+            assert!(lit(&screen).is_empty(), "{resolution}");
+
+            // Scrolling further than the screen clears it.
+            screen.draw_row(0, 0, 0xFF00, false);
+            screen.scroll_down(resolution.height + 1);
+            screen.draw_row(0, 0, 0xFF00, false);
+            screen.scroll_left(resolution.width);
+            assert!(lit(&screen).is_empty(), "{resolution}");
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! The buzzer, which sounds while the sound timer is above 0.
+
+// implement way to play a buzzer sound here
+
+use super::event_log::Event;
+use super::Chip8;
+
+/// Called every time the sound timer counts down. Playing the sound is left
+/// to the frontend, which can check [`Chip8::is_buzzer_active`].
+pub fn play_buzzer() {}
+
+/// A point where the buzzer started or stopped sounding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuzzerEvent {
+    /// The value of [`Stats::total_cycles`](super::stats::Stats::total_cycles)
+    /// when it happened.
+    pub cycle: u64,
+    /// Whether the buzzer started (true) or stopped (false) sounding.
+    pub active: bool,
+}
+
+impl Chip8 {
+    /// Starts or stops keeping a log of [`BuzzerEvent`]s, which can be used to
+    /// recreate the buzzer's output after the fact. Stopping clears the log.
+    pub fn set_buzzer_recording(&mut self, enabled: bool) {
+        self.buzzer_events = enabled.then(Vec::new);
+    }
+
+    /// Returns the [`BuzzerEvent`]s since the last call, if recording was
+    /// turned on with [`Self::set_buzzer_recording`].
+    pub fn take_buzzer_events(&mut self) -> Vec<BuzzerEvent> {
+        self.buzzer_events
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Logs a [`BuzzerEvent`] if the buzzer has started or stopped since
+    /// `was_active` was read.
+    pub(crate) fn record_buzzer_change(&mut self, was_active: bool) {
+        let active = self.is_buzzer_active();
+
+        if active == was_active {
+            return;
+        }
+
+        if let Some(buzzer_events) = &mut self.buzzer_events {
+            buzzer_events.push(BuzzerEvent {
+                cycle: self.stats.total_cycles,
+                active,
+            });
+        }
+
+        self.log_event(Event::Buzzer { active });
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::BuzzerEvent;
+    use crate::Chip8;
+
+    #[test]
+    fn buzzer_events_follow_the_sound_timer() {
+        // Sets the sound timer to 2, then loops forever.
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8
+            .load_program(vec![0x60, 0x02, 0xF0, 0x18, 0x12, 0x04])
+            .unwrap();
+        chip_8.set_buzzer_recording(true);
+
+        for _ in 0..3 {
+            chip_8.run_frame().unwrap();
+        }
+
+        // The timer is set on the second cycle, and runs out at the end of the
+        // second frame of 12 cycles.
+        assert_eq!(
+            chip_8.take_buzzer_events(),
+            [
+                BuzzerEvent {
+                    cycle: 1,
+                    active: true
+                },
+                BuzzerEvent {
+                    cycle: 24,
+                    active: false
+                },
+            ]
+        );
+        assert!(chip_8.take_buzzer_events().is_empty());
+    }
+}
@@ -0,0 +1,103 @@
+//! Views of memory as sprites, for finding and inspecting the graphics in
+//! a program.
+
+use std::ops::Range;
+
+use super::palette::Palette;
+use super::Chip8;
+
+/// The width of every CHIP-8 sprite.
+pub const SPRITE_WIDTH: usize = 8;
+
+/// A small bitmap of one 8xN sprite read from memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpriteBitmap {
+    /// The address of the sprite's first row.
+    pub address: u16,
+    /// One byte per row, where the most significant bit is the leftmost pixel.
+    pub rows: Vec<u8>,
+}
+
+impl SpriteBitmap {
+    /// The height of the sprite in pixels.
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns true if the pixel at the given x and y is set.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        (self.rows[y] >> (SPRITE_WIDTH - 1 - x)) & 1 == 1
+    }
+
+    /// Converts the sprite into `0RGB` pixels using the palette's colors, with
+    /// each sprite pixel drawn as a `scale` by `scale` square. The thumbnail is
+    /// `SPRITE_WIDTH * scale` pixels wide, laid out as `location = width*y + x`.
+    pub fn to_rgba(&self, palette: &Palette, scale: usize) -> Vec<u32> {
+        let width = SPRITE_WIDTH * scale;
+        let mut pixels = vec![palette.background; width * self.height() * scale];
+
+        for (location, pixel) in pixels.iter_mut().enumerate() {
+            let (x, y) = (location % width / scale, location / width / scale);
+
+            if self.pixel(x, y) {
+                *pixel = palette.foreground;
+            }
+        }
+
+        pixels
+    }
+}
+
+/// Splits `bytes`, which start at `address`, into sprites `height` rows tall.
+/// A shorter sprite is left at the end if the bytes don't divide evenly.
+pub fn sprites(bytes: &[u8], address: u16, height: usize) -> Vec<SpriteBitmap> {
+    bytes
+        .chunks(height.max(1))
+        .enumerate()
+        .map(|(index, rows)| SpriteBitmap {
+            address: address + (index * height.max(1)) as u16,
+            rows: rows.to_vec(),
+        })
+        .collect()
+}
+
+impl Chip8 {
+    /// Reads the memory in `addresses` as sprites `height` rows tall. See
+    /// [`sprites`]. Addresses past the end of memory are left out.
+    pub fn sprites(&self, addresses: Range<u16>, height: usize) -> Vec<SpriteBitmap> {
+        let end = (addresses.end as usize).min(self.memory.size().bytes());
+        let start = (addresses.start as usize).min(end);
+
+        sprites(&self.memory.bytes()[start..end], start as u16, height)
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use crate::memory::FONT_SET_OFFSET;
+    use crate::palette::Palette;
+    use crate::Chip8;
+
+    #[test]
+    fn font_is_read_as_sprites() {
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+
+        let start = FONT_SET_OFFSET as u16;
+        let sprites = chip_8.sprites(start..start + 10, 5);
+
+        assert_eq!(sprites.len(), 2);
+        assert_eq!(sprites[1].address, start + 5);
+        // The font's 1 is a vertical line with a flag at the top.
+        assert_eq!(sprites[1].rows, [0x20, 0x60, 0x20, 0x20, 0x70]);
+
+        let palette = Palette {
+            foreground: 1,
+            background: 0,
+        };
+        let thumbnail = sprites[1].to_rgba(&palette, 2);
+
+        assert_eq!(thumbnail.len(), 16 * 10);
+        assert_eq!(&thumbnail[..8], [0, 0, 0, 0, 1, 1, 0, 0]);
+    }
+}
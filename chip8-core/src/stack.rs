@@ -0,0 +1,334 @@
+use crate::{Chip8, Chip8Error};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use tracing::warn;
+
+// When the stack is kept in memory, the bottom of it is at 0x1FE (must be an
+// even number if we want to increase the stack by 2 at a time), and it grows
+// downwards towards 0x000. I've always wanted to actually implement a stack
+// and I wanted to have it grow downwards for the true stack experience.
+pub(crate) const STACK_WINDOW_BOTTOM: u16 = 0x1FE;
+
+/// The most return addresses the stack can hold, which is as many as fit in
+/// the interpreter's area of memory.
+pub const MAX_STACK_DEPTH: usize = 256;
+/// How many return addresses the stack holds unless set with
+/// [`Chip8::set_stack_depth`]. This is what SUPER-CHIP and most
+/// interpreters since allow.
+pub const DEFAULT_STACK_DEPTH: usize = 16;
+
+/// Where the stack's return addresses are kept.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StackStorage {
+    /// Apart from memory, so that programs can't overwrite it and it can't
+    /// overwrite them.
+    #[default]
+    Dedicated,
+    /// In the interpreter's area of memory, growing down from 0x1FE like the
+    /// original interpreters. Programs that read or patch their own stack
+    /// need this. Deep enough stacks run into the fonts.
+    Memory,
+}
+
+/// The return addresses of the calls that haven't returned yet.
+#[derive(Debug, Clone)]
+pub(crate) struct Stack {
+    entries: [u16; MAX_STACK_DEPTH],
+    len: usize,
+    depth: usize,
+    storage: StackStorage,
+}
+
+impl Default for Stack {
+    fn default() -> Self {
+        Self {
+            entries: [0; MAX_STACK_DEPTH],
+            len: 0,
+            depth: DEFAULT_STACK_DEPTH,
+            storage: StackStorage::default(),
+        }
+    }
+}
+
+impl Stack {
+    /// Returns the return addresses, from the bottom up.
+    pub(crate) fn entries(&self) -> &[u16] {
+        &self.entries[..self.len]
+    }
+
+    /// Empties the stack, keeping its depth and storage.
+    pub(crate) fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Replaces the return addresses, like when loading a
+    /// [`SaveState`](super::save_state::SaveState).
+    pub(crate) fn restore(&mut self, entries: &[u16]) -> Result<(), Chip8Error> {
+        if entries.len() > MAX_STACK_DEPTH {
+            return Err(Chip8Error::InvalidSaveState);
+        }
+
+        self.entries[..entries.len()].copy_from_slice(entries);
+        self.len = entries.len();
+
+        Ok(())
+    }
+
+    /// The address in memory of the entry at `index` from the bottom, when
+    /// the stack is kept in memory.
+    fn address(index: usize) -> usize {
+        STACK_WINDOW_BOTTOM as usize - index * 2
+    }
+}
+
+/// A subroutine call that hasn't returned yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallFrame {
+    /// The address of the `2NNN` instruction that made the call.
+    pub call_site: u16,
+    /// The address of the subroutine.
+    pub target: u16,
+    /// The value of [`Stats::total_cycles`](super::stats::Stats::total_cycles)
+    /// when the call was made.
+    pub cycle: u64,
+}
+
+impl Chip8 {
+    /// The subroutine calls that haven't returned yet, outermost first.
+    ///
+    /// This is tracked separately from the stack, so it stays readable even
+    /// if a program overwrites a stack kept in memory.
+    pub fn call_stack(&self) -> &[CallFrame] {
+        &self.call_stack
+    }
+
+    /// Returns the return addresses on the stack, innermost call first.
+    ///
+    /// Unlike [`Self::call_stack`], these are where returns will really go,
+    /// including any that a program wrote over when the stack is kept in
+    /// memory.
+    pub fn stack_frames(&self) -> impl Iterator<Item = u16> + '_ {
+        (0..self.stack.len)
+            .rev()
+            .map(|index| self.stack_entry(index))
+    }
+
+    /// Returns the return address at `index` from the bottom of the stack.
+    fn stack_entry(&self, index: usize) -> u16 {
+        // Programs can change their stack when it is in memory, so memory
+        // wins over the copy we kept.
+        match self.stack.storage {
+            StackStorage::Dedicated => self.stack.entries[index],
+            StackStorage::Memory => self.memory.word(Stack::address(index)),
+        }
+    }
+
+    /// Formats the call stack innermost first, like a debugger's backtrace.
+    /// Addresses found in `symbols` (like the labels from an Octo program)
+    /// are shown by name.
+    pub fn backtrace(&self, symbols: &BTreeMap<u16, String>) -> String {
+        let name = |address: u16| match symbols.get(&address) {
+            Some(symbol) => format!("0x{address:03X} ({symbol})"),
+            None => format!("0x{address:03X}"),
+        };
+
+        let mut backtrace = format!(
+            "  at {}
+",
+            name(self.program_counter)
+        );
+
+        for frame in self.call_stack.iter().rev() {
+            let _ = writeln!(
+                backtrace,
+                "  in {} called from {} on cycle {}",
+                name(frame.target),
+                name(frame.call_site),
+                frame.cycle
+            );
+        }
+
+        backtrace
+    }
+
+    /// Pushes the return address for a call to `target`, and records the call.
+    pub(crate) fn push_call(&mut self, target: u16) -> Result<(), Chip8Error> {
+        self.push(self.program_counter)?;
+
+        self.call_stack.push(CallFrame {
+            call_site: self.program_counter.wrapping_sub(2),
+            target,
+            cycle: self.stats.total_cycles,
+        });
+
+        Ok(())
+    }
+
+    /// Pops the return address for the innermost call.
+    pub(crate) fn pop_call(&mut self) -> Result<u16, Chip8Error> {
+        let return_address = self.pop()?;
+
+        match self.call_stack.pop() {
+            Some(frame) if frame.call_site.wrapping_add(2) != return_address => warn!(
+                "Return at 0x{:03X} goes to 0x{return_address:03X}, but the call was made from 0x{:03X}",
+                self.program_counter.wrapping_sub(2),
+                frame.call_site
+            ),
+            Some(_) => {}
+            None => warn!(
+                "Return at 0x{:03X} without a matching call",
+                self.program_counter.wrapping_sub(2)
+            ),
+        }
+
+        Ok(return_address)
+    }
+
+    pub(crate) fn push(&mut self, word: u16) -> Result<(), Chip8Error> {
+        let stack = &mut self.stack;
+
+        if stack.len >= stack.depth {
+            return Err(Chip8Error::StackOverflow {
+                pc: self.program_counter.wrapping_sub(2),
+                depth: stack.len,
+            });
+        }
+
+        if stack.storage == StackStorage::Memory {
+            self.memory.set_word(Stack::address(stack.len), word);
+        }
+
+        stack.entries[stack.len] = word;
+        stack.len += 1;
+
+        Ok(())
+    }
+
+    pub(crate) fn pop(&mut self) -> Result<u16, Chip8Error> {
+        if self.stack.len == 0 {
+            return Err(Chip8Error::StackUnderflow {
+                pc: self.program_counter.wrapping_sub(2),
+            });
+        }
+
+        self.stack.len -= 1;
+
+        Ok(self.stack_entry(self.stack.len))
+    }
+
+    /// Sets how many return addresses the stack can hold before a call
+    /// overflows it, from 1 to [`MAX_STACK_DEPTH`]. Depths outside of that
+    /// are clamped. The original COSMAC VIP interpreter had room for 12.
+    pub fn set_stack_depth(&mut self, depth: usize) {
+        self.stack.depth = depth.clamp(1, MAX_STACK_DEPTH);
+    }
+
+    /// Returns how many return addresses the stack can hold.
+    pub fn stack_depth(&self) -> usize {
+        self.stack.depth
+    }
+
+    /// Sets where the stack is kept. Changing it while there are calls on the
+    /// stack is only safe with the program stopped, as the calls' return
+    /// addresses aren't copied into memory.
+    pub fn set_stack_storage(&mut self, storage: StackStorage) {
+        self.stack.storage = storage;
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{CallFrame, StackStorage};
+    use crate::Chip8;
+    use crate::Chip8Error;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn calls_are_tracked_until_they_return() {
+        // Calls 0x206, which calls 0x208, which returns twice.
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8
+            .load_program(vec![
+                0x22, 0x06, 0x12, 0x02, 0x00, 0x00, 0x22, 0x08, 0x00, 0xEE,
+            ])
+            .unwrap();
+
+        chip_8.cycle().unwrap();
+        chip_8.cycle().unwrap();
+
+        assert_eq!(
+            chip_8.call_stack(),
+            [
+                CallFrame {
+                    call_site: 0x200,
+                    target: 0x206,
+                    cycle: 0
+                },
+                CallFrame {
+                    call_site: 0x206,
+                    target: 0x208,
+                    cycle: 1
+                },
+            ]
+        );
+
+        assert_eq!(chip_8.stack_frames().collect::<Vec<_>>(), [0x208, 0x202]);
+
+        let symbols = BTreeMap::from([(0x208, "inner".to_string())]);
+        assert_eq!(
+            chip_8.backtrace(&symbols),
+            "  at 0x208 (inner)\n  in 0x208 (inner) called from 0x206 on cycle 1\n  in 0x206 called from 0x200 on cycle 0\n"
+        );
+
+        chip_8.cycle().unwrap();
+        assert_eq!(chip_8.call_stack().len(), 1);
+        assert_eq!(chip_8.program_counter, 0x208);
+    }
+
+    #[test]
+    fn stack_underflow_reports_the_return() {
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8.load_program(vec![0x00, 0xE0, 0x00, 0xEE]).unwrap();
+
+        chip_8.cycle().unwrap();
+
+        assert!(matches!(
+            chip_8.cycle(),
+            Err(Chip8Error::StackUnderflow { pc: 0x202 })
+        ));
+    }
+
+    #[test]
+    fn stack_overflow_reports_the_depth() {
+        // Calls itself forever.
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8.load_program(vec![0x22, 0x00]).unwrap();
+        chip_8.set_stack_depth(12);
+
+        for _ in 0..12 {
+            chip_8.cycle().unwrap();
+        }
+
+        assert!(matches!(
+            chip_8.cycle(),
+            Err(Chip8Error::StackOverflow {
+                pc: 0x200,
+                depth: 12
+            })
+        ));
+
+        // Kept in memory, the stack starts at 0x1FE and grows downwards.
+        chip_8.initialize().unwrap();
+        chip_8.load_program(vec![0x22, 0x00]).unwrap();
+        chip_8.set_stack_storage(StackStorage::Memory);
+
+        for _ in 0..2 {
+            chip_8.cycle().unwrap();
+        }
+
+        assert_eq!(chip_8.memory()[0x1FC..0x200], [0x02, 0x02, 0x02, 0x02]);
+    }
+}
@@ -0,0 +1,59 @@
+//! Counters describing what the emulator has done, for profiling programs.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Counters describing what the emulator has done since it was initialized.
+///
+/// Retrieved with [`Chip8::stats`](super::Chip8::stats).
+#[derive(Debug, Default, Clone)]
+pub struct Stats {
+    /// The number of cycles that have been run.
+    pub total_cycles: u64,
+    /// How many times each kind of instruction has been executed, keyed
+    /// by the name of its [`Instruction`](super::instructions::Instruction) variant.
+    pub instruction_counts: BTreeMap<&'static str, u64>,
+    /// The number of `DXYN` instructions that have been executed.
+    pub draw_calls: u64,
+    /// The number of times the sound timer was set to a non-zero value,
+    /// which starts the buzzer.
+    pub sound_activations: u64,
+    /// The number of instructions that have checked the keypad, which are
+    /// `EX9E`, `EXA1` and `FX0A`.
+    pub key_reads: u64,
+}
+
+impl Stats {
+    /// Records that an instruction was executed, given the name of its
+    /// [`Instruction`](super::instructions::Instruction) variant.
+    pub(crate) fn record_instruction(&mut self, name: &'static str) {
+        self.total_cycles += 1;
+        *self.instruction_counts.entry(name).or_insert(0) += 1;
+
+        match name {
+            "Draw" => self.draw_calls += 1,
+            "SkipIfKeyPressed" | "SkipIfKeyNotPressed" | "AwaitKeyInput" => self.key_reads += 1,
+            _ => {}
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Total cycles: {}", self.total_cycles)?;
+        writeln!(f, "Draw calls: {}", self.draw_calls)?;
+        writeln!(f, "Sound activations: {}", self.sound_activations)?;
+        writeln!(f, "Key reads: {}", self.key_reads)?;
+        writeln!(f, "Instructions executed:")?;
+
+        // Show the hottest instructions first.
+        let mut counts: Vec<_> = self.instruction_counts.iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(a.1));
+
+        for (name, count) in counts {
+            writeln!(f, "  {name:<28} {count}")?;
+        }
+
+        Ok(())
+    }
+}
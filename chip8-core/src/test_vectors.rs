@@ -0,0 +1,308 @@
+//! Test vectors for every instruction: the machine's state before and after
+//! running a single instruction, in a plain text format that other emulators
+//! can check themselves against too. The format is described at the top of
+//! `test_vectors.txt`.
+//!
+//! [`TestVector::run`] checks this emulator against a vector, and the vectors
+//! compiled into the emulator are returned by [`embedded`].
+
+use super::save_state::{RegisterChange, StateDiff};
+use super::{Chip8, Chip8Error, Keys};
+
+/// The vectors that are compiled into the emulator.
+const EMBEDDED_VECTORS: &str = include_str!("test_vectors.txt");
+
+/// Part of the machine's state, set before an instruction runs or expected
+/// after it has.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Assignment {
+    /// One of V0 to VF.
+    Register {
+        /// The register, from `0x0` to `0xF`.
+        register: u8,
+        /// Its value.
+        value: u8,
+    },
+    /// I.
+    IndexRegister(u16),
+    /// The address of the next instruction.
+    ProgramCounter(u16),
+    /// The value of the delay timer.
+    DelayTimer(u8),
+    /// The value of the sound timer.
+    SoundTimer(u8),
+    /// The keys held down.
+    Keys(Keys),
+    /// The return addresses on the stack, from the bottom up.
+    Stack(Vec<u16>),
+    /// A byte of memory.
+    Memory {
+        /// The address of the byte.
+        address: u16,
+        /// Its value.
+        value: u8,
+    },
+    /// The pixels that are lit, as `(x, y)` pairs. Every other pixel is dark.
+    Screen(Vec<(u8, u8)>),
+    /// One of the SUPER-CHIP RPL user flags.
+    RplFlag {
+        /// The flag, from 0 to 7.
+        flag: u8,
+        /// Its value.
+        value: u8,
+    },
+}
+
+impl Assignment {
+    fn apply(&self, chip_8: &mut Chip8) {
+        match self {
+            Self::Register { register, value } => chip_8.registers[*register as usize] = *value,
+            Self::IndexRegister(address) => chip_8.index_register = *address,
+            Self::ProgramCounter(address) => chip_8.program_counter = *address,
+            Self::DelayTimer(value) => chip_8.timers.set_delay(*value),
+            Self::SoundTimer(value) => chip_8.timers.set_sound(*value),
+            Self::Keys(keys) => chip_8.set_keys(*keys),
+            Self::Stack(return_addresses) => {
+                chip_8.stack.clear();
+                chip_8.call_stack.clear();
+
+                for &address in return_addresses {
+                    // A vector never has enough addresses to overflow.
+                    let _ = chip_8.push(address);
+                }
+            }
+            Self::Memory { address, value } => chip_8.memory.set_byte(*address as usize, *value),
+            Self::Screen(pixels) => {
+                chip_8.screen.clear();
+
+                for &(x, y) in pixels {
+                    chip_8.screen.draw_row(x, y, 0x8000, false);
+                }
+            }
+            Self::RplFlag { flag, value } => chip_8.rpl_flags[*flag as usize] = *value,
+        }
+    }
+}
+
+/// A single instruction, with the state it runs from and what it should
+/// change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestVector {
+    /// The raw instruction.
+    pub opcode: u16,
+    /// What to set before running it, on top of the state after a reset.
+    pub before: Vec<Assignment>,
+    /// What should have changed afterwards. The program counter should
+    /// move on to the next instruction unless it is given here.
+    pub after: Vec<Assignment>,
+}
+
+impl TestVector {
+    /// Runs the instruction from the vector's starting state with the default
+    /// quirks, returning how the result differs from what was expected. The
+    /// "before" side of the diff is the expected state and the "after" side
+    /// is what the emulator did, so an empty diff means it passed.
+    ///
+    /// Returns an error if the instruction did.
+    pub fn run(&self) -> Result<StateDiff, Chip8Error> {
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize()?;
+        chip_8.load_program(Vec::new())?;
+
+        for assignment in &self.before {
+            assignment.apply(&mut chip_8);
+        }
+
+        chip_8
+            .memory
+            .set_word(chip_8.program_counter as usize, self.opcode);
+
+        let mut expected = Chip8::new();
+        expected.load_state(&chip_8.save_state())?;
+        expected.rpl_flags = chip_8.rpl_flags;
+        expected.program_counter = expected.program_counter.wrapping_add(2);
+
+        for assignment in &self.after {
+            assignment.apply(&mut expected);
+        }
+
+        chip_8.cycle()?;
+
+        let mut diff = expected.save_state().diff(&chip_8.save_state());
+
+        // The flags are kept outside of the machine, so they aren't part of
+        // its saved state.
+        for (flag, (&before, &after)) in
+            expected.rpl_flags.iter().zip(&chip_8.rpl_flags).enumerate()
+        {
+            if before != after {
+                diff.registers.push(RegisterChange {
+                    name: format!("r{flag}"),
+                    before: before.into(),
+                    after: after.into(),
+                });
+            }
+        }
+
+        Ok(diff)
+    }
+}
+
+/// Returns the vectors that are compiled into the emulator, which cover
+/// every instruction apart from `0NNN`.
+pub fn embedded() -> Vec<TestVector> {
+    parse(EMBEDDED_VECTORS).expect("the embedded test vectors should be valid")
+}
+
+/// Parses vectors written in the same format as the embedded ones.
+pub fn parse(text: &str) -> Result<Vec<TestVector>, Chip8Error> {
+    let mut vectors = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let vector = parse_vector(line).map_err(|reason| Chip8Error::InvalidTestVector {
+            line: index + 1,
+            reason,
+        })?;
+
+        vectors.push(vector);
+    }
+
+    Ok(vectors)
+}
+
+fn parse_vector(line: &str) -> Result<TestVector, String> {
+    let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+
+    let [opcode, before, after] = fields[..] else {
+        return Err(format!("expected 3 fields, found {}", fields.len()));
+    };
+
+    if opcode.len() != 4 {
+        return Err(format!("invalid opcode {opcode:?}"));
+    }
+
+    Ok(TestVector {
+        opcode: parse_hex(opcode)?,
+        before: parse_assignments(before)?,
+        after: parse_assignments(after)?,
+    })
+}
+
+fn parse_assignments(assignments: &str) -> Result<Vec<Assignment>, String> {
+    if assignments == "-" {
+        return Ok(Vec::new());
+    }
+
+    assignments
+        .split_whitespace()
+        .map(parse_assignment)
+        .collect()
+}
+
+fn parse_assignment(assignment: &str) -> Result<Assignment, String> {
+    let Some((name, value)) = assignment.split_once('=') else {
+        return Err(format!("expected name=value, found {assignment:?}"));
+    };
+
+    let assignment = match name {
+        "i" => Assignment::IndexRegister(parse_hex(value)?),
+        "pc" => Assignment::ProgramCounter(parse_hex(value)?),
+        "dt" => Assignment::DelayTimer(parse_hex(value)?),
+        "st" => Assignment::SoundTimer(parse_hex(value)?),
+        "keys" => Assignment::Keys(Keys(parse_hex(value)?)),
+        "stack" => Assignment::Stack(split_list(value).map(parse_hex).collect::<Result<_, _>>()?),
+        "screen" => Assignment::Screen(
+            split_list(value)
+                .map(parse_pixel)
+                .collect::<Result<_, _>>()?,
+        ),
+        _ if name.len() == 2 && name.starts_with('v') => Assignment::Register {
+            register: parse_hex(&name[1..])?,
+            value: parse_hex(value)?,
+        },
+        _ if name.len() == 2 && name.starts_with('r') => Assignment::RplFlag {
+            flag: match parse_hex(&name[1..])? {
+                flag @ 0..=7 => flag,
+                _ => return Err(format!("unknown name {name:?}")),
+            },
+            value: parse_hex(value)?,
+        },
+        _ if name.len() == 4 && name.starts_with('m') => Assignment::Memory {
+            address: parse_hex(&name[1..])?,
+            value: parse_hex(value)?,
+        },
+        _ => return Err(format!("unknown name {name:?}")),
+    };
+
+    Ok(assignment)
+}
+
+/// Splits a comma separated list, where an empty string is an empty list.
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',').filter(|item| !item.is_empty())
+}
+
+fn parse_hex<T: TryFrom<u32>>(digits: &str) -> Result<T, String> {
+    u32::from_str_radix(digits, 16)
+        .ok()
+        .and_then(|value| T::try_from(value).ok())
+        .ok_or_else(|| format!("invalid hexadecimal value {digits:?}"))
+}
+
+fn parse_pixel(pixel: &str) -> Result<(u8, u8), String> {
+    let invalid = || format!("invalid pixel {pixel:?}");
+
+    let (x, y) = pixel.split_once(':').ok_or_else(invalid)?;
+    let x: u8 = x.parse().map_err(|_| invalid())?;
+    let y: u8 = y.parse().map_err(|_| invalid())?;
+
+    if x as u32 >= crate::WIDTH || y as u32 >= crate::HEIGHT {
+        return Err(invalid());
+    }
+
+    Ok((x, y))
+}
+
+#[cfg(test)]
+mod test_super {
+    use std::collections::BTreeSet;
+
+    use super::embedded;
+    use crate::Instruction;
+
+    #[test]
+    fn the_embedded_vectors_pass() {
+        let vectors = embedded();
+
+        let failures: Vec<_> = vectors
+            .iter()
+            .filter_map(|vector| match vector.run() {
+                Ok(diff) if diff.is_empty() => None,
+                Ok(diff) => Some(format!("{:04X}:\n{diff}", vector.opcode)),
+                Err(err) => Some(format!("{:04X}: {err}", vector.opcode)),
+            })
+            .collect();
+
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+
+        // Every instruction apart from 0NNN has at least one vector.
+        let covered: BTreeSet<_> = vectors
+            .iter()
+            .map(|vector| Instruction::new(vector.opcode).unwrap().name())
+            .collect();
+
+        let uncovered: Vec<_> = Instruction::NAMES
+            .iter()
+            .copied()
+            .filter(|name| !covered.contains(name))
+            .collect();
+
+        assert_eq!(uncovered, ["CallMachineCodeRoutine", "Unknown"]);
+    }
+}
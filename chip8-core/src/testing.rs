@@ -0,0 +1,265 @@
+//! A [`proptest`] harness that runs single instructions from random machine
+//! states and checks the invariants every CHIP-8 interpreter keeps, whatever
+//! its quirks: how VF is set by arithmetic, how far the program counter
+//! moves, and which registers and memory an instruction may change.
+//!
+//! Only built with the `testing` feature (and for this crate's own tests), so
+//! that crates adding quirks can run it against their own [`Quirks`]:
+//!
+//! ```ignore
+//! proptest! {
+//!     #[test]
+//!     fn my_quirks_keep_the_invariants(
+//!         state in testing::machine_state(),
+//!         instruction in testing::instruction(),
+//!     ) {
+//!         testing::check_instruction(my_quirks(), &state, instruction)?;
+//!     }
+//! }
+//! ```
+
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseError;
+
+use super::memory::MEMORY_SIZE;
+use super::quirks::Quirks;
+use super::{Chip8, Instruction};
+
+/// The registers an instruction starts from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachineState {
+    /// V0 to VF.
+    pub registers: [u8; 16],
+    /// The index register, I.
+    pub index_register: u16,
+    /// The address the instruction is placed at.
+    pub program_counter: u16,
+}
+
+/// Generates random registers, with I anywhere in memory and the program
+/// counter anywhere in the program area.
+pub fn machine_state() -> impl Strategy<Value = MachineState> {
+    (
+        any::<[u8; 16]>(),
+        0..MEMORY_SIZE as u16,
+        (0x100..0x7FF_u16).prop_map(|half| half * 2),
+    )
+        .prop_map(
+            |(registers, index_register, program_counter)| MachineState {
+                registers,
+                index_register,
+                program_counter,
+            },
+        )
+}
+
+/// Generates every instruction the emulator can run, which leaves out `0NNN`.
+pub fn instruction() -> impl Strategy<Value = Instruction> {
+    any::<u16>().prop_filter_map("not an instruction the emulator runs", |raw| {
+        Instruction::new(raw)
+            .ok()
+            .filter(|instruction| *instruction != Instruction::CallMachineCodeRoutine)
+    })
+}
+
+/// Runs `instruction` from `state` with `quirks`, and fails if it broke an
+/// invariant. Instructions that return an error, like a return with nothing
+/// on the stack, pass as long as they don't panic.
+pub fn check_instruction(
+    quirks: Quirks,
+    state: &MachineState,
+    instruction: Instruction,
+) -> Result<(), TestCaseError> {
+    let mut chip_8 = Chip8::new();
+    chip_8.initialize().unwrap();
+    chip_8.load_program(vec![0; 2]).unwrap();
+    chip_8.quirks = quirks;
+    chip_8.registers = state.registers;
+    chip_8.index_register = state.index_register;
+    chip_8.program_counter = state.program_counter;
+
+    let pc = state.program_counter;
+    chip_8.memory.set_word(pc as usize, instruction.encode());
+
+    let memory_before = chip_8.memory.bytes().to_vec();
+
+    if chip_8.cycle().is_err() {
+        return Ok(());
+    }
+
+    let before = &state.registers;
+    let after = &chip_8.registers;
+
+    check_program_counter(instruction, before, pc, chip_8.program_counter)?;
+    check_flags(quirks, instruction, before, after)?;
+
+    for register in 0..16 {
+        if before[register] != after[register] {
+            prop_assert!(
+                may_change_register(quirks, instruction, register as u8),
+                "{instruction} changed V{register:X}"
+            );
+        }
+    }
+
+    let written = written_addresses(instruction, state);
+
+    for (address, (before, after)) in memory_before.iter().zip(chip_8.memory.bytes()).enumerate() {
+        prop_assert!(
+            before == after || written.contains(&address),
+            "{instruction} wrote to 0x{address:03X}"
+        );
+    }
+
+    Ok(())
+}
+
+fn check_program_counter(
+    instruction: Instruction,
+    registers: &[u8; 16],
+    pc: u16,
+    new_pc: u16,
+) -> Result<(), TestCaseError> {
+    let next = pc + 2;
+
+    let allowed = match instruction {
+        Instruction::Jump { nnn } | Instruction::Call { nnn } => vec![nnn],
+        Instruction::JumpWithPcOffset { nnn } => vec![registers[0] as u16 + nnn],
+        Instruction::SkipIfRegisterEquals { .. }
+        | Instruction::SkipIfRegisterNotEquals { .. }
+        | Instruction::SkipIfRegisterVxEqualsVy { .. }
+        | Instruction::SkipIfRegisterVxNotEqualsVy { .. }
+        | Instruction::SkipIfKeyPressed { .. }
+        | Instruction::SkipIfKeyNotPressed { .. } => vec![next, next + 2],
+        // These can run again instead of carrying on.
+        Instruction::AwaitKeyInput { .. } | Instruction::Draw { .. } => vec![pc, next],
+        _ => vec![next],
+    };
+
+    // The program counter wraps around at the end of memory.
+    let allowed: Vec<u16> = allowed
+        .into_iter()
+        .map(|address| address % MEMORY_SIZE as u16)
+        .collect();
+
+    prop_assert!(
+        allowed.contains(&new_pc),
+        "{instruction} at 0x{pc:03X} moved the PC to 0x{new_pc:03X}"
+    );
+
+    Ok(())
+}
+
+/// Checks the VF semantics of the arithmetic instructions. When VX is VF, the
+/// flag wins over the result.
+fn check_flags(
+    quirks: Quirks,
+    instruction: Instruction,
+    before: &[u8; 16],
+    after: &[u8; 16],
+) -> Result<(), TestCaseError> {
+    let flag = match instruction {
+        Instruction::Add { vx, vy } => before[vx as usize]
+            .checked_add(before[vy as usize])
+            .is_none() as u8,
+        Instruction::Subtract { vx, vy } => (before[vx as usize] >= before[vy as usize]) as u8,
+        Instruction::SetVxToVyMinusVx { vx, vy } => {
+            (before[vy as usize] >= before[vx as usize]) as u8
+        }
+        Instruction::RightShift { vx, vy } => before[shift_source(quirks, vx, vy)] & 1,
+        Instruction::LeftShift { vx, vy } => before[shift_source(quirks, vx, vy)] >> 7,
+        Instruction::AddImmediate { vx, .. } if vx != 0xF => before[0xF],
+        Instruction::Draw { .. } => {
+            // A draw waiting for the vertical blank changes nothing.
+            prop_assert!(
+                after[0xF] <= 1 || after == before,
+                "a draw set VF to {}",
+                after[0xF]
+            );
+            return Ok(());
+        }
+        _ => return Ok(()),
+    };
+
+    prop_assert_eq!(after[0xF], flag, "{} set the wrong flag", instruction);
+
+    Ok(())
+}
+
+fn shift_source(quirks: Quirks, vx: u8, vy: u8) -> usize {
+    if quirks.shift_vy {
+        vy as usize
+    } else {
+        vx as usize
+    }
+}
+
+/// Returns true if `instruction` is allowed to change `register`.
+fn may_change_register(quirks: Quirks, instruction: Instruction, register: u8) -> bool {
+    match instruction {
+        Instruction::SetImmediate { vx, .. }
+        | Instruction::AddImmediate { vx, .. }
+        | Instruction::Copy { vx, .. }
+        | Instruction::BitwiseOr { vx, .. }
+        | Instruction::BitwiseAnd { vx, .. }
+        | Instruction::BitwiseXor { vx, .. }
+        | Instruction::Random { vx, .. }
+        | Instruction::SetVxToDelayTimer { vx }
+        | Instruction::AwaitKeyInput { vx } => register == vx,
+        Instruction::Add { vx, .. }
+        | Instruction::Subtract { vx, .. }
+        | Instruction::RightShift { vx, .. }
+        | Instruction::SetVxToVyMinusVx { vx, .. }
+        | Instruction::LeftShift { vx, .. } => register == vx || register == 0xF,
+        Instruction::Draw { .. } => register == 0xF,
+        Instruction::AddToIndex { .. } => quirks.index_overflow_flag && register == 0xF,
+        Instruction::LoadRegisters { vx } => register <= vx,
+        Instruction::LoadFlags { vx } => register <= vx.min(7),
+        _ => false,
+    }
+}
+
+/// Returns the addresses `instruction` is allowed to write to.
+fn written_addresses(instruction: Instruction, state: &MachineState) -> Vec<usize> {
+    let count = match instruction {
+        Instruction::SetIndexToBinaryCodedVx { .. } => 3,
+        Instruction::DumpRegisters { vx } => vx as usize + 1,
+        _ => 0,
+    };
+
+    (0..count)
+        .map(|offset| (state.index_register as usize + offset) % MEMORY_SIZE)
+        .collect()
+}
+
+#[cfg(test)]
+mod test_super {
+    use proptest::prelude::*;
+
+    use super::{check_instruction, instruction, machine_state};
+    use crate::quirks::Quirks;
+
+    proptest! {
+        #[test]
+        fn instructions_keep_their_invariants(
+            state in machine_state(),
+            instruction in instruction(),
+            display_wait: bool,
+            wrap_sprites: bool,
+            shift_vy: bool,
+            increment_index: bool,
+            index_overflow_flag: bool,
+            console_output: bool,
+        ) {
+            let quirks = Quirks {
+                display_wait,
+                wrap_sprites,
+                shift_vy,
+                increment_index,
+                index_overflow_flag,
+                console_output,
+            };
+            check_instruction(quirks, &state, instruction)?;
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! The delay and sound timers. Programs set both, but can only read the delay
+//! timer back, while the sound timer sounds the buzzer for as long as it's
+//! above 0.
+//!
+//! Both count down at 60Hz, which [`Chip8`](super::Chip8) does on its own
+//! unless told otherwise with
+//! [`Chip8::set_cycles_per_second`](super::Chip8::set_cycles_per_second).
+
+use super::sound::play_buzzer;
+
+/// The delay and sound timers. Each counts down by one every tick until it
+/// reaches 0.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Timers {
+    delay: u8,
+    sound: u8,
+}
+
+impl Timers {
+    /// Returns the delay timer, which `FX07` reads and `FX15` sets.
+    pub fn delay(&self) -> u8 {
+        self.delay
+    }
+
+    /// Returns the sound timer, which `FX18` sets.
+    pub fn sound(&self) -> u8 {
+        self.sound
+    }
+
+    /// Sets the delay timer.
+    pub fn set_delay(&mut self, value: u8) {
+        self.delay = value;
+    }
+
+    /// Sets the sound timer.
+    pub fn set_sound(&mut self, value: u8) {
+        self.sound = value;
+    }
+
+    /// Counts both timers down by one, leaving any that are already at 0.
+    pub(crate) fn tick(&mut self) {
+        self.delay = self.delay.saturating_sub(1);
+
+        if self.sound > 0 {
+            self.sound -= 1;
+            play_buzzer();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use crate::Chip8;
+
+    #[test]
+    fn timers_count_down_at_60hz() {
+        let program = vec![
+            0x60, 0x78, // V0 = 120
+            0x61, 0x3C, // V1 = 60
+            0xF0, 0x15, // delay timer = V0
+            0xF1, 0x18, // sound timer = V1
+            0xF2, 0x07, // V2 = delay timer
+            0x12, 0x08, // jump back to reading it
+        ];
+
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8.load_program(program).unwrap();
+
+        // At the default 720 cycles a second, the timers tick every 12
+        // cycles, so they have ticked 99 times by the last read.
+        for _ in 0..1199 {
+            chip_8.cycle().unwrap();
+        }
+
+        assert_eq!(chip_8.timers.delay(), 21);
+        assert_eq!(chip_8.timers.sound(), 0);
+        assert_eq!(chip_8.registers[2], 21);
+
+        for _ in 0..2000 {
+            chip_8.cycle().unwrap();
+        }
+
+        assert_eq!(chip_8.timers.delay(), 0);
+        assert_eq!(chip_8.registers[2], 0);
+    }
+}
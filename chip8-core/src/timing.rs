@@ -0,0 +1,122 @@
+//! How long each instruction takes to run, which decides how many of them fit
+//! in a 60Hz frame.
+//!
+//! Most interpreters run a fixed number of instructions per second no matter
+//! what they are. The original COSMAC VIP interpreter didn't: simple
+//! instructions like `6XNN` took a few dozen microseconds, while drawing a
+//! sprite took milliseconds. Games tuned on the VIP expect that, so
+//! [`TimingModel::Vip`] charges each instruction roughly what it cost there.
+
+use super::DEFAULT_CYCLES_PER_SECOND;
+
+/// How many microseconds pass each second.
+const MICROSECONDS_PER_SECOND: u32 = 1_000_000;
+
+/// Roughly how many instructions per second a typical game runs at under
+/// [`TimingModel::Vip`].
+const VIP_CYCLES_PER_SECOND: u32 = 600;
+
+/// How the emulator decides how long an instruction takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimingModel {
+    /// Every instruction takes the same time, with this many run per second.
+    Fixed(u32),
+    /// Every instruction takes about as long as it did on the COSMAC VIP,
+    /// with drawing sprites being far slower than anything else.
+    Vip,
+}
+
+impl Default for TimingModel {
+    fn default() -> Self {
+        Self::Fixed(DEFAULT_CYCLES_PER_SECOND)
+    }
+}
+
+impl TimingModel {
+    /// Roughly how many instructions run per second. For [`Self::Vip`] this
+    /// depends on the program, so it is only an estimate.
+    pub fn cycles_per_second(self) -> u32 {
+        match self {
+            Self::Fixed(cycles_per_second) => cycles_per_second,
+            Self::Vip => VIP_CYCLES_PER_SECOND,
+        }
+    }
+
+    /// How many units of time pass each second. A unit is one instruction
+    /// for [`Self::Fixed`] and one microsecond for [`Self::Vip`].
+    pub(crate) fn units_per_second(self) -> u32 {
+        match self {
+            Self::Fixed(cycles_per_second) => cycles_per_second.max(1),
+            Self::Vip => MICROSECONDS_PER_SECOND,
+        }
+    }
+
+    /// How many units of time the instruction `word` takes to run.
+    pub(crate) fn cost(self, word: u16) -> u32 {
+        match self {
+            Self::Fixed(_) => 1,
+            Self::Vip => vip_microseconds(word),
+        }
+    }
+}
+
+/// Approximately how many microseconds the COSMAC VIP interpreter spent on
+/// `word`, including fetching and decoding it.
+fn vip_microseconds(word: u16) -> u32 {
+    let rows = u32::from(word & 0xF);
+
+    match word & 0xF000 {
+        0x0000 if word == 0x00E0 => 109,
+        0x0000 => 105,
+        0x1000 | 0x2000 | 0xB000 => 105,
+        0x3000 | 0x4000 | 0xA000 => 55,
+        0x5000 | 0x9000 => 73,
+        0x6000 => 27,
+        0x7000 => 45,
+        0x8000 => 200,
+        0xC000 => 164,
+        // Each row has to be shifted into place and XORed onto the screen
+        // byte by byte, which is what makes drawing so slow.
+        0xD000 => 2_000 + 400 * rows,
+        0xE000 => 73,
+        _ => match word & 0xFF {
+            0x1E => 86,
+            0x29 => 91,
+            0x33 => 927,
+            0x55 | 0x65 => 605,
+            _ => 45,
+        },
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::TimingModel;
+    use crate::Chip8;
+
+    /// Runs `program` for a frame, returning how many instructions ran.
+    fn cycles_in_a_frame(timing: TimingModel, program: Vec<u8>) -> u64 {
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8.load_program(program).unwrap();
+        chip_8.set_timing_model(timing);
+
+        chip_8.run_frame().unwrap();
+        chip_8.stats().total_cycles
+    }
+
+    #[test]
+    fn drawing_is_slow_on_the_vip() {
+        // Loops forever, either setting a register or drawing a sprite.
+        let set = vec![0x60, 0x01, 0x12, 0x00];
+        let draw = vec![0xD0, 0x18, 0x12, 0x00];
+
+        assert_eq!(cycles_in_a_frame(TimingModel::Fixed(600), set.clone()), 10);
+        assert_eq!(cycles_in_a_frame(TimingModel::Fixed(600), draw.clone()), 10);
+
+        let set = cycles_in_a_frame(TimingModel::Vip, set);
+        let draw = cycles_in_a_frame(TimingModel::Vip, draw);
+        assert!(set > 100, "{set}");
+        assert!(draw < 10, "{draw}");
+    }
+}
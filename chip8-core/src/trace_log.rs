@@ -0,0 +1,247 @@
+//! Execution logs with one line per instruction, for comparing a program's run
+//! against a reference log from another emulator or an earlier version.
+//!
+//! Each line holds the state before an instruction runs as `key:value` pairs
+//! in hex, like `pc:0200 i:0000 v0:00 ... vf:00 op:00E0`. `pc`, `i` and `v0`
+//! to `vf` are compared, and any of them can be left out of a reference log
+//! that doesn't record them. Other words are ignored, and lines starting with
+//! `#` are comments.
+
+use std::fmt::{self, Write};
+
+use super::{Chip8, Chip8Error, Instruction};
+
+/// The state of the emulator before one instruction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// The program counter.
+    pub pc: Option<u16>,
+    /// The index register.
+    pub i: Option<u16>,
+    /// V0 to VF.
+    pub registers: [Option<u8>; 16],
+    /// The instruction about to run. It is written, but not compared.
+    pub opcode: Option<u16>,
+}
+
+impl TraceEntry {
+    /// Records the emulator's current state.
+    pub fn capture(chip_8: &Chip8) -> Self {
+        Self {
+            pc: Some(chip_8.program_counter),
+            i: Some(chip_8.index_register),
+            registers: chip_8.registers.map(Some),
+            opcode: Some(chip_8.memory.word(chip_8.program_counter as usize)),
+        }
+    }
+
+    /// Parses a line of a log, or returns `None` for comments and blank lines.
+    pub fn parse(line: &str) -> Result<Option<Self>, String> {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(None);
+        }
+
+        let mut entry = Self::default();
+
+        for (key, value) in line
+            .split_whitespace()
+            .filter_map(|word| word.split_once(':'))
+        {
+            let key = key.to_ascii_lowercase();
+            let number = || {
+                u16::from_str_radix(value.trim_start_matches("0x"), 16)
+                    .map_err(|e| format!("invalid {key} {value:?}: {e}"))
+            };
+
+            match key.as_str() {
+                "pc" => entry.pc = Some(number()?),
+                "i" => entry.i = Some(number()?),
+                "op" => entry.opcode = Some(number()?),
+                _ => {
+                    let Some(register) = key
+                        .strip_prefix('v')
+                        .filter(|digit| digit.len() == 1)
+                        .and_then(|digit| u8::from_str_radix(digit, 16).ok())
+                    else {
+                        continue;
+                    };
+
+                    let value = u8::try_from(number()?)
+                        .map_err(|_| format!("{key} {value:?} doesn't fit in a byte"))?;
+                    entry.registers[register as usize] = Some(value);
+                }
+            }
+        }
+
+        Ok(Some(entry))
+    }
+
+    /// Returns the names of the values that both entries have but differ on.
+    pub fn differences(&self, other: &Self) -> Vec<String> {
+        let differs =
+            |a: Option<u16>, b: Option<u16>| matches!((a, b), (Some(a), Some(b)) if a != b);
+        let mut differences = Vec::new();
+
+        if differs(self.pc, other.pc) {
+            differences.push("pc".to_string());
+        }
+
+        if differs(self.i, other.i) {
+            differences.push("i".to_string());
+        }
+
+        for (register, (a, b)) in self.registers.iter().zip(other.registers).enumerate() {
+            if differs(a.map(u16::from), b.map(u16::from)) {
+                differences.push(format!("v{register:x}"));
+            }
+        }
+
+        differences
+    }
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut words = Vec::new();
+
+        if let Some(pc) = self.pc {
+            words.push(format!("pc:{pc:04X}"));
+        }
+
+        if let Some(i) = self.i {
+            words.push(format!("i:{i:04X}"));
+        }
+
+        for (register, value) in self.registers.iter().enumerate() {
+            if let Some(value) = value {
+                words.push(format!("v{register:x}:{value:02X}"));
+            }
+        }
+
+        if let Some(opcode) = self.opcode {
+            words.push(format!("op:{opcode:04X}"));
+        }
+
+        write!(f, "{}", words.join(" "))
+    }
+}
+
+/// Where a run first differed from a reference log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// How many instructions ran before the divergence.
+    pub cycle: u64,
+    /// The line of the log, counting from 1.
+    pub line: usize,
+    /// The state in the log.
+    pub expected: TraceEntry,
+    /// The state of the emulator.
+    pub actual: TraceEntry,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Diverged after {} instructions, on line {} of the log",
+            self.cycle, self.line
+        )?;
+        writeln!(f, "  expected: {}", self.expected)?;
+        writeln!(f, "  actual:   {}", self.actual)?;
+
+        if let Some(instruction) = self
+            .actual
+            .opcode
+            .and_then(|opcode| Instruction::new(opcode).ok())
+        {
+            writeln!(f, "  next:     {instruction}")?;
+        }
+
+        write!(
+            f,
+            "  differs:  {}",
+            self.expected.differences(&self.actual).join(", ")
+        )
+    }
+}
+
+/// Runs a program loaded at `offset` for `cycles` instructions with no keys
+/// pressed, writing a log line before each one.
+pub fn write_log(program: &[u8], offset: u16, cycles: u64) -> Result<String, Chip8Error> {
+    let mut chip_8 = start(program, offset)?;
+    let mut log = String::new();
+
+    for _ in 0..cycles {
+        let _ = writeln!(log, "{}", TraceEntry::capture(&chip_8));
+        chip_8.cycle()?;
+    }
+
+    Ok(log)
+}
+
+/// Runs a program loaded at `offset` with no keys pressed, one instruction
+/// per entry in `log`, and returns the first entry the emulator's state
+/// doesn't match. Returns `None` if the whole log matched.
+pub fn compare(program: &[u8], offset: u16, log: &str) -> Result<Option<Divergence>, Chip8Error> {
+    let mut chip_8 = start(program, offset)?;
+    let mut cycle = 0;
+
+    for (index, line) in log.lines().enumerate() {
+        let entry = TraceEntry::parse(line).map_err(|reason| Chip8Error::InvalidTraceLog {
+            line: index + 1,
+            reason,
+        })?;
+
+        let Some(expected) = entry else {
+            continue;
+        };
+
+        let actual = TraceEntry::capture(&chip_8);
+
+        if !expected.differences(&actual).is_empty() {
+            return Ok(Some(Divergence {
+                cycle,
+                line: index + 1,
+                expected,
+                actual,
+            }));
+        }
+
+        chip_8.cycle()?;
+        cycle += 1;
+    }
+
+    Ok(None)
+}
+
+fn start(program: &[u8], offset: u16) -> Result<Chip8, Chip8Error> {
+    let mut chip_8 = Chip8::new();
+    chip_8.initialize()?;
+    chip_8.load_program_at(offset as usize, program.to_vec())?;
+
+    Ok(chip_8)
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{compare, write_log};
+
+    #[test]
+    fn runs_are_compared_against_logs() {
+        // Counts up in V0 forever.
+        let program = [0x70, 0x01, 0x12, 0x00];
+        let log = write_log(&program, 0x200, 6).unwrap();
+
+        assert_eq!(compare(&program, 0x200, &log).unwrap(), None);
+
+        // A log from an emulator that counts in twos, and only records V0.
+        let log = "# pc v0\npc:200 v0:00\npc:202 v0:02\npc:200 v0:02\npc:202 v0:04\n";
+        let divergence = compare(&program, 0x200, log).unwrap().unwrap();
+
+        assert_eq!(divergence.cycle, 1);
+        assert_eq!(divergence.line, 3);
+        assert_eq!(divergence.expected.differences(&divergence.actual), ["v0"]);
+    }
+}
@@ -0,0 +1,161 @@
+//! Messages logged whenever an address runs, without stopping the program.
+//!
+//! Messages are logged with the `chip_8::trace` target, so they can be picked
+//! out with `RUST_LOG=chip_8::trace=info`.
+
+use std::fmt::Write;
+
+use tracing::info;
+
+use super::{Chip8, Chip8Error};
+
+/// A value from the emulator that can be put in a tracepoint's message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Value {
+    /// `{v0}` to `{vf}`, shown in decimal.
+    Register(u8),
+    /// `{i}`, shown in hex.
+    IndexRegister,
+    /// `{dt}`, shown in decimal.
+    DelayTimer,
+    /// `{st}`, shown in decimal.
+    SoundTimer,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Value(Value),
+}
+
+/// A message logged every time the instruction at an address is about to run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tracepoint {
+    segments: Vec<Segment>,
+}
+
+impl Tracepoint {
+    /// Parses a message, where `{v0}` to `{vf}`, `{i}`, `{dt}` and `{st}` are
+    /// replaced by the registers, index register and timers when it's logged.
+    pub fn new(message: &str) -> Result<Self, Chip8Error> {
+        let mut segments = Vec::new();
+        let mut rest = message;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                segments.push(Segment::Text(rest[..start].to_string()));
+            }
+
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| Chip8Error::InvalidTracepoint {
+                    reason: "unclosed {".to_string(),
+                })?
+                + start;
+
+            segments.push(Segment::Value(parse_value(&rest[start + 1..end])?));
+            rest = &rest[end + 1..];
+        }
+
+        if !rest.is_empty() {
+            segments.push(Segment::Text(rest.to_string()));
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Fills in the message from the emulator's current state.
+    fn format(&self, chip_8: &Chip8) -> String {
+        let mut message = String::new();
+
+        for segment in &self.segments {
+            let _ = match segment {
+                Segment::Text(text) => write!(message, "{text}"),
+                Segment::Value(Value::Register(register)) => {
+                    write!(message, "{}", chip_8.registers[*register as usize])
+                }
+                Segment::Value(Value::IndexRegister) => {
+                    write!(message, "0x{:03X}", chip_8.index_register)
+                }
+                Segment::Value(Value::DelayTimer) => write!(message, "{}", chip_8.timers.delay()),
+                Segment::Value(Value::SoundTimer) => write!(message, "{}", chip_8.timers.sound()),
+            };
+        }
+
+        message
+    }
+}
+
+fn parse_value(name: &str) -> Result<Value, Chip8Error> {
+    let value = match name.to_ascii_lowercase().as_str() {
+        "i" => Value::IndexRegister,
+        "dt" => Value::DelayTimer,
+        "st" => Value::SoundTimer,
+        register => register
+            .strip_prefix('v')
+            .filter(|digit| digit.len() == 1)
+            .and_then(|digit| u8::from_str_radix(digit, 16).ok())
+            .map(Value::Register)
+            .ok_or_else(|| Chip8Error::InvalidTracepoint {
+                reason: format!("unknown value {{{name}}}"),
+            })?,
+    };
+
+    Ok(value)
+}
+
+impl Chip8 {
+    /// Logs `tracepoint`'s message every time the instruction at `address` is
+    /// about to run. The program carries on running.
+    pub fn add_tracepoint(&mut self, address: u16, tracepoint: Tracepoint) {
+        self.tracepoints
+            .entry(address)
+            .or_default()
+            .push(tracepoint);
+    }
+
+    /// Removes every tracepoint.
+    pub fn clear_tracepoints(&mut self) {
+        self.tracepoints.clear();
+    }
+
+    /// Logs the messages of the tracepoints at the program counter.
+    pub(crate) fn run_tracepoints(&self) {
+        let Some(tracepoints) = self.tracepoints.get(&self.program_counter) else {
+            return;
+        };
+
+        for tracepoint in tracepoints {
+            info!(
+                target: "chip_8::trace",
+                "0x{:03X} cycle {}: {}",
+                self.program_counter,
+                self.stats.total_cycles,
+                tracepoint.format(self)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::Tracepoint;
+    use crate::Chip8;
+
+    #[test]
+    fn values_are_substituted() {
+        let mut chip_8 = Chip8::new();
+        chip_8.registers[0xA] = 42;
+        chip_8.index_register = 0x2F0;
+
+        let tracepoint = Tracepoint::new("score={vA} sprite={i}").unwrap();
+
+        assert_eq!(tracepoint.format(&chip_8), "score=42 sprite=0x2F0");
+    }
+
+    #[test]
+    fn unknown_values_are_rejected() {
+        assert!(Tracepoint::new("{v10}").is_err());
+        assert!(Tracepoint::new("{pc").is_err());
+    }
+}
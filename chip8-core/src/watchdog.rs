@@ -0,0 +1,96 @@
+//! A limit on how long a frame can run without drawing or waiting for a key.
+//!
+//! A program stuck in a tight loop never draws, so with a high clock rate
+//! every frame runs its full budget of cycles and the thread running the
+//! emulator has no time left for anything else. With the watchdog set, a frame
+//! that runs too many instructions without a `DXYN`, `00E0` or `FX0A` ends
+//! early, and the emulator reports that it was [`Throttled`].
+
+use super::{Chip8, FrameRun};
+
+/// Reported by [`Chip8::take_throttled`] when a frame was cut short by the
+/// watchdog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throttled {
+    /// The address of the instruction the frame stopped before.
+    pub address: u16,
+    /// How many instructions ran without drawing or waiting for a key.
+    pub instructions: u32,
+}
+
+impl Chip8 {
+    /// Sets how many instructions a frame run with [`Self::run_frame`] can
+    /// run without drawing or waiting for a key before it is ended early.
+    /// `None`, the default, never ends frames early.
+    pub fn set_watchdog(&mut self, budget: Option<u32>) {
+        self.watchdog = budget;
+    }
+
+    /// Returns the last time the watchdog ended a frame early, if it has
+    /// since the last call.
+    pub fn take_throttled(&mut self) -> Option<Throttled> {
+        self.throttled.take()
+    }
+
+    /// Counts the instruction `word` against the watchdog's budget, returning
+    /// false if the frame has to end before it runs.
+    pub(crate) fn watch(&mut self, frame: &mut FrameRun, word: u16) -> bool {
+        let Some(budget) = self.watchdog else {
+            return true;
+        };
+
+        if word == 0x00E0 || word & 0xF000 == 0xD000 || word & 0xF0FF == 0xF00A {
+            frame.instructions_since_draw = 0;
+            return true;
+        }
+
+        if frame.instructions_since_draw >= budget {
+            self.throttled = Some(Throttled {
+                address: self.program_counter,
+                instructions: frame.instructions_since_draw,
+            });
+            return false;
+        }
+
+        frame.instructions_since_draw += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::Throttled;
+    use crate::Chip8;
+
+    /// Runs `program` for a frame with a watchdog budget of 5 instructions.
+    fn run_watched(program: Vec<u8>) -> Chip8 {
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8.set_watchdog(Some(5));
+        chip_8.load_program(program).unwrap();
+        chip_8.run_frame().unwrap();
+        chip_8
+    }
+
+    #[test]
+    fn loops_that_never_draw_are_throttled() {
+        // Jumps to itself forever.
+        let mut chip_8 = run_watched(vec![0x12, 0x00]);
+
+        assert_eq!(chip_8.stats().total_cycles, 5);
+        assert_eq!(
+            chip_8.take_throttled(),
+            Some(Throttled {
+                address: 0x200,
+                instructions: 5
+            })
+        );
+        assert_eq!(chip_8.take_throttled(), None);
+
+        // Draws, then jumps back.
+        let mut chip_8 = run_watched(vec![0xD0, 0x11, 0x12, 0x00]);
+
+        assert_eq!(chip_8.stats().total_cycles, 12);
+        assert_eq!(chip_8.take_throttled(), None);
+    }
+}
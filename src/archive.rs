@@ -0,0 +1,92 @@
+//! Loading ROMs out of `.zip` and `.gz` archives, since most ROM collections
+//! are distributed compressed.
+
+use flate2::read::GzDecoder;
+use std::io::{self, Read, Seek};
+use zip::ZipArchive;
+
+/// The extensions of files inside a `.zip` that are treated as ROMs.
+const ROM_EXTENSIONS: [&str; 2] = ["ch8", "rom"];
+
+/// Extracts the first file ending in `.ch8` or `.rom` from a `.zip` archive.
+pub fn extract_zip(reader: impl Read + Seek) -> io::Result<Vec<u8>> {
+    let mut archive = ZipArchive::new(reader)?;
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+
+        let is_rom = file
+            .enclosed_name()
+            .and_then(|path| path.extension())
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                ROM_EXTENSIONS
+                    .iter()
+                    .any(|rom_extension| extension.eq_ignore_ascii_case(rom_extension))
+            });
+
+        if file.is_file() && is_rom {
+            let mut program_bytes = Vec::new();
+            file.read_to_end(&mut program_bytes)?;
+            return Ok(program_bytes);
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "archive does not contain a .ch8 or .rom file",
+    ))
+}
+
+/// Decompresses a `.gz` file, which only ever contains a single ROM.
+pub fn extract_gz(reader: impl Read) -> io::Result<Vec<u8>> {
+    let mut program_bytes = Vec::new();
+    GzDecoder::new(reader).read_to_end(&mut program_bytes)?;
+    Ok(program_bytes)
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{extract_gz, extract_zip};
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::{Cursor, Write};
+    use zip::{write::FileOptions, ZipWriter};
+
+    const PROGRAM: [u8; 4] = [0x00, 0xE0, 0x12, 0x02];
+
+    #[test]
+    fn first_rom_in_a_zip_is_extracted() {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .start_file("README.txt", FileOptions::default())
+            .unwrap();
+        writer.write_all(b"Not a ROM").unwrap();
+        writer
+            .start_file("games/PONG.CH8", FileOptions::default())
+            .unwrap();
+        writer.write_all(&PROGRAM).unwrap();
+        let archive = writer.finish().unwrap();
+
+        assert_eq!(extract_zip(archive).unwrap(), PROGRAM);
+    }
+
+    #[test]
+    fn zips_without_roms_are_rejected() {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .start_file("README.txt", FileOptions::default())
+            .unwrap();
+        let archive = writer.finish().unwrap();
+
+        assert!(extract_zip(archive).is_err());
+    }
+
+    #[test]
+    fn gz_files_are_decompressed() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&PROGRAM).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        assert_eq!(extract_gz(compressed.as_slice()).unwrap(), PROGRAM);
+    }
+}
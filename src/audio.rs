@@ -0,0 +1,165 @@
+//! Recreating the buzzer's output from [`BuzzerEvent`]s, so it can be saved
+//! to a WAV file.
+
+use chip8_core::sound::BuzzerEvent;
+use std::io::{self, Write};
+
+const SAMPLE_RATE: u32 = 44_100;
+/// Every emulator frame is exactly 1/60th of a second of audio, no matter how
+/// long it took to run.
+const SAMPLES_PER_FRAME: u64 = SAMPLE_RATE as u64 / 60;
+/// The pitch of the buzzer in Hz.
+const TONE: u64 = 440;
+/// A quarter of full volume, so the square wave isn't unpleasantly loud.
+const AMPLITUDE: i16 = i16::MAX / 4;
+
+/// Collects the points where the buzzer started and stopped, measured in
+/// samples from the start of the recording.
+#[derive(Debug, Default)]
+pub struct BuzzerRecorder {
+    events: Vec<(u64, bool)>,
+    total_samples: u64,
+}
+
+impl BuzzerRecorder {
+    /// Adds a 60Hz frame to the recording. The events are placed within the
+    /// frame based on how far into its `cycles_per_frame` cycles they
+    /// happened, where the frame started at cycle `start_cycle`.
+    pub fn record_frame(
+        &mut self,
+        events: &[BuzzerEvent],
+        start_cycle: u64,
+        cycles_per_frame: u64,
+    ) {
+        let cycles_per_frame = cycles_per_frame.max(1);
+
+        for event in events {
+            // The cycle count starts over if the emulator is reset part way
+            // through the frame, which puts the event at the start.
+            let cycle = event
+                .cycle
+                .saturating_sub(start_cycle)
+                .min(cycles_per_frame);
+            let offset = cycle * SAMPLES_PER_FRAME / cycles_per_frame;
+
+            self.events
+                .push((self.total_samples + offset, event.active));
+        }
+
+        self.total_samples += SAMPLES_PER_FRAME;
+    }
+
+    /// Returns the recording as 16 bit mono samples, with the buzzer played
+    /// as a square wave.
+    pub fn samples(&self) -> Vec<i16> {
+        let mut samples = Vec::with_capacity(self.total_samples as usize);
+        let mut events = self.events.iter().peekable();
+        let mut active = false;
+
+        for sample in 0..self.total_samples {
+            while let Some(&(_, event_active)) = events.next_if(|(at, _)| *at <= sample) {
+                active = event_active;
+            }
+
+            // The wave is high for the first half of each period.
+            let high = (sample * TONE * 2 / SAMPLE_RATE as u64).is_multiple_of(2);
+
+            samples.push(match (active, high) {
+                (false, _) => 0,
+                (true, true) => AMPLITUDE,
+                (true, false) => -AMPLITUDE,
+            });
+        }
+
+        samples
+    }
+
+    /// Writes the recording as a WAV file.
+    pub fn write_wav(&self, mut writer: impl Write) -> io::Result<()> {
+        let samples = self.samples();
+        let data_size = samples.len() as u32 * 2;
+
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&(36 + data_size).to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+
+        writer.write_all(b"fmt ")?;
+        writer.write_all(&16_u32.to_le_bytes())?;
+        // PCM, 1 channel.
+        writer.write_all(&1_u16.to_le_bytes())?;
+        writer.write_all(&1_u16.to_le_bytes())?;
+        writer.write_all(&SAMPLE_RATE.to_le_bytes())?;
+        // The byte rate and the size of each sample.
+        writer.write_all(&(SAMPLE_RATE * 2).to_le_bytes())?;
+        writer.write_all(&2_u16.to_le_bytes())?;
+        writer.write_all(&16_u16.to_le_bytes())?;
+
+        writer.write_all(b"data")?;
+        writer.write_all(&data_size.to_le_bytes())?;
+
+        for sample in samples {
+            writer.write_all(&sample.to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{BuzzerRecorder, SAMPLES_PER_FRAME};
+    use chip8_core::sound::BuzzerEvent;
+
+    #[test]
+    fn buzzer_is_placed_within_the_frame() {
+        let mut recorder = BuzzerRecorder::default();
+
+        // Starts half way through the first frame and stops at the end of the
+        // second.
+        recorder.record_frame(
+            &[BuzzerEvent {
+                cycle: 106,
+                active: true,
+            }],
+            100,
+            12,
+        );
+        recorder.record_frame(
+            &[BuzzerEvent {
+                cycle: 124,
+                active: false,
+            }],
+            112,
+            12,
+        );
+        recorder.record_frame(&[], 124, 12);
+
+        let samples = recorder.samples();
+        let half_frame = SAMPLES_PER_FRAME as usize / 2;
+
+        assert_eq!(samples.len(), SAMPLES_PER_FRAME as usize * 3);
+        assert!(samples[..half_frame].iter().all(|&sample| sample == 0));
+        assert!(samples[half_frame..SAMPLES_PER_FRAME as usize * 2]
+            .iter()
+            .all(|&sample| sample != 0));
+        assert!(samples[SAMPLES_PER_FRAME as usize * 2..]
+            .iter()
+            .all(|&sample| sample == 0));
+    }
+
+    #[test]
+    fn wav_header_matches_the_samples() {
+        let mut recorder = BuzzerRecorder::default();
+        recorder.record_frame(&[], 0, 12);
+
+        let mut wav = Vec::new();
+        recorder.write_wav(&mut wav).unwrap();
+
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(wav.len(), 44 + SAMPLES_PER_FRAME as usize * 2);
+        assert_eq!(
+            u32::from_le_bytes(wav[40..44].try_into().unwrap()),
+            SAMPLES_PER_FRAME as u32 * 2
+        );
+    }
+}
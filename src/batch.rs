@@ -0,0 +1,136 @@
+//! Running every ROM in a directory with no window, to check a whole
+//! collection against the emulator at once.
+//!
+//! The ROMs are run in parallel, each for the same number of cycles with no
+//! keys pressed. A ROM that panics the emulator is caught and reported rather
+//! than stopping the rest.
+
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use chip8_core::{headless, Chip8Error};
+use rayon::prelude::*;
+
+/// What happened when one ROM was run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The ROM ran for all its cycles, leaving the screen with this hash.
+    Finished { screen_hash: String },
+    /// The ROM ran into an instruction the emulator can't run.
+    InvalidInstruction(String),
+    /// The ROM couldn't be read, or the emulator failed or panicked running it.
+    Crashed(String),
+}
+
+/// The outcome of every ROM run by [`run`], sorted by path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report(pub Vec<(PathBuf, Outcome)>);
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut failures = 0;
+
+        for (path, outcome) in &self.0 {
+            let name = path
+                .file_name()
+                .unwrap_or(path.as_os_str())
+                .to_string_lossy();
+
+            match outcome {
+                Outcome::Finished { screen_hash } => writeln!(f, "ok       {name} {screen_hash}")?,
+                Outcome::InvalidInstruction(err) => {
+                    failures += 1;
+                    writeln!(f, "invalid  {name}: {err}")?;
+                }
+                Outcome::Crashed(err) => {
+                    failures += 1;
+                    writeln!(f, "crashed  {name}: {err}")?;
+                }
+            }
+        }
+
+        write!(f, "{} ROMs, {failures} failed", self.0.len())
+    }
+}
+
+/// Runs each of `roms` for `cycles` instructions.
+pub fn run(roms: &[PathBuf], cycles: u64) -> Report {
+    let mut outcomes: Vec<_> = roms
+        .par_iter()
+        .map(|path| (path.clone(), run_rom(path, cycles)))
+        .collect();
+
+    outcomes.sort_by(|a, b| a.0.cmp(&b.0));
+
+    Report(outcomes)
+}
+
+fn run_rom(path: &Path, cycles: u64) -> Outcome {
+    let program = match crate::read_program(&path.to_string_lossy()) {
+        Ok(program) => program,
+        Err(err) => return Outcome::Crashed(err.to_string()),
+    };
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        headless::run(&program, chip8_core::PROGRAM_OFFSET as u16, cycles, None)
+    }));
+
+    match result {
+        Ok(Ok(run)) => Outcome::Finished {
+            screen_hash: run.screen_hash,
+        },
+        Ok(Err(
+            err @ (Chip8Error::InvalidInstruction { .. }
+            | Chip8Error::UnimplementedInstruction { .. }
+            | Chip8Error::ProgramNotCompatible),
+        )) => Outcome::InvalidInstruction(err.to_string()),
+        Ok(Err(err)) => Outcome::Crashed(err.to_string()),
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+
+            Outcome::Crashed(format!("panicked: {message}"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{run, Outcome};
+
+    #[test]
+    fn every_rom_is_reported() {
+        let directory = std::env::temp_dir().join(format!("chip-8-batch-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        // Loops forever, runs into 0xFFFF, and returns with nothing to return to.
+        let roms = [
+            ("LOOP.ch8", vec![0x12, 0x00]),
+            ("INVALID.ch8", vec![0xFF, 0xFF]),
+            ("UNDERFLOW.ch8", vec![0x00, 0xEE]),
+        ];
+
+        let paths: Vec<_> = roms
+            .iter()
+            .map(|(name, program)| {
+                let path = directory.join(name);
+                std::fs::write(&path, program).unwrap();
+                path
+            })
+            .collect();
+
+        let report = run(&paths, 100);
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        let outcomes: Vec<_> = report.0.iter().map(|(_, outcome)| outcome).collect();
+
+        assert!(matches!(outcomes[0], Outcome::InvalidInstruction(_)));
+        assert!(matches!(outcomes[1], Outcome::Finished { .. }));
+        assert!(matches!(outcomes[2], Outcome::Crashed(_)));
+        assert!(report.to_string().ends_with("3 ROMs, 2 failed"));
+    }
+}
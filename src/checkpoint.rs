@@ -0,0 +1,165 @@
+//! Automatic checkpoints: the emulator's state saved every so often into a
+//! rotating set of files next to the ROM, so a crash or an experiment gone
+//! wrong can be recovered from with `--load-state`.
+//!
+//! The files are save states written with
+//! [`SaveState::to_bytes`](chip8_core::save_state::SaveState::to_bytes).
+//! With the `json` feature, states can also be loaded from JSON written by
+//! `convert-state`.
+
+use chip8_core::save_state::SaveState;
+use chip8_core::Chip8;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Saves a checkpoint every interval, writing over the oldest once all the
+/// slots are used.
+#[derive(Debug)]
+pub struct Checkpoints {
+    rom: PathBuf,
+    interval: Duration,
+    count: usize,
+    /// The slot the next checkpoint is written to.
+    next: usize,
+    last_saved: Instant,
+}
+
+impl Checkpoints {
+    /// Starts saving `count` checkpoints for the ROM at `rom`. The first one
+    /// goes in whichever slot is empty or was written longest ago, so the
+    /// checkpoints from the last run aren't the first to go.
+    pub fn new(rom: &Path, interval: Duration, count: usize) -> Self {
+        let mut checkpoints = Self {
+            rom: rom.to_path_buf(),
+            interval,
+            count,
+            next: 0,
+            last_saved: Instant::now(),
+        };
+
+        // Missing files sort before any that have been written.
+        checkpoints.next = (0..count)
+            .min_by_key(|&slot| {
+                std::fs::metadata(checkpoints.path(slot))
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+            })
+            .unwrap_or(0);
+
+        checkpoints
+    }
+
+    /// Returns the path of a slot, like `game.checkpoint-2.state` for
+    /// `game.ch8`.
+    pub fn path(&self, slot: usize) -> PathBuf {
+        self.rom.with_extension(format!("checkpoint-{slot}.state"))
+    }
+
+    /// Saves a checkpoint if the interval has passed since the last one,
+    /// returning the path it was saved to.
+    pub fn save_if_due(&mut self, chip_8: &Chip8) -> io::Result<Option<PathBuf>> {
+        if self.last_saved.elapsed() < self.interval {
+            return Ok(None);
+        }
+
+        // A checkpoint that fails isn't retried until the next interval.
+        self.last_saved = Instant::now();
+
+        let path = self.path(self.next);
+        self.next = (self.next + 1) % self.count;
+
+        #[cfg(feature = "lz4")]
+        let bytes = chip_8.save_state().to_compressed_bytes();
+        #[cfg(not(feature = "lz4"))]
+        let bytes = chip_8.save_state().to_bytes();
+
+        std::fs::write(&path, bytes)?;
+
+        Ok(Some(path))
+    }
+}
+
+/// Puts the emulator into a state saved to `path`, like a checkpoint.
+pub fn load(chip_8: &mut Chip8, path: &Path) -> io::Result<()> {
+    let state = read_state(path)?;
+    chip_8.load_state(&state).map_err(invalid)
+}
+
+/// Reads the save state at `path`, as JSON if it ends in `.json`.
+pub fn read_state(path: &Path) -> io::Result<SaveState> {
+    #[cfg(feature = "json")]
+    if is_json(path) {
+        return SaveState::from_json(&std::fs::read_to_string(path)?).map_err(invalid);
+    }
+
+    SaveState::from_bytes(&std::fs::read(path)?).map_err(invalid)
+}
+
+/// Writes `state` to `path`, as JSON if it ends in `.json`.
+#[cfg(feature = "json")]
+pub fn write_state(path: &Path, state: &SaveState) -> io::Result<()> {
+    match is_json(path) {
+        true => std::fs::write(path, state.to_json()),
+        false => std::fs::write(path, state.to_bytes()),
+    }
+}
+
+#[cfg(feature = "json")]
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "json")
+}
+
+fn invalid(err: chip8_core::Chip8Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{load, Checkpoints};
+    use crate::Chip8;
+    use std::time::Duration;
+
+    #[test]
+    fn checkpoints_rotate_through_the_slots() {
+        let rom =
+            std::env::temp_dir().join(format!("chip-8-checkpoints-{}.ch8", std::process::id()));
+
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8.load_program(vec![0x70, 0x01, 0x12, 0x00]).unwrap();
+
+        let mut checkpoints = Checkpoints::new(&rom, Duration::ZERO, 2);
+        let mut saved = Vec::new();
+
+        for _ in 0..3 {
+            chip_8.cycle().unwrap();
+            saved.push(checkpoints.save_if_due(&chip_8).unwrap().unwrap());
+        }
+
+        assert_eq!(
+            saved,
+            [
+                checkpoints.path(0),
+                checkpoints.path(1),
+                checkpoints.path(0)
+            ]
+        );
+
+        // The first slot now holds the third checkpoint, after V0 was
+        // incremented twice.
+        let mut restored = Chip8::new();
+        restored.initialize().unwrap();
+        restored.load_program(Vec::new()).unwrap();
+        load(&mut restored, &saved[2]).unwrap();
+
+        for path in &saved[..2] {
+            std::fs::remove_file(path).unwrap();
+        }
+
+        let state = restored.save_state();
+        assert_eq!(state.registers[0], 2);
+        assert_eq!(state.program_counter, 0x202);
+    }
+}
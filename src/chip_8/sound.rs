@@ -1,3 +0,0 @@
-// implement way to play a buzzer sound here
-
-pub fn play_buzzer() {}
@@ -0,0 +1,303 @@
+//! Settings the frontend remembers between runs, stored as `key = value`
+//! lines in the user's config directory.
+//!
+//! Settings for a single game go in a [`Profile`], with keys starting with
+//! `profile.` and the ROM's SHA-1 hash or file name:
+//!
+//! ```text
+//! profile.BRIX.ch8.keys = 4:Left 6:Right
+//! profile.0a1b2c3d4e5f60718293a4b5c6d7e8f901234567.timing = 1200
+//! ```
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chip8_core::timing::TimingModel;
+use minifb::Key;
+
+use crate::keymap::{self, Keymap};
+
+/// The settings stored in the config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// The ROM last picked in the ROM library browser.
+    pub last_rom: Option<PathBuf>,
+    /// Whether emulation pauses while the window is in the background.
+    pub pause_on_focus_loss: bool,
+    /// How long instructions take, written as `vip` or a number of cycles per
+    /// second. Overrides the speed from the ROM database when set.
+    pub timing: Option<TimingModel>,
+    /// The settings for particular games.
+    pub profiles: Vec<Profile>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            last_rom: None,
+            pause_on_focus_loss: true,
+            timing: None,
+            profiles: Vec::new(),
+        }
+    }
+}
+
+/// Settings for one game, since games use such different keys and speeds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    /// The SHA-1 hash of the ROM in hex, or its file name.
+    pub rom: String,
+    /// Keyboard keys that press keypad keys instead of the usual ones,
+    /// written as the keypad key in hex and the [key's
+    /// name](keymap::key_name), like `5:Up`.
+    pub keys: Vec<(u8, Key)>,
+    /// Overrides [`Config::timing`] for this game.
+    pub timing: Option<TimingModel>,
+}
+
+impl Profile {
+    /// Returns the default keymap with this profile's keys swapped in.
+    pub fn keymap(&self) -> Keymap {
+        let mut keymap = Keymap::default();
+
+        for &(keycode, key) in &self.keys {
+            keymap.remap(keycode, key);
+        }
+
+        keymap
+    }
+}
+
+impl Config {
+    /// Loads the config file, or returns the defaults if there isn't one yet.
+    pub fn load() -> io::Result<Self> {
+        let Some(path) = path() else {
+            return Ok(Self::default());
+        };
+
+        match std::fs::read_to_string(path) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the profile for the ROM with the SHA-1 hash `sha1` loaded from
+    /// `path`. A profile for its hash is used over one for its file name.
+    pub fn profile(&self, sha1: [u8; 20], path: Option<&Path>) -> Option<&Profile> {
+        let sha1: String = sha1.iter().map(|byte| format!("{byte:02x}")).collect();
+        let file_name = path
+            .and_then(Path::file_name)
+            .and_then(|file_name| file_name.to_str());
+
+        self.profiles
+            .iter()
+            .find(|profile| profile.rom.eq_ignore_ascii_case(&sha1))
+            .or_else(|| {
+                self.profiles
+                    .iter()
+                    .find(|profile| Some(profile.rom.as_str()) == file_name)
+            })
+    }
+
+    /// Writes the config file, creating its directory if needed.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = path() else {
+            return Ok(());
+        };
+
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+
+        std::fs::write(path, self.to_string())
+    }
+
+    /// Parses a config file. Unknown keys and malformed lines are ignored, so
+    /// that config files from newer versions still load.
+    fn parse(text: &str) -> Self {
+        let mut config = Self::default();
+
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            let value = value.trim();
+
+            match key.trim() {
+                "last_rom" => config.last_rom = Some(PathBuf::from(value)),
+                "pause_on_focus_loss" => {
+                    if let Ok(value) = value.parse() {
+                        config.pause_on_focus_loss = value;
+                    }
+                }
+                "timing" => {
+                    if let Some(timing) = parse_timing(value) {
+                        config.timing = Some(timing);
+                    }
+                }
+                key => {
+                    if let Some((rom, setting)) = key
+                        .strip_prefix("profile.")
+                        .and_then(|key| key.rsplit_once('.'))
+                    {
+                        config.parse_profile_setting(rom, setting, value);
+                    }
+                }
+            }
+        }
+
+        config
+    }
+
+    fn parse_profile_setting(&mut self, rom: &str, setting: &str, value: &str) {
+        let index = match self.profiles.iter().position(|profile| profile.rom == rom) {
+            Some(index) => index,
+            None => {
+                self.profiles.push(Profile {
+                    rom: rom.to_string(),
+                    keys: Vec::new(),
+                    timing: None,
+                });
+                self.profiles.len() - 1
+            }
+        };
+        let profile = &mut self.profiles[index];
+
+        match setting {
+            "keys" => {
+                profile.keys = value
+                    .split_whitespace()
+                    .filter_map(|mapping| {
+                        let (keycode, key) = mapping.split_once(':')?;
+                        let keycode = u8::from_str_radix(keycode, 16)
+                            .ok()
+                            .filter(|&keycode| keycode < 16)?;
+                        Some((keycode, keymap::parse_key(key)?))
+                    })
+                    .collect();
+            }
+            "timing" => profile.timing = parse_timing(value),
+            _ => {}
+        }
+    }
+}
+
+impl std::fmt::Display for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(last_rom) = &self.last_rom {
+            writeln!(f, "last_rom = {}", last_rom.display())?;
+        }
+
+        writeln!(f, "pause_on_focus_loss = {}", self.pause_on_focus_loss)?;
+
+        if let Some(timing) = self.timing {
+            writeln!(f, "timing = {}", format_timing(timing))?;
+        }
+
+        for profile in &self.profiles {
+            if !profile.keys.is_empty() {
+                let keys: Vec<String> = profile
+                    .keys
+                    .iter()
+                    .map(|&(keycode, key)| format!("{keycode:X}:{}", keymap::key_name(key)))
+                    .collect();
+                writeln!(f, "profile.{}.keys = {}", profile.rom, keys.join(" "))?;
+            }
+
+            if let Some(timing) = profile.timing {
+                writeln!(
+                    f,
+                    "profile.{}.timing = {}",
+                    profile.rom,
+                    format_timing(timing)
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes a timing model the way [`parse_timing`] reads it.
+fn format_timing(timing: TimingModel) -> String {
+    match timing {
+        TimingModel::Fixed(cycles_per_second) => cycles_per_second.to_string(),
+        TimingModel::Vip => "vip".to_string(),
+    }
+}
+
+/// Parses a timing model, either `vip` or a number of cycles per second.
+fn parse_timing(value: &str) -> Option<TimingModel> {
+    match value {
+        "vip" => Some(TimingModel::Vip),
+        _ => value
+            .parse()
+            .ok()
+            .filter(|&cycles_per_second| cycles_per_second > 0)
+            .map(TimingModel::Fixed),
+    }
+}
+
+/// Where the config file lives, following the XDG base directory spec on
+/// Linux and using `%APPDATA%` on Windows.
+fn path() -> Option<PathBuf> {
+    let directory = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(directory.join("chip-8-emulator").join("config"))
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{Config, Profile};
+    use chip8_core::timing::TimingModel;
+    use minifb::Key;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn config_round_trips() {
+        let config = Config {
+            last_rom: Some(PathBuf::from("/roms/PONG.ch8")),
+            pause_on_focus_loss: false,
+            timing: Some(TimingModel::Vip),
+            profiles: vec![
+                Profile {
+                    rom: "BRIX.ch8".to_string(),
+                    keys: vec![(0x4, Key::Left), (0x6, Key::Right)],
+                    timing: None,
+                },
+                Profile {
+                    rom: "0a".repeat(20),
+                    keys: Vec::new(),
+                    timing: Some(TimingModel::Fixed(1200)),
+                },
+            ],
+        };
+
+        assert_eq!(Config::parse(&config.to_string()), config);
+        assert_eq!(
+            config.profile([0x0A; 20], Some(Path::new("/roms/BRIX.ch8"))),
+            Some(&config.profiles[1])
+        );
+        assert_eq!(
+            config.profile([0; 20], Some(Path::new("/roms/BRIX.ch8"))),
+            Some(&config.profiles[0])
+        );
+        assert_eq!(config.profile([0; 20], None), None);
+
+        let config = Config {
+            timing: Some(TimingModel::Fixed(1000)),
+            ..Config::default()
+        };
+
+        assert_eq!(Config::parse(&config.to_string()), config);
+        assert_eq!(
+            Config::parse("unknown = 1\nnot a setting"),
+            Config::default()
+        );
+    }
+}
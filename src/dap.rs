@@ -0,0 +1,574 @@
+//! A [Debug Adapter Protocol](https://microsoft.github.io/debug-adapter-protocol/)
+//! server, so editors like VS Code can run programs in the emulator and debug them.
+//!
+//! The server listens on a TCP port, which VS Code connects to with the
+//! `debugServer` setting of a launch configuration. The `program` given when
+//! launching is run without a window. Breakpoints can be set on the lines of
+//! `.8o` programs or on addresses in the disassembly view, and the registers,
+//! call stack and memory can be inspected while the program is stopped.
+
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crossbeam_channel::{Receiver, TryRecvError};
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::CYCLES_PER_SECOND;
+use chip8_core::debugger::StopReason;
+use chip8_core::octo::{self, SourceMap};
+use chip8_core::scheduler::{Clock, Scheduler};
+use chip8_core::Chip8;
+
+/// The emulator only has one thread of execution.
+const THREAD_ID: u64 = 1;
+/// The `variablesReference` of the registers scope.
+const REGISTERS_REFERENCE: u64 = 1;
+
+/// Waits for a debugger to connect on `port`, and then runs the debug session
+/// until it disconnects.
+pub fn serve(port: u16) -> io::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    info!("Waiting for a debugger on port {port}");
+
+    let (stream, address) = listener.accept()?;
+    info!("Debugger connected from {address}");
+
+    let (tx_message, rx_message) = crossbeam_channel::unbounded();
+    let reader = BufReader::new(stream.try_clone()?);
+
+    // Messages are read on their own thread so the emulator can keep running
+    // between them.
+    std::thread::spawn(move || {
+        let mut reader = reader;
+
+        while let Ok(Some(message)) = read_message(&mut reader) {
+            if tx_message.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    Session::new(stream).run(rx_message)
+}
+
+/// Reads one message, or `None` if the connection was closed.
+fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+
+    loop {
+        let mut header = String::new();
+
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim();
+
+        // The headers end with an empty line.
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(length) = header.strip_prefix("Content-Length:") {
+            content_length = length.trim().parse().ok();
+        }
+    }
+
+    let content_length = content_length.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message without a Content-Length",
+        )
+    })?;
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+/// A connection to a debugger.
+struct Session {
+    stream: TcpStream,
+    /// The sequence number of the last message we sent.
+    sequence: u64,
+    chip_8: Chip8,
+    /// The path of the program, if it was an Octo source file.
+    source_path: Option<String>,
+    source_map: SourceMap,
+    stop_on_entry: bool,
+    /// The addresses of the breakpoints set on source lines.
+    line_breakpoints: BTreeSet<u16>,
+    /// The addresses of the breakpoints set in the disassembly.
+    instruction_breakpoints: BTreeSet<u16>,
+    /// Whether the emulator should be running frames.
+    running: bool,
+}
+
+impl Session {
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            sequence: 0,
+            chip_8: Chip8::new(),
+            source_path: None,
+            source_map: SourceMap::default(),
+            stop_on_entry: false,
+            line_breakpoints: BTreeSet::new(),
+            instruction_breakpoints: BTreeSet::new(),
+            running: false,
+        }
+    }
+
+    fn run(mut self, rx_message: Receiver<Value>) -> io::Result<()> {
+        let mut scheduler = Scheduler::new(60);
+
+        loop {
+            // While stopped there's nothing to do but wait for the debugger.
+            let message = match self.running {
+                true => match rx_message.try_recv() {
+                    Ok(message) => Some(message),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => return Ok(()),
+                },
+                false => match rx_message.recv() {
+                    Ok(message) => Some(message),
+                    Err(_) => return Ok(()),
+                },
+            };
+
+            if let Some(message) = message {
+                if !self.handle(&message)? {
+                    return Ok(());
+                }
+
+                scheduler.reset(scheduler.clock().now());
+                continue;
+            }
+
+            // A frame can stop the emulator, which skips the rest.
+            for _ in 0..scheduler.frames_due(scheduler.clock().now()) {
+                if self.running {
+                    self.run_frame()?;
+                }
+            }
+
+            scheduler.wait();
+        }
+    }
+
+    fn run_frame(&mut self) -> io::Result<()> {
+        if let Err(err) = self.chip_8.run_frame() {
+            self.running = false;
+            self.send_event("output", json!({ "output": format!("{err}\n") }))?;
+
+            return self.send_event(
+                "stopped",
+                json!({
+                    "reason": "exception",
+                    "description": err.to_string(),
+                    "threadId": THREAD_ID,
+                }),
+            );
+        }
+
+        let Some(stop) = self.chip_8.stopped() else {
+            return Ok(());
+        };
+
+        self.running = false;
+
+        let reason = match stop.reason {
+            StopReason::Breakpoint => "breakpoint",
+            StopReason::Step => "step",
+            StopReason::Pause => "pause",
+            StopReason::Instruction(_) => "instruction breakpoint",
+            StopReason::SelfModifyingWrite { .. } | StopReason::ProtectedWrite { .. } => {
+                "data breakpoint"
+            }
+        };
+
+        self.send_event(
+            "stopped",
+            json!({
+                "reason": reason,
+                "description": stop.reason.to_string(),
+                "threadId": THREAD_ID,
+            }),
+        )
+    }
+
+    /// Handles a request from the debugger, returning false once the session
+    /// should end.
+    fn handle(&mut self, request: &Value) -> io::Result<bool> {
+        let command = request["command"].as_str().unwrap_or_default();
+        let arguments = &request["arguments"];
+
+        let body = match command {
+            "initialize" => Ok(json!({
+                "supportsConfigurationDoneRequest": true,
+                "supportsInstructionBreakpoints": true,
+                "supportsReadMemoryRequest": true,
+            })),
+            "launch" => self.launch(arguments),
+            "setBreakpoints" => Ok(self.set_line_breakpoints(arguments)),
+            "setInstructionBreakpoints" => Ok(self.set_instruction_breakpoints(arguments)),
+            "setExceptionBreakpoints" => Ok(json!({})),
+            "configurationDone" => {
+                match self.stop_on_entry {
+                    true => {
+                        self.chip_8.pause();
+                        self.send_event(
+                            "stopped",
+                            json!({ "reason": "entry", "threadId": THREAD_ID }),
+                        )?;
+                    }
+                    false => self.running = true,
+                }
+
+                Ok(json!({}))
+            }
+            "threads" => Ok(json!({
+                "threads": [{ "id": THREAD_ID, "name": "CHIP-8" }],
+            })),
+            "stackTrace" => Ok(self.stack_trace()),
+            "scopes" => Ok(json!({
+                "scopes": [{
+                    "name": "Registers",
+                    "variablesReference": REGISTERS_REFERENCE,
+                    "expensive": false,
+                }],
+            })),
+            "variables" => Ok(self.variables()),
+            "readMemory" => self.read_memory(arguments),
+            "continue" => {
+                self.chip_8.resume();
+                self.running = true;
+                Ok(json!({ "allThreadsContinued": true }))
+            }
+            // There are no lines to step over, so every kind of step runs
+            // one instruction.
+            "next" | "stepIn" | "stepOut" => {
+                self.chip_8.step();
+                self.running = true;
+                Ok(json!({}))
+            }
+            "pause" => {
+                self.chip_8.pause();
+                self.running = false;
+                self.send_event(
+                    "stopped",
+                    json!({ "reason": "pause", "threadId": THREAD_ID }),
+                )?;
+                Ok(json!({}))
+            }
+            "disconnect" | "terminate" => {
+                self.respond(request, Ok(json!({})))?;
+                return Ok(false);
+            }
+            _ => Err(format!("{command} isn't supported")),
+        };
+
+        self.respond(request, body)?;
+        Ok(true)
+    }
+
+    fn launch(&mut self, arguments: &Value) -> Result<Value, String> {
+        let path = arguments["program"]
+            .as_str()
+            .ok_or("no program to launch")?
+            .to_string();
+
+        let program = match path.ends_with(".8o") {
+            true => {
+                let source = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+                let (program, source_map) =
+                    octo::assemble_with_source_map(&source).map_err(|e| e.to_string())?;
+
+                self.source_map = source_map;
+                self.source_path = Some(path.clone());
+                program
+            }
+            false => std::fs::read(&path).map_err(|e| e.to_string())?,
+        };
+
+        self.chip_8.initialize().map_err(|e| e.to_string())?;
+        self.chip_8
+            .load_program(program)
+            .map_err(|e| e.to_string())?;
+        self.chip_8.set_cycles_per_second(Some(CYCLES_PER_SECOND));
+
+        self.stop_on_entry = arguments["stopOnEntry"].as_bool().unwrap_or(false);
+
+        // Breakpoints can only be placed once the program is assembled.
+        self.send_event("initialized", json!({}))
+            .map_err(|e| e.to_string())?;
+
+        Ok(json!({}))
+    }
+
+    fn set_line_breakpoints(&mut self, arguments: &Value) -> Value {
+        let lines = arguments["breakpoints"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+
+        self.line_breakpoints.clear();
+
+        let breakpoints: Vec<Value> = lines
+            .iter()
+            .map(|breakpoint| {
+                let line = breakpoint["line"].as_u64().unwrap_or_default() as usize;
+
+                match self.source_map.address_of_line(line) {
+                    Some((line, address)) => {
+                        self.line_breakpoints.insert(address);
+                        json!({ "verified": true, "line": line })
+                    }
+                    None => json!({ "verified": false, "message": "no code here" }),
+                }
+            })
+            .collect();
+
+        self.apply_breakpoints();
+        json!({ "breakpoints": breakpoints })
+    }
+
+    fn set_instruction_breakpoints(&mut self, arguments: &Value) -> Value {
+        let requested = arguments["breakpoints"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+
+        self.instruction_breakpoints.clear();
+
+        let breakpoints: Vec<Value> = requested
+            .iter()
+            .map(|breakpoint| {
+                let address = breakpoint["instructionReference"]
+                    .as_str()
+                    .and_then(parse_reference)
+                    .map(|address| {
+                        address.wrapping_add(breakpoint["offset"].as_i64().unwrap_or(0) as u16)
+                    });
+
+                match address {
+                    Some(address) => {
+                        self.instruction_breakpoints.insert(address);
+                        json!({ "verified": true, "instructionReference": reference(address) })
+                    }
+                    None => json!({ "verified": false, "message": "invalid address" }),
+                }
+            })
+            .collect();
+
+        self.apply_breakpoints();
+        json!({ "breakpoints": breakpoints })
+    }
+
+    fn apply_breakpoints(&mut self) {
+        let addresses = self
+            .line_breakpoints
+            .union(&self.instruction_breakpoints)
+            .copied();
+        self.chip_8.set_breakpoints(addresses);
+    }
+
+    /// The call stack, innermost first. Each frame is named after the label
+    /// of the subroutine it's in.
+    fn stack_trace(&self) -> Value {
+        let state = self.chip_8.save_state();
+        let call_stack = self.chip_8.call_stack();
+
+        // The innermost frame is wherever the program counter is.
+        let mut locations = vec![state.program_counter];
+        locations.extend(call_stack.iter().rev().map(|frame| frame.call_site));
+
+        let mut subroutines: Vec<u16> = call_stack.iter().rev().map(|frame| frame.target).collect();
+        subroutines.push(chip8_core::PROGRAM_OFFSET as u16);
+
+        let frames: Vec<Value> = locations
+            .iter()
+            .zip(&subroutines)
+            .enumerate()
+            .map(|(id, (&address, &subroutine))| {
+                let mut frame = json!({
+                    "id": id,
+                    "name": self.symbol(subroutine),
+                    "line": self.source_map.line_of_address(address).unwrap_or(0),
+                    "column": 1,
+                    "instructionPointerReference": reference(address),
+                });
+
+                if let Some(path) = &self.source_path {
+                    frame["source"] = json!({ "path": path });
+                }
+
+                frame
+            })
+            .collect();
+
+        json!({ "stackFrames": frames, "totalFrames": frames.len() })
+    }
+
+    fn symbol(&self, address: u16) -> String {
+        self.source_map
+            .labels()
+            .get(&address)
+            .cloned()
+            .unwrap_or_else(|| reference(address))
+    }
+
+    fn variables(&self) -> Value {
+        let state = self.chip_8.save_state();
+
+        let byte = |name: String, value: u8| {
+            json!({
+                "name": name,
+                "value": format!("0x{value:02X} ({value})"),
+                "variablesReference": 0,
+            })
+        };
+        let address = |name: &str, value: u16| {
+            json!({
+                "name": name,
+                "value": reference(value),
+                "variablesReference": 0,
+                "memoryReference": reference(value),
+            })
+        };
+
+        let mut variables: Vec<Value> = state
+            .registers
+            .iter()
+            .enumerate()
+            .map(|(index, &value)| byte(format!("V{index:X}"), value))
+            .collect();
+
+        variables.push(address("I", state.index_register));
+        variables.push(address("PC", state.program_counter));
+        variables.push(json!({
+            "name": "Stack depth",
+            "value": state.stack.len().to_string(),
+            "variablesReference": 0,
+        }));
+        variables.push(byte("DT".to_string(), state.delay_timer));
+        variables.push(byte("ST".to_string(), state.sound_timer));
+
+        json!({ "variables": variables })
+    }
+
+    fn read_memory(&self, arguments: &Value) -> Result<Value, String> {
+        // The offset and count come from the debugger, so they are kept in
+        // bounds rather than trusted.
+        let start = (arguments["memoryReference"]
+            .as_str()
+            .and_then(parse_reference)
+            .ok_or("invalid memory reference")? as i64)
+            .saturating_add(arguments["offset"].as_i64().unwrap_or(0));
+        let count = arguments["count"].as_u64().unwrap_or(0) as usize;
+
+        let memory = self.chip_8.save_state().memory;
+        let start = start.clamp(0, memory.len() as i64) as usize;
+        let end = start.saturating_add(count).min(memory.len());
+
+        Ok(json!({
+            "address": reference(start as u16),
+            "data": base64(&memory[start..end]),
+            "unreadableBytes": count - (end - start),
+        }))
+    }
+
+    fn respond(&mut self, request: &Value, body: Result<Value, String>) -> io::Result<()> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": body.is_ok(),
+        });
+
+        match body {
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = json!(message),
+        }
+
+        self.send(response)
+    }
+
+    fn send_event(&mut self, event: &str, body: Value) -> io::Result<()> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn send(&mut self, mut message: Value) -> io::Result<()> {
+        self.sequence += 1;
+        message["seq"] = json!(self.sequence);
+
+        let body = message.to_string();
+        write!(self.stream, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+        self.stream.flush()
+    }
+}
+
+/// Formats an address the way it's given to the debugger, like `0x2A4`.
+fn reference(address: u16) -> String {
+    format!("0x{address:03X}")
+}
+
+/// Parses an address given by the debugger.
+fn parse_reference(reference: &str) -> Option<u16> {
+    u16::from_str_radix(reference.trim_start_matches("0x"), 16).ok()
+}
+
+/// Encodes bytes as base64, which is how memory is sent to the debugger.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::new();
+
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (index, &byte)| {
+                group | (byte as u32) << (16 - index * 8)
+            });
+
+        for index in 0..4 {
+            match index <= chunk.len() {
+                true => encoded.push(ALPHABET[(group >> (18 - index * 6)) as usize & 0x3F] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{base64, read_message};
+    use std::io::Cursor;
+
+    #[test]
+    fn messages_are_framed_by_their_length() {
+        let mut input =
+            Cursor::new("Content-Length: 15\r\n\r\n{\"seq\":1,\"a\":2}Content-Length: 2\r\n\r\n{}");
+
+        let first = read_message(&mut input).unwrap().unwrap();
+        assert_eq!(first["seq"], 1);
+
+        let second = read_message(&mut input).unwrap().unwrap();
+        assert!(second.as_object().unwrap().is_empty());
+
+        assert!(read_message(&mut input).unwrap().is_none());
+    }
+
+    #[test]
+    fn memory_is_base64_encoded() {
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+    }
+}
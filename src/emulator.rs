@@ -0,0 +1,511 @@
+//! The emulator thread. It owns the [`Chip8`], runs the frames that are due
+//! each time the window finishes drawing one, and publishes a copy of the
+//! screen afterwards, so the window never has to wait on a lock.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use arc_swap::ArcSwap;
+use chip8_core::debugger::Stop;
+use chip8_core::event_log::EventLog;
+use chip8_core::replay::{FrameInput, Replay};
+use chip8_core::scheduler::{Clock, Scheduler};
+use chip8_core::{Chip8, Frame, Keys, Preserve};
+use crossbeam_channel::{Receiver, Sender};
+use tracing::{debug, info, warn};
+
+use crate::audio::BuzzerRecorder;
+use crate::checkpoint::Checkpoints;
+use crate::netplay::{Input, Netplay};
+use crate::watch::Watcher;
+use crate::{read_patched_program, stats, write_rpl_flags, FRAME_HZ};
+
+/// The latest state of the screen, published by the emulator thread after every frame.
+#[derive(Debug, Default, Clone)]
+pub struct PublishedFrame {
+    /// Incremented every time the screen changes.
+    pub sequence: u64,
+    pub frame: Frame,
+    /// The rows that changed since the frame with the previous sequence number.
+    pub dirty_rows: Range<usize>,
+    /// The number of cycles the emulator has run.
+    pub total_cycles: u64,
+    /// Whether the buzzer was sounding at the end of the frame.
+    pub buzzer_active: bool,
+    /// Where the emulator is stopped, if it is.
+    pub stopped: Option<Stop>,
+}
+
+#[derive(Debug)]
+pub struct FrameFinishedSignal {
+    /// The keys held down just after the newly created frame.
+    pub keys: Keys,
+    /// Whether the program should be restarted before the next frame.
+    pub restart: bool,
+    /// Whether to step backwards through the history instead of running
+    /// the next frame.
+    pub rewind: bool,
+    /// Whether to carry on after the emulator stopped.
+    pub resume: bool,
+    /// Whether to run `--turbo` times as many frames as usual.
+    pub turbo: bool,
+    /// When the earliest of the keys pressed since the last signal that got
+    /// through was pressed, if any were.
+    pub pressed_at: Option<Instant>,
+    /// Whether the window was paused since the last signal that got through.
+    pub paused: bool,
+}
+
+/// Everything the emulator thread owns. What is worth saving is handed back
+/// to the main thread once it finishes.
+pub struct Emulator {
+    pub chip_8: Chip8,
+    pub buzzer_recorder: Option<BuzzerRecorder>,
+    pub replay: Option<Replay>,
+    pub event_log: Option<EventLog<BufWriter<File>>>,
+    pub netplay: Option<Netplay>,
+    #[cfg(feature = "remote")]
+    pub remote: Option<crate::remote::Remote>,
+    pub checkpoints: Option<Checkpoints>,
+    /// Reloads the ROM when it changes, with the patches and load offset it
+    /// was first loaded with.
+    pub watcher: Option<Watcher>,
+    pub patches: Vec<String>,
+    pub load_offset: usize,
+    /// What is kept when the ROM is reloaded.
+    pub preserve: Vec<Preserve>,
+    /// Where the RPL flags are saved whenever the program changes them.
+    pub flags_path: Option<PathBuf>,
+    /// How many times as many frames to run in turbo.
+    pub turbo_speed: u32,
+    pub cycles_per_second: u32,
+    pub published_frame: Arc<ArcSwap<PublishedFrame>>,
+    /// How long key presses took to be seen by the program, for the stats
+    /// overlay.
+    pub input_latency: Sender<Duration>,
+    /// Set once the window has closed, when losing the other player is
+    /// expected.
+    pub shutting_down: Arc<AtomicBool>,
+}
+
+/// What the emulator thread keeps track of from one signal to the next.
+struct FrameLoop<C: Clock> {
+    /// Frames are run by the clock rather than a set number per window
+    /// frame, so the emulator keeps time however unevenly the window is
+    /// drawn.
+    scheduler: Scheduler<C>,
+    throttle_logged: bool,
+    /// When the key press waiting to be seen by the program happened, and
+    /// how many times the keypad had been read by then.
+    key_press: Option<(Instant, u64)>,
+}
+
+impl<C: Clock> FrameLoop<C> {
+    fn new(scheduler: Scheduler<C>) -> Self {
+        Self {
+            scheduler,
+            throttle_logged: false,
+            key_press: None,
+        }
+    }
+}
+
+impl Emulator {
+    /// Runs the frames `scheduler` says are due each time a signal comes in
+    /// from the window, until the window closes the channel. Errors stop the
+    /// emulator, but everything recorded up to then is kept.
+    pub fn run<C: Clock>(
+        &mut self,
+        signals: &Receiver<FrameFinishedSignal>,
+        scheduler: Scheduler<C>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut frame_loop = FrameLoop::new(scheduler);
+        let mut result = Ok(());
+
+        // wait here until we get the signal that the frame has been drawn. The
+        // channel is closed when the window is, which ends the loop.
+        while let Ok(finished_signal) = signals.recv() {
+            match self.handle_signal(finished_signal, &mut frame_loop) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+
+        if let Some(Err(err)) = self.event_log.as_mut().map(EventLog::flush) {
+            warn!("Couldn't finish writing the event log: {err}");
+        }
+
+        result
+    }
+
+    /// Runs the frames that are due once the window has drawn one. Returns
+    /// whether to carry on.
+    fn handle_signal<C: Clock>(
+        &mut self,
+        finished_signal: FrameFinishedSignal,
+        frame_loop: &mut FrameLoop<C>,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let chip_8 = &mut self.chip_8;
+        let scheduler = &mut frame_loop.scheduler;
+
+        let keys = finished_signal.keys;
+
+        // Only the newest press is timed. Pausing, rewinding and turbo
+        // hold the program up or rush it along, so presses across them
+        // aren't timed at all.
+        if finished_signal.paused || finished_signal.rewind || finished_signal.turbo {
+            frame_loop.key_press = None;
+        } else if let Some(pressed_at) = finished_signal.pressed_at {
+            frame_loop.key_press = Some((pressed_at, chip_8.stats().key_reads));
+        }
+
+        // In netplay, restarts are sent to the other player so both
+        // emulators restart on the same frame.
+        let mut restart = finished_signal.restart;
+
+        if self.netplay.is_none() {
+            chip_8.needs_program_restart |= restart;
+        }
+
+        // Keys held in the window and by clients are all held down.
+        #[cfg(feature = "remote")]
+        let keys = match &mut self.remote {
+            Some(remote) => {
+                remote.apply_commands(chip_8);
+                keys | remote.keys()
+            }
+            None => keys,
+        };
+
+        if finished_signal.resume {
+            chip_8.resume();
+        }
+
+        // The program was held up while paused or rewinding, so the
+        // frames missed in the meantime aren't caught up on.
+        if finished_signal.paused || finished_signal.rewind {
+            scheduler.reset(scheduler.clock().now());
+        }
+
+        // Netplay runs in step with the other player, so it can't speed up.
+        let turbo = finished_signal.turbo && self.netplay.is_none();
+        let frames_due = scheduler.frames_due(scheduler.clock().now());
+        let frames = match turbo {
+            true => frames_due * self.turbo_speed,
+            false => frames_due,
+        };
+
+        if finished_signal.rewind {
+            // Undo as many cycles as a window frame normally runs. Running
+            // out of history just leaves the emulator where it is.
+            for _ in 0..self.cycles_per_second / FRAME_HZ {
+                if chip_8.step_back().is_err() {
+                    break;
+                }
+            }
+        } else {
+            for _ in 0..frames {
+                let start_cycle = chip_8.stats().total_cycles;
+
+                let keys = match &mut self.netplay {
+                    Some(netplay) => {
+                        let input = Input {
+                            keys,
+                            restart: std::mem::take(&mut restart),
+                        };
+
+                        match netplay.exchange(input) {
+                            Ok(input) => {
+                                chip_8.needs_program_restart |= input.restart;
+                                input.keys
+                            }
+                            // Leaving the session closes the connection too.
+                            Err(_) if self.shutting_down.load(Ordering::Relaxed) => {
+                                return Ok(false);
+                            }
+                            Err(err) => return Err(format!("Lost the other player: {err}").into()),
+                        }
+                    }
+                    None => keys,
+                };
+
+                chip_8.set_keys(keys);
+
+                // Frames don't run while the emulator is stopped, so
+                // they aren't recorded either.
+                let input = (chip_8.stopped().is_none()).then_some(FrameInput {
+                    keys,
+                    restart: chip_8.needs_program_restart,
+                });
+
+                if let Err(err) = chip_8.run_frame() {
+                    let return_addresses: Vec<String> = chip_8
+                        .stack_frames()
+                        .map(|address| format!("0x{address:03X}"))
+                        .collect();
+
+                    return Err(format!(
+                        "The emulator stopped: {err}\n{}  return addresses: [{}]",
+                        chip_8.backtrace(&BTreeMap::new()),
+                        return_addresses.join(", ")
+                    )
+                    .into());
+                }
+
+                if let (Some(replay), Some(input)) = (&mut self.replay, input) {
+                    replay.frames.push(input);
+                }
+
+                // The press counts as seen once the program checks the
+                // keypad, whichever key it asks about.
+                if let Some((pressed_at, key_reads)) = frame_loop.key_press {
+                    let latency = scheduler
+                        .clock()
+                        .now()
+                        .saturating_duration_since(pressed_at);
+
+                    if chip_8.stats().key_reads > key_reads {
+                        // Nobody is left to show it once the window closes.
+                        let _ = self.input_latency.send(latency);
+                        frame_loop.key_press = None;
+                    } else if latency > stats::MAX_INPUT_LATENCY {
+                        // The program wasn't listening, like on a title
+                        // screen, which says nothing about the emulator.
+                        frame_loop.key_press = None;
+                    }
+                }
+
+                let events = chip_8.take_events();
+
+                if let (Some(log), Some(input)) = (&mut self.event_log, input) {
+                    // A full disk shouldn't stop the game, so the log
+                    // just ends there.
+                    if let Err(err) = log.write_frame(input, &events) {
+                        warn!("Couldn't write to the event log, so it ends here: {err}");
+                        self.event_log = None;
+                    }
+                }
+
+                // A stuck program is throttled every frame, so only say so once.
+                if let Some(throttled) = chip_8.take_throttled() {
+                    if !frame_loop.throttle_logged {
+                        warn!(
+                            "Throttled a frame at 0x{:03X} after {} instructions without drawing",
+                            throttled.address, throttled.instructions
+                        );
+                        frame_loop.throttle_logged = true;
+                    }
+                }
+
+                // Saved straight away, so they aren't lost if the
+                // emulator crashes later on.
+                if let Some(flags_path) = self
+                    .flags_path
+                    .as_ref()
+                    .filter(|_| chip_8.take_rpl_flags_changed())
+                {
+                    if let Err(err) = write_rpl_flags(flags_path, &chip_8.rpl_flags()) {
+                        warn!("Couldn't save the flags to {}: {err}", flags_path.display());
+                    }
+                }
+
+                if let Some(buzzer_recorder) = &mut self.buzzer_recorder {
+                    buzzer_recorder.record_frame(
+                        &chip_8.take_buzzer_events(),
+                        start_cycle,
+                        self.cycles_per_second.div_ceil(60) as u64,
+                    );
+                }
+            }
+        }
+
+        let console_output = chip_8.take_console_output();
+
+        if !console_output.is_empty() {
+            let mut stdout = std::io::stdout().lock();
+
+            if let Err(err) = stdout
+                .write_all(&console_output)
+                .and_then(|()| stdout.flush())
+            {
+                warn!("Couldn't print the program's console output: {err}");
+            }
+        }
+
+        let changed = self.watcher.as_mut().is_some_and(Watcher::changed);
+
+        if let Some(watcher) = self.watcher.as_ref().filter(|_| changed) {
+            let path = watcher.path().to_string_lossy();
+
+            match read_patched_program(&path, &self.patches, self.load_offset)
+                .and_then(|program| Ok(chip_8.reload_program(program, &self.preserve)?))
+            {
+                Ok(loaded_rom) => info!("Reloaded {path}: {loaded_rom}"),
+                Err(err) => {
+                    warn!("Couldn't reload {path}, so the old build keeps running: {err}")
+                }
+            }
+        }
+
+        if let Some(checkpoints) = &mut self.checkpoints {
+            match checkpoints.save_if_due(chip_8) {
+                Ok(Some(path)) => debug!("Saved a checkpoint to {}", path.display()),
+                Ok(None) => {}
+                Err(err) => warn!("Couldn't save a checkpoint: {err}"),
+            }
+        }
+
+        let total_cycles = chip_8.stats().total_cycles;
+        // The buzzer would flicker on and off too fast to make sense of.
+        let buzzer_active = chip_8.is_buzzer_active() && !turbo;
+        let stopped = chip_8.stopped();
+
+        // Only log the stop when it happens, not every frame after.
+        if let (Some(stop), None) = (stopped, self.published_frame.load().stopped) {
+            info!(
+                "Stopped at 0x{:03X} ({})\n{}",
+                stop.address,
+                stop.reason,
+                chip_8.backtrace(&BTreeMap::new())
+            );
+        }
+
+        let published_frame = match chip_8.take_frame() {
+            Some((frame, dirty_rows)) => PublishedFrame {
+                sequence: self.published_frame.load().sequence + 1,
+                frame,
+                dirty_rows,
+                total_cycles,
+                buzzer_active,
+                stopped,
+            },
+            None => PublishedFrame {
+                total_cycles,
+                buzzer_active,
+                stopped,
+                ..PublishedFrame::clone(&self.published_frame.load())
+            },
+        };
+
+        self.published_frame.store(Arc::new(published_frame));
+
+        #[cfg(feature = "remote")]
+        if let Some(remote) = &self.remote {
+            remote.publish(chip_8);
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{Emulator, FrameFinishedSignal, FrameLoop, PublishedFrame};
+    use arc_swap::ArcSwap;
+    use chip8_core::scheduler::{MockClock, Scheduler};
+    use chip8_core::timing::TimingModel;
+    use chip8_core::{Chip8, Keys};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// An emulator running 10 cycles a frame of a program that never draws.
+    fn emulator() -> Emulator {
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8.load_program(vec![0x70, 0x01, 0x12, 0x00]).unwrap();
+        chip_8.set_timing_model(TimingModel::Fixed(600));
+
+        Emulator {
+            chip_8,
+            buzzer_recorder: None,
+            replay: None,
+            event_log: None,
+            netplay: None,
+            #[cfg(feature = "remote")]
+            remote: None,
+            checkpoints: None,
+            watcher: None,
+            patches: Vec::new(),
+            load_offset: 0x200,
+            preserve: Vec::new(),
+            flags_path: None,
+            turbo_speed: 2,
+            cycles_per_second: 600,
+            published_frame: Arc::new(ArcSwap::from_pointee(PublishedFrame::default())),
+            input_latency: crossbeam_channel::unbounded().0,
+            shutting_down: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn signal(turbo: bool) -> FrameFinishedSignal {
+        FrameFinishedSignal {
+            keys: Keys(0),
+            restart: false,
+            rewind: false,
+            resume: false,
+            turbo,
+            pressed_at: None,
+            paused: false,
+        }
+    }
+
+    #[test]
+    fn frames_are_run_when_the_clock_says_they_are_due() {
+        let mut emulator = emulator();
+        let mut frame_loop = FrameLoop::new(Scheduler::with_clock(60, MockClock::default()));
+
+        // The first frame is due straight away, and the window drawing again
+        // before the next one is doesn't run it early.
+        for _ in 0..3 {
+            assert!(emulator
+                .handle_signal(signal(false), &mut frame_loop)
+                .unwrap());
+        }
+        assert_eq!(emulator.published_frame.load().total_cycles, 10);
+
+        // A window frame that took longer catches up on the frames it missed.
+        frame_loop
+            .scheduler
+            .clock()
+            .advance(Duration::from_millis(50));
+        emulator
+            .handle_signal(signal(false), &mut frame_loop)
+            .unwrap();
+        assert_eq!(emulator.published_frame.load().total_cycles, 40);
+
+        frame_loop
+            .scheduler
+            .clock()
+            .advance(Duration::from_millis(17));
+        emulator
+            .handle_signal(signal(true), &mut frame_loop)
+            .unwrap();
+        assert_eq!(emulator.published_frame.load().total_cycles, 60);
+    }
+
+    #[test]
+    fn the_emulator_stops_when_the_window_closes() {
+        let mut emulator = emulator();
+        let (tx_signals, rx_signals) = crossbeam_channel::unbounded();
+
+        for _ in 0..3 {
+            tx_signals.send(signal(false)).unwrap();
+        }
+        drop(tx_signals);
+
+        let scheduler = Scheduler::with_clock(60, MockClock::default());
+        emulator.run(&rx_signals, scheduler).unwrap();
+        assert_eq!(emulator.chip_8.stats().total_cycles, 10);
+    }
+}
@@ -0,0 +1,87 @@
+//! Saving high scores for games that keep them in memory, which is all of them
+//! from before SUPER-CHIP's flags. Which parts of memory hold the scores comes
+//! from the ROM database.
+//!
+//! The file is just the bytes of each range, one after the other.
+
+use chip8_core::Chip8;
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+/// Puts the bytes saved by [`save`] back into memory. Returns false if there
+/// was no file to restore from.
+pub fn restore(
+    chip_8: &mut Chip8,
+    ranges: &[RangeInclusive<u16>],
+    path: &Path,
+) -> io::Result<bool> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err),
+    };
+
+    // The file is useless if the database entry changed since it was saved.
+    let expected: usize = ranges.iter().map(|range| range.len()).sum();
+
+    if bytes.len() != expected {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("expected {expected} bytes, found {}", bytes.len()),
+        ));
+    }
+
+    let addresses = ranges.iter().flat_map(|range| range.clone());
+
+    for (address, byte) in addresses.zip(bytes) {
+        chip_8.set_memory_byte(address, byte);
+    }
+
+    Ok(true)
+}
+
+/// Saves the bytes in `ranges` to `path`.
+pub fn save(chip_8: &Chip8, ranges: &[RangeInclusive<u16>], path: &Path) -> io::Result<()> {
+    let memory = chip_8.memory();
+    let bytes: Vec<u8> = ranges
+        .iter()
+        .flat_map(|range| range.clone())
+        .map(|address| memory[address as usize])
+        .collect();
+
+    std::fs::write(path, bytes)
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{restore, save};
+    use crate::Chip8;
+
+    #[test]
+    fn scores_are_restored_into_the_same_ranges() {
+        let path = std::env::temp_dir().join(format!("chip-8-scores-{}", std::process::id()));
+        let ranges = [0x300..=0x301, 0x310..=0x310];
+
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8.load_program(vec![0x12, 0x00]).unwrap();
+
+        assert!(!restore(&mut chip_8, &ranges, &path).unwrap());
+
+        for (address, byte) in [(0x300, 1), (0x301, 2), (0x302, 3), (0x310, 4)] {
+            chip_8.set_memory_byte(address, byte);
+        }
+
+        save(&chip_8, &ranges, &path).unwrap();
+
+        let mut restored = Chip8::new();
+        restored.initialize().unwrap();
+        restored.load_program(vec![0x12, 0x00]).unwrap();
+        assert!(restore(&mut restored, &ranges, &path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.memory()[0x300..0x303], [1, 2, 0]);
+        assert_eq!(restored.memory()[0x310], 4);
+    }
+}
@@ -0,0 +1,386 @@
+//! Keyboard input. Every window reads the keypad through a [`Keymap`], from
+//! the keys a [`KeyTracker`] has seen held down.
+
+use std::time::{Duration, Instant};
+
+use minifb::{Key, KeyRepeat, Window};
+
+use chip8_core::Keys;
+
+/// How long a key has to stay up before its release counts, unless told
+/// otherwise.
+pub const DEBOUNCE: Duration = Duration::from_millis(20);
+
+/// Which keyboard keys press which keypad keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keymap(pub [(Key, u8); 16]);
+
+impl Keymap {
+    /// The left hand side of the keyboard, which is the usual mapping:
+    /// ```
+    /// Keypad                   Keyboard
+    /// +-+-+-+-+                +-+-+-+-+
+    /// |1|2|3|C|                |1|2|3|4|
+    /// +-+-+-+-+                +-+-+-+-+
+    /// |4|5|6|D|                |Q|W|E|R|
+    /// +-+-+-+-+       =>       +-+-+-+-+
+    /// |7|8|9|E|                |A|S|D|F|
+    /// +-+-+-+-+                +-+-+-+-+
+    /// |A|0|B|F|                |Z|X|C|V|
+    /// +-+-+-+-+                +-+-+-+-+
+    /// ```
+    pub const LEFT: Self = Self([
+        (Key::Key1, 0x1),
+        (Key::Key2, 0x2),
+        (Key::Key3, 0x3),
+        (Key::Key4, 0xC),
+        (Key::Q, 0x4),
+        (Key::W, 0x5),
+        (Key::E, 0x6),
+        (Key::R, 0xD),
+        (Key::A, 0x7),
+        (Key::S, 0x8),
+        (Key::D, 0x9),
+        (Key::F, 0xE),
+        (Key::Z, 0xA),
+        (Key::X, 0x0),
+        (Key::C, 0xB),
+        (Key::V, 0xF),
+    ]);
+
+    /// The right hand side of the keyboard, for a second player:
+    /// ```
+    /// Keypad                   Keyboard
+    /// +-+-+-+-+                +-+-+-+-+
+    /// |1|2|3|C|                |7|8|9|0|
+    /// +-+-+-+-+                +-+-+-+-+
+    /// |4|5|6|D|                |U|I|O|P|
+    /// +-+-+-+-+       =>       +-+-+-+-+
+    /// |7|8|9|E|                |J|K|L|;|
+    /// +-+-+-+-+                +-+-+-+-+
+    /// |A|0|B|F|                |M|,|.|/|
+    /// +-+-+-+-+                +-+-+-+-+
+    /// ```
+    pub const RIGHT: Self = Self([
+        (Key::Key7, 0x1),
+        (Key::Key8, 0x2),
+        (Key::Key9, 0x3),
+        (Key::Key0, 0xC),
+        (Key::U, 0x4),
+        (Key::I, 0x5),
+        (Key::O, 0x6),
+        (Key::P, 0xD),
+        (Key::J, 0x7),
+        (Key::K, 0x8),
+        (Key::L, 0x9),
+        (Key::Semicolon, 0xE),
+        (Key::M, 0xA),
+        (Key::Comma, 0x0),
+        (Key::Period, 0xB),
+        (Key::Slash, 0xF),
+    ]);
+
+    /// Makes the keyboard key `key` press the keypad key `keycode`, instead
+    /// of the one that pressed it before. If `key` already pressed another
+    /// keypad key, the two swap, so no key presses two keypad keys.
+    pub fn remap(&mut self, keycode: u8, key: Key) {
+        let Some(&(old_key, _)) = self.0.iter().find(|&&(_, mapped)| mapped == keycode) else {
+            return;
+        };
+
+        for (mapped_key, mapped) in &mut self.0 {
+            if *mapped == keycode {
+                *mapped_key = key;
+            } else if *mapped_key == key {
+                *mapped_key = old_key;
+            }
+        }
+    }
+
+    /// Returns the keypad keys held down, according to `tracker`.
+    pub fn keys(&self, tracker: &KeyTracker) -> Keys {
+        let mut keys = Keys::default();
+
+        for &(key, keycode) in &self.0 {
+            if tracker.is_down(key) {
+                keys.press(keycode);
+            }
+        }
+
+        keys
+    }
+
+    /// Returns when the first of the keypad keys in `keys` was pressed,
+    /// according to `tracker`.
+    pub fn pressed_at(&self, tracker: &KeyTracker, keys: Keys) -> Option<Instant> {
+        self.0
+            .iter()
+            .filter(|&&(_, keycode)| keys.is_down(keycode))
+            .filter_map(|&(key, _)| tracker.pressed_at(key))
+            .min()
+    }
+
+    /// Returns the character printed on the keyboard key that presses the
+    /// keypad key `keycode`, if it has one.
+    pub fn label(&self, keycode: u8) -> Option<char> {
+        let &(key, _) = self.0.iter().find(|&&(_, mapped)| mapped == keycode)?;
+
+        match key {
+            Key::Semicolon => Some(';'),
+            Key::Comma => Some(','),
+            Key::Period => Some('.'),
+            Key::Slash => Some('/'),
+            // The rest are named after their character, like `Key1` and `Q`.
+            _ => {
+                let name = format!("{key:?}");
+                let mut characters = name.strip_prefix("Key").unwrap_or(&name).chars();
+
+                match (characters.next(), characters.next()) {
+                    (Some(character), None) => Some(character),
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::LEFT
+    }
+}
+
+/// Follows which keys are held down from when they are pressed and released,
+/// instead of only whether they are down each time the window is polled.
+///
+/// Polling alone misses keys tapped between two frames, and some systems
+/// repeat a held key by sending a release and a press straight after each
+/// other, which polling can see as the key being let go. Programs moving
+/// with `EX9E` then stutter. Here a key pressed since the last frame is held
+/// for at least a frame, and a release only counts once the key has stayed
+/// up for the debounce time, so repeats never let go of it.
+#[derive(Debug)]
+pub struct KeyTracker {
+    debounce: Duration,
+    held: Vec<HeldKey>,
+}
+
+impl KeyTracker {
+    /// Creates a tracker with no keys held, where releases count once a key
+    /// has been up for `debounce`.
+    pub fn new(debounce: Duration) -> Self {
+        Self {
+            debounce,
+            held: Vec::new(),
+        }
+    }
+
+    /// Catches up with the keys pressed and released in `window` since the
+    /// last call. Should be called after every update of the window.
+    pub fn update(&mut self, window: &Window) {
+        self.apply(
+            &window.get_keys_pressed(KeyRepeat::No),
+            |key| window.is_key_down(key),
+            Instant::now(),
+        );
+    }
+
+    fn apply(&mut self, pressed: &[Key], is_key_down: impl Fn(Key) -> bool, now: Instant) {
+        // Releases from earlier updates are only dropped now, so a key that
+        // was tapped is seen as held for at least one frame.
+        self.held.retain(|held| {
+            held.released_at
+                .is_none_or(|released_at| now - released_at < self.debounce)
+        });
+
+        for &key in pressed {
+            match self.held.iter_mut().find(|held| held.key == key) {
+                Some(held) => held.released_at = None,
+                None => self.held.push(HeldKey {
+                    key,
+                    pressed_at: now,
+                    released_at: None,
+                }),
+            }
+        }
+
+        // A release followed by a press since the last update is a repeat,
+        // so only keys that are up now have been released.
+        for held in &mut self.held {
+            if !is_key_down(held.key) && held.released_at.is_none() {
+                held.released_at = Some(now);
+            }
+        }
+    }
+
+    /// Returns true if `key` is held down.
+    pub fn is_down(&self, key: Key) -> bool {
+        self.pressed_at(key).is_some()
+    }
+
+    /// Returns when `key` was pressed, if it is held down. A key the system
+    /// repeats keeps the time it was first pressed.
+    pub fn pressed_at(&self, key: Key) -> Option<Instant> {
+        self.held
+            .iter()
+            .find(|held| held.key == key)
+            .map(|held| held.pressed_at)
+    }
+}
+
+/// A key in a [`KeyTracker`].
+#[derive(Debug)]
+struct HeldKey {
+    key: Key,
+    pressed_at: Instant,
+    /// When it was released, if it is waiting out the debounce time.
+    released_at: Option<Instant>,
+}
+
+/// The keyboard keys that can be named in the config file. Keys are named
+/// after their [`Key`] variant, like `Q`, `Key1`, `Left` or `NumPad5`.
+const NAMED_KEYS: [Key; 62] = [
+    Key::Key0,
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Key4,
+    Key::Key5,
+    Key::Key6,
+    Key::Key7,
+    Key::Key8,
+    Key::Key9,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::Up,
+    Key::Down,
+    Key::Left,
+    Key::Right,
+    Key::Enter,
+    Key::LeftShift,
+    Key::RightShift,
+    Key::LeftCtrl,
+    Key::RightCtrl,
+    Key::Semicolon,
+    Key::Comma,
+    Key::Period,
+    Key::Slash,
+    Key::NumPad0,
+    Key::NumPad1,
+    Key::NumPad2,
+    Key::NumPad3,
+    Key::NumPad4,
+    Key::NumPad5,
+    Key::NumPad6,
+    Key::NumPad7,
+    Key::NumPad8,
+    Key::NumPad9,
+    Key::NumPadPlus,
+    Key::NumPadMinus,
+    Key::NumPadEnter,
+];
+
+/// Returns the key named `name`, as written by [`key_name`].
+pub fn parse_key(name: &str) -> Option<Key> {
+    NAMED_KEYS
+        .into_iter()
+        .find(|&key| key_name(key).eq_ignore_ascii_case(name))
+}
+
+/// Returns the name `key` is given in the config file.
+pub fn key_name(key: Key) -> String {
+    format!("{key:?}")
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{KeyTracker, Keymap};
+    use chip8_core::Keys;
+    use minifb::Key;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn releases_wait_out_the_debounce_time() {
+        let mut tracker = KeyTracker::new(Duration::from_millis(20));
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        // Tapped between two frames.
+        tracker.apply(&[Key::W], |_| false, at(0));
+        assert!(tracker.is_down(Key::W));
+
+        // Held, then seen up for a moment while it repeats.
+        tracker.apply(&[Key::A], |_| true, at(10));
+        tracker.apply(&[], |_| false, at(15));
+        tracker.apply(&[Key::A], |_| true, at(30));
+        tracker.apply(&[], |_| true, at(50));
+        assert!(tracker.is_down(Key::A));
+        assert!(!tracker.is_down(Key::W));
+
+        // Let go of for real.
+        tracker.apply(&[], |_| false, at(60));
+        tracker.apply(&[], |_| false, at(70));
+        assert!(tracker.is_down(Key::A));
+
+        tracker.apply(&[], |_| false, at(80));
+        assert!(!tracker.is_down(Key::A));
+    }
+
+    #[test]
+    fn remapping_to_a_bound_key_swaps_them() {
+        // W presses 5 by default, and Q presses 4.
+        let mut keymap = Keymap::default();
+        keymap.remap(0x4, Key::W);
+
+        assert_eq!(keymap.label(0x4), Some('W'));
+        assert_eq!(keymap.label(0x5), Some('Q'));
+
+        let mut tracker = KeyTracker::new(Duration::ZERO);
+        tracker.apply(&[Key::W], |_| true, Instant::now());
+
+        assert_eq!(keymap.keys(&tracker), Keys(1 << 0x4));
+    }
+
+    #[test]
+    fn the_earliest_press_of_the_keys_is_found() {
+        // W presses 5 and Q presses 4.
+        let keymap = Keymap::default();
+        let mut tracker = KeyTracker::new(Duration::ZERO);
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        tracker.apply(&[Key::W], |_| true, at(0));
+        tracker.apply(&[Key::Q], |_| true, at(10));
+
+        assert_eq!(keymap.pressed_at(&tracker, Keys(1 << 0x4)), Some(at(10)));
+        assert_eq!(
+            keymap.pressed_at(&tracker, Keys(1 << 0x4 | 1 << 0x5)),
+            Some(at(0))
+        );
+        assert_eq!(keymap.pressed_at(&tracker, Keys(1 << 0x6)), None);
+    }
+}
@@ -0,0 +1,139 @@
+//! A menu for picking a ROM out of a directory, used by `--romdir`.
+
+use crate::render;
+use chip8_core::palette::Palette;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The extensions of files that show up in the menu.
+const ROM_EXTENSIONS: [&str; 5] = ["ch8", "rom", "8o", "zip", "gz"];
+
+/// How many times larger than the font's 4x5 pixels the text is drawn.
+const TEXT_SCALE: usize = 2;
+
+/// Returns the ROMs in a directory (not including subdirectories), sorted
+/// by name.
+pub fn scan(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut roms = Vec::new();
+
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+
+        let is_rom = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                ROM_EXTENSIONS
+                    .iter()
+                    .any(|rom_extension| extension.eq_ignore_ascii_case(rom_extension))
+            });
+
+        if path.is_file() && is_rom {
+            roms.push(path);
+        }
+    }
+
+    roms.sort();
+
+    Ok(roms)
+}
+
+/// The state of the ROM menu.
+#[derive(Debug)]
+pub struct RomMenu {
+    roms: Vec<PathBuf>,
+    selected: usize,
+}
+
+impl RomMenu {
+    /// Creates a menu of `roms`, starting with `last_rom` selected if it is
+    /// one of them.
+    pub fn new(roms: Vec<PathBuf>, last_rom: Option<&Path>) -> Self {
+        let selected = last_rom
+            .and_then(|last_rom| roms.iter().position(|rom| rom == last_rom))
+            .unwrap_or(0);
+
+        Self { roms, selected }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roms.is_empty()
+    }
+
+    pub fn selected(&self) -> Option<&Path> {
+        self.roms.get(self.selected).map(PathBuf::as_path)
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.roms.len().saturating_sub(1));
+    }
+
+    /// Draws the menu into `buffer`, which is `width` by `height` pixels,
+    /// scrolling so the selected ROM is always visible.
+    pub fn render(&self, buffer: &mut [u32], width: usize, height: usize, palette: &Palette) {
+        buffer.fill(palette.background);
+
+        // The title takes up the first two lines.
+        let visible_rows = (height / (6 * TEXT_SCALE)).saturating_sub(3).max(1);
+        let first_row = self.selected.saturating_sub(visible_rows - 1);
+
+        let mut lines = vec!["SELECT A ROM".to_string(), String::new()];
+
+        lines.extend(
+            self.roms
+                .iter()
+                .enumerate()
+                .skip(first_row)
+                .take(visible_rows)
+                .map(|(index, rom)| {
+                    let marker = if index == self.selected { '>' } else { ' ' };
+                    let name = rom.file_name().unwrap_or_default().to_string_lossy();
+                    format!("{marker} {name}")
+                }),
+        );
+
+        render::draw_text(buffer, width, &lines, TEXT_SCALE, palette.foreground);
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{scan, RomMenu};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn scan_finds_roms_sorted_by_name() {
+        let directory = std::env::temp_dir().join(format!("chip-8-library-{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("subdirectory.ch8")).unwrap();
+
+        for name in ["TETRIS.ch8", "README.md", "BLITZ.zip", "PONG.ROM"] {
+            std::fs::write(directory.join(name), []).unwrap();
+        }
+
+        let names: Vec<_> = scan(&directory)
+            .unwrap()
+            .iter()
+            .map(|rom| rom.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(names, ["BLITZ.zip", "PONG.ROM", "TETRIS.ch8"]);
+    }
+
+    #[test]
+    fn menu_starts_at_the_last_rom() {
+        let roms = vec![PathBuf::from("a.ch8"), PathBuf::from("b.ch8")];
+        let mut menu = RomMenu::new(roms, Some(Path::new("b.ch8")));
+
+        assert_eq!(menu.selected(), Some(Path::new("b.ch8")));
+        menu.select_next();
+        assert_eq!(menu.selected(), Some(Path::new("b.ch8")));
+        menu.select_previous();
+        assert_eq!(menu.selected(), Some(Path::new("a.ch8")));
+    }
+}
@@ -1,134 +1,1386 @@
-use chip_8::Chip8;
-use chip_8::{HEIGHT, WIDTH};
+use arc_swap::ArcSwap;
+use audio::BuzzerRecorder;
+use chip8_core::database::RomDatabase;
+use chip8_core::debugger::WriteProtection;
+use chip8_core::disassembler::disassemble;
+use chip8_core::event_log::EventLog;
+use chip8_core::headless;
+use chip8_core::lint::lint;
+use chip8_core::lockstep::lockstep;
+use chip8_core::octo;
+use chip8_core::palette::Palette;
+use chip8_core::quirks::Quirks;
+use chip8_core::replay::Replay;
+use chip8_core::rpl::RPL_FLAG_COUNT;
+use chip8_core::scheduler::Scheduler;
+use chip8_core::timing::TimingModel;
+use chip8_core::trace_log;
+use chip8_core::tracepoint::Tracepoint;
+use chip8_core::{Chip8, Preserve};
+use chip8_core::{HEIGHT, PROGRAM_OFFSET, WIDTH};
 use clap::Parser;
-use env_logger::Env;
-use log::error;
+use config::{Config, Profile};
+use emulator::{Emulator, FrameFinishedSignal, PublishedFrame};
+use keymap::Keymap;
+use library::RomMenu;
 use minifb::Key;
+use minifb::KeyRepeat;
+use minifb::ScaleMode;
 use minifb::Window;
 use minifb::WindowOptions;
-use std::io::Write;
-use std::sync::{Arc, Mutex};
+use netplay::Netplay;
+use render::{Effect, Rotation, VisualBell};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use window::{EmulatorLink, WindowSettings};
 
-mod chip_8;
+#[cfg(feature = "zip")]
+mod archive;
+mod audio;
+mod batch;
+mod checkpoint;
+mod config;
+#[cfg(feature = "dap")]
+mod dap;
+mod emulator;
+mod high_scores;
+mod keymap;
+mod library;
+mod netplay;
+#[cfg(feature = "remote")]
+mod remote;
+mod render;
+#[cfg(feature = "script")]
+mod script;
+mod split;
+mod stats;
+#[cfg(feature = "video")]
+mod video;
+mod watch;
+mod window;
+
+/// The splash screen run when no ROM is given, assembled from
+/// `src/demo/splash.8o`.
+const DEMO_ROM: &[u8] = include_bytes!("demo/splash.ch8");
 
-// We scale everything up by a factor of 8
-const SCALE: u32 = 8;
 const FRAME_HZ: u32 = 30;
 const CYCLES_PER_SECOND: u32 = 720;
-const CYCLES_PER_FRAME: u32 = CYCLES_PER_SECOND / FRAME_HZ;
-const CYCLES_PER_CLOCK: u32 = CYCLES_PER_SECOND / 60;
+/// The emulator runs in 60Hz frames, so each window frame covers several of them.
+const EMULATOR_FRAMES_PER_FRAME: u32 = 60 / FRAME_HZ;
 #[derive(clap::Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    /// Path to the ROM that will be loaded.
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Path to the ROM that will be loaded. Files ending in `.8o` are
+    /// assembled as Octo source code first, and `.zip` and `.gz` files are
+    /// extracted (if built with the `zip` feature). Without a ROM, a built-in
+    /// splash screen is shown.
     #[arg(short, long)]
-    rom: String,
+    rom: Option<String>,
+    /// Pick the ROM from a menu of the ROMs in this directory instead. The
+    /// last ROM picked is remembered.
+    #[arg(long, conflicts_with = "rom")]
+    romdir: Option<String>,
+    /// The preset colors used to draw the screen. Defaults to the colors from
+    /// the ROM database, or classic if the ROM isn't in it.
+    #[arg(long, value_enum)]
+    theme: Option<Theme>,
+    /// The color of pixels that are on, as RRGGBB. Overrides the theme.
+    #[arg(long, value_parser = parse_color)]
+    fg: Option<u32>,
+    /// The color of pixels that are off, as RRGGBB. Overrides the theme.
+    #[arg(long, value_parser = parse_color)]
+    bg: Option<u32>,
+    /// Fades pixels out instead of turning them off instantly, losing this
+    /// fraction of their brightness each frame. Reduces flicker. Must be
+    /// above 0.0, which would never let pixels go out, and at most 1.0.
+    #[arg(long, value_parser = parse_decay)]
+    phosphor_decay: Option<f32>,
+    /// Show when the buzzer is sounding on screen, since there is no audio.
+    #[arg(long, value_enum)]
+    visual_bell: Option<VisualBell>,
+    /// Retro display effects to apply. Can be given multiple times.
+    #[arg(long, value_enum)]
+    effect: Vec<Effect>,
+    /// How many times larger than 64x32 the window starts out. The picture is
+    /// scaled by the largest whole number that fits when the window is resized.
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u32).range(1..))]
+    scale: u32,
+    /// Turn the picture clockwise by this many degrees, for screens that are
+    /// mounted sideways or upside down. The overlay is turned with it.
+    #[arg(long, value_enum, default_value_t = Rotation::None)]
+    rotate: Rotation,
+    /// Start in fullscreen. Fullscreen can also be toggled with F11.
+    #[arg(long)]
+    fullscreen: bool,
+    /// Start with the keypad shown in the corner, labeled with the keyboard
+    /// key for each of its keys and with the keys held down filled in. It
+    /// can also be toggled with F2.
+    #[arg(long)]
+    show_keypad: bool,
+    /// How many milliseconds a key has to stay up before letting go of it
+    /// counts. This stops keys that the system repeats while they are held
+    /// from flickering on and off, which makes movement stutter.
+    #[arg(long, default_value_t = keymap::DEBOUNCE.as_millis() as u64)]
+    key_debounce: u64,
+    /// The size of the screen used for fullscreen, as WIDTHxHEIGHT.
+    #[arg(long, default_value = "1920x1080", value_parser = parse_size)]
+    fullscreen_size: (usize, usize),
+    /// The address to load the ROM at and start running it from, like 0x600
+    /// for ETI-660 programs.
+    #[arg(long, default_value = "0x200", value_parser = parse_address)]
+    load_offset: usize,
+    /// An IPS or BPS patch to apply to the ROM before it is loaded. Can be
+    /// given multiple times, and the patches are applied in order.
+    #[arg(long)]
+    patch: Vec<String>,
+    /// Interpreter quirks to turn on, on top of any the ROM database
+    /// recommends. Can be given multiple times.
+    #[arg(long, value_enum)]
+    quirk: Vec<Quirk>,
+    /// A file of extra ROM database entries, in the same format as the
+    /// embedded database. Its entries win over the embedded ones.
+    #[arg(long)]
+    rom_database: Option<String>,
+    /// Keep this many seconds of history, which can be rewound through by
+    /// holding Backspace.
+    #[arg(long)]
+    rewind: Option<u32>,
+    /// The most megabytes the rewind history can take up. The oldest
+    /// history is dropped to stay under it, even if that leaves less than
+    /// `--rewind` seconds.
+    #[arg(long, requires = "rewind")]
+    rewind_memory: Option<usize>,
+    /// How many times faster than normal the emulator runs while Space is
+    /// held. Only the last of the frames run in each window frame is shown,
+    /// and the buzzer isn't shown either. Has no effect in netplay.
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    turbo: u32,
+    /// Record the buzzer into this WAV file, which is written when the
+    /// emulator exits. It follows emulated time, so it lines up with the
+    /// game even if the emulator ran slow.
+    #[arg(long)]
+    record_audio: Option<String>,
+    /// Record the keys pressed into this `.c8rec` replay, which is written
+    /// when the emulator exits and can be turned into a video with
+    /// `render-replay`.
+    #[arg(long, conflicts_with_all = ["load_state", "rewind"])]
+    record_input: Option<PathBuf>,
+    /// Write what happens in each frame, like the keys held, sprites drawn
+    /// and random numbers picked, to this file as JSON Lines. Two logs of
+    /// the same ROM can be compared to find where the runs went apart.
+    #[arg(long, conflicts_with_all = ["load_state", "rewind", "watch"])]
+    event_log: Option<PathBuf>,
+    /// Stop before running an instruction of this kind, given the name of
+    /// its variant like `Draw`, `Random` or `Call`. F5 carries on. Can be
+    /// given multiple times.
+    #[arg(long)]
+    break_on: Vec<String>,
+    /// Stop when the program writes over code that has already run, like
+    /// programs that patch their own instructions do. F5 carries on.
+    #[arg(long)]
+    break_on_self_modifying_code: bool,
+    /// Stop when the program writes below 0x200 or over its own bytes, which
+    /// usually means I pointed somewhere it shouldn't have. F5 carries on.
+    #[arg(long)]
+    protect_writes: bool,
+    /// Log a message every time the instruction at an address runs, as
+    /// ADDRESS:MESSAGE like `0x2A4:score={v3} sprite={i}`. `{v0}` to `{vf}`,
+    /// `{i}`, `{dt}` and `{st}` are filled in from the emulator. Can be given
+    /// multiple times.
+    #[arg(long, value_parser = parse_tracepoint)]
+    trace: Vec<(u16, Tracepoint)>,
+    /// Write which addresses were run, read and written to this file when the
+    /// emulator exits. Files ending in `.json` are written as JSON, and
+    /// anything else as text.
+    #[arg(long)]
+    coverage: Option<String>,
+    /// The file SUPER-CHIP programs keep the flags they save with FX75 in,
+    /// like high scores, so they are still there next time. Defaults to the
+    /// ROM's path with a `.flags` extension, which is only written once the
+    /// program saves something. With the `json` feature, files ending in
+    /// `.json` are kept as the JSON list Octo saves its flags as, so they can
+    /// be copied to and from it.
+    #[arg(long)]
+    flags_file: Option<PathBuf>,
+    /// The file high scores are saved to for games the ROM database knows
+    /// keep them in memory. Defaults to the ROM's path with a `.scores`
+    /// extension.
+    #[arg(long)]
+    high_score_file: Option<PathBuf>,
+    /// Save the emulator's state every this many seconds, into a rotating set
+    /// of files next to the ROM like `game.checkpoint-0.state`, so it can be
+    /// picked up again with `--load-state` after a crash.
+    #[arg(long)]
+    checkpoint_interval: Option<u64>,
+    /// How many checkpoints to keep before writing over the oldest.
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    checkpoints: u32,
+    /// Start from a state saved to this file, like one of the checkpoints,
+    /// instead of the start of the program.
+    #[arg(long)]
+    load_state: Option<PathBuf>,
+    /// Reload the ROM whenever its file changes, reassembling it if it is
+    /// Octo source code. The program starts over, keeping anything given
+    /// with `--preserve`. A ROM that fails to build leaves the old one
+    /// running.
+    #[arg(long, conflicts_with_all = ["netplay_host", "netplay_connect", "record_input"])]
+    watch: bool,
+    /// Something to keep when the ROM is reloaded by `--watch`: a register
+    /// like `v3`, `i` for I, or memory like `0x300` or `0x300-0x3FF`. Can be
+    /// given multiple times.
+    #[arg(long, requires = "watch", value_parser = parse_preserve)]
+    preserve: Vec<Preserve>,
+    /// Print execution statistics when the emulator exits.
+    #[arg(long)]
+    stats: bool,
+    /// How log messages are written to stderr. Which messages are written is
+    /// set with `RUST_LOG`, like `RUST_LOG=chip8_core=trace` to log every
+    /// frame, cycle and instruction.
+    #[arg(long, value_enum, default_value_t = LogFormat::Text, global = true)]
+    log_format: LogFormat,
+    /// End a 60Hz frame early once this many instructions have run in it
+    /// without drawing or waiting for a key, so a program stuck in a loop
+    /// can't use up all of the emulator thread's time.
+    #[arg(long)]
+    watchdog: Option<u32>,
+    /// Serve the screen and registers over a WebSocket on this address, like
+    /// `0.0.0.0:8080`, and take key presses and debugger commands from the
+    /// clients that connect.
+    #[cfg(feature = "remote")]
+    #[arg(long)]
+    remote: Option<std::net::SocketAddr>,
+    /// A Rhai script to run alongside the program, which can read and change
+    /// its registers and memory every frame, instruction or memory write.
+    #[cfg(feature = "script")]
+    #[arg(long)]
+    script: Option<PathBuf>,
+    /// Host a netplay session on this port, sharing the keypad with a player
+    /// who joins with `--netplay-connect`. Both players need the same ROM and
+    /// the same options.
+    #[arg(long, conflicts_with_all = ["netplay_connect", "rewind"])]
+    netplay_host: Option<u16>,
+    /// Join the netplay session hosted at this address, like `10.0.0.2:7000`.
+    #[arg(long, conflicts_with = "rewind")]
+    netplay_connect: Option<String>,
+    /// How many frames after a key press it is applied in netplay, to give it
+    /// time to reach the other player. Only the host's setting is used.
+    #[arg(long, default_value_t = 4)]
+    netplay_delay: u8,
 }
 
-/// Represents characters 0-F on the keypad (encoded as 0x0-0xF)
-#[derive(Default, Debug, Clone, Copy)]
-struct Keycode(pub Option<u8>);
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Assemble an Octo `.8o` source file into a ROM.
+    Assemble {
+        /// The Octo source file.
+        source: String,
+        /// Where to write the ROM.
+        #[arg(short, long)]
+        output: String,
+    },
+    /// Disassemble a ROM into Octo source code, leaving out the parts that
+    /// can't be reached as data.
+    Disassemble {
+        /// The ROM to disassemble.
+        rom: String,
+        /// The address the ROM is loaded at and starts running from.
+        #[arg(long, default_value = "0x200", value_parser = parse_address)]
+        load_offset: usize,
+    },
+    /// Report the instructions in a ROM that behave differently between
+    /// interpreters, and which quirks it depends on.
+    Lint {
+        /// The ROM to check.
+        rom: String,
+        /// The address the ROM is loaded at and starts running from.
+        #[arg(long, default_value = "0x200", value_parser = parse_address)]
+        load_offset: usize,
+        /// How many 60Hz frames to run the ROM for, to see which instructions run.
+        #[arg(long, default_value_t = 600)]
+        frames: u32,
+    },
+    /// Run a ROM with no keys pressed and print its state before every
+    /// instruction, in the format read by `compare-trace`.
+    Trace {
+        /// The ROM to run.
+        rom: String,
+        /// The address the ROM is loaded at and starts running from.
+        #[arg(long, default_value = "0x200", value_parser = parse_address)]
+        load_offset: usize,
+        /// How many instructions to run.
+        #[arg(long, default_value_t = 10_000)]
+        cycles: u64,
+    },
+    /// Run a ROM with no window and no keys pressed, then print how it ended
+    /// up. Exits with an error if it didn't end up as expected.
+    Headless {
+        /// The ROM to run.
+        rom: String,
+        /// The address the ROM is loaded at and starts running from.
+        #[arg(long, default_value = "0x200", value_parser = parse_address)]
+        load_offset: usize,
+        /// How many instructions to run at most.
+        #[arg(long)]
+        cycles: u64,
+        /// Stop once the next instruction is at this address, and fail if it
+        /// never is.
+        #[arg(long, value_parser = parse_address)]
+        until_pc: Option<usize>,
+        /// Fail unless the SHA-1 hash of the screen is this at the end.
+        #[arg(long)]
+        expect_screen_hash: Option<String>,
+        /// Print the screen at the end in block characters.
+        #[arg(long)]
+        print_final_frame: bool,
+    },
+    /// Run every ROM in a directory with no window and no keys pressed, in
+    /// parallel, and report which ones crashed or ran into an invalid
+    /// instruction, and the screen hash of the rest.
+    Batch {
+        /// The directory of ROMs.
+        romdir: String,
+        /// How many instructions to run each ROM for.
+        #[arg(long, default_value_t = 100_000)]
+        cycles: u64,
+        /// Write the report to this file instead of printing it.
+        #[arg(long)]
+        report: Option<String>,
+    },
+    /// Run a ROM alongside a reference execution log, and stop at the first
+    /// instruction where the program counter or registers differ from it.
+    CompareTrace {
+        /// The ROM to run.
+        rom: String,
+        /// The reference log, with one line per instruction like
+        /// `pc:0200 i:0000 v0:00 ... vf:00`.
+        log: String,
+        /// The address the ROM is loaded at and starts running from.
+        #[arg(long, default_value = "0x200", value_parser = parse_address)]
+        load_offset: usize,
+    },
+    /// Run a ROM with two sets of quirks side by side, and report the first
+    /// cycle where the two runs differ.
+    Diff {
+        /// The ROM to run.
+        rom: String,
+        /// The address the ROM is loaded at and starts running from.
+        #[arg(long, default_value = "0x200", value_parser = parse_address)]
+        load_offset: usize,
+        /// A quirk to turn on for the first run. Can be given multiple times.
+        #[arg(long, value_enum)]
+        quirk_a: Vec<Quirk>,
+        /// A quirk to turn on for the second run. Can be given multiple times.
+        #[arg(long, value_enum)]
+        quirk_b: Vec<Quirk>,
+        /// How many 60Hz frames to run the ROM for.
+        #[arg(long, default_value_t = 600)]
+        frames: u32,
+    },
+    /// Run two ROMs (or the same ROM twice) side by side in one window. The
+    /// left one is played with 1234/QWER/ASDF/ZXCV, and the right one with
+    /// 7890/UIOP/JKL;/M,./.
+    Split {
+        /// The ROM on the left.
+        rom_a: String,
+        /// The ROM on the right. Defaults to the same ROM as the left.
+        rom_b: Option<String>,
+        /// A quirk to turn on for the left ROM, on top of any the ROM database
+        /// recommends. Can be given multiple times.
+        #[arg(long, value_enum)]
+        quirk_a: Vec<Quirk>,
+        /// A quirk to turn on for the right ROM, on top of any the ROM
+        /// database recommends. Can be given multiple times.
+        #[arg(long, value_enum)]
+        quirk_b: Vec<Quirk>,
+        /// How many instructions the left ROM runs per second. Defaults to
+        /// the ROM database's speed, or 720.
+        #[arg(long)]
+        speed_a: Option<u32>,
+        /// How many instructions the right ROM runs per second. Defaults to
+        /// the ROM database's speed, or 720.
+        #[arg(long)]
+        speed_b: Option<u32>,
+        /// How many times larger than 64x32 each screen is.
+        #[arg(long, default_value_t = 6, value_parser = clap::value_parser!(u32).range(1..))]
+        scale: u32,
+    },
+    /// Play a `.c8rec` replay recorded with `--record-input` with no window,
+    /// and write what was on the screen to a GIF or a directory of PNGs.
+    #[cfg(feature = "video")]
+    RenderReplay {
+        /// The ROM the replay was recorded with.
+        rom: String,
+        /// The replay.
+        replay: PathBuf,
+        /// Where to write the video. Paths ending in `.gif` are written as
+        /// an animated GIF, and anything else as a directory of PNGs, one
+        /// for every 60Hz frame.
+        output: PathBuf,
+        /// How many times larger than 64x32 the video is.
+        #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+        scale: u32,
+        /// The preset colors used to draw the screen.
+        #[arg(long, value_enum)]
+        theme: Option<Theme>,
+        /// The color of pixels that are on, as RRGGBB. Overrides the theme.
+        #[arg(long, value_parser = parse_color)]
+        fg: Option<u32>,
+        /// The color of pixels that are off, as RRGGBB. Overrides the theme.
+        #[arg(long, value_parser = parse_color)]
+        bg: Option<u32>,
+    },
+    /// Convert a save state, like a checkpoint, to or from JSON that can be
+    /// edited by hand and loaded with `--load-state`. Paths ending in
+    /// `.json` are JSON.
+    #[cfg(feature = "json")]
+    ConvertState {
+        /// The save state to convert.
+        input: PathBuf,
+        /// Where to write it.
+        output: PathBuf,
+    },
+    /// Wait for a debugger like VS Code to connect using the Debug Adapter
+    /// Protocol, and run the program it launches without a window.
+    #[cfg(feature = "dap")]
+    Dap {
+        /// The port to listen on.
+        #[arg(long, default_value_t = 4711)]
+        port: u16,
+    },
+}
 
-#[derive(Debug)]
-struct FrameFinishedSignal {
-    /// The key that was pressed down just after the newly created frame.
-    current_keycode: Keycode,
+/// The [`Quirks`] that can be turned on from the command line.
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum Quirk {
+    /// DXYN waits for the next frame before drawing, like the COSMAC VIP.
+    DisplayWait,
+    /// Sprites drawn past the edge of the screen wrap around instead of being clipped.
+    WrapSprites,
+    /// 8XY6 and 8XYE shift VY into VX instead of shifting VX in place.
+    ShiftVy,
+    /// FX55 and FX65 add X + 1 to I, like the COSMAC VIP.
+    IncrementIndex,
+    /// FX1E sets VF when I goes past 0xFFF, like the Amiga interpreter.
+    IndexOverflowFlag,
+    /// Bytes FX55 and FX33 write to 0x000 are printed to stdout instead, for
+    /// printf-style debugging. Not a quirk of any real interpreter.
+    ConsoleOutput,
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let env = Env::default().default_filter_or("warn");
+impl Quirk {
+    fn enable(self, quirks: &mut Quirks) {
+        match self {
+            Self::DisplayWait => quirks.display_wait = true,
+            Self::WrapSprites => quirks.wrap_sprites = true,
+            Self::ShiftVy => quirks.shift_vy = true,
+            Self::IncrementIndex => quirks.increment_index = true,
+            Self::IndexOverflowFlag => quirks.index_overflow_flag = true,
+            Self::ConsoleOutput => quirks.console_output = true,
+        }
+    }
+}
 
-    let (tx_frame_finished, rx_frame_finished) =
-        crossbeam_channel::unbounded::<FrameFinishedSignal>();
+/// How log messages are written.
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum LogFormat {
+    /// One line of text per message.
+    Text,
+    /// One JSON object per message, with the spans it happened in.
+    Json,
+}
 
-    env_logger::Builder::from_env(env)
-        .format(|buf, record| writeln!(buf, "{}: {}", record.level(), record.args()))
-        .init();
+/// Preset palettes that can be picked from the command line.
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum Theme {
+    /// White on black.
+    Classic,
+    /// Green on dark green, like an old phosphor monitor.
+    GreenPhosphor,
+    /// Amber on dark brown, like an old phosphor monitor.
+    Amber,
+}
 
+impl Theme {
+    fn palette(self) -> Palette {
+        match self {
+            Self::Classic => Palette::CLASSIC,
+            Self::GreenPhosphor => Palette::GREEN_PHOSPHOR,
+            Self::Amber => Palette::AMBER,
+        }
+    }
+}
+
+/// Parses a size written as `WIDTHxHEIGHT`.
+fn parse_size(size: &str) -> Result<(usize, usize), String> {
+    let (width, height) = size
+        .split_once('x')
+        .ok_or_else(|| format!("expected a size like 1920x1080, got {size:?}"))?;
+
+    match (width.parse(), height.parse()) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(format!("invalid size {size:?}")),
+    }
+}
+
+/// Parses a memory address, written in hexadecimal with an optional `0x` prefix.
+fn parse_address(address: &str) -> Result<usize, String> {
+    let digits = address.trim_start_matches("0x");
+
+    match usize::from_str_radix(digits, 16) {
+        Ok(address) if (PROGRAM_OFFSET..0x1000).contains(&address) => Ok(address),
+        Ok(_) => Err(format!(
+            "expected an address between 0x200 and 0xFFF, got {address}"
+        )),
+        Err(e) => Err(format!("invalid address {address:?}: {e}")),
+    }
+}
+
+/// Parses a tracepoint as ADDRESS:MESSAGE.
+fn parse_tracepoint(tracepoint: &str) -> Result<(u16, Tracepoint), String> {
+    let (address, message) = tracepoint
+        .split_once(':')
+        .ok_or_else(|| format!("expected ADDRESS:MESSAGE, got {tracepoint:?}"))?;
+
+    let address = parse_address(address)? as u16;
+    let tracepoint = Tracepoint::new(message).map_err(|e| e.to_string())?;
+
+    Ok((address, tracepoint))
+}
+
+/// Parses something to keep between reloads, as `vX`, `i`, an address or
+/// an address range like `0x300-0x3FF`.
+fn parse_preserve(preserve: &str) -> Result<Preserve, String> {
+    let lowercase = preserve.to_ascii_lowercase();
+
+    if lowercase == "i" {
+        return Ok(Preserve::IndexRegister);
+    }
+
+    if let Some(register) = lowercase.strip_prefix('v') {
+        return match u8::from_str_radix(register, 16) {
+            Ok(register) if register < 16 => Ok(Preserve::Register(register)),
+            _ => Err(format!("expected a register from v0 to vf, got {preserve}")),
+        };
+    }
+
+    let parse = |address: &str| {
+        u16::from_str_radix(address.trim_start_matches("0x"), 16)
+            .map_err(|e| format!("invalid address {address:?}: {e}"))
+    };
+
+    let range = match lowercase.split_once('-') {
+        Some((start, end)) => parse(start)?..=parse(end)?,
+        None => parse(&lowercase)?..=parse(&lowercase)?,
+    };
+
+    match range.is_empty() {
+        true => Err(format!("the range {preserve} ends before it starts")),
+        false => Ok(Preserve::Memory(range)),
+    }
+}
+
+/// Parses a phosphor decay, which [`render::check_decay`] has to accept.
+fn parse_decay(decay: &str) -> Result<f32, String> {
+    match decay.parse::<f32>() {
+        Ok(decay) => render::check_decay(decay),
+        Err(e) => Err(format!("invalid number {decay:?}: {e}")),
+    }
+}
+
+/// Parses a color written as `RRGGBB`, optionally prefixed with `#` or `0x`.
+fn parse_color(color: &str) -> Result<u32, String> {
+    let hex = color
+        .strip_prefix('#')
+        .or_else(|| color.strip_prefix("0x"))
+        .unwrap_or(color);
+
+    if hex.len() != 6 {
+        return Err(format!("expected a color like #RRGGBB, got {color:?}"));
+    }
+
+    u32::from_str_radix(hex, 16).map_err(|e| format!("invalid color {color:?}: {e}"))
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
-    // I'm sorry I put this in a mutex, I need to multithread and the Chip8 doesn't
-    // care about the performance loss.
-    let chip_8_ref_1 = Arc::new(Mutex::new(Chip8::new()));
-    let chip_8_ref_2 = Arc::clone(&chip_8_ref_1);
+    let mut filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn"));
+
+    // Tracepoints and self-modifying code would be pointless to look for if
+    // their messages were filtered out.
+    if !args.trace.is_empty() || args.break_on_self_modifying_code {
+        filter = filter.add_directive("chip_8::trace=info".parse()?);
+    }
+
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+
+    match args.log_format {
+        LogFormat::Text => subscriber.without_time().with_target(false).init(),
+        LogFormat::Json => subscriber.json().with_span_list(true).init(),
+    }
+
+    match &args.command {
+        Some(Command::Assemble { source, output }) => {
+            let program = octo::assemble(&std::fs::read_to_string(source)?)?;
+            std::fs::write(output, program)?;
+            return Ok(());
+        }
+        Some(Command::Disassemble { rom, load_offset }) => {
+            let program = read_program(rom)?;
+            print!("{}", disassemble(&program, *load_offset as u16, None));
+            return Ok(());
+        }
+        Some(Command::Lint {
+            rom,
+            load_offset,
+            frames,
+        }) => {
+            let findings = lint(&read_program(rom)?, *load_offset as u16, *frames)?;
+
+            for finding in &findings {
+                println!("{finding}");
+            }
+
+            let quirks: BTreeSet<_> = findings
+                .iter()
+                .map(|finding| finding.quirk.name())
+                .collect();
+
+            match quirks.is_empty() {
+                true => println!("No quirk dependence found"),
+                false => println!("Depends on: {}", Vec::from_iter(quirks).join(", ")),
+            }
+
+            return Ok(());
+        }
+        Some(Command::Trace {
+            rom,
+            load_offset,
+            cycles,
+        }) => {
+            print!(
+                "{}",
+                trace_log::write_log(&read_program(rom)?, *load_offset as u16, *cycles)?
+            );
+            return Ok(());
+        }
+        Some(Command::Headless {
+            rom,
+            load_offset,
+            cycles,
+            until_pc,
+            expect_screen_hash,
+            print_final_frame,
+        }) => {
+            let until_pc = until_pc.map(|address| address as u16);
+            let run = headless::run(&read_program(rom)?, *load_offset as u16, *cycles, until_pc)?;
+            println!("{run}");
 
-    chip_8_ref_1.lock().unwrap().initialize()?;
+            if *print_final_frame {
+                print!("{}", run.frame.to_ascii());
+            }
 
-    let program_bytes = std::fs::read(args.rom)?;
-    chip_8_ref_1
-        .lock()
-        .unwrap()
-        .load_program(program_bytes.clone())?;
+            let mut failed = false;
 
-    let _game_loop = std::thread::spawn(move || {
-        // looping cycle count used for knowing when to decrement timers
-        let mut cycle_count: u64 = 0;
+            if let (Some(address), false) = (until_pc, run.reached_pc) {
+                println!("Never reached 0x{address:03X}");
+                failed = true;
+            }
 
-        loop {
-            // wait here until we get the signal that the frame has been drawn.
-            let finished_signal = rx_frame_finished.recv().unwrap();
-            let keycode = finished_signal.current_keycode;
+            if let Some(expected) = expect_screen_hash {
+                if !expected.eq_ignore_ascii_case(&run.screen_hash) {
+                    println!("Expected the screen to be {expected}");
+                    failed = true;
+                }
+            }
 
-            let mut chip_8_guard = chip_8_ref_1.lock().unwrap();
+            if failed {
+                std::process::exit(1);
+            }
 
-            for _ in 0..CYCLES_PER_FRAME {
-                chip_8_guard.cycle(keycode).unwrap();
-                cycle_count = cycle_count.wrapping_add(1);
+            return Ok(());
+        }
+        Some(Command::Batch {
+            romdir,
+            cycles,
+            report,
+        }) => {
+            let report_text = batch::run(&library::scan(Path::new(romdir))?, *cycles).to_string();
+
+            match report {
+                Some(path) => std::fs::write(path, report_text + "\n")?,
+                None => println!("{report_text}"),
+            }
 
-                if (cycle_count % 12) == 0 {
-                    chip_8_guard.delay_timer.decrement();
-                    chip_8_guard.sound_timer.decrement();
+            return Ok(());
+        }
+        Some(Command::CompareTrace {
+            rom,
+            log,
+            load_offset,
+        }) => {
+            let log = std::fs::read_to_string(log)?;
+
+            match trace_log::compare(&read_program(rom)?, *load_offset as u16, &log)? {
+                Some(divergence) => {
+                    println!("{divergence}");
+                    std::process::exit(1);
                 }
+                None => println!("The run matched the whole log"),
             }
+
+            return Ok(());
         }
+        Some(Command::Diff {
+            rom,
+            load_offset,
+            quirk_a,
+            quirk_b,
+            frames,
+        }) => {
+            let [a, b] = [quirk_a, quirk_b].map(|quirks| {
+                let mut enabled = Quirks::default();
+                quirks.iter().for_each(|quirk| quirk.enable(&mut enabled));
+                enabled
+            });
+
+            match lockstep(&read_program(rom)?, *load_offset as u16, a, b, *frames)? {
+                Some(divergence) => {
+                    println!("{divergence}");
+                    std::process::exit(1);
+                }
+                None => println!("The runs never differed"),
+            }
+
+            return Ok(());
+        }
+        Some(Command::Split {
+            rom_a,
+            rom_b,
+            quirk_a,
+            quirk_b,
+            speed_a,
+            speed_b,
+            scale,
+        }) => {
+            let rom_b = rom_b.as_ref().unwrap_or(rom_a);
+            let sides = [
+                split_side(rom_a, quirk_a, *speed_a, Keymap::LEFT, *scale)?,
+                split_side(rom_b, quirk_b, *speed_b, Keymap::RIGHT, *scale)?,
+            ];
+
+            split::run(
+                sides,
+                *scale as usize,
+                &Palette::default(),
+                Duration::from_millis(args.key_debounce),
+            )?;
+            return Ok(());
+        }
+        #[cfg(feature = "video")]
+        Some(Command::RenderReplay {
+            rom,
+            replay,
+            output,
+            scale,
+            theme,
+            fg,
+            bg,
+        }) => {
+            let replay = Replay::from_bytes(&std::fs::read(replay)?)?;
+            let theme_palette = theme.map(Theme::palette).unwrap_or_default();
+            let palette = Palette {
+                foreground: fg.unwrap_or(theme_palette.foreground),
+                background: bg.unwrap_or(theme_palette.background),
+            };
+
+            let images = video::render(
+                &replay,
+                &read_program(rom)?,
+                output,
+                *scale as usize,
+                &palette,
+            )?;
+            println!("Wrote {images} images to {}", output.display());
+            return Ok(());
+        }
+        #[cfg(feature = "json")]
+        Some(Command::ConvertState { input, output }) => {
+            checkpoint::write_state(output, &checkpoint::read_state(input)?)?;
+            return Ok(());
+        }
+        #[cfg(feature = "dap")]
+        Some(Command::Dap { port }) => {
+            dap::serve(*port)?;
+            return Ok(());
+        }
+        None => {}
+    }
+
+    // The menu is shown before we know which ROM (and so which database
+    // entry) is being used.
+    let theme_palette = args.theme.map(Theme::palette).unwrap_or_default();
+    let menu_palette = Palette {
+        foreground: args.fg.unwrap_or(theme_palette.foreground),
+        background: args.bg.unwrap_or(theme_palette.background),
+    };
+
+    let mut config = Config::load()?;
+
+    // Without a ROM there are no files next to it to keep flags, scores or
+    // checkpoints in, so those aren't saved.
+    let rom = match (&args.rom, &args.romdir) {
+        (Some(rom), _) => Some(rom.clone()),
+        (None, Some(romdir)) => {
+            match choose_rom(Path::new(romdir), &mut config, args.scale, &menu_palette)? {
+                Some(rom) => Some(rom.to_string_lossy().into_owned()),
+                // The menu was closed without picking anything.
+                None => return Ok(()),
+            }
+        }
+        (None, None) => None,
+    };
+
+    let mut rom_database = RomDatabase::embedded();
+
+    if let Some(path) = &args.rom_database {
+        rom_database.extend(RomDatabase::parse(&std::fs::read_to_string(path)?)?);
+    }
+
+    // The emulator thread owns the Chip8 and publishes a copy of the screen
+    // after every frame, so the UI never has to wait on a lock.
+    let mut chip_8 = Chip8::new();
+    chip_8.initialize()?;
+
+    let program_bytes = match &rom {
+        Some(rom) => read_patched_program(rom, &args.patch, args.load_offset)?,
+        None => DEMO_ROM.to_vec(),
+    };
+    let loaded_rom = chip_8.load_program_at(args.load_offset, program_bytes)?;
+    info!(
+        "Loaded {}: {loaded_rom}",
+        rom.as_deref().unwrap_or("the splash screen")
+    );
+
+    if let Some(path) = &args.load_state {
+        checkpoint::load(&mut chip_8, path)?;
+        info!("Loaded the state saved in {}", path.display());
+    }
+
+    // Hi-res programs are detected when they are loaded, and need a taller window.
+    let resolution = chip_8.resolution();
+
+    let flags_path = args.flags_file.clone().or_else(|| {
+        rom.as_ref()
+            .map(|rom| Path::new(rom).with_extension("flags"))
     });
 
-    let mut buffer: Vec<u32> = vec![0; (WIDTH * HEIGHT).try_into().unwrap()];
+    if let Some(flags_path) = &flags_path {
+        chip_8.set_rpl_flags(read_rpl_flags(flags_path)?);
+    }
 
-    let mut window = Window::new(
-        "Test - ESC to exit",
-        (WIDTH * SCALE).try_into().unwrap(),
-        (HEIGHT * SCALE).try_into().unwrap(),
-        WindowOptions::default(),
-    )
-    .unwrap_or_else(|e| {
-        panic!("{}", e);
+    let rom_info = rom_database.lookup(&loaded_rom);
+
+    if let Some(rom_info) = rom_info {
+        info!(
+            "Found {} ({}) in the ROM database",
+            rom_info.title, rom_info.platform
+        );
+        chip_8.quirks = rom_info.quirks;
+    }
+
+    for quirk in &args.quirk {
+        quirk.enable(&mut chip_8.quirks);
+    }
+
+    let high_score_ranges = rom_info.map_or(Vec::new(), |rom_info| rom_info.high_scores.clone());
+    let high_score_path = args
+        .high_score_file
+        .clone()
+        .or_else(|| {
+            rom.as_ref()
+                .map(|rom| Path::new(rom).with_extension("scores"))
+        })
+        .filter(|_| !high_score_ranges.is_empty());
+
+    if let Some(high_score_path) = &high_score_path {
+        match high_scores::restore(&mut chip_8, &high_score_ranges, high_score_path) {
+            Ok(true) => info!("Restored high scores from {}", high_score_path.display()),
+            Ok(false) => {}
+            Err(err) => warn!(
+                "Couldn't restore high scores from {}: {err}",
+                high_score_path.display()
+            ),
+        }
+    }
+
+    let profile = config.profile(loaded_rom.sha1, rom.as_deref().map(Path::new));
+
+    if let Some(profile) = profile {
+        info!("Using the profile for {} from the config file", profile.rom);
+    }
+
+    let keymap = profile.map_or_else(Keymap::default, Profile::keymap);
+
+    let timing = profile
+        .and_then(|profile| profile.timing)
+        .or(config.timing)
+        .unwrap_or_else(|| {
+            TimingModel::Fixed(
+                rom_info
+                    .and_then(|rom_info| rom_info.tickrate)
+                    .map_or(CYCLES_PER_SECOND, |tickrate| tickrate * 60),
+            )
+        });
+    chip_8.set_timing_model(timing);
+
+    // Only an estimate with the VIP timing model, which is close enough for
+    // the rewind history, the buzzer and the stats.
+    let cycles_per_second = timing.cycles_per_second();
+
+    let base_palette = match (args.theme, rom_info.and_then(|rom_info| rom_info.palette)) {
+        (Some(theme), _) => theme.palette(),
+        (None, Some(palette)) => palette,
+        (None, None) => Palette::default(),
+    };
+
+    let palette = Palette {
+        foreground: args.fg.unwrap_or(base_palette.foreground),
+        background: args.bg.unwrap_or(base_palette.background),
+    };
+
+    let rom_name = match (rom_info, &rom) {
+        (Some(rom_info), _) => rom_info.title.clone(),
+        (None, Some(rom)) => Path::new(rom)
+            .file_stem()
+            .map_or_else(|| rom.clone(), |stem| stem.to_string_lossy().into_owned()),
+        (None, None) => "No ROM".to_string(),
+    };
+
+    let buzzer_recorder = args.record_audio.as_ref().map(|_| {
+        chip_8.set_buzzer_recording(true);
+        BuzzerRecorder::default()
     });
 
-    // Limit to max ~60 fps update rate
-    window.set_target_fps(FRAME_HZ as usize);
+    if let Some(seconds) = args.rewind {
+        chip_8.set_history_capacity((seconds * cycles_per_second) as usize);
+        chip_8.set_history_memory_limit(args.rewind_memory.map(|megabytes| megabytes << 20));
+    }
 
-    while window.is_open() && !window.is_key_down(Key::Escape) {
-        let pixel_frame = chip_8_ref_2.lock().unwrap().clone_frame();
+    for name in &args.break_on {
+        chip_8.break_on_instruction(name)?;
+    }
 
-        for (real_pixel, screen_pixel) in buffer.iter_mut().zip(pixel_frame.iter()) {
-            *real_pixel = match screen_pixel {
-                true => 0x00FFFFFF,
-                false => 0,
-            }
+    for (address, tracepoint) in &args.trace {
+        chip_8.add_tracepoint(*address, tracepoint.clone());
+    }
+
+    // Self-modifying code is found from the coverage.
+    chip_8.set_coverage_enabled(args.coverage.is_some() || args.break_on_self_modifying_code);
+    chip_8.set_break_on_self_modifying_code(args.break_on_self_modifying_code);
+
+    if args.protect_writes {
+        chip_8.set_write_protection(WriteProtection::Break);
+    }
+    chip_8.set_watchdog(args.watchdog);
+
+    #[cfg(feature = "script")]
+    if let Some(path) = &args.script {
+        chip_8.set_hooks(Some(Box::new(script::Script::load(path)?)));
+    }
+
+    let netplay = match (args.netplay_host, &args.netplay_connect) {
+        (Some(port), _) => Some(Netplay::host(port, loaded_rom.sha1, args.netplay_delay)?),
+        (None, Some(address)) => Some(Netplay::connect(address.as_str(), loaded_rom.sha1)?),
+        (None, None) => None,
+    };
+
+    // Closing the window has to wake up the emulator thread if it is
+    // waiting on the other player.
+    let netplay_disconnector = netplay.as_ref().map(Netplay::disconnector).transpose()?;
+    let shutting_down = Arc::new(AtomicBool::new(false));
+
+    // Both netplay and replays need CXNN to pick the same numbers every time.
+    let random_seed = netplay.as_ref().map_or_else(rand::random, Netplay::seed);
+    chip_8.set_random_seed(random_seed);
+
+    let replay = args.record_input.as_ref().map(|_| {
+        Replay::new(
+            &chip_8,
+            loaded_rom.sha1,
+            args.load_offset as u16,
+            random_seed,
+        )
+    });
+
+    let event_log = match &args.event_log {
+        Some(path) => {
+            chip_8.set_event_logging(true);
+            let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+            Some(EventLog::new(file, loaded_rom.sha1, random_seed)?)
         }
+        None => None,
+    };
 
-        let current_keycode = chip_8::keycode::get_available_keycode(&window);
+    #[cfg(feature = "remote")]
+    let remote = args.remote.map(remote::Remote::listen).transpose()?;
 
-        // We unwrap here as we want this code to exit if it fails. Real applications may want to handle this in a different way
-        window
-            .update_with_buffer(
-                &buffer,
-                WIDTH.try_into().unwrap(),
-                HEIGHT.try_into().unwrap(),
+    let checkpoints = args
+        .checkpoint_interval
+        .zip(rom.as_ref())
+        .map(|(seconds, rom)| {
+            checkpoint::Checkpoints::new(
+                Path::new(rom),
+                Duration::from_secs(seconds),
+                args.checkpoints as usize,
             )
-            .unwrap();
+        });
+
+    let watcher = rom
+        .as_ref()
+        .filter(|_| args.watch)
+        .map(|rom| watch::Watcher::new(Path::new(rom), watch::POLL_INTERVAL));
+
+    let published_frame = Arc::new(ArcSwap::from_pointee(PublishedFrame::default()));
+    let (tx_frame_finished, rx_frame_finished) =
+        crossbeam_channel::bounded::<FrameFinishedSignal>(1);
+    let (tx_input_latency, rx_input_latency) = crossbeam_channel::unbounded::<Duration>();
+
+    let mut emulator = Emulator {
+        chip_8,
+        buzzer_recorder,
+        replay,
+        event_log,
+        netplay,
+        #[cfg(feature = "remote")]
+        remote,
+        checkpoints,
+        watcher,
+        patches: args.patch.clone(),
+        load_offset: args.load_offset,
+        preserve: args.preserve.clone(),
+        flags_path,
+        turbo_speed: args.turbo,
+        cycles_per_second,
+        published_frame: Arc::clone(&published_frame),
+        input_latency: tx_input_latency,
+        shutting_down: Arc::clone(&shutting_down),
+    };
+
+    let game_loop = std::thread::spawn(move || {
+        let result = emulator.run(&rx_frame_finished, Scheduler::new(60));
+        (emulator, result)
+    });
+
+    let window_result = window::run(
+        &args,
+        WindowSettings {
+            rom_name,
+            resolution,
+            palette,
+            keymap,
+            cycles_per_second,
+            pause_on_focus_loss: config.pause_on_focus_loss,
+        },
+        EmulatorLink {
+            published_frame: &published_frame,
+            signals: &tx_frame_finished,
+            input_latency: &rx_input_latency,
+            is_finished: &|| game_loop.is_finished(),
+        },
+    );
+
+    // Closing the channel lets the emulator thread finish and hand back the
+    // Chip8, unless it is stuck waiting on the other player.
+    shutting_down.store(true, Ordering::Relaxed);
+    drop(tx_frame_finished);
+
+    if let Some(netplay_disconnector) = &netplay_disconnector {
+        netplay_disconnector.disconnect();
+    }
+
+    let Ok((emulator, emulator_result)) = game_loop.join() else {
+        error!("The emulator thread panicked");
+        std::process::exit(1);
+    };
+
+    if let Err(err) = &emulator_result {
+        error!("{err}");
+    }
+
+    let Emulator {
+        chip_8,
+        buzzer_recorder,
+        replay,
+        ..
+    } = emulator;
+
+    if args.stats {
+        print!("{}", chip_8.stats());
+    }
+
+    if let Some(high_score_path) = &high_score_path {
+        high_scores::save(&chip_8, &high_score_ranges, high_score_path)?;
+    }
+
+    if let (Some(path), Some(buzzer_recorder)) = (&args.record_audio, buzzer_recorder) {
+        buzzer_recorder.write_wav(std::io::BufWriter::new(std::fs::File::create(path)?))?;
+    }
+
+    if let (Some(path), Some(replay)) = (&args.record_input, replay) {
+        std::fs::write(path, replay.to_bytes())?;
+    }
+
+    if let (Some(path), Some(coverage)) = (&args.coverage, chip_8.coverage()) {
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+
+        match Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("json") => coverage.write_json(file)?,
+            _ => coverage.write_text(file)?,
+        }
+    }
+
+    // Everything is saved even after an error, as it's most useful then.
+    if emulator_result.is_err() {
+        std::process::exit(1);
+    }
+
+    window_result
+}
+
+/// Shows a menu of the ROMs in `romdir` and returns the one picked, or `None`
+/// if the window was closed first. The choice is saved to the config file.
+fn choose_rom(
+    romdir: &Path,
+    config: &mut Config,
+    scale: u32,
+    palette: &Palette,
+) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+    let mut menu = RomMenu::new(library::scan(romdir)?, config.last_rom.as_deref());
+
+    if menu.is_empty() {
+        return Err(format!("no ROMs found in {}", romdir.display()).into());
+    }
+
+    let size = ((WIDTH * scale) as usize, (HEIGHT * scale) as usize);
+    let mut window = create_window("Select a ROM - ESC to exit", size, false, palette)?;
+    let mut buffer = vec![0; size.0 * size.1];
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        if window.is_key_pressed(Key::Up, KeyRepeat::Yes) {
+            menu.select_previous();
+        }
+
+        if window.is_key_pressed(Key::Down, KeyRepeat::Yes) {
+            menu.select_next();
+        }
+
+        if window.is_key_pressed(Key::Enter, KeyRepeat::No) {
+            let rom = menu.selected().map(Path::to_path_buf);
+
+            config.last_rom = rom.clone();
+            config.save()?;
+
+            return Ok(rom);
+        }
 
-        tx_frame_finished
-            .send(FrameFinishedSignal { current_keycode })
-            .unwrap();
+        menu.render(&mut buffer, size.0, size.1, palette);
+        window.update_with_buffer(&buffer, size.0, size.1)?;
     }
 
-    Ok(())
+    Ok(None)
 }
 
-fn log_pixels_error<E: std::error::Error + 'static>(method_name: &str, err: E) {
-    error!("{method_name}() failed: {err}");
-    if let Some(e) = err.source() {
-        error!("  Caused by: {}", e);
+/// Loads a ROM for one side of the split screen, with the quirks and speed
+/// from the ROM database unless they're given.
+fn split_side(
+    rom: &str,
+    quirks: &[Quirk],
+    speed: Option<u32>,
+    keymap: Keymap,
+    scale: u32,
+) -> Result<split::Side, Box<dyn std::error::Error>> {
+    let mut chip_8 = Chip8::new();
+    chip_8.initialize()?;
+
+    let loaded_rom = chip_8.load_program(read_program(rom)?)?;
+    let rom_info = RomDatabase::embedded().lookup(&loaded_rom).cloned();
+
+    if let Some(rom_info) = &rom_info {
+        chip_8.quirks = rom_info.quirks;
+    }
+
+    for quirk in quirks {
+        quirk.enable(&mut chip_8.quirks);
     }
+
+    let tickrate = rom_info.and_then(|rom_info| rom_info.tickrate);
+    chip_8.set_cycles_per_second(Some(
+        speed
+            .or(tickrate.map(|tickrate| tickrate * 60))
+            .unwrap_or(CYCLES_PER_SECOND),
+    ));
+
+    let label = Path::new(rom).file_stem().map_or_else(
+        || rom.to_string(),
+        |stem| stem.to_string_lossy().into_owned(),
+    );
+
+    Ok(split::Side::new(chip_8, label, keymap, scale as usize))
+}
+
+/// Reads a ROM from disk, assembling it first if it is Octo source code or
+/// extracting it if it is in an archive.
+fn read_program(path: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let extension = std::path::Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+
+    match extension.as_deref() {
+        Some("8o") => Ok(octo::assemble(&std::fs::read_to_string(path)?)?),
+        #[cfg(feature = "zip")]
+        Some("zip") => Ok(archive::extract_zip(std::fs::File::open(path)?)?),
+        #[cfg(feature = "zip")]
+        Some("gz") => Ok(archive::extract_gz(std::fs::File::open(path)?)?),
+        #[cfg(not(feature = "zip"))]
+        Some("zip" | "gz") => Err(format!(
+            "{path} is an archive, but archive support was not enabled (build with --features zip)"
+        )
+        .into()),
+        _ => Ok(std::fs::read(path)?),
+    }
+}
+
+/// Reads a ROM like [`read_program`], then applies `patches` to it in order.
+fn read_patched_program(
+    path: &str,
+    patches: &[String],
+    load_offset: usize,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut program_bytes = read_program(path)?;
+
+    for patch in patches {
+        program_bytes =
+            chip8_core::patch::apply(&program_bytes, &std::fs::read(patch)?, load_offset)?;
+        info!("Applied {patch}");
+    }
+
+    Ok(program_bytes)
+}
+
+/// Reads the RPL user flags saved by an earlier run. A missing file means
+/// nothing was saved yet, and a short one only sets the first flags. With the
+/// `json` feature, files ending in `.json` are read as flags saved by Octo.
+fn read_rpl_flags(path: &Path) -> std::io::Result<[u8; RPL_FLAG_COUNT]> {
+    let mut flags = [0; RPL_FLAG_COUNT];
+
+    #[cfg(feature = "json")]
+    if is_octo_flags(path) {
+        for (flag, octo_flag) in flags.iter_mut().zip(read_octo_flags(path)?) {
+            *flag = octo_flag.unwrap_or(0);
+        }
+
+        return Ok(flags);
+    }
+
+    match std::fs::read(path) {
+        Ok(bytes) => {
+            let count = bytes.len().min(RPL_FLAG_COUNT);
+            flags[..count].copy_from_slice(&bytes[..count]);
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err),
+    }
+
+    Ok(flags)
+}
+
+/// Saves the RPL user flags in the format [`read_rpl_flags`] reads.
+///
+/// Octo keeps more flags than there are RPL user flags, so the ones after
+/// them are written back as they were in the file.
+fn write_rpl_flags(path: &Path, flags: &[u8; RPL_FLAG_COUNT]) -> std::io::Result<()> {
+    #[cfg(feature = "json")]
+    if is_octo_flags(path) {
+        let mut octo_flags = read_octo_flags(path)?;
+
+        for (octo_flag, &flag) in octo_flags.iter_mut().zip(flags) {
+            *octo_flag = Some(flag);
+        }
+
+        return std::fs::write(path, chip8_core::rpl::flags_to_octo_json(&octo_flags));
+    }
+
+    std::fs::write(path, flags)
+}
+
+/// Reads all of the flags in an Octo flags file, or none if it doesn't exist.
+#[cfg(feature = "json")]
+fn read_octo_flags(path: &Path) -> std::io::Result<[Option<u8>; chip8_core::rpl::OCTO_FLAG_COUNT]> {
+    match std::fs::read_to_string(path) {
+        Ok(json) => chip8_core::rpl::flags_from_octo_json(&json)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            Ok([None; chip8_core::rpl::OCTO_FLAG_COUNT])
+        }
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(feature = "json")]
+fn is_octo_flags(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "json")
+}
+
+/// Creates the emulator window. Fullscreen windows are borderless and cover
+/// the top left `size` pixels of the screen.
+fn create_window(
+    title: &str,
+    size: (usize, usize),
+    fullscreen: bool,
+    palette: &Palette,
+) -> minifb::Result<Window> {
+    let mut window = Window::new(
+        title,
+        size.0,
+        size.1,
+        WindowOptions {
+            borderless: fullscreen,
+            title: !fullscreen,
+            topmost: fullscreen,
+            resize: true,
+            scale_mode: ScaleMode::Center,
+            ..WindowOptions::default()
+        },
+    )?;
+
+    if fullscreen {
+        window.set_position(0, 0);
+    }
+
+    let background = palette.background;
+    window.set_background_color(
+        (background >> 16) as u8,
+        (background >> 8) as u8,
+        background as u8,
+    );
+
+    // Limit to max ~60 fps update rate
+    window.set_target_fps(FRAME_HZ as usize);
+
+    Ok(window)
 }
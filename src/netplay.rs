@@ -0,0 +1,256 @@
+//! Two players sharing one keypad over TCP, each running their own copy of
+//! the emulator.
+//!
+//! Only key presses are sent. Both emulators run the same program with the
+//! same random seed, and every frame gets the same keys on both sides, so they
+//! stay in step without ever sending the screen. A key press is applied a few
+//! frames after it happens (the input delay), which gives it time to reach the
+//! other player. A frame can't run until the other player's keys for it have
+//! arrived, so a slow connection slows both players down rather than letting
+//! them drift apart.
+//!
+//! Both players' keys are held down on the shared keypad together.
+
+use std::collections::VecDeque;
+use std::io::{self, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+
+use tracing::info;
+
+use chip8_core::Keys;
+
+/// Sent first by both sides, followed by the ROM's SHA-1, the random seed and
+/// the input delay.
+const MAGIC: &[u8; 4] = b"C8NP";
+const HELLO_SIZE: usize = MAGIC.len() + 20 + 8 + 1;
+
+/// What a player does in one frame.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Input {
+    /// The keys held down.
+    pub keys: Keys,
+    /// True if the program should be restarted before the frame.
+    pub restart: bool,
+}
+
+/// A connection to the other player.
+#[derive(Debug)]
+pub struct Netplay {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    seed: u64,
+    /// The inputs sent to the other player that haven't been applied here yet.
+    pending: VecDeque<Input>,
+}
+
+impl Netplay {
+    /// Waits for the other player to connect on `port`. The host picks the
+    /// random seed, and applies key presses `delay` frames after they happen.
+    pub fn host(port: u16, rom_sha1: [u8; 20], delay: u8) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        info!("Waiting for the other player on port {port}");
+
+        let (stream, address) = listener.accept()?;
+        info!("{address} joined");
+
+        Self::start(stream, true, rom_sha1, rand::random(), delay)
+    }
+
+    /// Joins the session hosted at `address`.
+    pub fn connect(address: impl ToSocketAddrs, rom_sha1: [u8; 20]) -> io::Result<Self> {
+        let stream = TcpStream::connect(address)?;
+        info!("Joined {}", stream.peer_addr()?);
+
+        Self::start(stream, false, rom_sha1, 0, 0)
+    }
+
+    /// Exchanges hellos, checking that both players have the same ROM.
+    fn start(
+        stream: TcpStream,
+        host: bool,
+        rom_sha1: [u8; 20],
+        seed: u64,
+        delay: u8,
+    ) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+
+        let mut hello = MAGIC.to_vec();
+        hello.extend(rom_sha1);
+        hello.extend(seed.to_le_bytes());
+        hello.push(delay);
+        writer.write_all(&hello)?;
+
+        let mut theirs = [0; HELLO_SIZE];
+        reader.read_exact(&mut theirs)?;
+
+        if theirs[..4] != *MAGIC {
+            return Err(invalid_data("the other side isn't a netplay session"));
+        }
+
+        if theirs[4..24] != rom_sha1 {
+            return Err(invalid_data("the other player has a different ROM"));
+        }
+
+        // The guest uses the host's settings.
+        let (seed, delay) = match host {
+            true => (seed, delay),
+            false => (
+                u64::from_le_bytes(theirs[24..32].try_into().unwrap()),
+                theirs[32],
+            ),
+        };
+
+        let mut netplay = Self {
+            reader,
+            writer,
+            seed,
+            pending: VecDeque::new(),
+        };
+
+        // Nothing can have been pressed in time for the first frames.
+        for _ in 0..delay {
+            netplay.send(Input::default())?;
+        }
+
+        Ok(netplay)
+    }
+
+    /// The random seed both emulators have to use.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns a handle that ends the connection from another thread, waking
+    /// up an [`Self::exchange`] that is waiting on the other player.
+    pub fn disconnector(&self) -> io::Result<Disconnector> {
+        Ok(Disconnector(self.writer.try_clone()?))
+    }
+
+    /// Sends this frame's input, then waits for the other player's input for
+    /// the frame that is due to run. Returns what both players did in that
+    /// frame.
+    pub fn exchange(&mut self, input: Input) -> io::Result<Input> {
+        self.send(input)?;
+
+        let local = self.pending.pop_front().expect("an input was just sent");
+
+        let mut message = [0; 3];
+        self.reader.read_exact(&mut message)?;
+
+        let remote = Input {
+            keys: Keys(u16::from_le_bytes([message[0], message[1]])),
+            restart: message[2] != 0,
+        };
+
+        Ok(Input {
+            keys: local.keys | remote.keys,
+            restart: local.restart || remote.restart,
+        })
+    }
+
+    fn send(&mut self, input: Input) -> io::Result<()> {
+        let [low, high] = input.keys.0.to_le_bytes();
+        self.writer.write_all(&[low, high, input.restart as u8])?;
+        self.pending.push_back(input);
+
+        Ok(())
+    }
+}
+
+/// Ends a netplay connection. See [`Netplay::disconnector`].
+#[derive(Debug)]
+pub struct Disconnector(TcpStream);
+
+impl Disconnector {
+    /// Closes the connection. Anything still using it gets an error.
+    pub fn disconnect(&self) {
+        // It doesn't matter if the other player already left.
+        let _ = self.0.shutdown(Shutdown::Both);
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test_super {
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    use super::{Input, Netplay};
+    use chip8_core::Keys;
+
+    /// Plays `keys` one per frame, returning the keys each frame ran with.
+    fn play(mut netplay: Netplay, keys: [u16; 4]) -> Vec<u16> {
+        keys.iter()
+            .map(|&keys| {
+                let input = Input {
+                    keys: Keys(keys),
+                    restart: false,
+                };
+                netplay.exchange(input).unwrap().keys.0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn both_players_see_the_same_keys() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let host = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let netplay = Netplay::start(stream, true, [1; 20], 42, 1).unwrap();
+            play(netplay, [0b0010, 0, 0b1000, 0])
+        });
+
+        let guest =
+            Netplay::start(TcpStream::connect(address).unwrap(), false, [1; 20], 0, 0).unwrap();
+        assert_eq!(guest.seed(), 42);
+
+        let guest = play(guest, [0b0100, 0b0001, 0, 0]);
+        let host = host.join().unwrap();
+
+        // Each key arrives a frame late, and both players' keys are held.
+        assert_eq!(host, [0, 0b0110, 0b0001, 0b1000]);
+        assert_eq!(guest, host);
+
+        // Different ROMs can't play together.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let host = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            Netplay::start(stream, true, [1; 20], 42, 1).is_err()
+        });
+
+        assert!(
+            Netplay::start(TcpStream::connect(address).unwrap(), false, [2; 20], 0, 0).is_err()
+        );
+        assert!(host.join().unwrap());
+    }
+
+    #[test]
+    fn disconnecting_wakes_up_a_waiting_exchange() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        // The host never sends anything, so the guest would wait forever.
+        let host = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            Netplay::start(stream, true, [1; 20], 42, 0).unwrap()
+        });
+
+        let mut guest =
+            Netplay::start(TcpStream::connect(address).unwrap(), false, [1; 20], 0, 0).unwrap();
+        let _host = host.join().unwrap();
+
+        let disconnector = guest.disconnector().unwrap();
+        let waiting = thread::spawn(move || guest.exchange(Input::default()).is_err());
+
+        disconnector.disconnect();
+        assert!(waiting.join().unwrap());
+    }
+}
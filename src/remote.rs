@@ -0,0 +1,370 @@
+//! A WebSocket server for watching and controlling the emulator from another
+//! machine, like a browser showing the screen of an emulator running on a
+//! Raspberry Pi.
+//!
+//! Clients are sent JSON text messages whenever the screen or the registers
+//! change:
+//!
+//! - `{"type": "frame", "rows": [...]}`, with each row of the screen (32 of
+//!   them for most programs) as one hex digit per 4 pixels, where the most
+//!   significant bit is the leftmost pixel.
+//! - `{"type": "state", "pc": 512, "i": 0, "stack": [...], "v": [...],
+//!   "dt": 0, "st": 0, "cycles": 0, "stopped": null}`, where `stack` is the
+//!   return addresses from the bottom up and `stopped` is
+//!   `{"address": 676, "reason": "breakpoint"}` while the emulator is stopped.
+//!
+//! And can send:
+//!
+//! - `{"type": "key", "key": 5}` to hold down a key, or `"key": null` to let go.
+//! - `{"type": "pause"}`, `{"type": "resume"}` and `{"type": "step"}`.
+//! - `{"type": "breakpoints", "addresses": [676]}`, replacing the breakpoints.
+//! - `{"type": "restart"}`.
+//! - `{"type": "read_memory", "address": 512, "count": 16}`, which is answered
+//!   with `{"type": "memory", "address": 512, "data": [...]}`.
+//!
+//! Anything that can't be understood is answered with
+//! `{"type": "error", "message": "..."}`.
+
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+
+use arc_swap::ArcSwapOption;
+use crossbeam_channel::{Receiver, Sender};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+use tungstenite::Message;
+
+use chip8_core::debugger::Stop;
+use chip8_core::save_state::SaveState;
+use chip8_core::{Chip8, Frame, Keys};
+
+/// How often each client is checked for changes to send.
+const POLL_INTERVAL: Duration = Duration::from_millis(1000 / 60);
+
+/// The emulator's side of the server.
+pub struct Remote {
+    snapshot: Arc<ArcSwapOption<Snapshot>>,
+    rx_command: Receiver<Command>,
+    /// The keys held down by clients.
+    keys: Keys,
+}
+
+/// What clients are sent, published by the emulator thread.
+#[derive(Debug)]
+struct Snapshot {
+    state: SaveState,
+    total_cycles: u64,
+    stopped: Option<Stop>,
+}
+
+/// Something a client asked the emulator to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Command {
+    Key(Option<u8>),
+    Pause,
+    Resume,
+    Step,
+    Breakpoints(Vec<u16>),
+    Restart,
+}
+
+/// A message sent by a client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ClientMessage {
+    Command(Command),
+    /// Memory is read from the last snapshot, so it doesn't need the emulator.
+    ReadMemory {
+        address: usize,
+        count: usize,
+    },
+}
+
+impl Remote {
+    /// Starts accepting clients on `address`. Each client is served on its
+    /// own thread.
+    pub fn listen(address: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        info!("Listening for remote clients on {address}");
+
+        let snapshot = Arc::new(ArcSwapOption::empty());
+        let (tx_command, rx_command) = crossbeam_channel::unbounded();
+
+        let clients_snapshot = Arc::clone(&snapshot);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let snapshot = Arc::clone(&clients_snapshot);
+                let tx_command = tx_command.clone();
+
+                std::thread::spawn(move || {
+                    if let Err(err) = serve_client(stream, &snapshot, &tx_command) {
+                        warn!("Lost a remote client: {err}");
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            snapshot,
+            rx_command,
+            keys: Keys::default(),
+        })
+    }
+
+    /// Carries out the commands clients sent since the last call.
+    pub fn apply_commands(&mut self, chip_8: &mut Chip8) {
+        for command in self.rx_command.try_iter() {
+            match command {
+                Command::Key(key) => {
+                    self.keys = Keys::default();
+
+                    if let Some(key) = key {
+                        self.keys.press(key);
+                    }
+                }
+                Command::Pause => chip_8.pause(),
+                Command::Resume => chip_8.resume(),
+                Command::Step => chip_8.step(),
+                Command::Breakpoints(addresses) => chip_8.set_breakpoints(addresses),
+                Command::Restart => chip_8.needs_program_restart = true,
+            }
+        }
+    }
+
+    /// The keys clients are holding down.
+    pub fn keys(&self) -> Keys {
+        self.keys
+    }
+
+    /// Sends the screen and registers to every client.
+    pub fn publish(&self, chip_8: &Chip8) {
+        self.snapshot.store(Some(Arc::new(Snapshot {
+            state: chip_8.save_state(),
+            total_cycles: chip_8.stats().total_cycles,
+            stopped: chip_8.stopped(),
+        })));
+    }
+}
+
+fn serve_client(
+    stream: TcpStream,
+    snapshot: &ArcSwapOption<Snapshot>,
+    tx_command: &Sender<Command>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let address = stream.peer_addr()?;
+    let mut socket = tungstenite::accept(stream).map_err(|err| err.to_string())?;
+    info!("Remote client connected from {address}");
+
+    // Reads time out so that changes can be sent while the client is quiet.
+    socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+
+    let mut sent_frame = None;
+    let mut sent_state = None;
+
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => match parse_message(&text) {
+                Ok(ClientMessage::Command(command)) => {
+                    // The emulator has gone away, so there's nothing left to serve.
+                    if tx_command.send(command).is_err() {
+                        return Ok(());
+                    }
+                }
+                Ok(ClientMessage::ReadMemory { address, count }) => {
+                    let snapshot = snapshot.load();
+                    let memory = snapshot
+                        .as_ref()
+                        .map_or(&[][..], |snapshot| &snapshot.state.memory);
+
+                    socket.send(Message::Text(memory_message(memory, address, count)))?;
+                }
+                Err(message) => socket.send(Message::Text(
+                    json!({ "type": "error", "message": message }).to_string(),
+                ))?,
+            },
+            Ok(Message::Close(_)) | Err(tungstenite::Error::ConnectionClosed) => {
+                info!("Remote client {address} disconnected");
+                // Sends our half of the closing handshake.
+                let _ = socket.flush();
+                return Ok(());
+            }
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(err))
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        let Some(snapshot) = snapshot.load_full() else {
+            continue;
+        };
+
+        for (message, sent) in [
+            (frame_message(&snapshot.state.frame), &mut sent_frame),
+            (state_message(&snapshot), &mut sent_state),
+        ] {
+            if sent.as_ref() != Some(&message) {
+                socket.send(Message::Text(message.clone()))?;
+                *sent = Some(message);
+            }
+        }
+    }
+}
+
+fn parse_message(text: &str) -> Result<ClientMessage, String> {
+    let message: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+
+    let command = match message["type"].as_str().unwrap_or_default() {
+        "key" => Command::Key(match &message["key"] {
+            Value::Null => None,
+            key => Some(
+                key.as_u64()
+                    .filter(|&key| key < 16)
+                    .ok_or("keys go from 0 to 15")? as u8,
+            ),
+        }),
+        "pause" => Command::Pause,
+        "resume" => Command::Resume,
+        "step" => Command::Step,
+        "breakpoints" => Command::Breakpoints(
+            message["addresses"]
+                .as_array()
+                .ok_or("expected a list of addresses")?
+                .iter()
+                .map(|address| {
+                    address
+                        .as_u64()
+                        .filter(|&address| address < 0x1000)
+                        .map(|address| address as u16)
+                        .ok_or_else(|| format!("invalid address {address}"))
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        "restart" => Command::Restart,
+        "read_memory" => {
+            return Ok(ClientMessage::ReadMemory {
+                address: message["address"].as_u64().unwrap_or(0) as usize,
+                count: message["count"].as_u64().unwrap_or(0) as usize,
+            })
+        }
+        kind => return Err(format!("unknown message type {kind:?}")),
+    };
+
+    Ok(ClientMessage::Command(command))
+}
+
+/// The reply to a `read_memory` message. Reads past the end of memory are cut
+/// short, since the address and count come straight from the client.
+fn memory_message(memory: &[u8], address: usize, count: usize) -> String {
+    let start = address.min(memory.len());
+    let end = start.saturating_add(count).min(memory.len());
+
+    json!({ "type": "memory", "address": start, "data": &memory[start..end] }).to_string()
+}
+
+fn frame_message(frame: &Frame) -> String {
+    let rows: Vec<String> = frame
+        .rows()
+        .iter()
+        .map(|row| format!("{row:0digits$X}", digits = frame.width() / 4))
+        .collect();
+
+    json!({ "type": "frame", "rows": rows }).to_string()
+}
+
+fn state_message(snapshot: &Snapshot) -> String {
+    let state = &snapshot.state;
+
+    json!({
+        "type": "state",
+        "pc": state.program_counter,
+        "i": state.index_register,
+        "stack": state.stack,
+        "v": state.registers,
+        "dt": state.delay_timer,
+        "st": state.sound_timer,
+        "cycles": snapshot.total_cycles,
+        "stopped": snapshot.stopped.map(|stop| json!({
+            "address": stop.address,
+            "reason": stop.reason.to_string(),
+        })),
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{frame_message, memory_message, parse_message, ClientMessage, Command};
+    use crate::Chip8;
+
+    #[test]
+    fn messages_are_parsed() {
+        assert_eq!(
+            parse_message(r#"{"type": "key", "key": 10}"#),
+            Ok(ClientMessage::Command(Command::Key(Some(10))))
+        );
+        assert_eq!(
+            parse_message(r#"{"type": "key", "key": null}"#),
+            Ok(ClientMessage::Command(Command::Key(None)))
+        );
+        assert_eq!(
+            parse_message(r#"{"type": "breakpoints", "addresses": [512, 676]}"#),
+            Ok(ClientMessage::Command(Command::Breakpoints(vec![
+                0x200, 0x2A4
+            ])))
+        );
+        assert_eq!(
+            parse_message(r#"{"type": "read_memory", "address": 512, "count": 2}"#),
+            Ok(ClientMessage::ReadMemory {
+                address: 0x200,
+                count: 2
+            })
+        );
+
+        assert_eq!(
+            parse_message(
+                r#"{"type": "read_memory", "address": 512, "count": 18446744073709551615}"#
+            ),
+            Ok(ClientMessage::ReadMemory {
+                address: 0x200,
+                count: usize::MAX
+            })
+        );
+
+        assert!(parse_message(r#"{"type": "key", "key": 16}"#).is_err());
+        assert!(parse_message(r#"{"type": "jump"}"#).is_err());
+    }
+
+    #[test]
+    fn memory_reads_stop_at_the_end_of_memory() {
+        let memory = [1, 2, 3, 4];
+
+        assert_eq!(
+            memory_message(&memory, 2, usize::MAX),
+            r#"{"address":2,"data":[3,4],"type":"memory"}"#
+        );
+        assert_eq!(
+            memory_message(&memory, usize::MAX, 2),
+            r#"{"address":4,"data":[],"type":"memory"}"#
+        );
+    }
+
+    #[test]
+    fn frames_are_sent_as_hex_rows() {
+        // Draws the font's 0 in the top left corner.
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8.load_program(vec![0xA0, 0x50, 0xD0, 0x05]).unwrap();
+
+        for _ in 0..2 {
+            chip_8.cycle().unwrap();
+        }
+
+        let message = frame_message(&chip_8.save_state().frame);
+
+        assert!(message.starts_with(r#"{"rows":["F000000000000000","9000000000000000""#));
+    }
+}
@@ -0,0 +1,576 @@
+//! Post-processing stages applied to frames before they are presented.
+
+use chip8_core::display::Resolution;
+use chip8_core::palette::Palette;
+use chip8_core::{Frame, Keys, FONT_SET};
+
+use crate::keymap::Keymap;
+
+/// Any pixel dimmer than this is treated as fully off.
+const MIN_INTENSITY: f32 = 1.0 / 255.0;
+
+/// Emulates the persistence of a CRT's phosphor by fading pixels out over
+/// a few frames instead of turning them off instantly. This hides most of
+/// the flicker caused by games erasing and redrawing sprites with XOR.
+#[derive(Debug)]
+pub struct PhosphorDecay {
+    /// The fraction of a pixel's brightness that is lost each frame.
+    decay: f32,
+    /// The brightness of each pixel, from 0.0 (off) to 1.0 (on).
+    intensity: Vec<f32>,
+    width: usize,
+}
+
+impl PhosphorDecay {
+    /// Creates a filter that removes `decay` of a pixel's brightness each
+    /// frame after it is turned off, for frames of the given size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `decay` isn't one [`check_decay`] accepts.
+    pub fn new(decay: f32, resolution: Resolution) -> Self {
+        if let Err(err) = check_decay(decay) {
+            panic!("{err}");
+        }
+
+        Self {
+            decay,
+            intensity: vec![0.0; resolution.width * resolution.height],
+            width: resolution.width,
+        }
+    }
+
+    /// Advances the fade by one frame and writes the whole frame into `buffer`.
+    ///
+    /// Returns true if any pixel changed brightness, meaning the buffer
+    /// needs to be presented again.
+    pub fn apply(&mut self, frame: &Frame, buffer: &mut [u32], palette: &Palette) -> bool {
+        let mut changed = false;
+
+        for (address, (intensity, real_pixel)) in
+            self.intensity.iter_mut().zip(buffer.iter_mut()).enumerate()
+        {
+            let x = address % self.width;
+            let y = address / self.width;
+
+            let new_intensity = match frame.pixel(x, y) {
+                true => 1.0,
+                false => match *intensity * (1.0 - self.decay) {
+                    faded if faded < MIN_INTENSITY => 0.0,
+                    faded => faded,
+                },
+            };
+
+            if new_intensity != *intensity {
+                changed = true;
+            }
+
+            *intensity = new_intensity;
+            *real_pixel = blend(palette.background, palette.foreground, new_intensity);
+        }
+
+        changed
+    }
+}
+
+/// Returns `decay` if it is above 0.0 and at most 1.0. With no decay at all,
+/// pixels would never go out.
+pub fn check_decay(decay: f32) -> Result<f32, String> {
+    match decay > 0.0 && decay <= 1.0 {
+        true => Ok(decay),
+        false => Err(format!(
+            "expected a phosphor decay above 0.0 and at most 1.0, got {decay}"
+        )),
+    }
+}
+
+/// Linearly interpolates each channel of two `0RGB` colors, where an
+/// `amount` of 0.0 is `from` and 1.0 is `to`.
+fn blend(from: u32, to: u32, amount: f32) -> u32 {
+    let mut color = 0;
+
+    for shift in [16, 8, 0] {
+        let from_channel = ((from >> shift) & 0xFF) as f32;
+        let to_channel = ((to >> shift) & 0xFF) as f32;
+        let channel = from_channel + (to_channel - from_channel) * amount;
+
+        color |= (channel.round() as u32) << shift;
+    }
+
+    color
+}
+
+/// Retro display effects that can be layered on top of the upscaled frame.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    /// Darkens the bottom line of every pixel row, like the gaps between a CRT's scanlines.
+    Scanlines,
+    /// Darkens the edges of every pixel so the individual pixels stand out.
+    Grid,
+    /// Lets bright pixels glow slightly onto their neighbours.
+    Bloom,
+}
+
+/// Ways of showing that the buzzer is sounding, for when it can't be heard.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisualBell {
+    /// Draws a border around the screen in the foreground color.
+    Border,
+    /// Swaps the foreground and background colors.
+    Invert,
+}
+
+/// How far the picture is turned clockwise, for screens that are mounted
+/// sideways or upside down.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    /// Upright.
+    #[default]
+    #[value(name = "0")]
+    None,
+    /// A quarter turn clockwise.
+    #[value(name = "90")]
+    Clockwise,
+    /// Upside down.
+    #[value(name = "180")]
+    HalfTurn,
+    /// A quarter turn anticlockwise.
+    #[value(name = "270")]
+    Anticlockwise,
+}
+
+impl Rotation {
+    /// Returns the size of a `width` by `height` picture once it is turned.
+    pub fn size(self, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Rotation::None | Rotation::HalfTurn => (width, height),
+            Rotation::Clockwise | Rotation::Anticlockwise => (height, width),
+        }
+    }
+
+    /// Turns `source`, which is `width` by `height` pixels, into `output`,
+    /// which ends up [`Self::size`] pixels.
+    pub fn apply(self, source: &[u32], width: usize, height: usize, output: &mut Vec<u32>) {
+        let (output_width, output_height) = self.size(width, height);
+        output.resize(output_width * output_height, 0);
+
+        for (y, row) in output.chunks_exact_mut(output_width).enumerate() {
+            for (x, real_pixel) in row.iter_mut().enumerate() {
+                let (source_x, source_y) = match self {
+                    Rotation::None => (x, y),
+                    Rotation::Clockwise => (y, height - 1 - x),
+                    Rotation::HalfTurn => (width - 1 - x, height - 1 - y),
+                    Rotation::Anticlockwise => (width - 1 - y, x),
+                };
+
+                *real_pixel = source[source_y * width + source_x];
+            }
+        }
+    }
+}
+
+/// Draws a `thickness` pixel border around the edge of `buffer`, which is
+/// `width` by `height` pixels.
+pub fn draw_border(buffer: &mut [u32], width: usize, height: usize, thickness: usize, color: u32) {
+    for (y, row) in buffer.chunks_exact_mut(width).take(height).enumerate() {
+        if y < thickness || y >= height.saturating_sub(thickness) {
+            row.fill(color);
+            continue;
+        }
+
+        let edge = thickness.min(width);
+        row[..edge].fill(color);
+        row[width - edge..].fill(color);
+    }
+}
+
+/// Upscales frames by an integer factor and applies [`Effect`]s to them.
+///
+/// The effects need more than one real pixel per CHIP-8 pixel to be visible,
+/// so we do the upscaling ourselves instead of leaving it to the window.
+#[derive(Debug)]
+pub struct CrtFilter {
+    effects: Vec<Effect>,
+    scale: usize,
+    /// The size of the source frame.
+    resolution: Resolution,
+    /// The source frame after bloom has been applied.
+    bloomed: Vec<u32>,
+    /// The upscaled frame that gets presented.
+    output: Vec<u32>,
+}
+
+impl CrtFilter {
+    /// Creates a filter that upscales frames of the given size by `scale`
+    /// and applies `effects` in order.
+    pub fn new(effects: Vec<Effect>, scale: usize, resolution: Resolution) -> Self {
+        let pixels = resolution.width * resolution.height;
+
+        Self {
+            effects,
+            scale,
+            resolution,
+            bloomed: vec![0; pixels],
+            output: vec![0; pixels * scale * scale],
+        }
+    }
+
+    /// The factor frames are upscaled by.
+    pub fn scale(&self) -> usize {
+        self.scale
+    }
+
+    /// Changes the factor frames are upscaled by.
+    pub fn set_scale(&mut self, scale: usize) {
+        self.scale = scale;
+        self.output = vec![0; self.width() * self.height()];
+    }
+
+    /// The width of the upscaled frame.
+    pub fn width(&self) -> usize {
+        self.resolution.width * self.scale
+    }
+
+    /// The height of the upscaled frame.
+    pub fn height(&self) -> usize {
+        self.resolution.height * self.scale
+    }
+
+    /// Upscales `source` (laid out as `location = width*y + x`) and applies the
+    /// effects, returning a frame of [`Self::width`] by [`Self::height`] pixels.
+    pub fn apply(&mut self, source: &[u32]) -> &mut [u32] {
+        let source = match self.effects.contains(&Effect::Bloom) {
+            true => {
+                bloom(source, &mut self.bloomed, self.resolution.width);
+                &self.bloomed
+            }
+            false => source,
+        };
+
+        let scale = self.scale;
+        let width = self.resolution.width;
+        let scaled_width = width * scale;
+
+        for (scaled_y, scaled_row) in self.output.chunks_exact_mut(scaled_width).enumerate() {
+            let y = scaled_y / scale;
+            let is_last_line = scaled_y % scale == scale - 1;
+
+            for (scaled_x, real_pixel) in scaled_row.iter_mut().enumerate() {
+                let x = scaled_x / scale;
+                let is_last_column = scaled_x % scale == scale - 1;
+
+                let mut color = source[y * width + x];
+
+                // With a scale of 1 there are no lines to darken.
+                if scale > 1 {
+                    for effect in &self.effects {
+                        color = match effect {
+                            Effect::Scanlines if is_last_line => dim(color, 0.5),
+                            Effect::Grid if is_last_line || is_last_column => dim(color, 0.75),
+                            _ => color,
+                        };
+                    }
+                }
+
+                *real_pixel = color;
+            }
+        }
+
+        &mut self.output
+    }
+}
+
+/// Adds a quarter of the average color of each pixel's 8 neighbours onto it.
+fn bloom(source: &[u32], output: &mut [u32], width: usize) {
+    let width = width as isize;
+    let height = source.len() as isize / width;
+
+    for (address, real_pixel) in output.iter_mut().enumerate() {
+        let x = address as isize % width;
+        let y = address as isize / width;
+
+        let mut glow = [0.0_f32; 3];
+
+        for (dx, dy) in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy))) {
+            let (nx, ny) = (x + dx, y + dy);
+
+            if (dx, dy) == (0, 0) || !(0..width).contains(&nx) || !(0..height).contains(&ny) {
+                continue;
+            }
+
+            let neighbour = source[(ny * width + nx) as usize];
+
+            for (channel, shift) in glow.iter_mut().zip([16, 8, 0]) {
+                *channel += ((neighbour >> shift) & 0xFF) as f32 / 8.0 * 0.25;
+            }
+        }
+
+        let mut color = 0;
+
+        for (channel, shift) in glow.iter().zip([16, 8, 0]) {
+            let base = ((source[address] >> shift) & 0xFF) as f32;
+            color |= ((base + channel).min(255.0).round() as u32) << shift;
+        }
+
+        *real_pixel = color;
+    }
+}
+
+/// Scales the brightness of an `0RGB` color by `amount` (from 0.0 to 1.0).
+fn dim(color: u32, amount: f32) -> u32 {
+    blend(0, color, amount)
+}
+
+/// Glyphs for the overlay and menus that aren't hex digits, in the same 4x5
+/// format as the CHIP-8 font set.
+const EXTRA_GLYPHS: [(char, [u8; 5]); 28] = [
+    ('G', [0xF0, 0x80, 0xB0, 0x90, 0xF0]),
+    ('H', [0x90, 0x90, 0xF0, 0x90, 0x90]),
+    ('I', [0xE0, 0x40, 0x40, 0x40, 0xE0]),
+    ('J', [0x10, 0x10, 0x10, 0x90, 0x60]),
+    ('K', [0x90, 0xA0, 0xC0, 0xA0, 0x90]),
+    ('L', [0x80, 0x80, 0x80, 0x80, 0xF0]),
+    ('M', [0x90, 0xF0, 0xF0, 0x90, 0x90]),
+    ('N', [0x90, 0xD0, 0xB0, 0x90, 0x90]),
+    ('O', [0x60, 0x90, 0x90, 0x90, 0x60]),
+    ('P', [0xE0, 0x90, 0xE0, 0x80, 0x80]),
+    ('Q', [0x60, 0x90, 0x90, 0xB0, 0x70]),
+    ('R', [0xE0, 0x90, 0xE0, 0xA0, 0x90]),
+    ('S', [0xF0, 0x80, 0xF0, 0x10, 0xF0]),
+    ('T', [0xE0, 0x40, 0x40, 0x40, 0x40]),
+    ('U', [0x90, 0x90, 0x90, 0x90, 0xF0]),
+    ('V', [0x90, 0x90, 0x90, 0x60, 0x60]),
+    ('W', [0x90, 0x90, 0xF0, 0xF0, 0x90]),
+    ('X', [0x90, 0x90, 0x60, 0x90, 0x90]),
+    ('Y', [0xA0, 0xA0, 0x40, 0x40, 0x40]),
+    ('Z', [0xF0, 0x10, 0x60, 0x80, 0xF0]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x40]),
+    (',', [0x00, 0x00, 0x00, 0x40, 0x80]),
+    (';', [0x00, 0x40, 0x00, 0x40, 0x80]),
+    ('/', [0x10, 0x20, 0x20, 0x40, 0x80]),
+    ('-', [0x00, 0x00, 0xF0, 0x00, 0x00]),
+    ('_', [0x00, 0x00, 0x00, 0x00, 0xF0]),
+    ('>', [0x80, 0x40, 0x20, 0x40, 0x80]),
+    (' ', [0x00, 0x00, 0x00, 0x00, 0x00]),
+];
+
+/// Returns the 4x5 glyph for a character, using the CHIP-8 font for hex digits.
+fn glyph(character: char) -> Option<[u8; 5]> {
+    if let Some(digit) = character.to_digit(16) {
+        let start = digit as usize * 5;
+        return FONT_SET[start..start + 5].try_into().ok();
+    }
+
+    EXTRA_GLYPHS
+        .iter()
+        .find(|(extra, _)| *extra == character.to_ascii_uppercase())
+        .map(|(_, glyph)| *glyph)
+}
+
+/// Draws lines of text into the top left corner of `buffer` (which is `width`
+/// pixels wide), with each font pixel drawn as a `scale` by `scale` square.
+/// Characters without a glyph are skipped.
+pub fn draw_text(buffer: &mut [u32], width: usize, lines: &[String], scale: usize, color: u32) {
+    // Each glyph is 4x5, with a 1 pixel gap around it.
+    let (advance, line_height) = (5 * scale, 6 * scale);
+
+    for (line_number, line) in lines.iter().enumerate() {
+        for (column, character) in line.chars().enumerate() {
+            let left = scale + column * advance;
+            let top = scale + line_number * line_height;
+
+            draw_glyph(buffer, width, character, (left, top), scale, color);
+        }
+    }
+}
+
+/// The keypad as it is laid out on the COSMAC VIP.
+const KEYPAD: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// Draws the keypad into the bottom right corner of `buffer`, which is
+/// `width` by `height` pixels. Each key shows its keypad key above the
+/// keyboard key that presses it in `keymap`, and the keys held down in
+/// `keys` are filled in.
+pub fn draw_keypad(
+    buffer: &mut [u32],
+    (width, height): (usize, usize),
+    keymap: &Keymap,
+    keys: Keys,
+    scale: usize,
+    palette: &Palette,
+) {
+    // Each key fits two glyphs above each other, with a border around it
+    // that is shared with the keys next to it.
+    let (key_width, key_height) = (8 * scale, 14 * scale);
+    let left = width.saturating_sub(4 * key_width + 2 * scale);
+    let top = height.saturating_sub(4 * key_height + 2 * scale);
+
+    // The borders are what is left of this once the keys that aren't held
+    // are cleared.
+    let size = (width, height);
+    let grid = (4 * key_width + scale, 4 * key_height + scale);
+    fill_rect(buffer, size, (left, top), grid, palette.foreground);
+
+    for (row, keycodes) in KEYPAD.iter().enumerate() {
+        for (column, &keycode) in keycodes.iter().enumerate() {
+            let (left, top) = (left + column * key_width, top + row * key_height);
+
+            let color = match keys.is_down(keycode) {
+                true => palette.background,
+                false => {
+                    let inside = (key_width - scale, key_height - scale);
+                    fill_rect(
+                        buffer,
+                        size,
+                        (left + scale, top + scale),
+                        inside,
+                        palette.background,
+                    );
+                    palette.foreground
+                }
+            };
+
+            let label = char::from_digit(keycode.into(), 16).unwrap_or(' ');
+            draw_glyph(
+                buffer,
+                width,
+                label,
+                (left + 2 * scale, top + 2 * scale),
+                scale,
+                color,
+            );
+
+            if let Some(label) = keymap.label(keycode) {
+                draw_glyph(
+                    buffer,
+                    width,
+                    label,
+                    (left + 2 * scale, top + 8 * scale),
+                    scale,
+                    color,
+                );
+            }
+        }
+    }
+}
+
+/// Fills the rectangle of `buffer` (which is `width` by `height` pixels) at
+/// `(left, top)` with `color`, leaving out anything past the edges.
+fn fill_rect(
+    buffer: &mut [u32],
+    (width, height): (usize, usize),
+    (left, top): (usize, usize),
+    (fill_width, fill_height): (usize, usize),
+    color: u32,
+) {
+    for y in top..(top + fill_height).min(height) {
+        for x in left..(left + fill_width).min(width) {
+            buffer[y * width + x] = color;
+        }
+    }
+}
+
+/// Draws a character with its top left corner at `(left, top)`, with each
+/// font pixel drawn as a `scale` by `scale` square. Characters without a
+/// glyph are skipped.
+fn draw_glyph(
+    buffer: &mut [u32],
+    width: usize,
+    character: char,
+    (left, top): (usize, usize),
+    scale: usize,
+    color: u32,
+) {
+    let Some(glyph) = glyph(character) else {
+        return;
+    };
+
+    for (glyph_y, glyph_row) in glyph.iter().enumerate() {
+        for glyph_x in 0..4 {
+            if (glyph_row >> (7 - glyph_x)) & 1 == 0 {
+                continue;
+            }
+
+            for y in top + glyph_y * scale..top + (glyph_y + 1) * scale {
+                for x in left + glyph_x * scale..left + (glyph_x + 1) * scale {
+                    if x < width {
+                        if let Some(real_pixel) = buffer.get_mut(y * width + x) {
+                            *real_pixel = color;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::{draw_keypad, Rotation};
+    use crate::keymap::Keymap;
+    use chip8_core::palette::Palette;
+    use chip8_core::Keys;
+
+    #[test]
+    fn rotations_turn_the_picture_clockwise() {
+        // 1 2 3
+        // 4 5 6
+        let source = [1, 2, 3, 4, 5, 6];
+        let mut output = Vec::new();
+
+        let mut turned = |rotation: Rotation| {
+            rotation.apply(&source, 3, 2, &mut output);
+            (rotation.size(3, 2), output.clone())
+        };
+
+        assert_eq!(turned(Rotation::None), ((3, 2), vec![1, 2, 3, 4, 5, 6]));
+        assert_eq!(
+            turned(Rotation::Clockwise),
+            ((2, 3), vec![4, 1, 5, 2, 6, 3])
+        );
+        assert_eq!(turned(Rotation::HalfTurn), ((3, 2), vec![6, 5, 4, 3, 2, 1]));
+        assert_eq!(
+            turned(Rotation::Anticlockwise),
+            ((2, 3), vec![3, 6, 2, 5, 1, 4])
+        );
+    }
+
+    #[test]
+    fn the_keypad_fills_in_held_keys() {
+        let palette = Palette {
+            foreground: 1,
+            background: 0,
+        };
+        let (width, height) = (40, 60);
+        let mut buffer = vec![2; width * height];
+
+        let mut keys = Keys::default();
+        keys.press(0x1);
+        draw_keypad(
+            &mut buffer,
+            (width, height),
+            &Keymap::LEFT,
+            keys,
+            1,
+            &palette,
+        );
+
+        // The keypad is 33x57, a pixel in from the bottom right corner, with
+        // 1 at its top left and 2 next to it. The pixels checked inside the
+        // keys are between their two labels.
+        let pixel = |x: usize, y: usize| buffer[(height - 58 + y) * width + width - 34 + x];
+
+        assert_eq!(buffer[0], 2);
+        assert_eq!(pixel(0, 0), 1);
+        assert_eq!(pixel(4, 7), 1);
+        assert_eq!(pixel(12, 7), 0);
+
+        assert_eq!(Keymap::LEFT.label(0xC), Some('4'));
+        assert_eq!(Keymap::RIGHT.label(0xE), Some(';'));
+    }
+}
@@ -0,0 +1,314 @@
+//! Scripts that run alongside a program, written in [Rhai](https://rhai.rs),
+//! for things like trainers, game AI experiments and automated tests.
+//!
+//! A script can define any of these functions, which are called as the
+//! program runs:
+//!
+//! - `on_frame()` at the end of every 60Hz frame.
+//! - `on_instruction(address, word)` after every instruction.
+//! - `on_write(address, value)` after an instruction writes to memory.
+//!
+//! Inside them, the emulator can be read and changed with `v(x)`,
+//! `set_v(x, value)`, `i()`, `set_i(value)`, `pc()`, `set_pc(address)`,
+//! `dt()`, `set_dt(value)`, `st()`, `set_st(value)`, `peek(address)` and
+//! `poke(address, value)`. `this` is an object map kept between calls, for
+//! the script's own state, and `print` writes to the log.
+//!
+//! For example, this keeps the lives of a game stored at 0x3F0 topped up:
+//!
+//! ```text
+//! fn on_frame() {
+//!     if peek(0x3F0) < 3 {
+//!         poke(0x3F0, 3);
+//!     }
+//! }
+//! ```
+
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, FuncArgs, Map, Scope, AST, INT};
+use tracing::{error, info};
+
+use chip8_core::hooks::Hooks;
+use chip8_core::Chip8;
+
+/// A copy of the parts of the emulator that scripts can see. It is filled in
+/// before each callback, and what the script changed is copied back after.
+#[derive(Debug, Default)]
+struct Machine {
+    registers: [u8; 16],
+    index_register: u16,
+    program_counter: u16,
+    delay_timer: u8,
+    sound_timer: u8,
+    memory: Vec<u8>,
+    /// The bytes poked by the script, in order.
+    writes: Vec<(u16, u8)>,
+}
+
+impl Machine {
+    fn load(&mut self, chip_8: &Chip8) {
+        for (vx, register) in self.registers.iter_mut().enumerate() {
+            *register = chip_8.register(vx as u8);
+        }
+
+        self.index_register = chip_8.index_register();
+        self.program_counter = chip_8.program_counter();
+        self.delay_timer = chip_8.timers.delay();
+        self.sound_timer = chip_8.timers.sound();
+        self.memory.clear();
+        self.memory.extend_from_slice(chip_8.memory());
+        self.writes.clear();
+    }
+
+    fn store(&mut self, chip_8: &mut Chip8) {
+        for (vx, &register) in self.registers.iter().enumerate() {
+            chip_8.set_register(vx as u8, register);
+        }
+
+        chip_8.set_index_register(self.index_register);
+        chip_8.set_program_counter(self.program_counter);
+        chip_8.timers.set_delay(self.delay_timer);
+        chip_8.timers.set_sound(self.sound_timer);
+
+        for (address, value) in self.writes.drain(..) {
+            chip_8.set_memory_byte(address, value);
+        }
+    }
+}
+
+/// A loaded script, which runs as the emulator's [`Hooks`].
+#[derive(Debug)]
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    /// The script's `this`.
+    state: Dynamic,
+    machine: Arc<Mutex<Machine>>,
+    on_frame: bool,
+    on_instruction: bool,
+    on_write: bool,
+}
+
+impl Script {
+    /// Compiles the script at `path` and runs its top level, where the
+    /// emulator functions aren't available yet.
+    pub fn load(path: &Path) -> Result<Self, Box<EvalAltResult>> {
+        let machine = Arc::new(Mutex::new(Machine::default()));
+        let mut engine = Engine::new();
+
+        engine.on_print(|text| info!("{text}"));
+        register_functions(&mut engine, &machine);
+
+        let ast = engine.compile_file(path.to_path_buf())?;
+        let mut scope = Scope::new();
+        engine.run_ast_with_scope(&mut scope, &ast)?;
+
+        let defines = |name: &str, params: usize| {
+            ast.iter_functions()
+                .any(|function| function.name == name && function.params.len() == params)
+        };
+
+        Ok(Self {
+            on_frame: defines("on_frame", 0),
+            on_instruction: defines("on_instruction", 2),
+            on_write: defines("on_write", 2),
+            engine,
+            ast,
+            scope,
+            state: Dynamic::from_map(Map::new()),
+            machine,
+        })
+    }
+
+    /// Calls a function in the script with the emulator available. A function
+    /// that fails is logged and not called again, so it doesn't flood the log.
+    fn call(&mut self, chip_8: &mut Chip8, name: &str, args: impl FuncArgs) -> bool {
+        lock(&self.machine).load(chip_8);
+
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.state);
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            options,
+            &mut self.scope,
+            &self.ast,
+            name,
+            args,
+        );
+
+        lock(&self.machine).store(chip_8);
+
+        match result {
+            Ok(_) => true,
+            Err(err) => {
+                error!("The script's {name} failed, so it won't be called again: {err}");
+                false
+            }
+        }
+    }
+}
+
+impl Hooks for Script {
+    fn on_instruction(&mut self, chip_8: &mut Chip8, address: u16, word: u16) {
+        if self.on_instruction {
+            self.on_instruction =
+                self.call(chip_8, "on_instruction", (address as INT, word as INT));
+        }
+    }
+
+    fn on_memory_write(&mut self, chip_8: &mut Chip8, address: u16, value: u8) {
+        if self.on_write {
+            self.on_write = self.call(chip_8, "on_write", (address as INT, value as INT));
+        }
+    }
+
+    fn on_frame(&mut self, chip_8: &mut Chip8) {
+        if self.on_frame {
+            self.on_frame = self.call(chip_8, "on_frame", ());
+        }
+    }
+}
+
+fn lock(machine: &Mutex<Machine>) -> MutexGuard<'_, Machine> {
+    // A panic while the machine was locked can't leave it half changed in a
+    // way that matters, as it is filled in again before every call.
+    machine
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Checks that a number from a script fits in `limit`.
+fn checked(value: INT, limit: INT, what: &str) -> Result<INT, Box<EvalAltResult>> {
+    match (0..limit).contains(&value) {
+        true => Ok(value),
+        false => Err(format!("{what} {value} is out of range").into()),
+    }
+}
+
+fn register_functions(engine: &mut Engine, machine: &Arc<Mutex<Machine>>) {
+    let m = Arc::clone(machine);
+    engine.register_fn("v", move |x: INT| -> Result<INT, Box<EvalAltResult>> {
+        Ok(lock(&m).registers[checked(x, 16, "register")? as usize] as INT)
+    });
+
+    let m = Arc::clone(machine);
+    engine.register_fn(
+        "set_v",
+        move |x: INT, value: INT| -> Result<(), Box<EvalAltResult>> {
+            lock(&m).registers[checked(x, 16, "register")? as usize] =
+                checked(value, 0x100, "value")? as u8;
+            Ok(())
+        },
+    );
+
+    let m = Arc::clone(machine);
+    engine.register_fn("i", move || lock(&m).index_register as INT);
+
+    let m = Arc::clone(machine);
+    engine.register_fn(
+        "set_i",
+        move |value: INT| -> Result<(), Box<EvalAltResult>> {
+            lock(&m).index_register = checked(value, 0x10000, "value")? as u16;
+            Ok(())
+        },
+    );
+
+    let m = Arc::clone(machine);
+    engine.register_fn("pc", move || lock(&m).program_counter as INT);
+
+    let m = Arc::clone(machine);
+    engine.register_fn(
+        "set_pc",
+        move |address: INT| -> Result<(), Box<EvalAltResult>> {
+            lock(&m).program_counter = checked(address, 0x1000, "address")? as u16;
+            Ok(())
+        },
+    );
+
+    let m = Arc::clone(machine);
+    engine.register_fn("dt", move || lock(&m).delay_timer as INT);
+
+    let m = Arc::clone(machine);
+    engine.register_fn(
+        "set_dt",
+        move |value: INT| -> Result<(), Box<EvalAltResult>> {
+            lock(&m).delay_timer = checked(value, 0x100, "value")? as u8;
+            Ok(())
+        },
+    );
+
+    let m = Arc::clone(machine);
+    engine.register_fn("st", move || lock(&m).sound_timer as INT);
+
+    let m = Arc::clone(machine);
+    engine.register_fn(
+        "set_st",
+        move |value: INT| -> Result<(), Box<EvalAltResult>> {
+            lock(&m).sound_timer = checked(value, 0x100, "value")? as u8;
+            Ok(())
+        },
+    );
+
+    let m = Arc::clone(machine);
+    engine.register_fn(
+        "peek",
+        move |address: INT| -> Result<INT, Box<EvalAltResult>> {
+            let address = checked(address, 0x1000, "address")? as usize;
+            // Memory is empty while the top level runs.
+            Ok(lock(&m).memory.get(address).copied().unwrap_or(0) as INT)
+        },
+    );
+
+    let m = Arc::clone(machine);
+    engine.register_fn(
+        "poke",
+        move |address: INT, value: INT| -> Result<(), Box<EvalAltResult>> {
+            let address = checked(address, 0x1000, "address")? as usize;
+            let value = checked(value, 0x100, "value")? as u8;
+            let mut machine = lock(&m);
+
+            // Later peeks in the same call see the new value.
+            if let Some(byte) = machine.memory.get_mut(address) {
+                *byte = value;
+            }
+
+            machine.writes.push((address as u16, value));
+            Ok(())
+        },
+    );
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::Script;
+    use crate::Chip8;
+
+    #[test]
+    fn callbacks_change_the_program() {
+        let path = std::env::temp_dir().join("chip_8_emulator_script_test.rhai");
+        std::fs::write(
+            &path,
+            "fn on_write(address, value) { poke(address + 1, value * 2); }\n\
+             fn on_frame() { this.frames = (this.frames ?? 0) + 1; set_v(1, this.frames); }\n",
+        )
+        .unwrap();
+
+        // Sets V0 to 5, points I at 0x300, saves V0 there, then loops forever.
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        chip_8
+            .load_program(vec![0x60, 0x05, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x06])
+            .unwrap();
+        chip_8.set_hooks(Some(Box::new(Script::load(&path).unwrap())));
+
+        for _ in 0..3 {
+            chip_8.run_frame().unwrap();
+        }
+
+        assert_eq!(chip_8.memory()[0x300..0x302], [5, 10]);
+        assert_eq!(chip_8.register(1), 3);
+    }
+}
@@ -0,0 +1,131 @@
+//! Two emulators side by side in one window, for comparing two ROMs (or one
+//! ROM with different speeds or quirks) and for two player setups.
+//!
+//! The left emulator is played with the left hand side of the keyboard and
+//! the right one with the right hand side (see [`Keymap`]). Both are run from
+//! the window loop, so they stay frame for frame in step with each other.
+
+use std::time::Duration;
+
+use minifb::{Key, KeyRepeat};
+use tracing::error;
+
+use crate::keymap::{KeyTracker, Keymap};
+use crate::render::{self, CrtFilter};
+use crate::{create_window, EMULATOR_FRAMES_PER_FRAME};
+use chip8_core::palette::Palette;
+use chip8_core::Chip8;
+
+/// One of the two emulators.
+#[derive(Debug)]
+pub struct Side {
+    chip_8: Chip8,
+    /// What is shown above the screen, like the name of the ROM.
+    label: String,
+    keymap: Keymap,
+    buffer: Vec<u32>,
+    filter: CrtFilter,
+    /// Why the emulator stopped, if it failed.
+    error: Option<String>,
+}
+
+impl Side {
+    /// Wraps an emulator that already has its program loaded.
+    pub fn new(chip_8: Chip8, label: String, keymap: Keymap, scale: usize) -> Self {
+        let resolution = chip_8.resolution();
+
+        Self {
+            chip_8,
+            label,
+            keymap,
+            buffer: vec![0; resolution.width * resolution.height],
+            filter: CrtFilter::new(Vec::new(), scale, resolution),
+            error: None,
+        }
+    }
+}
+
+/// Runs both emulators until the window is closed. Tab restarts both. Keys
+/// are debounced for `key_debounce`, like in [`KeyTracker::new`].
+pub fn run(
+    mut sides: [Side; 2],
+    scale: usize,
+    palette: &Palette,
+    key_debounce: Duration,
+) -> minifb::Result<()> {
+    // The screens are separated by a line one scaled pixel wide. Each side
+    // is as big as the biggest screen, leaving a gap around a smaller one.
+    let side_width = sides[0].filter.width().max(sides[1].filter.width());
+    let height = sides[0].filter.height().max(sides[1].filter.height());
+    let size = (side_width * 2 + scale, height);
+
+    let mut window = create_window("Split screen - ESC to exit", size, false, palette)?;
+    let mut buffer = vec![palette.background; size.0 * size.1];
+
+    for row in buffer.chunks_exact_mut(size.0) {
+        row[side_width..side_width + scale].fill(palette.foreground);
+    }
+
+    for side in &mut sides {
+        side.buffer.fill(palette.background);
+    }
+
+    let mut key_tracker = KeyTracker::new(key_debounce);
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        let restart = window.is_key_pressed(Key::Tab, KeyRepeat::No);
+
+        for side in &mut sides {
+            side.chip_8.needs_program_restart |= restart;
+
+            if restart {
+                side.error = None;
+            }
+
+            if side.error.is_some() {
+                continue;
+            }
+
+            side.chip_8.set_keys(side.keymap.keys(&key_tracker));
+
+            for _ in 0..EMULATOR_FRAMES_PER_FRAME {
+                if let Err(err) = side.chip_8.run_frame() {
+                    error!("{} stopped: {err}", side.label);
+                    side.error = Some(err.to_string());
+                    break;
+                }
+            }
+
+            if let Some((frame, dirty_rows)) = side.chip_8.take_frame() {
+                frame.write_rgba(&mut side.buffer, dirty_rows, palette);
+            }
+        }
+
+        for (index, side) in sides.iter_mut().enumerate() {
+            let width = side.filter.width();
+            let pixels = side.filter.apply(&side.buffer);
+
+            let mut overlay_lines = vec![side.label.to_uppercase()];
+            overlay_lines.extend(side.error.as_ref().map(|_| "STOPPED".to_string()));
+            render::draw_text(
+                pixels,
+                width,
+                &overlay_lines,
+                (scale / 4).max(1),
+                palette.foreground,
+            );
+
+            let left = index * (side_width + scale);
+
+            for (row, pixels) in pixels.chunks_exact(width).enumerate() {
+                let start = row * size.0 + left;
+                buffer[start..start + width].copy_from_slice(pixels);
+            }
+        }
+
+        window.update_with_buffer(&buffer, size.0, size.1)?;
+        key_tracker.update(&window);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,153 @@
+//! Measurements of how fast the emulator is running and how quickly it
+//! responds to keys, shown in the overlay.
+
+use std::time::{Duration, Instant};
+
+/// How often the measurements are refreshed.
+const SAMPLE_PERIOD: Duration = Duration::from_secs(1);
+
+/// How long a key press can go without the program checking the keypad
+/// before it stops being timed, as the program clearly wasn't waiting for it.
+pub const MAX_INPUT_LATENCY: Duration = Duration::from_secs(1);
+
+/// Measures frames per second and cycles per second over one second windows.
+#[derive(Debug)]
+pub struct PerformanceStats {
+    /// When the current sample started.
+    sample_start: Instant,
+    /// Frames presented since the current sample started.
+    frames: u32,
+    /// The total cycle count when the current sample started.
+    cycles_at_sample_start: u64,
+    /// The cycles per second we are aiming for.
+    target_cycles_per_second: u32,
+    frames_per_second: f32,
+    cycles_per_second: f32,
+    /// The input latencies recorded since the current sample started, added
+    /// up.
+    latency_total: Duration,
+    latency_samples: u32,
+    /// The average input latency of the last sample that had any.
+    input_latency: Option<Duration>,
+}
+
+impl PerformanceStats {
+    /// Creates stats that compare the measured speed against `target_cycles_per_second`.
+    pub fn new(target_cycles_per_second: u32) -> Self {
+        Self {
+            sample_start: Instant::now(),
+            frames: 0,
+            cycles_at_sample_start: 0,
+            target_cycles_per_second,
+            frames_per_second: 0.0,
+            cycles_per_second: 0.0,
+            latency_total: Duration::ZERO,
+            latency_samples: 0,
+            input_latency: None,
+        }
+    }
+
+    /// Records that a frame was presented. `total_cycles` is the number of cycles
+    /// the emulator has run since it started.
+    pub fn record_frame(&mut self, total_cycles: u64) {
+        self.record_frame_at(total_cycles, Instant::now());
+    }
+
+    fn record_frame_at(&mut self, total_cycles: u64, now: Instant) {
+        self.frames += 1;
+
+        let elapsed = now - self.sample_start;
+
+        if elapsed < SAMPLE_PERIOD {
+            return;
+        }
+
+        let seconds = elapsed.as_secs_f32();
+        // The cycle count starts over when the emulator is reset.
+        let cycles = total_cycles
+            .checked_sub(self.cycles_at_sample_start)
+            .unwrap_or(total_cycles);
+
+        self.frames_per_second = self.frames as f32 / seconds;
+        self.cycles_per_second = cycles as f32 / seconds;
+
+        if self.latency_samples > 0 {
+            self.input_latency = Some(self.latency_total / self.latency_samples);
+        }
+
+        self.sample_start = now;
+        self.frames = 0;
+        self.cycles_at_sample_start = total_cycles;
+        self.latency_total = Duration::ZERO;
+        self.latency_samples = 0;
+    }
+
+    /// Records how long it took from a key being pressed to the program
+    /// first checking the keypad after it.
+    pub fn record_input_latency(&mut self, latency: Duration) {
+        self.latency_total += latency;
+        self.latency_samples += 1;
+    }
+
+    /// Frames presented per second during the last sample.
+    pub fn frames_per_second(&self) -> f32 {
+        self.frames_per_second
+    }
+
+    /// Emulator cycles run per second during the last sample.
+    pub fn cycles_per_second(&self) -> f32 {
+        self.cycles_per_second
+    }
+
+    /// How fast the emulator is running compared to its target speed, where
+    /// 1.0 is full speed.
+    pub fn speed_multiplier(&self) -> f32 {
+        self.cycles_per_second() / self.target_cycles_per_second as f32
+    }
+
+    /// The average time from a key being pressed to the program checking
+    /// the keypad during the last sample that had a key pressed, if one has.
+    pub fn input_latency(&self) -> Option<Duration> {
+        self.input_latency
+    }
+
+    /// The lines of text shown in the overlay.
+    pub fn overlay_lines(&self) -> [String; 4] {
+        [
+            format!("FPS {:.0}", self.frames_per_second()),
+            format!("CPS {:.0}", self.cycles_per_second()),
+            format!("{:.2}X", self.speed_multiplier()),
+            match self.input_latency() {
+                Some(latency) => format!("LATENCY {}MS", latency.as_millis()),
+                None => "LATENCY -".to_string(),
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::PerformanceStats;
+    use std::time::Duration;
+
+    #[test]
+    fn input_latency_is_averaged_over_a_sample() {
+        let mut stats = PerformanceStats::new(700);
+        let start = stats.sample_start;
+        let at = |millis| start + Duration::from_millis(millis);
+
+        stats.record_input_latency(Duration::from_millis(10));
+        stats.record_input_latency(Duration::from_millis(30));
+        stats.record_frame_at(350, at(500));
+        assert_eq!(stats.input_latency(), None);
+        assert_eq!(stats.overlay_lines()[3], "LATENCY -");
+
+        stats.record_frame_at(700, at(1000));
+        assert_eq!(stats.input_latency(), Some(Duration::from_millis(20)));
+        assert_eq!(stats.overlay_lines()[3], "LATENCY 20MS");
+
+        // A sample without any key presses keeps the last average.
+        stats.record_frame_at(1400, at(2000));
+        assert_eq!(stats.input_latency(), Some(Duration::from_millis(20)));
+    }
+}
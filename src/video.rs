@@ -0,0 +1,241 @@
+//! Rendering `.c8rec` replays (see [`chip8_core::replay`]) to a GIF or a
+//! sequence of PNGs with no window, as fast as the emulator can run. Only
+//! built with the `video` feature.
+//!
+//! Every image is the size of the biggest screen the replay shows, times the
+//! scale, and smaller screens are stretched to fit, like they are in the
+//! window.
+
+use chip8_core::display::Resolution;
+use chip8_core::palette::Palette;
+use chip8_core::replay::Replay;
+use chip8_core::Frame;
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Plays a replay of `program`, writing every frame to `output`. Outputs
+/// ending in `.gif` are written as an animated GIF, where frames that show
+/// the same thing are merged, and anything else is a directory of numbered
+/// PNGs at 60 frames a second, like `frame-00000.png`.
+///
+/// Returns how many images were written.
+pub fn render(
+    replay: &Replay,
+    program: &[u8],
+    output: &Path,
+    scale: usize,
+    palette: &Palette,
+) -> Result<usize, Box<dyn Error>> {
+    // The first pass only finds out how big the images need to be.
+    let mut size = Resolution::LORES;
+    play(replay, program, |frame| {
+        size.width = size.width.max(frame.width());
+        size.height = size.height.max(frame.height());
+        Ok(())
+    })?;
+
+    let size = Resolution {
+        width: size.width * scale,
+        height: size.height * scale,
+    };
+
+    match output
+        .extension()
+        .is_some_and(|extension| extension == "gif")
+    {
+        true => write_gif(replay, program, output, size, palette),
+        false => write_pngs(replay, program, output, size, palette),
+    }
+}
+
+fn write_gif(
+    replay: &Replay,
+    program: &[u8],
+    output: &Path,
+    size: Resolution,
+    palette: &Palette,
+) -> Result<usize, Box<dyn Error>> {
+    let mut encoder = gif::Encoder::new(
+        BufWriter::new(File::create(output)?),
+        size.width.try_into()?,
+        size.height.try_into()?,
+        &palette_rgb(palette),
+    )?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+
+    // The image being shown, and the frame it was first shown on.
+    let mut shown: Option<(Vec<u8>, usize)> = None;
+    let mut frame_number = 0;
+    let mut written = 0;
+
+    let mut write = |pixels: &[u8], start: usize, end: usize| -> Result<(), Box<dyn Error>> {
+        // GIF delays are in hundredths of a second, so the delay is rounded
+        // from when the image started and ended to keep it in time.
+        let delay = (end * 100 / 60 - start * 100 / 60) as u16;
+
+        encoder.write_frame(&gif::Frame {
+            width: size.width as u16,
+            height: size.height as u16,
+            delay,
+            buffer: Cow::Borrowed(pixels),
+            ..gif::Frame::default()
+        })?;
+        written += 1;
+        Ok(())
+    };
+
+    play(replay, program, |frame| {
+        let pixels = scale_frame(frame, size);
+
+        match &shown {
+            Some((shown_pixels, _)) if *shown_pixels == pixels => {}
+            Some((shown_pixels, start)) => {
+                write(shown_pixels, *start, frame_number)?;
+                shown = Some((pixels, frame_number));
+            }
+            None => shown = Some((pixels, frame_number)),
+        }
+
+        frame_number += 1;
+        Ok(())
+    })?;
+
+    if let Some((pixels, start)) = &shown {
+        write(pixels, *start, frame_number)?;
+    }
+
+    Ok(written)
+}
+
+fn write_pngs(
+    replay: &Replay,
+    program: &[u8],
+    output: &Path,
+    size: Resolution,
+    palette: &Palette,
+) -> Result<usize, Box<dyn Error>> {
+    std::fs::create_dir_all(output)?;
+
+    let mut written = 0;
+
+    play(replay, program, |frame| {
+        let path = output.join(format!("frame-{written:05}.png"));
+        let mut encoder = png::Encoder::new(
+            BufWriter::new(File::create(path)?),
+            size.width as u32,
+            size.height as u32,
+        );
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(palette_rgb(palette).to_vec());
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&scale_frame(frame, size))?;
+        written += 1;
+        Ok(())
+    })?;
+
+    Ok(written)
+}
+
+/// Plays the replay back, calling `on_frame` with the screen after every
+/// frame.
+fn play(
+    replay: &Replay,
+    program: &[u8],
+    mut on_frame: impl FnMut(&Frame) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let mut chip_8 = replay.start(program.to_vec())?;
+    let mut frame = Frame::default();
+
+    for input in &replay.frames {
+        input.run(&mut chip_8)?;
+
+        if let Some((new_frame, _)) = chip_8.take_frame() {
+            frame = new_frame;
+        }
+
+        on_frame(&frame)?;
+    }
+
+    Ok(())
+}
+
+/// Stretches a frame to `size`, as one byte per pixel which is 1 for pixels
+/// that are on and 0 for the rest.
+fn scale_frame(frame: &Frame, size: Resolution) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(size.width * size.height);
+
+    for y in 0..size.height {
+        let frame_y = y * frame.height() / size.height;
+
+        for x in 0..size.width {
+            let frame_x = x * frame.width() / size.width;
+            pixels.push(frame.pixel(frame_x, frame_y).into());
+        }
+    }
+
+    pixels
+}
+
+/// Returns the background and foreground colors as `RGB` bytes, in the
+/// order of the pixels from [`scale_frame`].
+fn palette_rgb(palette: &Palette) -> [u8; 6] {
+    let [_, r0, g0, b0] = palette.background.to_be_bytes();
+    let [_, r1, g1, b1] = palette.foreground.to_be_bytes();
+    [r0, g0, b0, r1, g1, b1]
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::render;
+    use chip8_core::palette::Palette;
+    use chip8_core::replay::{FrameInput, Replay};
+    use chip8_core::Chip8;
+
+    #[test]
+    fn replays_are_rendered_to_a_frame_per_image() {
+        // Draws a digit that counts up every 10 frames.
+        let program = vec![
+            0x00, 0xE0, // clear the screen
+            0xF0, 0x29, // I = the digit in V0
+            0xD1, 0x15, // draw it
+            0x70, 0x01, // V0 += 1
+            0x61, 0x0A, // V1 = 10
+            0xF1, 0x15, // delay timer = V1
+            0xF1, 0x07, // V1 = delay timer
+            0x31, 0x00, // skip once it's 0
+            0x12, 0x0C, // keep waiting
+            0x12, 0x00, // and start over
+        ];
+
+        let mut chip_8 = Chip8::new();
+        chip_8.initialize().unwrap();
+        let loaded_rom = chip_8.load_program(program.clone()).unwrap();
+
+        let mut replay = Replay::new(&chip_8, loaded_rom.sha1, 0x200, 0);
+        replay.frames = vec![FrameInput::default(); 25];
+
+        let directory = std::env::temp_dir().join(format!("chip-8-video-{}", std::process::id()));
+        let gif = directory.with_extension("gif");
+
+        let pngs = render(&replay, &program, &directory, 2, &Palette::default()).unwrap();
+        let gif_frames = render(&replay, &program, &gif, 2, &Palette::default()).unwrap();
+
+        let png_count = std::fs::read_dir(&directory).unwrap().count();
+        let png_size = std::fs::metadata(directory.join("frame-00000.png"))
+            .unwrap()
+            .len();
+        std::fs::remove_dir_all(&directory).unwrap();
+        std::fs::remove_file(&gif).unwrap();
+
+        assert_eq!((pngs, png_count), (25, 25));
+        assert!(png_size > 0);
+        // The digit changes 3 times in 25 frames, so only 3 images are
+        // needed for the GIF.
+        assert_eq!(gif_frames, 3);
+    }
+}
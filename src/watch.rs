@@ -0,0 +1,98 @@
+//! Live reloading for working on a ROM with `--watch`: the ROM file is
+//! checked for changes every so often, so it can be reloaded as soon as it is
+//! saved or reassembled.
+//!
+//! The modification time is polled instead of subscribing to the system's
+//! file events, which is plenty for a file that changes when someone saves
+//! it.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the file is checked, unless told otherwise.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watches a file for changes.
+#[derive(Debug)]
+pub struct Watcher {
+    path: PathBuf,
+    interval: Duration,
+    /// When the file was last modified, or `None` if it couldn't be read.
+    modified: Option<SystemTime>,
+    last_checked: Instant,
+}
+
+impl Watcher {
+    /// Starts watching the file at `path`, checking it at most once every
+    /// `interval`. The file as it is now doesn't count as a change.
+    pub fn new(path: &Path, interval: Duration) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            interval,
+            modified: modified(path),
+            last_checked: Instant::now(),
+        }
+    }
+
+    /// Returns the path of the file being watched.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns true if the file has changed since it was last checked, and
+    /// the interval has passed since then.
+    ///
+    /// A file that goes missing, like while an editor replaces it, isn't a
+    /// change until it comes back.
+    pub fn changed(&mut self) -> bool {
+        if self.last_checked.elapsed() < self.interval {
+            return false;
+        }
+
+        self.last_checked = Instant::now();
+
+        match modified(&self.path) {
+            Some(modified) if self.modified != Some(modified) => {
+                self.modified = Some(modified);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod test_super {
+    use super::Watcher;
+    use std::fs::File;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn changes_are_seen_once() {
+        let path = std::env::temp_dir().join(format!("chip-8-watch-{}.ch8", std::process::id()));
+        std::fs::write(&path, [0x12, 0x00]).unwrap();
+
+        let mut watcher = Watcher::new(&path, Duration::ZERO);
+        assert!(!watcher.changed());
+
+        let later = SystemTime::now() + Duration::from_secs(10);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(!watcher.changed());
+    }
+}
@@ -0,0 +1,355 @@
+//! The window the emulator is played in. It draws the frames the emulator
+//! thread publishes, with the overlays and effects on top, and sends the
+//! emulator the keys after each one.
+
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+use chip8_core::display::Resolution;
+use chip8_core::palette::Palette;
+use chip8_core::{Frame, Keys};
+use crossbeam_channel::{Receiver, Sender, TrySendError};
+use minifb::{Key, KeyRepeat};
+
+use crate::emulator::{FrameFinishedSignal, PublishedFrame};
+use crate::keymap::{KeyTracker, Keymap};
+use crate::render::{self, CrtFilter, PhosphorDecay, Rotation, VisualBell};
+use crate::stats::PerformanceStats;
+use crate::{create_window, Args};
+
+/// What the window shows, worked out from the ROM and the settings for it.
+pub struct WindowSettings {
+    /// Shown in the title.
+    pub rom_name: String,
+    pub resolution: Resolution,
+    pub palette: Palette,
+    pub keymap: Keymap,
+    /// How fast the emulator runs at normal speed, for the stats.
+    pub cycles_per_second: u32,
+    /// Whether to stop sending signals while the window is in the background.
+    pub pause_on_focus_loss: bool,
+}
+
+/// The window's side of the emulator thread.
+pub struct EmulatorLink<'a> {
+    pub published_frame: &'a ArcSwap<PublishedFrame>,
+    pub signals: &'a Sender<FrameFinishedSignal>,
+    pub input_latency: &'a Receiver<Duration>,
+    /// Returns whether the emulator thread has finished.
+    pub is_finished: &'a dyn Fn() -> bool,
+}
+
+/// Runs the window until it is closed or the emulator finishes.
+pub fn run(
+    args: &Args,
+    settings: WindowSettings,
+    emulator: EmulatorLink,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let WindowSettings {
+        rom_name,
+        resolution,
+        palette,
+        keymap,
+        cycles_per_second,
+        pause_on_focus_loss,
+    } = settings;
+
+    let mut title = window_title(&rom_name, None, 1.0);
+    let mut buffer: Vec<u32> = vec![0; resolution.width * resolution.height];
+
+    let windowed_size = args.rotate.size(
+        resolution.width * args.scale as usize,
+        resolution.height * args.scale as usize,
+    );
+    let mut fullscreen = args.fullscreen;
+    let mut window = match fullscreen {
+        true => create_window(&title, args.fullscreen_size, true, &palette)?,
+        false => create_window(&title, windowed_size, false, &palette)?,
+    };
+
+    // The most recent frame drawn by the emulator.
+    let mut frame = Frame::default();
+    let mut phosphor_decay = args
+        .phosphor_decay
+        .map(|decay| PhosphorDecay::new(decay, resolution));
+    // Also does our integer scaling when there are no effects.
+    let mut crt_filter = CrtFilter::new(args.effect.clone(), args.scale as usize, resolution);
+    // The picture after it is turned, when it is.
+    let mut rotated = Vec::new();
+
+    // The sequence number of the last frame we received.
+    let mut last_sequence = 0;
+
+    let mut performance_stats = PerformanceStats::new(cycles_per_second);
+    let mut show_stats = false;
+    let mut show_keypad = args.show_keypad;
+    let mut key_tracker = KeyTracker::new(Duration::from_millis(args.key_debounce));
+    // The keys in the last signal that got through to the emulator, or held
+    // while paused.
+    let mut sent_keys = Keys(0);
+    // Set while paused, until a signal gets through after it.
+    let mut paused_since_signal = false;
+    // Set by pressing Tab, which restarts the program.
+    let mut restart_requested = false;
+    // Set by pressing F5, which carries on after the emulator stopped.
+    let mut resume_requested = false;
+    let mut paused = false;
+    let mut bell_was_active = false;
+    let mut was_stopped = false;
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        // The window closes with the emulator, rather than being left showing
+        // its last frame.
+        if (emulator.is_finished)() {
+            break;
+        }
+
+        let mut window_changed = false;
+
+        let published_frame = emulator.published_frame.load();
+
+        let bell_active = args.visual_bell.is_some() && published_frame.buzzer_active;
+        let bell_changed = bell_active != bell_was_active;
+        bell_was_active = bell_active;
+
+        let dirty_rows = match published_frame.sequence {
+            // Inverting the colors changes every pixel.
+            _ if bell_changed && args.visual_bell == Some(VisualBell::Invert) => {
+                Some(0..resolution.height)
+            }
+            sequence if sequence == last_sequence => None,
+            sequence if sequence == last_sequence + 1 => Some(published_frame.dirty_rows.clone()),
+            // We missed some frames, so we don't know which rows they changed.
+            _ => Some(0..resolution.height),
+        };
+
+        if dirty_rows.is_some() {
+            frame = published_frame.frame;
+            last_sequence = published_frame.sequence;
+        }
+
+        if bell_changed {
+            window_changed = true;
+        }
+
+        // The overlay shows where the emulator stopped.
+        if published_frame.stopped.is_some() != was_stopped {
+            was_stopped = published_frame.stopped.is_some();
+            window_changed = true;
+        }
+
+        let frame_palette = match (args.visual_bell, bell_active) {
+            (Some(VisualBell::Invert), true) => palette.inverted(),
+            _ => palette,
+        };
+
+        let total_cycles = published_frame.total_cycles;
+
+        for latency in emulator.input_latency.try_iter() {
+            performance_stats.record_input_latency(latency);
+        }
+
+        performance_stats.record_frame(total_cycles);
+
+        let state = match (paused, published_frame.stopped) {
+            (true, _) => Some("paused"),
+            (false, Some(_)) => Some("stopped"),
+            (false, None) => None,
+        };
+        let new_title = window_title(&rom_name, state, performance_stats.speed_multiplier());
+
+        if new_title != title {
+            title = new_title;
+            window.set_title(&title);
+        }
+
+        if window.is_key_pressed(Key::F1, KeyRepeat::No) {
+            show_stats = !show_stats;
+            window_changed = true;
+        }
+
+        if window.is_key_pressed(Key::F2, KeyRepeat::No) {
+            show_keypad = !show_keypad;
+            window_changed = true;
+        }
+
+        // We stop sending signals while the window is in the background, which
+        // leaves the emulator thread (and so the timers and buzzer) waiting.
+        let in_background = pause_on_focus_loss && !window.is_active();
+
+        if in_background != paused {
+            paused = in_background;
+            window_changed = true;
+        }
+
+        // Going in or out of fullscreen means replacing the window, as minifb
+        // can't change the style of an existing one.
+        if window.is_key_pressed(Key::F11, KeyRepeat::No) {
+            fullscreen = !fullscreen;
+            let new_window = match fullscreen {
+                true => create_window(&title, args.fullscreen_size, true, &palette),
+                false => create_window(&title, windowed_size, false, &palette),
+            };
+
+            window = new_window.map_err(|err| format!("Couldn't create the window: {err}"))?;
+            window_changed = true;
+        }
+
+        // Use the largest whole number scale that fits in the window, so
+        // every CHIP-8 pixel is the same size. Any leftover space is filled
+        // with the background color.
+        let (window_width, window_height) = window.get_size();
+        let (screen_width, screen_height) = args.rotate.size(resolution.width, resolution.height);
+        let scale = (window_width / screen_width)
+            .min(window_height / screen_height)
+            .max(1);
+
+        if scale != crt_filter.scale() {
+            crt_filter.set_scale(scale);
+            window_changed = true;
+        }
+
+        let needs_present = match &mut phosphor_decay {
+            // Fading pixels change every frame, so the filter redraws the whole frame.
+            Some(phosphor_decay) => {
+                phosphor_decay.apply(&frame, &mut buffer, &frame_palette) || dirty_rows.is_some()
+            }
+            None => match dirty_rows {
+                Some(dirty_rows) => {
+                    // Only the rows that were drawn to need converting.
+                    frame.write_rgba(&mut buffer, dirty_rows, &frame_palette);
+                    true
+                }
+                None => false,
+            },
+        };
+
+        // The overlays change all the time, so they are redrawn every frame.
+        match needs_present || window_changed || show_stats || show_keypad {
+            true => {
+                let (width, height) = (crt_filter.width(), crt_filter.height());
+                let pixels = crt_filter.apply(&buffer);
+
+                if bell_active && args.visual_bell == Some(VisualBell::Border) {
+                    render::draw_border(pixels, width, height, scale, palette.foreground);
+                }
+
+                let mut overlay_lines = Vec::new();
+
+                if show_stats {
+                    overlay_lines.extend(performance_stats.overlay_lines());
+                }
+
+                if paused {
+                    overlay_lines.push("PAUSED".to_string());
+                }
+
+                if let Some(stop) = published_frame.stopped {
+                    overlay_lines.push(format!("BREAK {}", stop.reason.to_string().to_uppercase()));
+                }
+
+                render::draw_text(
+                    pixels,
+                    width,
+                    &overlay_lines,
+                    (scale / 4).max(1),
+                    palette.foreground,
+                );
+
+                if show_keypad {
+                    render::draw_keypad(
+                        pixels,
+                        (width, height),
+                        &keymap,
+                        keymap.keys(&key_tracker),
+                        (scale / 4).max(1),
+                        &palette,
+                    );
+                }
+
+                let (pixels, width, height) = match args.rotate {
+                    Rotation::None => (&*pixels, width, height),
+                    rotation => {
+                        rotation.apply(pixels, width, height, &mut rotated);
+                        let (width, height) = rotation.size(width, height);
+                        (rotated.as_slice(), width, height)
+                    }
+                };
+
+                window
+                    .update_with_buffer(pixels, width, height)
+                    .map_err(|err| format!("Couldn't draw the window: {err}"))?;
+            }
+            // Nothing changed, so we only need to process window events.
+            false => window.update(),
+        }
+
+        key_tracker.update(&window);
+        let keys = keymap.keys(&key_tracker);
+
+        if window.is_key_pressed(Key::Tab, KeyRepeat::No) {
+            restart_requested = true;
+        }
+
+        if window.is_key_pressed(Key::F5, KeyRepeat::No) {
+            resume_requested = true;
+        }
+
+        if paused {
+            // Keys pressed now are only seen once the pause is over, so
+            // they aren't timed.
+            sent_keys = keys;
+            paused_since_signal = true;
+            continue;
+        }
+
+        let pressed_keys = Keys(keys.0 & !sent_keys.0);
+
+        let signal = FrameFinishedSignal {
+            keys,
+            pressed_at: keymap.pressed_at(&key_tracker, pressed_keys),
+            paused: paused_since_signal,
+            restart: restart_requested,
+            rewind: window.is_key_down(Key::Backspace),
+            resume: resume_requested,
+            turbo: window.is_key_down(Key::Space),
+        };
+
+        // If the emulator is still busy with the last frame, this signal is stale
+        // by the time it would be read, so we drop it rather than queueing it up.
+        // A restart or resume is remembered until a signal carrying it gets through.
+        match emulator.signals.try_send(signal) {
+            Ok(()) => {
+                restart_requested = false;
+                resume_requested = false;
+                sent_keys = keys;
+                paused_since_signal = false;
+            }
+            Err(TrySendError::Full(_)) => {}
+            // The emulator thread only stops early once it has an error to
+            // hand back.
+            Err(TrySendError::Disconnected(_)) => break,
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the window's title, like `CHIP-8 — BRIX [paused] 2.0x`. The speed
+/// is left out while the emulator runs at about normal speed, or hasn't been
+/// measured yet.
+fn window_title(rom_name: &str, state: Option<&str>, speed: f32) -> String {
+    let mut title = format!("CHIP-8 \u{2014} {rom_name}");
+
+    if let Some(state) = state {
+        title += &format!(" [{state}]");
+    }
+
+    let speed = format!("{speed:.1}");
+
+    if state.is_none() && speed != "1.0" && speed != "0.0" {
+        title += &format!(" {speed}x");
+    }
+
+    title
+}